@@ -57,6 +57,8 @@ pub struct AstGrepConfig {
 #[derive(Clone)]
 pub struct ProjectConfig {
   pub project_dir: PathBuf,
+  /// path of the sgconfig.yml
+  pub config_path: PathBuf,
   /// YAML rule directories
   pub rule_dirs: Vec<PathBuf>,
   /// test configurations
@@ -73,13 +75,83 @@ impl ProjectConfig {
     let Some(config_path) = config_path else {
       return Ok(None);
     };
-    let config_str = read_to_string(&config_path).context(EC::ReadConfiguration)?;
-    let sg_config: AstGrepConfig = from_str(&config_str).context(EC::ParseConfiguration)?;
+    let sg_config = read_config_file(&config_path)?;
+    Ok(Some((config_path, sg_config)))
+  }
+
+  fn new(config_path: PathBuf, sg_config: &mut AstGrepConfig) -> Self {
     let project_dir = config_path
       .parent()
       .expect("config file must have parent directory")
       .to_path_buf();
-    Ok(Some((project_dir, sg_config)))
+    ProjectConfig {
+      project_dir,
+      config_path,
+      rule_dirs: sg_config.rule_dirs.drain(..).collect(),
+      test_configs: sg_config.test_configs.take(),
+      util_dirs: sg_config.util_dirs.take(),
+    }
+  }
+
+  /// Find the project of a directory by the nearest sgconfig.yml in it or its ancestors.
+  /// Languages declared by the project are not registered, see `register_languages`.
+  pub fn discover_in_dir(dir: &Path) -> Result<Option<Self>> {
+    let Some(config_path) = find_config_path_from(dir.to_path_buf()) else {
+      return Ok(None);
+    };
+    Self::read_from(&config_path).map(Some)
+  }
+
+  /// Read the project of a config file.
+  /// Languages declared by the project are not registered, see `register_languages`.
+  pub fn read_from(config_path: &Path) -> Result<Self> {
+    let mut sg_config = read_config_file(config_path)?;
+    Ok(Self::new(config_path.to_path_buf(), &mut sg_config))
+  }
+
+  /// Register custom languages, globs and injections declared by all the projects.
+  /// A language declared by several projects uses the first declaration.
+  /// This replaces previous registrations, so it must run before any file is parsed.
+  pub fn register_languages(projects: &[Self]) -> Result<()> {
+    let mut custom_languages = HashMap::new();
+    let mut language_globs = LanguageGlobs::new();
+    let mut language_injections = vec![];
+    let mut seen = vec![];
+    for project in projects {
+      if seen.contains(&&project.config_path) {
+        continue;
+      }
+      seen.push(&project.config_path);
+      let sg_config = read_config_file(&project.config_path)?;
+      for (name, lang) in sg_config.custom_languages.into_iter().flatten() {
+        let lang = lang.with_base(&project.project_dir);
+        custom_languages.entry(name).or_insert(lang);
+      }
+      for (lang, globs) in sg_config.language_globs.into_iter().flatten() {
+        language_globs.entry(lang).or_default().extend(globs);
+      }
+      language_injections.extend(sg_config.language_injections);
+    }
+    let merged = AstGrepConfig {
+      rule_dirs: vec![],
+      test_configs: None,
+      util_dirs: None,
+      custom_languages: Some(custom_languages),
+      language_globs: Some(language_globs),
+      language_injections,
+    };
+    // library paths are already resolved against each project
+    register_custom_language(Path::new(""), merged)
+  }
+
+  /// Whether the config declares custom languages, language globs or injections.
+  pub fn declares_languages(&self) -> Result<bool> {
+    let sg_config = read_config_file(&self.config_path)?;
+    Ok(
+      sg_config.custom_languages.is_some_and(|l| !l.is_empty())
+        || sg_config.language_globs.is_some_and(|g| !g.is_empty())
+        || !sg_config.language_injections.is_empty(),
+    )
   }
 
   pub fn find_rules(
@@ -90,25 +162,39 @@ impl ProjectConfig {
     read_directory_yaml(self, global_rules, rule_overwrite)
   }
 
+  /// List YAML files in util directories, skipping unreadable entries.
+  pub fn find_util_files(&self) -> Vec<PathBuf> {
+    let Some(mut walker) = build_util_walker(&self.project_dir, &self.util_dirs) else {
+      return vec![];
+    };
+    walker
+      .types(config_file_type())
+      .build()
+      .filter_map(Result::ok)
+      .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+      .map(|entry| entry.into_path())
+      .collect()
+  }
+
   /// returns a Result of Result.
   /// The inner Result is for configuration not found, or ProjectNotExist
   /// The outer Result is for definitely wrong config.
   pub fn setup(config_path: Option<PathBuf>) -> Result<Result<Self>> {
-    let Some((project_dir, mut sg_config)) = Self::discover_project(config_path)? else {
+    let Some((config_path, mut sg_config)) = Self::discover_project(config_path)? else {
       return Ok(Err(anyhow::anyhow!(EC::ProjectNotExist)));
     };
-    let config = ProjectConfig {
-      project_dir,
-      rule_dirs: sg_config.rule_dirs.drain(..).collect(),
-      test_configs: sg_config.test_configs.take(),
-      util_dirs: sg_config.util_dirs.take(),
-    };
+    let config = Self::new(config_path, &mut sg_config);
     // sg_config will not use rule dirs and test configs anymore
     register_custom_language(&config.project_dir, sg_config)?;
     Ok(Ok(config))
   }
 }
 
+fn read_config_file(config_path: &Path) -> Result<AstGrepConfig> {
+  let config_str = read_to_string(config_path).context(EC::ReadConfiguration)?;
+  from_str(&config_str).context(EC::ParseConfiguration)
+}
+
 fn register_custom_language(project_dir: &Path, sg_config: AstGrepConfig) -> Result<()> {
   if let Some(custom_langs) = sg_config.custom_languages {
     SgLang::register_custom_language(project_dir, custom_langs)?;
@@ -239,16 +325,20 @@ fn find_config_path_with_default(config_path: Option<PathBuf>) -> Result<Option<
   if config_path.is_some() {
     return Ok(config_path);
   }
-  let mut path = std::env::current_dir()?;
+  Ok(find_config_path_from(std::env::current_dir()?))
+}
+
+/// find the config file in the directory or its ancestors
+fn find_config_path_from(mut path: PathBuf) -> Option<PathBuf> {
   loop {
     let maybe_config = path.join(CONFIG_FILE);
     if maybe_config.exists() {
-      break Ok(Some(maybe_config));
+      break Some(maybe_config);
     }
     if let Some(parent) = path.parent() {
       path = parent.to_path_buf();
     } else {
-      break Ok(None);
+      break None;
     }
   }
 }
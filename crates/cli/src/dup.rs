@@ -0,0 +1,556 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+use ansi_term::{Color, Style};
+use anyhow::{Context, Result};
+use ast_grep_core::language::Normalization;
+use ast_grep_core::{tree_sitter::StrDoc, MatchStrictness, Node as SgNode};
+use ast_grep_language::{Language, LanguageExt};
+use clap::Parser;
+use ignore::WalkState;
+use serde::Serialize;
+
+use crate::lang::SgLang;
+use crate::print::{ColorArg, JsonStyle};
+use crate::run::Strictness;
+use crate::utils::{filter_result, read_file, Granularity, InputArgs, RunTrace};
+
+type Node<'r> = SgNode<'r, StrDoc<SgLang>>;
+
+#[derive(Parser)]
+pub struct DupArg {
+  /// The language of the files to find duplicates in.
+  #[clap(short, long)]
+  lang: SgLang,
+
+  /// Report duplicated subtrees with at least NUM nodes.
+  ///
+  /// Only nodes that are compared under the chosen strictness are counted.
+  #[clap(long, default_value = "40", value_name = "NUM")]
+  min_nodes: usize,
+
+  /// How subtrees are normalized before they are compared.
+  ///
+  /// The levels follow pattern strictness. `signature` ignores identifier names and
+  /// literal values, `relaxed` and `ast` compare them textually. `normalized` also
+  /// ignores redundant parentheses, trailing commas and the operand order of
+  /// commutative operators declared for the language.
+  #[clap(long, default_value = "signature")]
+  strictness: Strictness,
+
+  /// input related options
+  #[clap(flatten)]
+  input: InputArgs,
+
+  /// Output duplicate clusters in structured JSON.
+  ///
+  /// Like `run --json`, the flag must use `=` to specify its value.
+  #[clap(
+      long,
+      value_name="STYLE",
+      num_args(0..=1),
+      require_equals = true,
+      default_missing_value = "pretty"
+  )]
+  json: Option<JsonStyle>,
+
+  /// Controls output color.
+  #[clap(long, default_value = "auto", value_name = "WHEN")]
+  color: ColorArg,
+
+  /// Inspect information for file discovery and scanning.
+  #[clap(long, default_value = "nothing", value_name = "GRANULARITY")]
+  inspect: Granularity,
+}
+
+pub fn run_duplicate_detection(arg: DupArg) -> Result<()> {
+  let trace = arg.inspect.run_trace();
+  let clusters = collect_clusters(&arg, &trace)?;
+  trace.print()?;
+  let mut stdout = std::io::stdout();
+  if let Some(style) = arg.json {
+    print_json(&clusters, style, &mut stdout)
+  } else {
+    print_colored(&clusters, arg.color.should_use_color(), &mut stdout)
+  }
+}
+
+/// Zero-based line and character column.
+#[derive(Serialize, Clone, Copy)]
+struct Position {
+  line: usize,
+  column: usize,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Occurrence {
+  file: PathBuf,
+  kind: String,
+  byte_offset: std::ops::Range<usize>,
+  start: Position,
+  end: Position,
+}
+
+impl Occurrence {
+  fn new(path: &Path, node: &Node) -> Self {
+    let (start, end) = (node.start_pos(), node.end_pos());
+    Self {
+      file: path.to_path_buf(),
+      kind: node.kind().into_owned(),
+      byte_offset: node.range(),
+      start: Position {
+        line: start.line(),
+        column: start.column(node),
+      },
+      end: Position {
+        line: end.line(),
+        column: end.column(node),
+      },
+    }
+  }
+
+  fn contains(&self, other: &Self) -> bool {
+    self.file == other.file
+      && self.byte_offset.start <= other.byte_offset.start
+      && other.byte_offset.end <= self.byte_offset.end
+  }
+}
+
+/// A subtree fingerprint. Equal hashes mean equal normalized structure.
+struct Fingerprint {
+  hash: u64,
+  node_count: usize,
+  occurrence: Occurrence,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Cluster {
+  node_count: usize,
+  occurrences: Vec<Occurrence>,
+}
+
+/// Which parts of a node take part in the structural hash.
+/// It mirrors how `MatchStrictness` skips nodes when matching patterns.
+struct Normalizer {
+  strictness: MatchStrictness,
+  /// the language tables used by `MatchStrictness::Normalized`
+  normalization: Option<&'static Normalization>,
+}
+
+/// A comma followed by no other list item, e.g. the last comma in `[a, b,]`.
+fn is_trailing_comma(node: &Node) -> bool {
+  !node.is_named() && node.text() == "," && node.next_all().all(|n| !n.is_named() || is_comment(&n))
+}
+
+/// Comments are extras in tree-sitter grammars and can appear anywhere.
+fn is_comment(node: &Node) -> bool {
+  node.get_inner_node().is_extra()
+}
+
+impl Normalizer {
+  fn new(strictness: MatchStrictness, lang: SgLang) -> Self {
+    let normalization = match strictness {
+      MatchStrictness::Normalized => lang.normalization(),
+      _ => None,
+    };
+    Self {
+      strictness,
+      normalization,
+    }
+  }
+
+  fn include(&self, node: &Node) -> bool {
+    use MatchStrictness as M;
+    let is_comment = is_comment(node);
+    match self.strictness {
+      M::Cst | M::Smart => true,
+      M::Normalized => !is_trailing_comma(node),
+      M::Ast => node.is_named(),
+      M::Relaxed | M::Signature => node.is_named() && !is_comment,
+      M::Template => !is_comment,
+    }
+  }
+  fn hash_kind(&self) -> bool {
+    !matches!(self.strictness, MatchStrictness::Template)
+  }
+  fn hash_text(&self, node: &Node) -> bool {
+    if !node.is_leaf() {
+      return false;
+    }
+    // signature strictness ignores identifier names and literals
+    !matches!(self.strictness, MatchStrictness::Signature) || !node.is_named()
+  }
+
+  /// A parenthesized expression has the hash of its inner expression.
+  fn is_transparent(&self, node: &Node) -> bool {
+    self
+      .normalization
+      .is_some_and(|n| n.is_parenthesized(node.kind_id()))
+  }
+
+  /// Operands of a commutative binary expression are hashed in either order.
+  fn is_commutative(&self, node: &Node) -> bool {
+    let Some(normalization) = self.normalization else {
+      return false;
+    };
+    if !normalization.is_binary(node.kind_id()) {
+      return false;
+    }
+    let mut operators = node.children().filter(|n| !n.is_named());
+    operators.any(|op| normalization.is_commutative(&op.text()))
+  }
+
+  /// Computes fingerprints of all subtrees with at least `min_nodes` nodes.
+  fn fingerprint(&self, path: &Path, root: Node, min_nodes: usize) -> Vec<Fingerprint> {
+    // pre-order traversal reversed ensures children are visited before parents
+    let nodes: Vec<_> = root.dfs().collect();
+    let mut computed: HashMap<usize, (u64, usize)> = HashMap::new();
+    let mut ret = vec![];
+    for node in nodes.into_iter().rev() {
+      if !self.include(&node) {
+        continue;
+      }
+      let mut children = vec![];
+      let mut node_count = 1;
+      for child in node.children() {
+        if let Some((hash, count)) = computed.remove(&child.node_id()) {
+          children.push((hash, count, child.is_named()));
+          node_count += count;
+        }
+      }
+      if self.is_transparent(&node) {
+        let mut inner = children.iter().filter(|(_, _, named)| *named);
+        if let (Some((hash, count, _)), None) = (inner.next(), inner.next()) {
+          // the wrapper and parentheses are not counted and not reported on their own
+          computed.insert(node.node_id(), (*hash, *count));
+          continue;
+        }
+      }
+      if self.is_commutative(&node) {
+        let operands: Vec<_> = (0..children.len()).filter(|&i| children[i].2).collect();
+        if let [left, right] = operands[..] {
+          if children[left].0 > children[right].0 {
+            let (l, r) = (children[left].0, children[right].0);
+            children[left].0 = r;
+            children[right].0 = l;
+          }
+        }
+      }
+      let mut hasher = DefaultHasher::new();
+      if self.hash_kind() {
+        node.kind_id().hash(&mut hasher);
+      }
+      if self.hash_text(&node) {
+        node.text().hash(&mut hasher);
+      }
+      for (hash, _, _) in children {
+        hash.hash(&mut hasher);
+      }
+      let hash = hasher.finish();
+      if node_count >= min_nodes {
+        ret.push(Fingerprint {
+          hash,
+          node_count,
+          occurrence: Occurrence::new(path, &node),
+        });
+      }
+      computed.insert(node.node_id(), (hash, node_count));
+    }
+    ret
+  }
+}
+
+fn fingerprint_file(path: &Path, arg: &DupArg, trace: &RunTrace) -> Result<Vec<Fingerprint>> {
+  let Some(lang) = SgLang::from_path(path) else {
+    return Ok(vec![]);
+  };
+  if lang != arg.lang {
+    return Ok(vec![]);
+  }
+  trace.print_file(path, lang)?;
+  // empty files have nothing to compare
+  if std::fs::metadata(path).is_ok_and(|m| m.len() == 0) {
+    return Ok(vec![]);
+  }
+  let content =
+    read_file(path).with_context(|| format!("Cannot fingerprint {}", path.display()))?;
+  let grep = lang.ast_grep(content);
+  let normalizer = Normalizer::new(arg.strictness.0.clone(), lang);
+  Ok(normalizer.fingerprint(path, grep.root(), arg.min_nodes))
+}
+
+// files are fingerprinted in parallel and hashes are bucketed in this thread
+fn collect_clusters(arg: &DupArg, trace: &RunTrace) -> Result<Vec<Cluster>> {
+  let walker = arg.input.walk_lang(arg.lang)?;
+  let (tx, rx) = mpsc::channel();
+  std::thread::scope(|s| {
+    s.spawn(move || {
+      walker.run(|| {
+        let tx = tx.clone();
+        Box::new(move |result| {
+          let Some(path) = filter_result(result) else {
+            return WalkState::Continue;
+          };
+          trace.inner.add_scanned();
+          let fingerprints = match fingerprint_file(&path, arg, trace) {
+            Ok(fingerprints) => fingerprints,
+            Err(err) => {
+              eprintln!("ERROR: {err:#}");
+              trace.inner.add_skipped();
+              return WalkState::Continue;
+            }
+          };
+          match tx.send(fingerprints) {
+            Ok(_) => WalkState::Continue,
+            Err(_) => WalkState::Quit,
+          }
+        })
+      });
+    });
+    let mut buckets: HashMap<(u64, usize), Vec<Occurrence>> = HashMap::new();
+    for fingerprints in rx {
+      for Fingerprint {
+        hash,
+        node_count,
+        occurrence,
+      } in fingerprints
+      {
+        buckets
+          .entry((hash, node_count))
+          .or_default()
+          .push(occurrence);
+      }
+    }
+    Ok(build_clusters(buckets))
+  })
+}
+
+fn build_clusters(buckets: HashMap<(u64, usize), Vec<Occurrence>>) -> Vec<Cluster> {
+  let mut candidates: Vec<_> = buckets
+    .into_iter()
+    .filter(|(_, occurrences)| occurrences.len() > 1)
+    .map(|((_, node_count), mut occurrences)| {
+      occurrences
+        .sort_by(|a, b| (&a.file, a.byte_offset.start).cmp(&(&b.file, b.byte_offset.start)));
+      Cluster {
+        node_count,
+        occurrences,
+      }
+    })
+    .collect();
+  // report larger duplicates first, ties are ordered by location for stable output
+  candidates.sort_by(|a, b| {
+    let a_first = &a.occurrences[0];
+    let b_first = &b.occurrences[0];
+    b.node_count
+      .cmp(&a.node_count)
+      .then_with(|| a_first.file.cmp(&b_first.file))
+      .then_with(|| a_first.byte_offset.start.cmp(&b_first.byte_offset.start))
+  });
+  // a cluster nested entirely in a reported cluster is part of a larger duplicate
+  let mut ret: Vec<Cluster> = vec![];
+  for cluster in candidates {
+    let is_nested = cluster.occurrences.iter().all(|occ| {
+      ret
+        .iter()
+        .flat_map(|c| &c.occurrences)
+        .any(|reported| reported.contains(occ))
+    });
+    if !is_nested {
+      ret.push(cluster);
+    }
+  }
+  ret
+}
+
+fn print_json<W: Write>(clusters: &[Cluster], style: JsonStyle, output: &mut W) -> Result<()> {
+  match style {
+    JsonStyle::Pretty => {
+      serde_json::to_writer_pretty(&mut *output, clusters)?;
+      writeln!(output)?;
+    }
+    JsonStyle::Stream => {
+      for cluster in clusters {
+        serde_json::to_writer(&mut *output, cluster)?;
+        writeln!(output)?;
+      }
+    }
+    JsonStyle::Compact => {
+      serde_json::to_writer(&mut *output, clusters)?;
+      writeln!(output)?;
+    }
+  }
+  Ok(())
+}
+
+fn print_colored<W: Write>(clusters: &[Cluster], color: bool, output: &mut W) -> Result<()> {
+  let (title, file_path) = if color {
+    (Color::Yellow.bold(), Color::Cyan.italic())
+  } else {
+    (Style::new(), Style::new())
+  };
+  for (i, cluster) in clusters.iter().enumerate() {
+    let header = format!(
+      "Duplicate #{}: {} occurrences of {} nodes",
+      i + 1,
+      cluster.occurrences.len(),
+      cluster.node_count,
+    );
+    writeln!(output, "{}", title.paint(header))?;
+    for occ in &cluster.occurrences {
+      let path = file_path.paint(occ.file.display().to_string());
+      let (start, end) = (occ.start, occ.end);
+      writeln!(
+        output,
+        "{path}:{}:{}-{}:{} ({})",
+        start.line + 1,
+        start.column + 1,
+        end.line + 1,
+        end.column + 1,
+        occ.kind,
+      )?;
+    }
+    writeln!(output)?;
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use ast_grep_language::SupportLang;
+
+  const FOO: &str = "
+function foo(a, b) {
+  const total = a + b * 2;
+  console.log('total', total);
+  return total;
+}";
+  const BAR: &str = "
+function bar(x, y) {
+  const sum = x + y * 3;
+  console.log('sum', sum);
+  return sum;
+}";
+
+  fn fingerprint(src: &str, strictness: MatchStrictness) -> Vec<Fingerprint> {
+    let lang = SgLang::from(SupportLang::TypeScript);
+    let grep = lang.ast_grep(src);
+    let normalizer = Normalizer::new(strictness, lang);
+    normalizer.fingerprint(Path::new("test.ts"), grep.root(), 10)
+  }
+
+  fn find_function(src: &str, strictness: MatchStrictness) -> (u64, usize) {
+    let fingerprints = fingerprint(src, strictness);
+    let f = fingerprints
+      .iter()
+      .find(|f| f.occurrence.kind == "function_declaration")
+      .expect("should fingerprint function");
+    (f.hash, f.node_count)
+  }
+
+  #[test]
+  fn test_signature_ignores_names() {
+    let foo = find_function(FOO, MatchStrictness::Signature);
+    let bar = find_function(BAR, MatchStrictness::Signature);
+    assert_eq!(foo, bar);
+  }
+
+  #[test]
+  fn test_relaxed_compares_names() {
+    let foo = find_function(FOO, MatchStrictness::Relaxed);
+    let bar = find_function(BAR, MatchStrictness::Relaxed);
+    assert_ne!(foo.0, bar.0);
+  }
+
+  #[test]
+  fn test_relaxed_ignores_comment() {
+    let commented = FOO.replace("return total;", "return total; // done");
+    let foo = find_function(FOO, MatchStrictness::Relaxed);
+    let other = find_function(&commented, MatchStrictness::Relaxed);
+    assert_eq!(foo, other);
+    let foo = find_function(FOO, MatchStrictness::Cst);
+    let other = find_function(&commented, MatchStrictness::Cst);
+    assert_ne!(foo.0, other.0);
+  }
+
+  #[test]
+  fn test_normalized() {
+    let normalized = |src: &str| find_function(src, MatchStrictness::Normalized);
+    let foo = normalized(FOO);
+    let swapped = FOO.replace("a + b * 2", "a + (2 * b)");
+    assert_eq!(foo, normalized(&swapped));
+    let trailing = FOO.replace("'total', total", "'total', total,");
+    assert_eq!(foo, normalized(&trailing));
+    // addition may concatenate strings and is not commutative
+    let plus = |src: &str| normalized(&FOO.replace("a + b * 2", src));
+    assert_ne!(plus("a + b * 2").0, plus("b * 2 + a").0);
+    // smart strictness compares the source as is
+    let smart = find_function(FOO, MatchStrictness::Smart).0;
+    assert_ne!(smart, find_function(&swapped, MatchStrictness::Smart).0);
+  }
+
+  #[test]
+  fn test_min_nodes() {
+    let lang = SgLang::from(SupportLang::TypeScript);
+    let grep = lang.ast_grep(FOO);
+    let normalizer = Normalizer::new(MatchStrictness::Smart, lang);
+    let root = grep.root();
+    let total = root.dfs().count();
+    let ret = normalizer.fingerprint(Path::new("a.ts"), root.clone(), total);
+    assert_eq!(ret.len(), 1);
+    assert_eq!(ret[0].node_count, total);
+    let ret = normalizer.fingerprint(Path::new("a.ts"), root, total + 1);
+    assert!(ret.is_empty());
+  }
+
+  #[test]
+  fn test_nested_cluster_is_suppressed() {
+    let mut buckets: HashMap<(u64, usize), Vec<Occurrence>> = HashMap::new();
+    for (path, src) in [("a.ts", FOO), ("b.ts", BAR)] {
+      for f in fingerprint(src, MatchStrictness::Signature) {
+        buckets
+          .entry((f.hash, f.node_count))
+          .or_default()
+          .push(Occurrence {
+            file: PathBuf::from(path),
+            ..f.occurrence
+          });
+      }
+    }
+    let clusters = build_clusters(buckets);
+    assert_eq!(clusters.len(), 1);
+    let occurrences = &clusters[0].occurrences;
+    assert_eq!(occurrences.len(), 2);
+    assert_eq!(occurrences[0].file, PathBuf::from("a.ts"));
+    assert_eq!(occurrences[1].file, PathBuf::from("b.ts"));
+  }
+
+  #[test]
+  fn test_print_colored() {
+    let mut buckets: HashMap<(u64, usize), Vec<Occurrence>> = HashMap::new();
+    for (path, src) in [("a.ts", FOO), ("b.ts", BAR)] {
+      let f = fingerprint(src, MatchStrictness::Signature).pop().unwrap();
+      buckets
+        .entry((f.hash, f.node_count))
+        .or_default()
+        .push(Occurrence {
+          file: PathBuf::from(path),
+          ..f.occurrence
+        });
+    }
+    let clusters = build_clusters(buckets);
+    let mut output = vec![];
+    print_colored(&clusters, false, &mut output).expect("should print");
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.starts_with("Duplicate #1: 2 occurrences"));
+    assert!(output.contains("a.ts:"));
+    assert!(output.contains("b.ts:"));
+  }
+}
@@ -0,0 +1,359 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use ast_grep_config::{from_yaml_string, CombinedScan, RuleCollection, RuleConfig};
+use ast_grep_core::{tree_sitter::StrDoc, AstGrep, Pattern};
+use ast_grep_language::{Language, LanguageExt};
+use clap::Parser;
+use ignore::WalkParallel;
+
+use crate::config::{read_rule_file, with_rule_stats};
+use crate::lang::SgLang;
+use crate::print::{PrintProcessor, Printer, SimpleFile, TableColumn, TableFormat, TablePrinter};
+use crate::utils::ErrorContext as EC;
+use crate::utils::{filter_file_pattern, filter_file_rule, Granularity, InputArgs, Prefilter};
+use crate::utils::{FileTrace, RunTrace, ScanTrace};
+use crate::utils::{Items, PathWorker, StdInWorker, Worker};
+
+#[derive(Parser)]
+pub struct ExtractArg {
+  /// AST pattern to match. Requires lang be set explicitly.
+  #[clap(short, long, requires = "lang", required_unless_present_any = ["rule", "inline_rules"])]
+  pattern: Option<String>,
+
+  /// The language of the pattern.
+  #[clap(short, long)]
+  lang: Option<SgLang>,
+
+  /// Match with the rule located at the path RULE_FILE.
+  ///
+  /// Transformed variables defined in the rule can be used as columns.
+  #[clap(short, long, value_name = "RULE_FILE", conflicts_with = "pattern")]
+  rule: Option<PathBuf>,
+
+  /// Match with a rule defined by the provided RULE_TEXT.
+  #[clap(long, value_name = "RULE_TEXT", conflicts_with_all = ["pattern", "rule"])]
+  inline_rules: Option<String>,
+
+  /// Comma separated columns of the table.
+  ///
+  /// A column is either a metavariable like `$NAME`, a transformed variable,
+  /// or one of `file`, `line`, `column`, `end_line`, `end_column`, `range`, `text` and `rule`.
+  #[clap(
+    long,
+    value_delimiter = ',',
+    value_name = "COLUMNS",
+    required_unless_present = "group_by",
+    conflicts_with = "group_by"
+  )]
+  columns: Vec<TableColumn>,
+
+  /// Output format of the table.
+  #[clap(long, default_value = "csv", value_name = "FORMAT")]
+  format: TableFormat,
+
+  /// Count matches for every distinct value of the comma separated columns.
+  ///
+  /// It implies --count and prints the group columns instead of --columns.
+  #[clap(long, value_delimiter = ',', value_name = "COLUMNS")]
+  group_by: Vec<TableColumn>,
+
+  /// Merge identical rows and append the number of matches as `count` column.
+  ///
+  /// Counted rows are printed after all files are searched, most frequent first.
+  #[clap(long)]
+  count: bool,
+
+  /// input related options
+  #[clap(flatten)]
+  input: InputArgs,
+
+  /// Inspect information for file/rule discovery and scanning.
+  #[clap(long, default_value = "nothing", value_name = "GRANULARITY")]
+  inspect: Granularity,
+}
+
+pub fn run_extract(arg: ExtractArg) -> Result<()> {
+  let counted = arg.count || !arg.group_by.is_empty();
+  let columns = if arg.group_by.is_empty() {
+    arg.columns.clone()
+  } else {
+    arg.group_by.clone()
+  };
+  let printer = TablePrinter::stdout(arg.format, columns)?.count(counted);
+  if arg.pattern.is_some() {
+    let worker = ExtractWithPattern::try_new(arg)?;
+    if worker.arg.input.stdin {
+      worker.run_std_in(printer)
+    } else {
+      worker.run_path(printer)
+    }
+  } else {
+    let worker = ExtractWithRule::try_new(arg)?;
+    if worker.arg.input.stdin {
+      worker.run_std_in(printer)
+    } else {
+      worker.run_path(printer)
+    }
+  }
+}
+
+fn consume_with_printer<P: Printer>(items: Items<P::Processed>, mut printer: P) -> Result<()> {
+  printer.before_print()?;
+  for item in items {
+    printer.process(item)?;
+  }
+  printer.after_print()
+}
+
+struct ExtractWithPattern {
+  arg: ExtractArg,
+  lang: SgLang,
+  pattern: Pattern,
+  trace: RunTrace,
+}
+
+impl ExtractWithPattern {
+  fn try_new(arg: ExtractArg) -> Result<Self> {
+    let lang = arg.lang.ok_or(anyhow::anyhow!(EC::LanguageNotSpecified))?;
+    let pattern = arg.pattern.as_deref().expect("must present");
+    let pattern = Pattern::try_new(pattern, lang).context(EC::ParsePattern)?;
+    let trace = arg.inspect.run_trace();
+    Ok(Self {
+      arg,
+      lang,
+      pattern,
+      trace,
+    })
+  }
+}
+
+impl Worker for ExtractWithPattern {
+  fn consume_items<P: Printer>(&self, items: Items<P::Processed>, printer: P) -> Result<()> {
+    consume_with_printer(items, printer)?;
+    self.trace.print()
+  }
+}
+
+impl PathWorker for ExtractWithPattern {
+  fn build_walk(&self) -> Result<WalkParallel> {
+    self.arg.input.walk_lang(self.lang)
+  }
+  fn get_trace(&self) -> &FileTrace {
+    &self.trace.inner
+  }
+  fn produce_item<P: Printer>(
+    &self,
+    path: &Path,
+    processor: &P::Processor,
+  ) -> Result<Vec<P::Processed>> {
+    let Some(path_lang) = SgLang::from_path(path) else {
+      return Ok(vec![]);
+    };
+    self.trace.print_file(path, path_lang)?;
+    let sub_matchers = [(self.lang, self.pattern.clone())];
+    let (root_matcher, sub_matchers) = if path_lang == self.lang {
+      (Some(&self.pattern), &[][..])
+    } else {
+      (None, &sub_matchers[..])
+    };
+    let units = filter_file_pattern(path, path_lang, root_matcher, sub_matchers)?;
+    let mut ret = Vec::with_capacity(units.len());
+    for unit in units {
+      let matches: Vec<_> = unit.grep.root().find_all(unit.matcher).collect();
+      if !matches.is_empty() {
+        ret.push(processor.print_matches(matches, path)?);
+      }
+    }
+    Ok(ret)
+  }
+}
+
+impl StdInWorker for ExtractWithPattern {
+  fn parse_stdin<P: Printer>(
+    &self,
+    src: String,
+    processor: &P::Processor,
+  ) -> Result<Vec<P::Processed>> {
+    let grep = self.lang.ast_grep(src);
+    let matches = grep.root().find_all(&self.pattern).collect();
+    let processed = processor.print_matches(matches, Path::new("STDIN"))?;
+    Ok(vec![processed])
+  }
+}
+
+struct ExtractWithRule {
+  arg: ExtractArg,
+  langs: Vec<SgLang>,
+  configs: RuleCollection<SgLang>,
+  prefilter: Prefilter,
+  trace: ScanTrace,
+}
+
+impl ExtractWithRule {
+  fn try_new(arg: ExtractArg) -> Result<Self> {
+    let rules = if let Some(path) = &arg.rule {
+      read_rule_file(path, None)?
+    } else if let Some(text) = &arg.inline_rules {
+      from_yaml_string(text, &Default::default())
+        .with_context(|| EC::ParseRule("INLINE_RULES".into()))?
+    } else {
+      return Err(anyhow::anyhow!(EC::RuleNotSpecified));
+    };
+    let mut seen = HashSet::new();
+    let langs: Vec<_> = rules
+      .iter()
+      .map(|r| r.language)
+      .filter(|lang| seen.insert(*lang))
+      .collect();
+    let (configs, rule_trace) = with_rule_stats(rules)?;
+    let prefilter = Prefilter::new(&configs, &[]);
+    let trace = arg.inspect.scan_trace(rule_trace);
+    Ok(Self {
+      arg,
+      langs,
+      configs,
+      prefilter,
+      trace,
+    })
+  }
+}
+
+impl Worker for ExtractWithRule {
+  fn consume_items<P: Printer>(&self, items: Items<P::Processed>, printer: P) -> Result<()> {
+    consume_with_printer(items, printer)?;
+    self.trace.print()
+  }
+}
+
+fn extract_rule_matches<T>(
+  path: &Path,
+  grep: &AstGrep<StrDoc<SgLang>>,
+  rules: Vec<&RuleConfig<SgLang>>,
+  processor: &impl PrintProcessor<T>,
+) -> Result<Vec<T>> {
+  let combined = CombinedScan::new(rules);
+  let scanned = combined.scan(grep, false);
+  let mut ret = vec![];
+  for (rule, matches) in scanned.matches {
+    let file = SimpleFile::new(path.to_string_lossy(), grep.source());
+    ret.push(processor.print_rule(matches, file, rule)?);
+  }
+  Ok(ret)
+}
+
+impl PathWorker for ExtractWithRule {
+  fn build_walk(&self) -> Result<WalkParallel> {
+    self.arg.input.walk_langs(self.langs.iter().copied())
+  }
+  fn get_trace(&self) -> &FileTrace {
+    &self.trace.inner.file_trace
+  }
+  fn produce_item<P: Printer>(
+    &self,
+    path: &Path,
+    processor: &P::Processor,
+  ) -> Result<Vec<P::Processed>> {
+    let mut ret = vec![];
+    for grep in filter_file_rule(path, &self.configs, &self.prefilter, &self.trace)? {
+      let rules = self.configs.get_rule_from_lang(path, *grep.lang());
+      ret.extend(extract_rule_matches(path, &grep, rules, processor)?);
+    }
+    Ok(ret)
+  }
+}
+
+impl StdInWorker for ExtractWithRule {
+  fn parse_stdin<P: Printer>(
+    &self,
+    src: String,
+    processor: &P::Processor,
+  ) -> Result<Vec<P::Processed>> {
+    let Some(&lang) = self.langs.first() else {
+      return Err(anyhow::anyhow!(EC::RuleNotSpecified));
+    };
+    let grep = lang.ast_grep(src);
+    let path = Path::new("STDIN");
+    let rules = self.configs.get_rule_from_lang(path, lang);
+    extract_rule_matches(path, &grep, rules, processor)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use ast_grep_language::SupportLang;
+
+  fn default_extract_arg() -> ExtractArg {
+    ExtractArg {
+      pattern: None,
+      lang: None,
+      rule: None,
+      inline_rules: None,
+      columns: vec![TableColumn::File, TableColumn::Line],
+      format: TableFormat::Csv,
+      group_by: vec![],
+      count: false,
+      input: InputArgs {
+        no_ignore: vec![],
+        stdin: false,
+        follow: false,
+        paths: vec![PathBuf::from(".")],
+        globs: vec![],
+        threads: 0,
+      },
+      inspect: Default::default(),
+    }
+  }
+
+  #[test]
+  fn test_dedup_langs() -> Result<()> {
+    let rules = "
+id: a
+language: rs
+rule: {pattern: Some($A)}
+---
+id: b
+language: ts
+rule: {pattern: foo($A)}
+---
+id: c
+language: rs
+rule: {pattern: None}";
+    let arg = ExtractArg {
+      inline_rules: Some(rules.into()),
+      ..default_extract_arg()
+    };
+    let worker = ExtractWithRule::try_new(arg)?;
+    let rust = SgLang::from(SupportLang::Rust);
+    let ts = SgLang::from(SupportLang::TypeScript);
+    assert_eq!(worker.langs, [rust, ts]);
+    Ok(())
+  }
+
+  #[test]
+  fn test_stdin_without_rule() -> Result<()> {
+    let arg = ExtractArg {
+      inline_rules: Some("{id: a, language: rs, rule: {pattern: Some($A)}}".into()),
+      ..default_extract_arg()
+    };
+    let mut worker = ExtractWithRule::try_new(arg)?;
+    worker.langs.clear();
+    let printer = TablePrinter::new(vec![], TableFormat::Csv, vec![TableColumn::File])?;
+    let processor = printer.get_processor();
+    let ret = worker.parse_stdin::<TablePrinter<Vec<u8>>>("Some(1)".into(), &processor);
+    assert!(ret.is_err());
+    Ok(())
+  }
+
+  #[test]
+  fn test_extract_with_bad_pattern() {
+    let arg = ExtractArg {
+      pattern: Some("".into()),
+      lang: Some(SupportLang::Rust.into()),
+      ..default_extract_arg()
+    };
+    assert!(run_extract(arg).is_err());
+  }
+}
@@ -13,10 +13,8 @@ static mut LANG_GLOBS: Vec<(SgLang, Types)> = vec![];
 
 pub type LanguageGlobs = HashMap<String, Vec<String>>;
 
+// registering again replaces the globs, it must not race with reading them
 pub unsafe fn register(regs: LanguageGlobs) -> Result<()> {
-  debug_assert! {
-    (*addr_of!(LANG_GLOBS)).is_empty()
-  };
   let lang_globs = register_impl(regs)?;
   _ = std::mem::replace(&mut *addr_of_mut!(LANG_GLOBS), lang_globs);
   Ok(())
@@ -4,6 +4,7 @@ mod lang_globs;
 use crate::utils::ErrorContext as EC;
 
 use anyhow::{Context, Result};
+use ast_grep_core::language::{NodeTypes, Normalization};
 use ast_grep_core::matcher::{Pattern, PatternBuilder, PatternError};
 use ast_grep_core::{
   tree_sitter::{StrDoc, TSLanguage, TSRange},
@@ -188,6 +189,24 @@ impl Language for SgLang {
       Custom(c) => c.field_to_id(field),
     }
   }
+  fn normalization(&self) -> Option<&'static Normalization> {
+    match self {
+      Builtin(b) => b.normalization(),
+      Custom(c) => c.normalization(),
+    }
+  }
+  fn node_types(&self) -> Option<&'static NodeTypes> {
+    match self {
+      Builtin(b) => b.node_types(),
+      Custom(c) => c.node_types(),
+    }
+  }
+  fn list_separators(&self) -> &'static [&'static str] {
+    match self {
+      Builtin(b) => b.list_separators(),
+      Custom(c) => c.list_separators(),
+    }
+  }
   fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
     // respect user overriding like languageGlobs and custom lang
     // TODO: test this preference
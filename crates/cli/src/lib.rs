@@ -1,5 +1,7 @@
 mod completions;
 mod config;
+mod dup;
+mod extract;
 mod lang;
 mod lsp;
 mod new;
@@ -15,6 +17,8 @@ use std::path::PathBuf;
 
 use completions::{run_shell_completion, CompletionsArg};
 use config::ProjectConfig;
+use dup::{run_duplicate_detection, DupArg};
+use extract::{run_extract, ExtractArg};
 use lsp::{run_language_server, LspArg};
 use new::{run_create_new, NewArg};
 use run::{run_with_pattern, RunArg};
@@ -58,6 +62,10 @@ enum Commands {
   New(NewArg),
   /// Start language server.
   Lsp(LspArg),
+  /// Find structurally duplicated code.
+  Dup(DupArg),
+  /// Export captured metavariables of matches as CSV, TSV or NDJSON table.
+  Extract(ExtractArg),
   /// Generate shell completion script.
   Completions(CompletionsArg),
   /// Generate rule docs for current configuration. (Not Implemented Yet)
@@ -139,6 +147,8 @@ pub fn main_with_args(args: impl Iterator<Item = String>) -> Result<()> {
     Commands::Test(arg) => run_test_rule(arg, project),
     Commands::New(arg) => run_create_new(arg, project),
     Commands::Lsp(arg) => run_language_server(arg, project),
+    Commands::Dup(arg) => run_duplicate_detection(arg),
+    Commands::Extract(arg) => run_extract(arg),
     Commands::Completions(arg) => run_shell_completion::<App>(arg),
     #[cfg(debug_assertions)]
     Commands::Docs => todo!("todo, generate rule docs based on current config"),
@@ -228,6 +238,11 @@ mod test_cli {
     ok("run -p test --follow");
     ok("run -p test --globs '*.js'");
     ok("run -p test --globs '*.{js, ts}'");
+    ok("run -p test --format-template {file}:{start.line}");
+    ok("run -p test --count --json=stream");
+    ok("run -p test --files-with-matches -m 1");
+    ok("run -p test --files-without-match --max-count 3");
+    ok("run -p test --format-template {$A} --template-escape shell");
     ok("run -p test --globs '*.js' --globs '*.ts'");
     ok("run -p fubuki -j8");
     ok("run -p test --threads 12");
@@ -236,6 +251,13 @@ mod test_cli {
     error("run --debug-query test"); // missing lang
     error("run -r Test dir");
     error("run -p test -i --json dir"); // conflict
+    error("run -p test --format-template {file} --json"); // conflict
+    error("run -p test --format-template {unknown}");
+    error("run -p test --template-escape shell"); // requires template
+    error("run -p test --count --files-with-matches"); // conflict
+    error("run -p test --files-with-matches --files-without-match"); // conflict
+    error("run -p test --count -i"); // conflict
+    error("run -p test --max-count"); // missing value
     error("run -p test -U");
     error("run -p test --update-all");
     error("run -p test --strictness not");
@@ -267,17 +289,24 @@ mod test_cli {
     ok("scan -A 12");
     ok("scan --after 12");
     ok("scan --context 1");
+    ok("scan --format-template {rule}:{message} --template-escape json");
+    ok("scan --count -m 10");
+    ok("scan --file-timeout 100 --max-node-visits 10000");
+    ok("scan --files-with-matches --json");
     error("scan -i --json dir"); // conflict
     error("scan --report-style rich --json dir"); // conflict
     error("scan -r test.yml --inline-rules '{}'"); // conflict
     error("scan --format gitlab");
     error("scan --format github -i");
+    error("scan --format github --format-template {file}");
+    error("scan --count --format-template {file}"); // conflict
     error("scan --format local");
     error("scan --json=dir"); // wrong json flag
     error("scan --json= not-pretty"); // wrong json flag
     error("scan -j");
     error("scan --include-metadata"); // requires json
     error("scan --threads");
+    error("scan --file-timeout 1s");
   }
 
   #[test]
@@ -301,6 +330,35 @@ mod test_cli {
     error("new --base-dir");
   }
 
+  #[test]
+  fn test_dup() {
+    ok("dup --lang ts");
+    ok("dup --lang ts --min-nodes 40");
+    ok("dup -l rs --strictness relaxed dir");
+    ok("dup -l ts --json");
+    ok("dup -l ts --json=stream dir");
+    error("dup");
+    error("dup -l ts --min-nodes");
+    error("dup -l ts --strictness not");
+  }
+
+  #[test]
+  fn test_extract() {
+    ok("extract -p $A.track($B) -l ts --columns file,line,$A,$B");
+    ok("extract -p $A.track($B) -l ts --columns $A --format tsv dir");
+    ok("extract -p $A.track($B) -l ts --columns $A --format ndjson --count");
+    ok("extract -p $A.track($B) -l ts --group-by $A,$B");
+    ok("extract -r rule.yml --columns rule,file,range");
+    ok("extract --inline-rules {} --group-by rule");
+    error("extract -p $A.track($B) --columns $A"); // missing lang
+    error("extract -p $A.track($B) -l ts"); // missing columns
+    error("extract -p test -l ts --columns file --group-by file");
+    error("extract -p test -l ts -r rule.yml --columns file");
+    error("extract -l ts --columns file"); // missing pattern or rule
+    error("extract -p test -l ts --columns lowercase");
+    error("extract -p test -l ts --columns file --format xml");
+  }
+
   #[test]
   fn test_shell() {
     ok("completions");
@@ -1,9 +1,14 @@
 use crate::config::ProjectConfig;
-use crate::utils::{ErrorContext as EC, RuleOverwrite};
+use crate::lang::SgLang;
+use crate::utils::DebugFormat;
+use crate::utils::{filter_result, ErrorContext as EC, NoIgnore, RuleOverwrite};
 use anyhow::{Context, Result};
-use ast_grep_lsp::{Backend, LspService, Server};
+use ast_grep_lsp::{Backend, Dump, LspService, ProjectRules, Server};
 use clap::Args;
 
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
 #[derive(Args)]
 pub struct LspArg {}
 
@@ -15,6 +20,9 @@ async fn run_language_server_impl(_arg: LspArg, project: Result<ProjectConfig>)
   let stdout = tokio::io::stdout();
 
   let config_base = project_config.project_dir.clone();
+  let util_config = project_config.clone();
+  let startup_config = project_config.clone();
+  let registered = Mutex::new(None);
 
   // Create a rule finder closure that uses the CLI logic
   let rule_finder = move || {
@@ -22,12 +30,104 @@ async fn run_language_server_impl(_arg: LspArg, project: Result<ProjectConfig>)
     Ok(collection)
   };
 
-  let (service, socket) =
-    LspService::build(|client| Backend::new(client, config_base, rule_finder)).finish();
+  let builder = LspService::build(|client| {
+    Backend::new(client, config_base, rule_finder)
+      .with_file_walker(walk_workspace)
+      .with_util_finder(move || util_config.find_util_files())
+      .with_project_loader(load_folder_project)
+      .with_config_loader(|path| project_rules(ProjectConfig::read_from(path)?))
+      .with_language_setup(move |roots| setup_folder_languages(&startup_config, &registered, roots))
+      .with_tree_dumper(dump_for_playground)
+  });
+  let (service, socket) = Backend::custom_methods(builder).finish();
   Server::new(stdin, stdout, socket).serve(service).await;
   Ok(())
 }
 
+/// Load the rules of the project a workspace folder belongs to.
+fn load_folder_project(root: &Path) -> Option<Result<ProjectRules<SgLang>>> {
+  let project = match ProjectConfig::discover_in_dir(root) {
+    Ok(project) => project?,
+    Err(e) => return Some(Err(e)),
+  };
+  Some(project_rules(project))
+}
+
+fn project_rules(project: ProjectConfig) -> Result<ProjectRules<SgLang>> {
+  let (rules, _trace) = project.find_rules(RuleOverwrite::default())?;
+  Ok(ProjectRules {
+    util_files: project.find_util_files(),
+    base: project.project_dir,
+    rules,
+  })
+}
+
+/// Register languages of the projects of the initial workspace folders along with the startup project.
+/// Languages can only be registered before any file is parsed, so folders added later
+/// are rejected if their projects declare languages. `registered` lists the loaded configs.
+fn setup_folder_languages(
+  startup: &ProjectConfig,
+  registered: &Mutex<Option<Vec<PathBuf>>>,
+  roots: &[PathBuf],
+) -> Result<()> {
+  let mut projects = vec![startup.clone()];
+  for root in roots {
+    let Some(project) = ProjectConfig::discover_in_dir(root)? else {
+      continue;
+    };
+    if projects
+      .iter()
+      .all(|p| p.config_path != project.config_path)
+    {
+      projects.push(project);
+    }
+  }
+  let mut registered = registered
+    .lock()
+    .map_err(|_| anyhow::anyhow!("language setup panicked"))?;
+  if let Some(loaded) = registered.as_ref() {
+    let mut rejected = vec![];
+    for project in &projects {
+      if !loaded.contains(&project.config_path) && project.declares_languages()? {
+        rejected.push(project.config_path.display().to_string());
+      }
+    }
+    if rejected.is_empty() {
+      return Ok(());
+    }
+    anyhow::bail!(
+      "languages declared in {} are only loaded at startup, restart the language server to use them",
+      rejected.join(", ")
+    );
+  }
+  *registered = Some(projects.iter().map(|p| p.config_path.clone()).collect());
+  // the startup project is already registered
+  if projects.len() == 1 {
+    return Ok(());
+  }
+  ProjectConfig::register_languages(&projects)
+}
+
+/// Print the dumps of `--debug-query` for the playground requests.
+fn dump_for_playground(dump: Dump<SgLang>) -> Option<String> {
+  let (format, node) = match dump {
+    Dump::Pattern(pattern, lang) => return DebugFormat::dump_pattern(pattern, *lang, false),
+    Dump::Ast(node) => (DebugFormat::Ast, node),
+    Dump::Cst(node) => (DebugFormat::Cst, node),
+    Dump::Sexp(node) => (DebugFormat::Sexp, node),
+  };
+  Some(format.dump_tree(node.get_inner_node(), false))
+}
+
+/// List files in the workspace folder with the same ignore rules as `ast-grep scan`.
+fn walk_workspace(root: &Path) -> Vec<PathBuf> {
+  NoIgnore::disregard(&[])
+    .walk(&[root.to_path_buf()])
+    .build()
+    .filter_map(filter_result)
+    .collect()
+}
+
 pub fn run_language_server(arg: LspArg, project: Result<ProjectConfig>) -> Result<()> {
   tokio::runtime::Builder::new_multi_thread()
     .enable_all()
@@ -39,6 +139,8 @@ pub fn run_language_server(arg: LspArg, project: Result<ProjectConfig>) -> Resul
 #[cfg(test)]
 mod test {
   use super::*;
+  use std::fs::{create_dir_all, write};
+  use tempfile::TempDir;
 
   #[test]
   #[ignore = "test lsp later"]
@@ -46,4 +148,29 @@ mod test {
     let arg = LspArg {};
     assert!(run_language_server(arg, Err(anyhow::anyhow!("error"))).is_err())
   }
+
+  #[test]
+  fn test_added_folder_languages_rejected() -> Result<()> {
+    let dir = TempDir::new()?;
+    let startup = dir.path().join("startup");
+    let plain = dir.path().join("plain");
+    let added = dir.path().join("added");
+    for project in [&startup, &plain, &added] {
+      create_dir_all(project)?;
+    }
+    write(startup.join("sgconfig.yml"), "ruleDirs: []")?;
+    write(plain.join("sgconfig.yml"), "ruleDirs: []")?;
+    let globs = "ruleDirs: []\nlanguageGlobs:\n  html: ['*.vue']";
+    write(added.join("sgconfig.yml"), globs)?;
+    let startup = ProjectConfig::discover_in_dir(&startup)?.expect("should find config");
+    let registered = Mutex::new(None);
+    // no folders in initialize, only the startup project is registered
+    setup_folder_languages(&startup, &registered, &[])?;
+    // added folders without languages are accepted
+    setup_folder_languages(&startup, &registered, &[plain])?;
+    let error = setup_folder_languages(&startup, &registered, &[added]).unwrap_err();
+    assert!(error.to_string().contains("only loaded at startup"));
+    drop(dir);
+    Ok(())
+  }
 }
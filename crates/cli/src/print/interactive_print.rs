@@ -5,6 +5,7 @@ use crate::utils::{self, clear};
 
 use anyhow::{Context, Result};
 use ast_grep_config::RuleConfig;
+use ast_grep_core::source::{Edit, EditBatch, OverlapPolicy};
 use codespan_reporting::files::SimpleFile;
 use codespan_reporting::term::termcolor::{Buffer, StandardStream};
 use smallvec::{smallvec, SmallVec};
@@ -372,19 +373,23 @@ fn print_diff_and_prompt_action(
 }
 
 fn apply_rewrite(diffs: Diffs<()>) -> String {
-  let mut new_content = String::new();
-  let old_content = diffs.old_source;
-  let mut start = 0;
-  for mut diff_list in diffs.contents {
-    let diff = diff_list.remove(0);
-    let range = diff.range;
-    new_content.push_str(&old_content[start..range.start]);
-    new_content.push_str(&diff.replacement);
-    start = range.end;
-  }
-  // add trailing statements
-  new_content.push_str(&old_content[start..]);
-  new_content
+  let edits = diffs
+    .contents
+    .into_iter()
+    .map(|mut diff_list| {
+      let diff = diff_list.remove(0);
+      Edit::<String> {
+        position: diff.range.start,
+        deleted_length: diff.range.len(),
+        inserted_text: diff.replacement.into_bytes(),
+      }
+    })
+    .collect();
+  let source = diffs.old_source.as_bytes();
+  let batch = EditBatch::new(edits, OverlapPolicy::FirstWins, source.len())
+    .expect("edits of matches are in the source");
+  let new_content = batch.apply(source);
+  String::from_utf8_lossy(&new_content).into_owned()
 }
 
 /// start_line is zero-based
@@ -132,6 +132,15 @@ fn from_env<'t>(nm: &NodeMatch<'t>) -> Option<MetaVariables<'t>> {
   })
 }
 
+/// Matches of sequence patterns end at a later sibling.
+fn get_match_range(nm: &NodeMatch) -> Range {
+  Range {
+    byte_offset: nm.range(),
+    end: get_range(nm.get_end_node()).end,
+    ..get_range(nm)
+  }
+}
+
 fn get_range(n: &Node<'_, SgLang>) -> Range {
   let start_pos = n.start_pos();
   let end_pos = n.end_pos();
@@ -163,7 +172,7 @@ impl<'t, 'b> MatchJSON<'t, 'b> {
       language: *nm.lang(),
       replacement: None,
       replacement_offsets: None,
-      range: get_range(&nm),
+      range: get_match_range(&nm),
       meta_variables: from_env(&nm),
     }
   }
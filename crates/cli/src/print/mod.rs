@@ -2,6 +2,9 @@ mod cloud_print;
 mod colored_print;
 mod interactive_print;
 mod json_print;
+mod summary_print;
+mod table_print;
+mod template_print;
 
 use crate::lang::SgLang;
 use ast_grep_config::{Fixer, RuleConfig};
@@ -19,6 +22,9 @@ use codespan_reporting::term::termcolor::ColorChoice;
 pub use colored_print::{ColoredPrinter, Heading, ReportStyle};
 pub use interactive_print::InteractivePrinter;
 pub use json_print::{JSONPrinter, JsonStyle};
+pub use summary_print::{SummaryMode, SummaryPrinter};
+pub use table_print::{TableColumn, TableFormat, TablePrinter};
+pub use template_print::{FormatTemplate, TemplateEscape, TemplatePrinter};
 
 type NodeMatch<'a> = SgNodeMatch<'a, StrDoc<SgLang>>;
 
@@ -142,6 +148,24 @@ impl<'n> Diff<'n> {
   }
 }
 
+/// Returns the text of a single, multiple or transformed metavariable.
+/// Unmatched variables are empty.
+fn get_var_text(nm: &NodeMatch, var: &str) -> String {
+  let env = nm.get_env();
+  if let Some(node) = env.get_match(var) {
+    return node.text().into_owned();
+  }
+  let nodes = env.get_multiple_matches(var);
+  if let (Some(first), Some(last)) = (nodes.first(), nodes.last()) {
+    let source = nm.root().get_text();
+    return source[first.range().start..last.range().end].to_string();
+  }
+  if let Some(bytes) = env.get_transformed(var) {
+    return String::from_utf8_lossy(bytes).into_owned();
+  }
+  String::new()
+}
+
 #[derive(ValueEnum, Clone, Copy)]
 pub enum ColorArg {
   /// Try to use colors, but don't force the issue. If the output is piped to another program,
@@ -0,0 +1,259 @@
+use super::{Diff, JsonStyle, NodeMatch, PrintProcessor, Printer};
+use crate::lang::SgLang;
+use anyhow::Result;
+use ast_grep_config::RuleConfig;
+use codespan_reporting::files::SimpleFile;
+use serde::Serialize;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{Stdout, Write};
+use std::path::Path;
+
+/// Controls what is printed per file instead of the matches themselves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SummaryMode {
+  /// Print the number of matches in every matched file.
+  Count,
+  /// Print the path of every file with at least one match.
+  FilesWithMatches,
+  /// Print the path of every searched file without any match.
+  FilesWithoutMatch,
+}
+
+/// Number of matches found in one file by a single processor call.
+/// One file can produce multiple summaries, e.g. one per rule or per injected language.
+pub struct FileSummary {
+  path: String,
+  count: usize,
+}
+
+#[derive(Serialize)]
+struct SummaryJSON<'a> {
+  file: &'a str,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  count: Option<usize>,
+}
+
+/// Prints per-file match counts or file paths, like ripgrep's `--count` and `-l`.
+///
+/// Summaries are merged by path and printed after all files are searched,
+/// in the order files are first reported.
+pub struct SummaryPrinter<W: Write> {
+  output: W,
+  mode: SummaryMode,
+  json: Option<JsonStyle>,
+  files: Vec<(String, usize)>,
+  index: HashMap<String, usize>,
+}
+
+impl SummaryPrinter<Stdout> {
+  pub fn stdout(mode: SummaryMode) -> Self {
+    Self::new(std::io::stdout(), mode)
+  }
+}
+
+impl<W: Write> SummaryPrinter<W> {
+  pub fn new(output: W, mode: SummaryMode) -> Self {
+    Self {
+      output,
+      mode,
+      json: None,
+      files: vec![],
+      index: HashMap::new(),
+    }
+  }
+
+  pub fn json(mut self, json: Option<JsonStyle>) -> Self {
+    self.json = json;
+    self
+  }
+
+  fn selected(&self) -> impl Iterator<Item = SummaryJSON<'_>> {
+    let mode = self.mode;
+    self.files.iter().filter_map(move |(file, count)| {
+      let json = match mode {
+        SummaryMode::Count if *count > 0 => SummaryJSON {
+          file,
+          count: Some(*count),
+        },
+        SummaryMode::FilesWithMatches if *count > 0 => SummaryJSON { file, count: None },
+        SummaryMode::FilesWithoutMatch if *count == 0 => SummaryJSON { file, count: None },
+        _ => return None,
+      };
+      Some(json)
+    })
+  }
+
+  fn print_json(&mut self, style: JsonStyle) -> Result<()> {
+    let docs: Vec<_> = self.selected().collect();
+    let mut output = vec![];
+    match style {
+      JsonStyle::Pretty => {
+        serde_json::to_writer_pretty(&mut output, &docs)?;
+        writeln!(output)?;
+      }
+      JsonStyle::Stream => {
+        for doc in docs {
+          serde_json::to_writer(&mut output, &doc)?;
+          writeln!(output)?;
+        }
+      }
+      JsonStyle::Compact => {
+        serde_json::to_writer(&mut output, &docs)?;
+        writeln!(output)?;
+      }
+    }
+    self.output.write_all(&output)?;
+    Ok(())
+  }
+
+  fn print_text(&mut self) -> Result<()> {
+    let mut output = vec![];
+    for doc in self.selected() {
+      if let Some(count) = doc.count {
+        writeln!(output, "{}:{count}", doc.file)?;
+      } else {
+        writeln!(output, "{}", doc.file)?;
+      }
+    }
+    self.output.write_all(&output)?;
+    Ok(())
+  }
+}
+
+impl<W: Write> Printer for SummaryPrinter<W> {
+  type Processed = FileSummary;
+  type Processor = SummaryProcessor;
+
+  fn get_processor(&self) -> SummaryProcessor {
+    SummaryProcessor
+  }
+
+  fn process(&mut self, summary: FileSummary) -> Result<()> {
+    let FileSummary { path, count } = summary;
+    if let Some(&i) = self.index.get(&path) {
+      self.files[i].1 += count;
+    } else {
+      self.index.insert(path.clone(), self.files.len());
+      self.files.push((path, count));
+    }
+    Ok(())
+  }
+
+  fn after_print(&mut self) -> Result<()> {
+    if let Some(style) = self.json {
+      self.print_json(style)
+    } else {
+      self.print_text()
+    }
+  }
+}
+
+pub struct SummaryProcessor;
+
+fn summarize(path: &Path, count: usize) -> FileSummary {
+  FileSummary {
+    path: path.to_string_lossy().into_owned(),
+    count,
+  }
+}
+
+impl PrintProcessor<FileSummary> for SummaryProcessor {
+  fn print_rule(
+    &self,
+    matches: Vec<NodeMatch>,
+    file: SimpleFile<Cow<str>, &str>,
+    _rule: &RuleConfig<SgLang>,
+  ) -> Result<FileSummary> {
+    Ok(FileSummary {
+      path: file.name().to_string(),
+      count: matches.len(),
+    })
+  }
+
+  fn print_matches(&self, matches: Vec<NodeMatch>, path: &Path) -> Result<FileSummary> {
+    Ok(summarize(path, matches.len()))
+  }
+
+  fn print_diffs(&self, diffs: Vec<Diff>, path: &Path) -> Result<FileSummary> {
+    Ok(summarize(path, diffs.len()))
+  }
+
+  fn print_rule_diffs(
+    &self,
+    diffs: Vec<(Diff, &RuleConfig<SgLang>)>,
+    path: &Path,
+  ) -> Result<FileSummary> {
+    Ok(summarize(path, diffs.len()))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use ast_grep_language::{LanguageExt, SupportLang};
+
+  fn print_files(
+    mode: SummaryMode,
+    json: Option<JsonStyle>,
+    files: &[(&str, &str)],
+    pattern: &str,
+  ) -> String {
+    let mut printer = SummaryPrinter::new(vec![], mode).json(json);
+    let processor = printer.get_processor();
+    for (path, src) in files {
+      let grep = SgLang::from(SupportLang::TypeScript).ast_grep(src);
+      let matches = grep.root().find_all(pattern).collect();
+      let summary = processor
+        .print_matches(matches, path.as_ref())
+        .expect("should print");
+      printer.process(summary).expect("should process");
+    }
+    printer.after_print().expect("should print");
+    String::from_utf8(printer.output).expect("should be utf8")
+  }
+
+  const FILES: &[(&str, &str)] = &[
+    ("a.ts", "foo(1); foo(2)"),
+    ("b.ts", "bar(1)"),
+    ("a.ts", "foo(3)"),
+  ];
+
+  #[test]
+  fn test_count() {
+    let ret = print_files(SummaryMode::Count, None, FILES, "foo($A)");
+    assert_eq!(ret, "a.ts:3\n");
+  }
+
+  #[test]
+  fn test_files_with_matches() {
+    let ret = print_files(SummaryMode::FilesWithMatches, None, FILES, "$F(1)");
+    assert_eq!(ret, "a.ts\nb.ts\n");
+    let ret = print_files(SummaryMode::FilesWithoutMatch, None, FILES, "bar($A)");
+    assert_eq!(ret, "a.ts\n");
+  }
+
+  #[test]
+  fn test_json() {
+    let ret = print_files(
+      SummaryMode::Count,
+      Some(JsonStyle::Compact),
+      FILES,
+      "$F($A)",
+    );
+    assert_eq!(
+      ret,
+      "[{\"file\":\"a.ts\",\"count\":3},{\"file\":\"b.ts\",\"count\":1}]\n"
+    );
+    let ret = print_files(
+      SummaryMode::FilesWithoutMatch,
+      Some(JsonStyle::Stream),
+      FILES,
+      "bar($A)",
+    );
+    assert_eq!(ret, "{\"file\":\"a.ts\"}\n");
+    let ret = print_files(SummaryMode::Count, Some(JsonStyle::Pretty), FILES, "baz()");
+    assert_eq!(ret, "[]\n");
+  }
+}
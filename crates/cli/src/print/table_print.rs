@@ -0,0 +1,460 @@
+use super::{get_var_text, Diff, NodeMatch, PrintProcessor, Printer};
+use crate::lang::SgLang;
+use anyhow::{bail, Result};
+use ast_grep_config::RuleConfig;
+use clap::ValueEnum;
+use codespan_reporting::files::SimpleFile;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{Stdout, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// A column in the extracted table.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum TableColumn {
+  /// Path of the matched file.
+  File,
+  /// One-based start line of the match.
+  Line,
+  /// One-based start column of the match.
+  Column,
+  /// One-based end line of the match.
+  EndLine,
+  /// One-based end column of the match.
+  EndColumn,
+  /// `line:column-line:column` range of the match.
+  Range,
+  /// Full text of the match.
+  Text,
+  /// Id of the rule that reports the match.
+  Rule,
+  /// Text of a metavariable or transformed variable.
+  Var(String),
+}
+
+impl FromStr for TableColumn {
+  type Err = String;
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Ok(match s {
+      "file" => TableColumn::File,
+      "line" => TableColumn::Line,
+      "column" => TableColumn::Column,
+      "end_line" => TableColumn::EndLine,
+      "end_column" => TableColumn::EndColumn,
+      "range" => TableColumn::Range,
+      "text" => TableColumn::Text,
+      "rule" => TableColumn::Rule,
+      _ => {
+        let var = s.trim_start_matches('$');
+        let is_valid = var
+          .chars()
+          .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+        if var.is_empty() || !is_valid {
+          return Err(format!(
+            "`{s}` is neither a builtin column nor a metavariable like $NAME"
+          ));
+        }
+        TableColumn::Var(var.to_string())
+      }
+    })
+  }
+}
+
+impl fmt::Display for TableColumn {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let name = match self {
+      TableColumn::File => "file",
+      TableColumn::Line => "line",
+      TableColumn::Column => "column",
+      TableColumn::EndLine => "end_line",
+      TableColumn::EndColumn => "end_column",
+      TableColumn::Range => "range",
+      TableColumn::Text => "text",
+      TableColumn::Rule => "rule",
+      TableColumn::Var(var) => var,
+    };
+    write!(f, "{name}")
+  }
+}
+
+impl TableColumn {
+  fn extract(&self, nm: &NodeMatch, path: &str, rule: Option<&str>) -> String {
+    let start = nm.start_pos();
+    let end = nm.end_pos();
+    match self {
+      TableColumn::File => path.to_string(),
+      TableColumn::Line => (start.line() + 1).to_string(),
+      TableColumn::Column => (start.column(nm) + 1).to_string(),
+      TableColumn::EndLine => (end.line() + 1).to_string(),
+      TableColumn::EndColumn => (end.column(nm) + 1).to_string(),
+      TableColumn::Range => format!(
+        "{}:{}-{}:{}",
+        start.line() + 1,
+        start.column(nm) + 1,
+        end.line() + 1,
+        end.column(nm) + 1
+      ),
+      TableColumn::Text => nm.text().into_owned(),
+      TableColumn::Rule => rule.unwrap_or_default().to_string(),
+      TableColumn::Var(var) => get_var_text(nm, var),
+    }
+  }
+}
+
+/// Controls how the extracted table is formatted.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TableFormat {
+  /// Comma-separated values with a header row, quoted as in RFC 4180.
+  Csv,
+  /// Tab-separated values with a header row. Tabs and newlines in values are escaped.
+  Tsv,
+  /// One JSON object per row, keyed by column name.
+  Ndjson,
+}
+
+impl TableFormat {
+  fn write_row<W: Write>(
+    &self,
+    output: &mut W,
+    header: &[String],
+    row: &[String],
+    count: Option<usize>,
+  ) -> Result<()> {
+    let count = count.map(|c| c.to_string());
+    let fields = row.iter().map(String::as_str).chain(count.as_deref());
+    match self {
+      TableFormat::Csv => {
+        let fields: Vec<_> = fields.map(escape_csv).collect();
+        writeln!(output, "{}", fields.join(","))?;
+      }
+      TableFormat::Tsv => {
+        let fields: Vec<_> = fields.map(escape_tsv).collect();
+        writeln!(output, "{}", fields.join("\t"))?;
+      }
+      TableFormat::Ndjson => {
+        write!(output, "{{")?;
+        for (i, (key, value)) in header.iter().zip(row).enumerate() {
+          if i > 0 {
+            write!(output, ",")?;
+          }
+          serde_json::to_writer(&mut *output, key)?;
+          write!(output, ":")?;
+          serde_json::to_writer(&mut *output, value)?;
+        }
+        // count is the only numeric column
+        if let Some(count) = count {
+          write!(output, ",\"count\":{count}")?;
+        }
+        writeln!(output, "}}")?;
+      }
+    }
+    Ok(())
+  }
+}
+
+fn escape_csv(field: &str) -> Cow<'_, str> {
+  if field.contains([',', '"', '\n', '\r']) {
+    Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+  } else {
+    Cow::Borrowed(field)
+  }
+}
+
+fn escape_tsv(field: &str) -> Cow<'_, str> {
+  if !field.contains(['\\', '\t', '\n', '\r']) {
+    return Cow::Borrowed(field);
+  }
+  let mut ret = String::with_capacity(field.len());
+  for c in field.chars() {
+    match c {
+      '\\' => ret.push_str("\\\\"),
+      '\t' => ret.push_str("\\t"),
+      '\n' => ret.push_str("\\n"),
+      '\r' => ret.push_str("\\r"),
+      c => ret.push(c),
+    }
+  }
+  Cow::Owned(ret)
+}
+
+type Row = Vec<String>;
+
+/// Prints matches as rows of selected columns.
+/// Rows are streamed unless they are aggregated by counting.
+pub struct TablePrinter<W: Write> {
+  output: W,
+  format: TableFormat,
+  columns: Vec<TableColumn>,
+  count: bool,
+  // rows in first-seen order and their count
+  groups: Vec<(Row, usize)>,
+  group_index: HashMap<Row, usize>,
+}
+
+impl TablePrinter<Stdout> {
+  pub fn stdout(format: TableFormat, columns: Vec<TableColumn>) -> Result<Self> {
+    Self::new(std::io::stdout(), format, columns)
+  }
+}
+
+impl<W: Write> TablePrinter<W> {
+  pub fn new(output: W, format: TableFormat, columns: Vec<TableColumn>) -> Result<Self> {
+    if columns.is_empty() {
+      bail!("at least one column is required");
+    }
+    Ok(Self {
+      output,
+      format,
+      columns,
+      count: false,
+      groups: vec![],
+      group_index: HashMap::new(),
+    })
+  }
+
+  /// Group identical rows and append a `count` column.
+  pub fn count(mut self, count: bool) -> Self {
+    self.count = count;
+    self
+  }
+
+  fn header(&self) -> Vec<String> {
+    let mut header: Vec<_> = self.columns.iter().map(|c| c.to_string()).collect();
+    if self.count {
+      header.push("count".into());
+    }
+    header
+  }
+}
+
+impl<W: Write> Printer for TablePrinter<W> {
+  type Processed = Vec<Row>;
+  type Processor = TableProcessor;
+
+  fn get_processor(&self) -> TableProcessor {
+    TableProcessor {
+      columns: self.columns.clone(),
+    }
+  }
+
+  fn process(&mut self, rows: Vec<Row>) -> Result<()> {
+    if !self.count {
+      let header = self.header();
+      for row in rows {
+        self
+          .format
+          .write_row(&mut self.output, &header, &row, None)?;
+      }
+      return Ok(());
+    }
+    for row in rows {
+      if let Some(&i) = self.group_index.get(&row) {
+        self.groups[i].1 += 1;
+      } else {
+        self.group_index.insert(row.clone(), self.groups.len());
+        self.groups.push((row, 1));
+      }
+    }
+    Ok(())
+  }
+
+  fn before_print(&mut self) -> Result<()> {
+    if self.format == TableFormat::Ndjson {
+      return Ok(());
+    }
+    let header = self.header();
+    self
+      .format
+      .write_row(&mut self.output, &header, &header, None)
+  }
+
+  fn after_print(&mut self) -> Result<()> {
+    if !self.count {
+      return Ok(());
+    }
+    let header = self.header();
+    let mut groups = std::mem::take(&mut self.groups);
+    // stable sort keeps first-seen order for equal counts
+    groups.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    for (row, count) in groups {
+      self
+        .format
+        .write_row(&mut self.output, &header, &row, Some(count))?;
+    }
+    Ok(())
+  }
+}
+
+pub struct TableProcessor {
+  columns: Vec<TableColumn>,
+}
+
+impl TableProcessor {
+  fn to_row(&self, nm: &NodeMatch, path: &str, rule: Option<&str>) -> Row {
+    self
+      .columns
+      .iter()
+      .map(|c| c.extract(nm, path, rule))
+      .collect()
+  }
+}
+
+impl PrintProcessor<Vec<Row>> for TableProcessor {
+  fn print_rule(
+    &self,
+    matches: Vec<NodeMatch>,
+    file: SimpleFile<Cow<str>, &str>,
+    rule: &RuleConfig<SgLang>,
+  ) -> Result<Vec<Row>> {
+    let path = file.name();
+    let rows = matches
+      .iter()
+      .map(|nm| self.to_row(nm, path, Some(&rule.id)));
+    Ok(rows.collect())
+  }
+
+  fn print_matches(&self, matches: Vec<NodeMatch>, path: &Path) -> Result<Vec<Row>> {
+    let path = path.to_string_lossy();
+    let rows = matches.iter().map(|nm| self.to_row(nm, &path, None));
+    Ok(rows.collect())
+  }
+
+  fn print_diffs(&self, diffs: Vec<Diff>, path: &Path) -> Result<Vec<Row>> {
+    let path = path.to_string_lossy();
+    let rows = diffs
+      .iter()
+      .map(|diff| self.to_row(&diff.node_match, &path, None));
+    Ok(rows.collect())
+  }
+
+  fn print_rule_diffs(
+    &self,
+    diffs: Vec<(Diff, &RuleConfig<SgLang>)>,
+    path: &Path,
+  ) -> Result<Vec<Row>> {
+    let path = path.to_string_lossy();
+    let rows = diffs
+      .iter()
+      .map(|(diff, rule)| self.to_row(&diff.node_match, &path, Some(&rule.id)));
+    Ok(rows.collect())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use ast_grep_language::{LanguageExt, SupportLang};
+
+  fn columns(s: &str) -> Vec<TableColumn> {
+    s.split(',')
+      .map(|c| c.parse().expect("valid column"))
+      .collect()
+  }
+
+  fn print(source: &str, pattern: &str, cols: &str, format: TableFormat, count: bool) -> String {
+    let lang = SgLang::from(SupportLang::TypeScript);
+    let grep = lang.ast_grep(source);
+    let matches = grep.root().find_all(pattern).collect();
+    let mut printer = TablePrinter::new(vec![], format, columns(cols))
+      .unwrap()
+      .count(count);
+    printer.before_print().unwrap();
+    let rows = printer
+      .get_processor()
+      .print_matches(matches, "test.ts".as_ref())
+      .unwrap();
+    printer.process(rows).unwrap();
+    printer.after_print().unwrap();
+    String::from_utf8(printer.output).unwrap()
+  }
+
+  const SRC: &str = "
+analytics.track('click', 1)
+analytics.track('view', 2)
+tracker.track('click', a, b)";
+
+  #[test]
+  fn test_parse_column() {
+    assert_eq!("file".parse::<TableColumn>(), Ok(TableColumn::File));
+    assert_eq!(
+      "$OBJ".parse::<TableColumn>(),
+      Ok(TableColumn::Var("OBJ".into()))
+    );
+    assert_eq!(
+      "$$$ARGS".parse::<TableColumn>(),
+      Ok(TableColumn::Var("ARGS".into()))
+    );
+    assert_eq!(
+      "EVENT".parse::<TableColumn>(),
+      Ok(TableColumn::Var("EVENT".into()))
+    );
+    assert!("$".parse::<TableColumn>().is_err());
+    assert!("unknown".parse::<TableColumn>().is_err());
+  }
+
+  #[test]
+  fn test_csv() {
+    let ret = print(
+      SRC,
+      "$OBJ.track($EVENT, $$$ARGS)",
+      "file,line,$OBJ,$EVENT,$$$ARGS",
+      TableFormat::Csv,
+      false,
+    );
+    let expected = "\
+file,line,OBJ,EVENT,ARGS
+test.ts,2,analytics,'click',1
+test.ts,3,analytics,'view',2
+test.ts,4,tracker,'click',\"a, b\"
+";
+    assert_eq!(ret, expected);
+  }
+
+  #[test]
+  fn test_tsv() {
+    let ret = print(
+      "foo('a\\tb')\nfoo(`x\ny`)",
+      "foo($A)",
+      "range,$A",
+      TableFormat::Tsv,
+      false,
+    );
+    let expected = "range\tA\n1:1-1:12\t'a\\\\tb'\n2:1-3:4\t`x\\ny`\n";
+    assert_eq!(ret, expected);
+  }
+
+  #[test]
+  fn test_ndjson() {
+    let ret = print(
+      SRC,
+      "$OBJ.track('view', $$$)",
+      "$OBJ,text",
+      TableFormat::Ndjson,
+      false,
+    );
+    assert_eq!(
+      ret,
+      "{\"OBJ\":\"analytics\",\"text\":\"analytics.track('view', 2)\"}\n"
+    );
+  }
+
+  #[test]
+  fn test_count() {
+    let ret = print(
+      SRC,
+      "$OBJ.track($EVENT, $$$)",
+      "$EVENT",
+      TableFormat::Csv,
+      true,
+    );
+    assert_eq!(ret, "EVENT,count\n'click',2\n'view',1\n");
+    let ret = print(SRC, "$OBJ.track($$$)", "$OBJ", TableFormat::Ndjson, true);
+    assert_eq!(
+      ret,
+      "{\"OBJ\":\"analytics\",\"count\":2}\n{\"OBJ\":\"tracker\",\"count\":1}\n"
+    );
+  }
+}
@@ -0,0 +1,435 @@
+use super::{get_var_text, Diff, NodeMatch, PrintProcessor, Printer};
+use crate::lang::SgLang;
+use anyhow::Result;
+use ast_grep_config::{RuleConfig, Severity};
+use clap::ValueEnum;
+use codespan_reporting::files::SimpleFile;
+
+use std::borrow::Cow;
+use std::io::{Stdout, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// A value that can be interpolated in `--format-template`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Placeholder {
+  File,
+  Text,
+  Language,
+  StartLine,
+  StartColumn,
+  EndLine,
+  EndColumn,
+  StartOffset,
+  EndOffset,
+  Rule,
+  Severity,
+  Message,
+  Note,
+  Replacement,
+  Var(String),
+}
+
+impl FromStr for Placeholder {
+  type Err = String;
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    use Placeholder as P;
+    Ok(match s {
+      "file" => P::File,
+      "text" => P::Text,
+      "language" => P::Language,
+      "start.line" => P::StartLine,
+      "start.column" => P::StartColumn,
+      "end.line" => P::EndLine,
+      "end.column" => P::EndColumn,
+      "offset.start" => P::StartOffset,
+      "offset.end" => P::EndOffset,
+      "rule" => P::Rule,
+      "severity" => P::Severity,
+      "message" => P::Message,
+      "note" => P::Note,
+      "replacement" => P::Replacement,
+      _ if s.starts_with('$') => {
+        let var = s.trim_start_matches('$');
+        if var.is_empty() {
+          return Err(format!("`{{{s}}}` has no metavariable name"));
+        }
+        P::Var(var.to_string())
+      }
+      _ => return Err(format!("`{{{s}}}` is not a known template placeholder")),
+    })
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+  Literal(String),
+  Placeholder(Placeholder),
+}
+
+/// A parsed `--format-template`.
+///
+/// Placeholders are wrapped in braces like `{file}` or `{$NAME}`.
+/// Use `{{` and `}}` for literal braces.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormatTemplate(Vec<Segment>);
+
+impl FromStr for FormatTemplate {
+  type Err = String;
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut segments = vec![];
+    let mut literal = String::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+      match c {
+        '{' if chars.peek() == Some(&'{') => {
+          chars.next();
+          literal.push('{');
+        }
+        '}' if chars.peek() == Some(&'}') => {
+          chars.next();
+          literal.push('}');
+        }
+        '{' => {
+          let mut name = String::new();
+          let mut closed = false;
+          for c in chars.by_ref() {
+            if c == '}' {
+              closed = true;
+              break;
+            }
+            name.push(c);
+          }
+          if !closed {
+            return Err(format!("unclosed placeholder `{{{name}`"));
+          }
+          if !literal.is_empty() {
+            segments.push(Segment::Literal(std::mem::take(&mut literal)));
+          }
+          segments.push(Segment::Placeholder(name.trim().parse()?));
+        }
+        '}' => return Err("unmatched `}`, use `}}` for a literal brace".into()),
+        c => literal.push(c),
+      }
+    }
+    if !literal.is_empty() {
+      segments.push(Segment::Literal(literal));
+    }
+    Ok(Self(segments))
+  }
+}
+
+/// Controls how interpolated values are escaped in a format template.
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum TemplateEscape {
+  /// Insert values verbatim.
+  #[default]
+  None,
+  /// Quote every value as a single POSIX shell word.
+  Shell,
+  /// Escape values as the content of a JSON string, without surrounding quotes.
+  Json,
+}
+
+impl TemplateEscape {
+  fn escape<'a>(&self, value: &'a str) -> Cow<'a, str> {
+    match self {
+      TemplateEscape::None => Cow::Borrowed(value),
+      TemplateEscape::Shell => Cow::Owned(format!("'{}'", value.replace('\'', r"'\''"))),
+      TemplateEscape::Json => {
+        let quoted = serde_json::to_string(value).expect("string must serialize");
+        Cow::Owned(quoted[1..quoted.len() - 1].to_string())
+      }
+    }
+  }
+}
+
+/// Every value a template can refer to for one match.
+struct TemplateContext<'a, 'n> {
+  nm: &'a NodeMatch<'n>,
+  path: &'a str,
+  rule: Option<&'a RuleConfig<SgLang>>,
+  replacement: Option<&'a str>,
+}
+
+impl TemplateContext<'_, '_> {
+  fn get(&self, placeholder: &Placeholder) -> String {
+    use Placeholder as P;
+    let nm = self.nm;
+    match placeholder {
+      P::File => self.path.to_string(),
+      P::Text => nm.text().into_owned(),
+      P::Language => nm.lang().to_string(),
+      P::StartLine => (nm.start_pos().line() + 1).to_string(),
+      P::StartColumn => (nm.start_pos().column(nm) + 1).to_string(),
+      P::EndLine => (nm.end_pos().line() + 1).to_string(),
+      P::EndColumn => (nm.end_pos().column(nm) + 1).to_string(),
+      P::StartOffset => nm.range().start.to_string(),
+      P::EndOffset => nm.range().end.to_string(),
+      P::Rule => self.rule.map(|r| r.id.clone()).unwrap_or_default(),
+      P::Severity => self.rule.map(severity_name).unwrap_or_default().into(),
+      P::Message => self.rule.map(|r| r.get_message(nm)).unwrap_or_default(),
+      P::Note => self.rule.and_then(|r| r.note.clone()).unwrap_or_default(),
+      P::Replacement => self.replacement.unwrap_or_default().to_string(),
+      P::Var(var) => get_var_text(nm, var),
+    }
+  }
+}
+
+fn severity_name(rule: &RuleConfig<SgLang>) -> &'static str {
+  match rule.severity {
+    Severity::Error => "error",
+    Severity::Warning => "warning",
+    Severity::Info => "info",
+    Severity::Hint => "hint",
+    Severity::Off => "off",
+  }
+}
+
+/// Prints every match as one line rendered from a user provided template.
+pub struct TemplatePrinter<W: Write> {
+  output: W,
+  template: FormatTemplate,
+  escape: TemplateEscape,
+}
+
+impl TemplatePrinter<Stdout> {
+  pub fn stdout(template: FormatTemplate) -> Self {
+    Self::new(std::io::stdout(), template)
+  }
+}
+
+impl<W: Write> TemplatePrinter<W> {
+  pub fn new(output: W, template: FormatTemplate) -> Self {
+    Self {
+      output,
+      template,
+      escape: TemplateEscape::None,
+    }
+  }
+
+  pub fn escape(mut self, escape: TemplateEscape) -> Self {
+    self.escape = escape;
+    self
+  }
+}
+
+impl<W: Write> Printer for TemplatePrinter<W> {
+  type Processed = Buffer;
+  type Processor = TemplateProcessor;
+
+  fn get_processor(&self) -> TemplateProcessor {
+    TemplateProcessor {
+      template: self.template.clone(),
+      escape: self.escape,
+    }
+  }
+
+  fn process(&mut self, buffer: Buffer) -> Result<()> {
+    self.output.write_all(&buffer)?;
+    Ok(())
+  }
+}
+
+type Buffer = Vec<u8>;
+
+pub struct TemplateProcessor {
+  template: FormatTemplate,
+  escape: TemplateEscape,
+}
+
+impl TemplateProcessor {
+  fn render(&self, ctx: &TemplateContext, output: &mut Buffer) -> Result<()> {
+    for segment in &self.template.0 {
+      match segment {
+        Segment::Literal(s) => output.write_all(s.as_bytes())?,
+        Segment::Placeholder(p) => {
+          let value = ctx.get(p);
+          output.write_all(self.escape.escape(&value).as_bytes())?;
+        }
+      }
+    }
+    writeln!(output)?;
+    Ok(())
+  }
+}
+
+impl PrintProcessor<Buffer> for TemplateProcessor {
+  fn print_rule(
+    &self,
+    matches: Vec<NodeMatch>,
+    file: SimpleFile<Cow<str>, &str>,
+    rule: &RuleConfig<SgLang>,
+  ) -> Result<Buffer> {
+    let mut ret = vec![];
+    for nm in &matches {
+      let ctx = TemplateContext {
+        nm,
+        path: file.name(),
+        rule: Some(rule),
+        replacement: None,
+      };
+      self.render(&ctx, &mut ret)?;
+    }
+    Ok(ret)
+  }
+
+  fn print_matches(&self, matches: Vec<NodeMatch>, path: &Path) -> Result<Buffer> {
+    let path = path.to_string_lossy();
+    let mut ret = vec![];
+    for nm in &matches {
+      let ctx = TemplateContext {
+        nm,
+        path: &path,
+        rule: None,
+        replacement: None,
+      };
+      self.render(&ctx, &mut ret)?;
+    }
+    Ok(ret)
+  }
+
+  fn print_diffs(&self, diffs: Vec<Diff>, path: &Path) -> Result<Buffer> {
+    let path = path.to_string_lossy();
+    let mut ret = vec![];
+    for diff in &diffs {
+      let ctx = TemplateContext {
+        nm: &diff.node_match,
+        path: &path,
+        rule: None,
+        replacement: Some(&diff.replacement),
+      };
+      self.render(&ctx, &mut ret)?;
+    }
+    Ok(ret)
+  }
+
+  fn print_rule_diffs(
+    &self,
+    diffs: Vec<(Diff, &RuleConfig<SgLang>)>,
+    path: &Path,
+  ) -> Result<Buffer> {
+    let path = path.to_string_lossy();
+    let mut ret = vec![];
+    for (diff, rule) in &diffs {
+      let ctx = TemplateContext {
+        nm: &diff.node_match,
+        path: &path,
+        rule: Some(rule),
+        replacement: Some(&diff.replacement),
+      };
+      self.render(&ctx, &mut ret)?;
+    }
+    Ok(ret)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use ast_grep_config::{from_yaml_string, Fixer, GlobalRules};
+  use ast_grep_language::{LanguageExt, SupportLang};
+
+  fn make_printer(template: &str, escape: TemplateEscape) -> TemplatePrinter<Buffer> {
+    let template = template.parse().expect("should parse");
+    TemplatePrinter::new(vec![], template).escape(escape)
+  }
+
+  fn get_text(printer: TemplatePrinter<Buffer>) -> String {
+    String::from_utf8(printer.output).expect("should be utf8")
+  }
+
+  fn print_matches(template: &str, escape: TemplateEscape, src: &str, pattern: &str) -> String {
+    let grep = SgLang::from(SupportLang::TypeScript).ast_grep(src);
+    let matches = grep.root().find_all(pattern).collect();
+    let mut printer = make_printer(template, escape);
+    let buffer = printer
+      .get_processor()
+      .print_matches(matches, "test.ts".as_ref())
+      .expect("should print");
+    printer.process(buffer).expect("should process");
+    get_text(printer)
+  }
+
+  #[test]
+  fn test_parse_template() {
+    assert!("{file}:{start.line}".parse::<FormatTemplate>().is_ok());
+    assert!("{$A} {$$$ARGS}".parse::<FormatTemplate>().is_ok());
+    assert!("{{literal}}".parse::<FormatTemplate>().is_ok());
+    assert!("{unknown}".parse::<FormatTemplate>().is_err());
+    assert!("{file".parse::<FormatTemplate>().is_err());
+    assert!("file}".parse::<FormatTemplate>().is_err());
+    assert!("{$}".parse::<FormatTemplate>().is_err());
+  }
+
+  #[test]
+  fn test_print_matches() {
+    let ret = print_matches(
+      "{file}:{start.line}:{start.column}: {$A} {{{text}}}",
+      TemplateEscape::None,
+      "let a = 1\nfoo(a)\nfoo(b, c)",
+      "foo($A)",
+    );
+    assert_eq!(ret, "test.ts:2:1: a {foo(a)}\n");
+  }
+
+  #[test]
+  fn test_multi_metavar() {
+    let ret = print_matches(
+      "{$$$ARGS}|{$MISSING}|{end.line}:{end.column}",
+      TemplateEscape::None,
+      "foo(a, b)",
+      "foo($$$ARGS)",
+    );
+    assert_eq!(ret, "a, b||1:10\n");
+  }
+
+  #[test]
+  fn test_escape() {
+    let src = "log('it\\'s')";
+    let ret = print_matches("echo {$A}", TemplateEscape::Shell, src, "log($A)");
+    assert_eq!(ret, "echo ''\\''it\\'\\''s'\\'''\n");
+    let ret = print_matches(
+      "{{\"v\": \"{$A}\"}}",
+      TemplateEscape::Json,
+      "log(\"a\")",
+      "log($A)",
+    );
+    assert_eq!(ret, "{\"v\": \"\\\"a\\\"\"}\n");
+  }
+
+  #[test]
+  fn test_print_rule() {
+    let globals = GlobalRules::default();
+    let rule = from_yaml_string::<SgLang>(
+      r"
+id: no-foo
+message: Do not call foo with $A
+severity: warning
+language: TypeScript
+rule:
+  pattern: foo($A)
+fix: bar($A)
+",
+      &globals,
+    )
+    .expect("should parse")
+    .pop()
+    .unwrap();
+    let grep = SgLang::from(SupportLang::TypeScript).ast_grep("foo(123)");
+    let template = "{file}: [{rule}] {severity} {message} -> {replacement}";
+    let mut printer = make_printer(template, TemplateEscape::None);
+    let nm = grep.root().find(&rule.matcher).expect("should match");
+    let fixer = Fixer::from_str("bar($A)", &SgLang::from(SupportLang::TypeScript)).unwrap();
+    let diff = Diff::generate(nm, &rule.matcher, &fixer);
+    let buffer = printer
+      .get_processor()
+      .print_rule_diffs(vec![(diff, &rule)], "a.ts".as_ref())
+      .unwrap();
+    printer.process(buffer).unwrap();
+    assert_eq!(
+      get_text(printer),
+      "a.ts: [no-foo] warning Do not call foo with 123 -> bar(123)\n"
+    );
+  }
+}
@@ -11,6 +11,7 @@ use crate::config::ProjectConfig;
 use crate::lang::SgLang;
 use crate::print::{
   ColoredPrinter, Diff, Heading, InteractivePrinter, JSONPrinter, PrintProcessor, Printer,
+  SummaryPrinter, TemplatePrinter,
 };
 use crate::utils::ErrorContext as EC;
 use crate::utils::{filter_file_pattern, ContextArgs, InputArgs, MatchUnit, OutputArgs};
@@ -27,7 +28,7 @@ fn lang_help() -> String {
 const LANG_HELP_LONG: &str = "The language of the pattern. For full language list, visit https://ast-grep.github.io/reference/languages.html";
 
 #[derive(Clone)]
-struct Strictness(MatchStrictness);
+pub struct Strictness(pub MatchStrictness);
 impl ValueEnum for Strictness {
   fn value_variants<'a>() -> &'a [Self] {
     use MatchStrictness as M;
@@ -38,6 +39,7 @@ impl ValueEnum for Strictness {
       Strictness(M::Relaxed),
       Strictness(M::Signature),
       Strictness(M::Template),
+      Strictness(M::Normalized),
     ]
   }
   fn to_possible_value(&self) -> Option<PossibleValue> {
@@ -52,6 +54,8 @@ impl ValueEnum for Strictness {
       }
       M::Template => PossibleValue::new("template")
         .help("Similar to smart but match text only, node kinds are ignored"),
+      M::Normalized => PossibleValue::new("normalized")
+        .help("Similar to smart but ignore redundant parentheses and commutative operand order"),
     })
   }
 }
@@ -150,6 +154,14 @@ pub fn run_with_pattern(arg: RunArg, project: Result<ProjectConfig>) -> Result<(
   let proj = arg.output.inspect.project_trace();
   proj.print_project(&project)?;
   let context = arg.context.get();
+  if let Some(mode) = arg.output.summary_mode() {
+    let printer = SummaryPrinter::stdout(mode).json(arg.output.json);
+    return run_pattern_with_printer(arg, printer);
+  }
+  if let Some(template) = arg.output.format_template.clone() {
+    let printer = TemplatePrinter::stdout(template).escape(arg.output.template_escape);
+    return run_pattern_with_printer(arg, printer);
+  }
   if let Some(json) = arg.output.json {
     let printer = JSONPrinter::stdout(json).context(context);
     return run_pattern_with_printer(arg, printer);
@@ -236,11 +248,14 @@ impl PathWorker for RunWithInferredLang {
           eprintln!("╰▻ {e}");
           None
         });
-      let Some(processed) = match_one_file(processor, &unit, &rewrite)? else {
+      let Some(processed) = match_one_file(processor, &unit, &rewrite, &self.arg.output)? else {
         continue;
       };
       ret.push(processed);
     }
+    if ret.is_empty() && self.arg.output.files_without_match {
+      ret.push(processor.print_matches(vec![], path)?);
+    }
     Ok(ret)
   }
 }
@@ -318,11 +333,14 @@ impl PathWorker for RunWithSpecificLang {
     let filtered = filter_file_pattern(path, path_lang, root_matcher, &sub_matchers)?;
     let mut ret = Vec::with_capacity(filtered.len());
     for unit in filtered {
-      let Some(processed) = match_one_file(processor, &unit, &self.rewrite)? else {
+      let Some(processed) = match_one_file(processor, &unit, &self.rewrite, &arg.output)? else {
         continue;
       };
       ret.push(processed);
     }
+    if ret.is_empty() && arg.output.files_without_match {
+      ret.push(processor.print_matches(vec![], path)?);
+    }
     Ok(ret)
   }
 }
@@ -336,12 +354,16 @@ impl StdInWorker for RunWithSpecificLang {
     let lang = self.arg.lang.expect("must present");
     let grep = lang.ast_grep(src);
     let root = grep.root();
-    let mut matches = root.find_all(&self.pattern).peekable();
+    let max_count = self.arg.output.max_count.unwrap_or(usize::MAX);
+    let mut matches = root.find_all(&self.pattern).take(max_count).peekable();
+    let path = Path::new("STDIN");
     if matches.peek().is_none() {
+      if self.arg.output.files_without_match {
+        return Ok(vec![processor.print_matches(vec![], path)?]);
+      }
       return Ok(vec![]);
     }
     let rewrite = &self.rewrite;
-    let path = Path::new("STDIN");
     let processed = if let Some(rewrite) = rewrite {
       let diffs = matches.map(|m| Diff::generate(m, &self.pattern, rewrite));
       processor.print_diffs(diffs.collect(), path)?
@@ -355,6 +377,7 @@ fn match_one_file<T, P: PrintProcessor<T>>(
   processor: &P,
   match_unit: &MatchUnit<impl Matcher>,
   rewrite: &Option<Fixer>,
+  output: &OutputArgs,
 ) -> Result<Option<T>> {
   let MatchUnit {
     path,
@@ -363,7 +386,9 @@ fn match_one_file<T, P: PrintProcessor<T>>(
   } = match_unit;
 
   let root = grep.root();
-  let mut matches = root.find_all(matcher).peekable();
+  // find_all is lazy so take stops the traversal after max_count matches
+  let max_count = output.max_count.unwrap_or(usize::MAX);
+  let mut matches = root.find_all(matcher).take(max_count).peekable();
   if matches.peek().is_none() {
     return Ok(None);
   }
@@ -404,6 +429,12 @@ mod test {
         color: ColorArg::Never,
         interactive: false,
         json: None,
+        format_template: None,
+        template_escape: Default::default(),
+        count: false,
+        files_with_matches: false,
+        files_without_match: false,
+        max_count: None,
         update_all: false,
         inspect: Default::default(),
       },
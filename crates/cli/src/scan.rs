@@ -1,8 +1,10 @@
 use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
-use ast_grep_config::{from_yaml_string, CombinedScan, RuleCollection, RuleConfig, Severity};
-use ast_grep_core::{tree_sitter::StrDoc, NodeMatch};
+use ast_grep_config::{
+  from_yaml_string, BudgetExceeded, CombinedScan, RuleCollection, RuleConfig, ScanBudget, Severity,
+};
+use ast_grep_core::{tree_sitter::StrDoc, AstGrep, NodeMatch};
 use ast_grep_language::SupportLang;
 use clap::Args;
 use ignore::WalkParallel;
@@ -11,16 +13,19 @@ use crate::config::{read_rule_file, with_rule_stats, ProjectConfig};
 use crate::lang::SgLang;
 use crate::print::{
   CloudPrinter, ColoredPrinter, Diff, InteractivePrinter, JSONPrinter, Platform, PrintProcessor,
-  Printer, ReportStyle, SimpleFile,
+  Printer, ReportStyle, SimpleFile, SummaryPrinter, TemplatePrinter,
 };
 use crate::utils::ErrorContext as EC;
 use crate::utils::RuleOverwrite;
-use crate::utils::{filter_file_rule, ContextArgs, InputArgs, OutputArgs, OverwriteArgs};
+use crate::utils::{
+  filter_file_rule, ContextArgs, InputArgs, OutputArgs, OverwriteArgs, Prefilter,
+};
 use crate::utils::{FileTrace, ScanTrace};
 use crate::utils::{Items, PathWorker, StdInWorker, Worker};
 
 use std::collections::HashSet;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
 
 #[derive(Args)]
 pub struct ScanArg {
@@ -41,7 +46,12 @@ pub struct ScanArg {
   /// Output warning/error messages in GitHub Action format.
   ///
   /// Currently, only GitHub is supported.
-  #[clap(long, conflicts_with = "json", conflicts_with = "interactive")]
+  #[clap(
+    long,
+    conflicts_with = "json",
+    conflicts_with = "interactive",
+    conflicts_with = "format_template"
+  )]
   format: Option<Platform>,
 
   #[clap(long, default_value = "rich", conflicts_with = "json")]
@@ -53,6 +63,19 @@ pub struct ScanArg {
   #[clap(long, requires = "json")]
   include_metadata: bool,
 
+  /// Abort scanning a file after rules spend MILLISECONDS on it.
+  ///
+  /// Matches of the aborted file are dropped and a `budget-exceeded` diagnostic naming the rule is reported.
+  #[clap(long, value_name = "MILLISECONDS")]
+  file_timeout: Option<u64>,
+
+  /// Abort scanning a file after relational rules visit NUM nodes in it.
+  ///
+  /// Rules like `has` or `inside` with `stopBy: end` visit many nodes on large files.
+  /// Matches of the aborted file are dropped and a `budget-exceeded` diagnostic naming the rule is reported.
+  #[clap(long, value_name = "NUM")]
+  max_node_visits: Option<usize>,
+
   /// severity related options
   #[clap(flatten)]
   overwrite: OverwriteArgs,
@@ -73,6 +96,30 @@ impl ScanArg {
   fn include_all_rules(&self) -> bool {
     self.overwrite.include_all_rules() && self.rule.is_none() && self.inline_rules.is_none()
   }
+
+  fn budget(&self) -> ScanBudget {
+    ScanBudget {
+      timeout: self.file_timeout.map(Duration::from_millis),
+      max_node_visits: self.max_node_visits,
+      cancellation: None,
+    }
+  }
+}
+
+/// Report the aborted file as a diagnostic so it shows up in every output format.
+fn match_budget_exceeded<T>(
+  path: &Path,
+  grep: &AstGrep<StrDoc<SgLang>>,
+  exceeded: &BudgetExceeded,
+  severity: Severity,
+  processor: &impl PrintProcessor<T>,
+) -> Result<T> {
+  let rule = CombinedScan::budget_config(exceeded, severity.clone(), *grep.lang());
+  // report the first token instead of the whole file, which is usually large
+  let first = grep.root().dfs().find(|n| n.is_leaf());
+  let node = first.unwrap_or_else(|| grep.root());
+  let matches = vec![NodeMatch::from(node)];
+  match_rule_on_file(path, matches, &rule, grep.source(), processor)
 }
 
 pub fn run_with_config(arg: ScanArg, project: Result<ProjectConfig>) -> Result<()> {
@@ -83,6 +130,14 @@ pub fn run_with_config(arg: ScanArg, project: Result<ProjectConfig>) -> Result<(
     let printer = CloudPrinter::stdout();
     return run_scan(arg, printer, project);
   }
+  if let Some(mode) = arg.output.summary_mode() {
+    let printer = SummaryPrinter::stdout(mode).json(arg.output.json);
+    return run_scan(arg, printer, project);
+  }
+  if let Some(template) = arg.output.format_template.clone() {
+    let printer = TemplatePrinter::stdout(template).escape(arg.output.template_escape);
+    return run_scan(arg, printer, project);
+  }
   if let Some(json) = arg.output.json {
     let printer = JSONPrinter::stdout(json).include_metadata(arg.include_metadata);
     return run_scan(arg, printer, project);
@@ -118,7 +173,9 @@ fn run_scan<P: Printer + 'static>(
 struct ScanWithConfig {
   arg: ScanArg,
   configs: RuleCollection<SgLang>,
+  prefilter: Prefilter,
   unused_suppression_rule: RuleConfig<SgLang>,
+  budget_severity: Severity,
   trace: ScanTrace,
   proj_dir: PathBuf,
   // TODO: remove this
@@ -128,6 +185,10 @@ impl ScanWithConfig {
   fn try_new(arg: ScanArg, project: Result<ProjectConfig>) -> Result<Self> {
     let overwrite = RuleOverwrite::new(&arg.overwrite)?;
     let unused_suppression_rule = unused_suppression_rule_config(&arg, &overwrite);
+    let budget_severity = overwrite
+      .find("budget-exceeded")
+      .severity
+      .unwrap_or(Severity::Warning);
     let mut proj_dir = PathBuf::from(".");
     let (configs, rule_trace) = if let Some(path) = &arg.rule {
       let rules = read_rule_file(path, None)?;
@@ -143,6 +204,12 @@ impl ScanWithConfig {
       proj_dir = project_config.project_dir.clone();
       project_config.find_rules(overwrite)?
     };
+    // files with suppression comments are scanned to report unused suppressions
+    let always: &[&str] = match unused_suppression_rule.severity {
+      Severity::Off => &[],
+      _ => &["ast-grep-ignore"],
+    };
+    let prefilter = Prefilter::new(&configs, always);
     let trace = arg.output.inspect.scan_trace(rule_trace);
     trace.print_rules(&configs)?;
     let absolute_proj_dir = proj_dir
@@ -151,7 +218,9 @@ impl ScanWithConfig {
     Ok(Self {
       arg,
       configs,
+      prefilter,
       unused_suppression_rule,
+      budget_severity,
       trace,
       proj_dir: absolute_proj_dir,
       error_count: AtomicUsize::new(0),
@@ -210,10 +279,15 @@ impl PathWorker for ScanWithConfig {
     path: &Path,
     processor: &P::Processor,
   ) -> Result<Vec<P::Processed>> {
-    let items = filter_file_rule(path, &self.configs, &self.trace)?;
+    let items = filter_file_rule(path, &self.configs, &self.prefilter, &self.trace)?;
     let mut error_count = 0usize;
     let mut ret = vec![];
+    let budget = self.arg.budget();
+    let mut max_count = self.arg.output.max_count;
     for grep in items {
+      if max_count == Some(0) {
+        break;
+      }
       let file_content = grep.source();
       // use path relative to project director
       let abs_path = path.canonicalize()?;
@@ -225,13 +299,35 @@ impl PathWorker for ScanWithConfig {
       combined.set_unused_suppression_rule(&self.unused_suppression_rule);
       let interactive = self.arg.output.needs_interactive();
       // exclude_fix rule because we already have diff inspection before
-      let scanned = combined.scan(&grep, /* separate_fix*/ interactive);
-      if interactive {
+      let scanned = combined.scan_with_budget(
+        &grep,
+        /* separate_fix*/ interactive,
+        budget.clone(),
+        max_count,
+      );
+      if let Some(exceeded) = &scanned.budget_exceeded {
+        let severity = self.budget_severity.clone();
+        if matches!(severity, Severity::Error) {
+          error_count = error_count.saturating_add(1);
+        }
+        if !matches!(severity, Severity::Off) {
+          ret.push(match_budget_exceeded(
+            path, &grep, exceeded, severity, processor,
+          )?);
+        }
+      }
+      // max count is shared by all languages of the file
+      let found = scanned.diffs.len() + scanned.matches.iter().map(|m| m.1.len()).sum::<usize>();
+      max_count = max_count.map(|max| max.saturating_sub(found));
+      if !scanned.diffs.is_empty() {
         let diffs = scanned.diffs;
         let processed = match_rule_diff_on_file(path, diffs, processor)?;
         ret.push(processed);
       }
       for (rule, matches) in scanned.matches {
+        if matches.is_empty() {
+          continue;
+        }
         if matches!(rule.severity, Severity::Error) {
           error_count = error_count.saturating_add(matches.len());
         }
@@ -239,6 +335,9 @@ impl PathWorker for ScanWithConfig {
         ret.push(processed);
       }
     }
+    if ret.is_empty() && self.arg.output.files_without_match {
+      ret.push(processor.print_matches(vec![], path)?);
+    }
     self.error_count.fetch_add(error_count, Ordering::AcqRel);
     Ok(ret)
   }
@@ -246,6 +345,9 @@ impl PathWorker for ScanWithConfig {
 
 struct ScanStdin {
   rules: Vec<RuleConfig<SgLang>>,
+  files_without_match: bool,
+  budget: ScanBudget,
+  max_count: Option<usize>,
   // TODO: remove this
   error_count: AtomicUsize,
 }
@@ -261,6 +363,9 @@ impl ScanStdin {
     };
     Ok(Self {
       rules,
+      files_without_match: arg.output.files_without_match,
+      budget: arg.budget(),
+      max_count: arg.output.max_count,
       error_count: AtomicUsize::new(0),
     })
   }
@@ -295,20 +400,33 @@ impl StdInWorker for ScanStdin {
     let path = Path::new("STDIN");
     let file_content = grep.source();
     // do not separate_fix rule in stdin mode
-    let scanned = combined.scan(&grep, false);
+    let scanned = combined.scan_with_budget(&grep, false, self.budget.clone(), self.max_count);
     let mut error_count = 0usize;
     let mut ret = vec![];
+    if let Some(exceeded) = &scanned.budget_exceeded {
+      let severity = Severity::Warning;
+      ret.push(match_budget_exceeded(
+        path, &grep, exceeded, severity, processor,
+      )?);
+    }
     for (rule, matches) in scanned.matches {
+      if matches.is_empty() {
+        continue;
+      }
       if matches!(rule.severity, Severity::Error) {
         error_count = error_count.saturating_add(matches.len());
       }
       let processed = match_rule_on_file(path, matches, rule, file_content, processor)?;
       ret.push(processed);
     }
+    if ret.is_empty() && self.files_without_match {
+      ret.push(processor.print_matches(vec![], path)?);
+    }
     self.error_count.fetch_add(error_count, Ordering::AcqRel);
     Ok(ret)
   }
 }
+
 fn match_rule_diff_on_file<T>(
   path: &Path,
   matches: Vec<(&RuleConfig<SgLang>, NodeMatch<StrDoc<SgLang>>)>,
@@ -383,6 +501,8 @@ rule:
       inline_rules: None,
       report_style: ReportStyle::Rich,
       include_metadata: false,
+      file_timeout: None,
+      max_node_visits: None,
       input: InputArgs {
         no_ignore: vec![],
         paths: vec![PathBuf::from(".")],
@@ -402,6 +522,12 @@ rule:
       output: OutputArgs {
         interactive: false,
         json: None,
+        format_template: None,
+        template_escape: Default::default(),
+        count: false,
+        files_with_matches: false,
+        files_without_match: false,
+        max_count: None,
         update_all: false,
         color: ColorArg::Never,
         inspect: Default::default(),
@@ -1,5 +1,5 @@
 use crate::lang::SgLang;
-use crate::print::{ColorArg, JsonStyle};
+use crate::print::{ColorArg, FormatTemplate, JsonStyle, SummaryMode, TemplateEscape};
 use crate::utils::ErrorContext as EC;
 use crate::utils::Granularity;
 
@@ -143,6 +143,58 @@ pub struct OutputArgs {
   )]
   pub json: Option<JsonStyle>,
 
+  /// Print every match with a custom TEMPLATE.
+  ///
+  /// Placeholders in braces are replaced per match, e.g.
+  /// `--format-template '{file}:{start.line}:{start.column}: [{rule}] {message} {$NAME}'`.
+  /// Available placeholders are `file`, `text`, `language`, `start.line`, `start.column`,
+  /// `end.line`, `end.column`, `offset.start`, `offset.end`, `rule`, `severity`, `message`,
+  /// `note`, `replacement`, and metavariables or transformed variables like `$NAME`.
+  /// Lines and columns are one-based. Use `{{` and `}}` for literal braces.
+  /// It conflicts with json and interactive.
+  #[clap(
+    long,
+    conflicts_with_all = ["json", "interactive"],
+    value_name = "TEMPLATE"
+  )]
+  pub format_template: Option<FormatTemplate>,
+
+  /// Escape values interpolated by --format-template.
+  #[clap(
+    long,
+    default_value = "none",
+    requires = "format_template",
+    value_name = "CONTEXT"
+  )]
+  pub template_escape: TemplateEscape,
+
+  /// Only print the number of matches in each file, instead of the matches.
+  ///
+  /// Files without matches are not printed. With --json, every file is
+  /// printed as an object with `file` and `count` fields.
+  #[clap(
+    long,
+    conflicts_with_all = ["interactive", "update_all", "format_template", "files_with_matches", "files_without_match"]
+  )]
+  pub count: bool,
+
+  /// Only print the paths of files with at least one match.
+  #[clap(
+    long,
+    conflicts_with_all = ["interactive", "update_all", "format_template", "files_without_match"]
+  )]
+  pub files_with_matches: bool,
+
+  /// Only print the paths of searched files without any match.
+  #[clap(long, conflicts_with_all = ["interactive", "update_all", "format_template"])]
+  pub files_without_match: bool,
+
+  /// Stop searching a file after NUM matches.
+  ///
+  /// The traversal of the file stops as soon as NUM matches are found.
+  #[clap(short = 'm', long, value_name = "NUM")]
+  pub max_count: Option<usize>,
+
   /// Controls output color.
   ///
   /// This flag controls when to use colors. The default setting is 'auto', which
@@ -168,6 +220,19 @@ impl OutputArgs {
   pub fn needs_interactive(&self) -> bool {
     self.interactive || self.update_all
   }
+
+  /// The per-file summary to print instead of matches, if any.
+  pub fn summary_mode(&self) -> Option<SummaryMode> {
+    if self.count {
+      Some(SummaryMode::Count)
+    } else if self.files_with_matches {
+      Some(SummaryMode::FilesWithMatches)
+    } else if self.files_without_match {
+      Some(SummaryMode::FilesWithoutMatch)
+    } else {
+      None
+    }
+  }
 }
 
 /// context related options
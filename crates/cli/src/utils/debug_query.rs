@@ -22,10 +22,7 @@ impl DebugFormat {
   pub fn debug_pattern(&self, pattern: &Pattern, lang: SgLang, colored: bool) {
     match self {
       DebugFormat::Pattern => {
-        let lang = lang.get_ts_language();
-        let mut ret = String::new();
-        let fmt = DumpFmt::named(colored);
-        if dump_pattern(&pattern.node, &pattern.strictness, &lang, &fmt, 0, &mut ret).is_ok() {
+        if let Some(ret) = Self::dump_pattern(pattern, lang, colored) {
           eprintln!("Debug Pattern:\n{ret}");
         } else {
           eprintln!("unexpected error in writing pattern string");
@@ -39,21 +36,35 @@ impl DebugFormat {
 
   pub fn debug_tree(&self, src: &str, lang: SgLang, colored: bool) {
     let root = lang.ast_grep(src);
-    match self {
+    let title = match self {
       DebugFormat::Pattern => {
-        debug_assert!(false, "debug_tree cannot be called with Pattern")
-      }
-      DebugFormat::Sexp => {
-        eprintln!("Debug Sexp:\n{}", root.root().get_inner_node().to_sexp());
-      }
-      DebugFormat::Ast => {
-        let dumped = dump_node(root.root().get_inner_node());
-        eprintln!("Debug AST:\n{}", dumped.ast(colored));
-      }
-      DebugFormat::Cst => {
-        let dumped = dump_node(root.root().get_inner_node());
-        eprintln!("Debug CST:\n{}", dumped.cst(colored));
+        debug_assert!(false, "debug_tree cannot be called with Pattern");
+        return;
       }
+      DebugFormat::Sexp => "Sexp",
+      DebugFormat::Ast => "AST",
+      DebugFormat::Cst => "CST",
+    };
+    let dumped = self.dump_tree(root.root().get_inner_node(), colored);
+    eprintln!("Debug {title}:\n{dumped}");
+  }
+
+  /// Print the pattern, None if it cannot be written.
+  pub fn dump_pattern(pattern: &Pattern, lang: SgLang, colored: bool) -> Option<String> {
+    let lang = lang.get_ts_language();
+    let mut ret = String::new();
+    let fmt = DumpFmt::named(colored);
+    dump_pattern(&pattern.node, &pattern.strictness, &lang, &fmt, 0, &mut ret).ok()?;
+    Some(ret)
+  }
+
+  /// Print the node and its descendants, the Pattern format prints nothing.
+  pub fn dump_tree(&self, node: ts::Node, colored: bool) -> String {
+    match self {
+      DebugFormat::Pattern => String::new(),
+      DebugFormat::Sexp => node.to_sexp(),
+      DebugFormat::Ast => dump_node(node).ast(colored),
+      DebugFormat::Cst => dump_node(node).cst(colored),
     }
   }
 }
@@ -68,13 +79,16 @@ fn dump_pattern(
 ) -> FmtResult {
   let indent_str = "  ".repeat(indent);
   match pattern {
-    PatternNode::MetaVar { meta_var } => {
-      let meta_var = match meta_var {
+    PatternNode::MetaVar { meta_var, kind } => {
+      let mut meta_var = match meta_var {
         MetaVariable::Capture(name, _) => format!("${name}"),
         MetaVariable::MultiCapture(name) => format!("$$${name}"),
         MetaVariable::Multiple => "$$$".to_string(),
         MetaVariable::Dropped(_) => "$_".to_string(),
       };
+      if let Some(kind) = kind.and_then(|k| lang.node_kind_for_id(k)) {
+        meta_var = format!("{meta_var}_{kind}");
+      }
       let meta_var = style.kind_style.paint(meta_var);
       writeln!(
         ret,
@@ -124,6 +138,30 @@ fn dump_pattern(
         dump_pattern(child, strictness, lang, style, indent + 1, ret)?;
       }
     }
+    PatternNode::Optional(children) => {
+      writeln!(ret, "{indent_str}{}", style.field_style.paint("Optional"))?;
+      for child in children {
+        dump_pattern(child, strictness, lang, style, indent + 1, ret)?;
+      }
+    }
+    PatternNode::Alternatives(alts) => {
+      writeln!(
+        ret,
+        "{indent_str}{}",
+        style.field_style.paint("Alternatives")
+      )?;
+      let alt_indent = "  ".repeat(indent + 1);
+      for alt in alts {
+        writeln!(
+          ret,
+          "{alt_indent}{}",
+          style.field_style.paint("Alternative")
+        )?;
+        for child in alt {
+          dump_pattern(child, strictness, lang, style, indent + 2, ret)?;
+        }
+      }
+    }
   }
   Ok(())
 }
@@ -281,6 +319,32 @@ program (0,0)-(0,11)
     assert_eq!(DUMPED.trim(), dumped.ast(false).trim());
   }
 
+  #[test]
+  fn test_dump_formats() {
+    let lang = SgLang::Builtin(TypeScript.into());
+    let root = lang.ast_grep("var a = 123");
+    let node = root.root().get_inner_node();
+    assert_eq!(
+      DebugFormat::Ast.dump_tree(node, false).trim(),
+      DUMPED.trim()
+    );
+    let sexp = DebugFormat::Sexp.dump_tree(node, false);
+    assert!(sexp.starts_with("(program (variable_declaration"));
+    let pattern = Pattern::new("var $A = 123", lang);
+    let dumped = DebugFormat::dump_pattern(&pattern, lang, false).unwrap();
+    assert!(dumped.contains("MetaVar $A"));
+    let pattern = Pattern::new("var $A_identifier = $$$B_number", lang);
+    let dumped = DebugFormat::dump_pattern(&pattern, lang, false).unwrap();
+    assert!(dumped.contains("MetaVar $A_identifier"));
+    let pattern = Pattern::new("foo($A, $?B)", lang);
+    let dumped = DebugFormat::dump_pattern(&pattern, lang, false).unwrap();
+    assert!(dumped.contains("Optional\n"));
+    let pattern = Pattern::new("foo(${{ a | b, c }})", lang);
+    let dumped = DebugFormat::dump_pattern(&pattern, lang, false).unwrap();
+    assert!(dumped.contains("Alternatives\n"));
+    assert!(dumped.contains("    Alternative\n"));
+  }
+
   const MISSING: &str = r#"
 translation_unit (0,0)-(0,9)
   declaration (0,0)-(0,9)
@@ -6,13 +6,16 @@
 //!   * number file matched
 //! - Entity level: show how a file is scanned
 //!   * reasons if skipped (file too large, does not have fixed string in pattern, no matching rule, etc)
+//!   * files skipped by the prefilter before parsing, since they contain no literal required by rules
 //!   * number of rules applied
+//!   * number of node kinds each rule is indexed by, and a suggestion to add `kind` if it checks every node
 //!   * rules skipped (dues to ignore/files)
 //! - Detail level: show how a rule runs on a file
 
 use crate::config::ProjectConfig;
 use crate::lang::SgLang;
 use ast_grep_config::{RuleCollection, RuleConfig};
+use ast_grep_core::{Language, Matcher};
 
 use anyhow::Result;
 use clap::ValueEnum;
@@ -86,6 +89,7 @@ impl Granularity {
 pub struct FileTrace {
   files_scanned: AtomicUsize,
   files_skipped: AtomicUsize,
+  files_prefiltered: AtomicUsize,
 }
 
 impl FileTrace {
@@ -95,6 +99,9 @@ impl FileTrace {
   pub fn add_skipped(&self) {
     self.files_skipped.fetch_add(1, Ordering::AcqRel);
   }
+  pub fn add_prefiltered(&self) {
+    self.files_prefiltered.fetch_add(1, Ordering::AcqRel);
+  }
 }
 
 pub struct TraceInfo<T, W: Write> {
@@ -194,6 +201,15 @@ impl<W: Write + Sync> TraceInfo<RuleTrace, W> {
       )?;
       Ok(())
     })?;
+    self.print_summary("prefilter", |w| {
+      let prefiltered = self
+        .inner
+        .file_trace
+        .files_prefiltered
+        .load(Ordering::Acquire);
+      write!(w, "skippedFileCount={prefiltered}")?;
+      Ok(())
+    })?;
     Ok(())
   }
 
@@ -206,6 +222,14 @@ impl<W: Write + Sync> TraceInfo<RuleTrace, W> {
     Ok(())
   }
 
+  pub fn print_prefiltered(&self, path: &Path, lang: SgLang) -> Result<()> {
+    self.inner.file_trace.add_prefiltered();
+    self.print_entity("file", path.display(), |w| {
+      write!(w, "language={lang},prefiltered=true")?;
+      Ok(())
+    })
+  }
+
   pub fn print_rules(&self, rules: &RuleCollection<SgLang>) -> Result<()> {
     if self.level < Granularity::Entity {
       return Ok(());
@@ -213,6 +237,17 @@ impl<W: Write + Sync> TraceInfo<RuleTrace, W> {
     rules.for_each_rule(|rule| {
       _ = self.print_entity("rule", &rule.id, |w| {
         write!(w, "finalSeverity={:?}", rule.severity)?;
+        let kinds = rule.matcher.potential_kinds();
+        let node_types = rule.language.node_types();
+        match kinds {
+          Some(kinds) if !node_types.is_some_and(|t| t.covers_all(&kinds)) => {
+            write!(w, ",indexedKindCount={}", kinds.len())?;
+          }
+          _ => write!(
+            w,
+            ",indexedKindCount=all,suggestion=add kind to avoid checking every node"
+          )?,
+        }
         Ok(())
       });
     });
@@ -280,6 +315,7 @@ mod test {
       ret,
       r"sg: summary|file: scannedFileCount=0,skippedFileCount=0
 sg: summary|rule: effectiveRuleCount=10,skippedRuleCount=2
+sg: summary|prefilter: skippedFileCount=0
 "
     );
   }
@@ -2,17 +2,19 @@ mod args;
 mod debug_query;
 mod error_context;
 mod inspect;
+mod prefilter;
 mod print_diff;
 mod rule_overwrite;
 mod worker;
 
-pub use args::{ContextArgs, InputArgs, OutputArgs, OverwriteArgs};
+pub use args::{ContextArgs, InputArgs, NoIgnore, OutputArgs, OverwriteArgs};
 pub use debug_query::DebugFormat;
 pub use error_context::{exit_with_error, ErrorContext};
 pub use inspect::{FileTrace, Granularity, RuleTrace, RunTrace, ScanTrace};
+pub use prefilter::Prefilter;
 pub use print_diff::DiffStyles;
 pub use rule_overwrite::RuleOverwrite;
-pub use worker::{Items, PathWorker, StdInWorker, Worker};
+pub use worker::{filter_result, Items, PathWorker, StdInWorker, Worker};
 
 use crate::lang::SgLang;
 
@@ -97,7 +99,7 @@ pub fn prompt(prompt_text: &str, letters: &str, default: Option<char>) -> Result
   }
 }
 
-fn read_file(path: &Path) -> Result<String> {
+pub fn read_file(path: &Path) -> Result<String> {
   let file_content =
     read_to_string(path).with_context(|| format!("Cannot read file {}", path.to_string_lossy()))?;
   // skip large files or empty file
@@ -124,12 +126,17 @@ fn collect_file_stats(
 pub fn filter_file_rule(
   path: &Path,
   configs: &RuleCollection<SgLang>,
+  prefilter: &Prefilter,
   trace: &ScanTrace,
 ) -> Result<SmallVec<[AstGrep; 1]>> {
   let Some(lang) = SgLang::from_path(path) else {
     return Ok(smallvec![]);
   };
   let file_content = read_file(path)?;
+  if !prefilter.may_match(lang, &file_content) {
+    trace.print_prefiltered(path, lang)?;
+    return Ok(smallvec![]);
+  }
   let grep = lang.ast_grep(file_content);
   collect_file_stats(path, lang, configs, trace)?;
   let mut ret = smallvec![grep.clone()];
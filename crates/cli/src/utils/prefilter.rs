@@ -0,0 +1,105 @@
+//! Skip files before tree-sitter parsing if they cannot match any rule.
+//!
+//! Most rules require some literal text in the source, e.g. the fixed string of a pattern
+//! or the prefix of a regex. A file containing none of the literals of its language's rules
+//! is not parsed at all. The literals are searched with one Aho-Corasick automaton per language.
+
+use crate::lang::SgLang;
+use aho_corasick::AhoCorasick;
+use ast_grep_config::RuleCollection;
+
+use std::collections::HashMap;
+
+#[derive(Default)]
+pub struct Prefilter {
+  /// languages without automaton have some rule without required literal
+  automata: HashMap<SgLang, AhoCorasick>,
+}
+
+impl Prefilter {
+  /// `always` literals are added to every language, e.g. suppression comments that
+  /// must be scanned to report unused suppressions.
+  pub fn new(configs: &RuleCollection<SgLang>, always: &[&str]) -> Self {
+    let mut literals: HashMap<SgLang, Option<Vec<String>>> = HashMap::new();
+    configs.for_each_rule(|rule| {
+      let entry = literals
+        .entry(rule.language)
+        .or_insert_with(|| Some(always.iter().map(|s| s.to_string()).collect()));
+      match (entry.as_mut(), rule.matcher.required_literals()) {
+        (Some(lits), Some(required)) => lits.extend(required),
+        _ => *entry = None,
+      }
+    });
+    let mut automata = HashMap::new();
+    for (lang, lits) in &literals {
+      let Some(mut lits) = lits.clone() else {
+        continue;
+      };
+      // rules of injected languages also run on the host file
+      let injected = lang.injectable_sg_langs().into_iter().flatten();
+      let injected: Option<Vec<_>> = injected.filter_map(|l| literals.get(&l).cloned()).collect();
+      let Some(injected) = injected else {
+        continue;
+      };
+      lits.extend(injected.into_iter().flatten());
+      if let Ok(automaton) = AhoCorasick::new(&lits) {
+        automata.insert(*lang, automaton);
+      }
+    }
+    Self { automata }
+  }
+
+  /// Return false if the content cannot match any rule of the language.
+  pub fn may_match(&self, lang: SgLang, content: &str) -> bool {
+    match self.automata.get(&lang) {
+      Some(automaton) => automaton.is_match(content),
+      None => true,
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::config::with_rule_stats;
+  use ast_grep_config::{from_yaml_string, GlobalRules};
+  use ast_grep_language::SupportLang;
+
+  fn make_prefilter(rules: &str, always: &[&str]) -> Prefilter {
+    let rules = from_yaml_string(rules, &GlobalRules::default()).expect("should parse");
+    let (configs, _) = with_rule_stats(rules).expect("should collect");
+    Prefilter::new(&configs, always)
+  }
+
+  const RULES: &str = "
+id: log
+language: TypeScript
+rule: {pattern: console.log($A)}
+---
+id: fetch
+language: TypeScript
+rule: {regex: ^fetch, kind: call_expression}
+";
+
+  #[test]
+  fn test_prefilter() {
+    let ts = SgLang::Builtin(SupportLang::TypeScript);
+    let prefilter = make_prefilter(RULES, &[]);
+    assert!(prefilter.may_match(ts, "console.log(1)"));
+    assert!(prefilter.may_match(ts, "fetch(url)"));
+    assert!(!prefilter.may_match(ts, "let a = 123"));
+    // languages without rules are not filtered
+    let rust = SgLang::Builtin(SupportLang::Rust);
+    assert!(prefilter.may_match(rust, "let a = 123;"));
+    let prefilter = make_prefilter(RULES, &["ast-grep-ignore"]);
+    assert!(prefilter.may_match(ts, "// ast-grep-ignore"));
+  }
+
+  #[test]
+  fn test_rule_without_literal() {
+    let rules = format!("{RULES}---\nid: num\nlanguage: TypeScript\nrule: {{kind: number}}");
+    let prefilter = make_prefilter(&rules, &[]);
+    let ts = SgLang::Builtin(SupportLang::TypeScript);
+    assert!(prefilter.may_match(ts, "let a = 123"));
+  }
+}
@@ -87,7 +87,7 @@ impl<T> Items<T> {
   }
 }
 
-fn filter_result(result: Result<DirEntry, ignore::Error>) -> Option<PathBuf> {
+pub fn filter_result(result: Result<DirEntry, ignore::Error>) -> Option<PathBuf> {
   let entry = match result {
     Ok(entry) => entry,
     Err(err) => {
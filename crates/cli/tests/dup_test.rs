@@ -0,0 +1,94 @@
+mod common;
+
+use anyhow::Result;
+use assert_cmd::cargo::cargo_bin_cmd;
+use common::create_test_files;
+use predicates::prelude::*;
+use predicates::str::contains;
+use serde_json::{from_slice, Value};
+
+const FOO: &str = "
+function foo(a, b) {
+  const total = a + b * 2;
+  console.log('total', total);
+  return total;
+}
+foo(1, 2);";
+// the same function with redundant parentheses, swapped operands and trailing comma
+const BAR: &str = "
+function foo(a, b) {
+  const total = a + (2 * b);
+  console.log('total', total,);
+  return total;
+}
+export default foo;";
+
+#[test]
+fn test_dup_normalized() -> Result<()> {
+  let dir = create_test_files([("a.ts", FOO), ("b.ts", BAR), ("empty.ts", "")])?;
+  let output = cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args(["dup", "-l", "ts", "--min-nodes", "20", "--json=compact"])
+    .args(["--strictness", "normalized"])
+    .assert()
+    .success()
+    .stderr(contains("ERROR").not())
+    .get_output()
+    .stdout
+    .clone();
+  let clusters: Vec<Value> = from_slice(&output)?;
+  assert_eq!(clusters.len(), 1);
+  let occurrences = clusters[0]["occurrences"]
+    .as_array()
+    .expect("should be list");
+  assert_eq!(occurrences.len(), 2);
+  assert_eq!(occurrences[0]["file"], "a.ts");
+  assert_eq!(occurrences[1]["file"], "b.ts");
+  assert_eq!(occurrences[0]["kind"], "function_declaration");
+  Ok(())
+}
+
+#[test]
+fn test_dup_colored() -> Result<()> {
+  let bar = FOO.replace("foo(1, 2)", "bar()");
+  let dir = create_test_files([("a.ts", FOO), ("b.ts", bar.as_str())])?;
+  cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args(["dup", "-l", "ts", "--min-nodes", "20", "--color", "never"])
+    .assert()
+    .success()
+    .stdout(contains("Duplicate #1: 2 occurrences"))
+    .stdout(contains("a.ts:2:1-6:2 (function_declaration)"))
+    .stdout(contains("b.ts:2:1-6:2 (function_declaration)"));
+  // smart strictness compares the source as is
+  let dir = create_test_files([("a.ts", FOO), ("b.ts", BAR)])?;
+  cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args([
+      "dup",
+      "-l",
+      "ts",
+      "--min-nodes",
+      "20",
+      "--strictness",
+      "smart",
+    ])
+    .assert()
+    .success()
+    .stdout("");
+  Ok(())
+}
+
+#[test]
+fn test_dup_report_error() -> Result<()> {
+  let dir = create_test_files([("a.ts", FOO)])?;
+  std::fs::write(dir.path().join("bad.ts"), [0xff, 0xfe, 0x00])?;
+  cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args(["dup", "-l", "ts", "--json"])
+    .assert()
+    .success()
+    .stdout("[]\n")
+    .stderr(contains("ERROR: Cannot fingerprint bad.ts"));
+  Ok(())
+}
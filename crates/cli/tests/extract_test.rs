@@ -0,0 +1,61 @@
+mod common;
+
+use anyhow::Result;
+use assert_cmd::cargo::cargo_bin_cmd;
+use common::create_test_files;
+
+const TRACK: &str = "
+analytics.track('signup', user);
+logger.track('login', guest);
+analytics.track('signup', admin);
+";
+
+#[test]
+fn test_extract_pattern_columns() -> Result<()> {
+  let dir = create_test_files([("a.ts", TRACK)])?;
+  cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args(["extract", "-p", "$OBJ.track($EVENT, $$$)", "-l", "ts"])
+    .args(["--columns", "file,line,$OBJ,$EVENT"])
+    .assert()
+    .success()
+    .stdout(
+      "file,line,OBJ,EVENT\n\
+       a.ts,2,analytics,'signup'\n\
+       a.ts,3,logger,'login'\n\
+       a.ts,4,analytics,'signup'\n",
+    );
+  Ok(())
+}
+
+#[test]
+fn test_extract_rule_group_by() -> Result<()> {
+  let rust = "fn a() { track(1); track(1); track(1); }";
+  let dir = create_test_files([("a.ts", TRACK), ("b.ts", TRACK), ("c.rs", rust)])?;
+  let rules = "
+id: track
+language: ts
+rule: {pattern: '$OBJ.track($EVENT, $$$)'}
+---
+id: track-rs
+language: rs
+rule: {pattern: track($EVENT)}";
+  cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args([
+      "extract",
+      "--inline-rules",
+      rules,
+      "--group-by",
+      "rule,$EVENT",
+    ])
+    .assert()
+    .success()
+    .stdout(
+      "rule,EVENT,count\n\
+       track,'signup',4\n\
+       track-rs,1,3\n\
+       track,'login',2\n",
+    );
+  Ok(())
+}
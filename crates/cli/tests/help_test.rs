@@ -1,14 +1,14 @@
 mod common;
 
 use anyhow::Result;
-use assert_cmd::Command;
+use assert_cmd::cargo::cargo_bin_cmd;
 use common::create_test_files;
 use predicates::str::contains;
 
 #[test]
 fn test_help_work_for_invalid_sgconfig() -> Result<()> {
   let dir = create_test_files([("sgconfig.yml", "invalid")])?;
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path())
     .args(["help"])
     .assert()
@@ -1,7 +1,7 @@
 mod common;
 
 use anyhow::Result;
-use assert_cmd::Command;
+use assert_cmd::cargo::cargo_bin_cmd;
 use common::create_test_files;
 use predicates::prelude::*;
 use predicates::str::contains;
@@ -9,7 +9,7 @@ use predicates::str::contains;
 #[test]
 fn test_simple_infer_lang() -> Result<()> {
   let dir = create_test_files([("a.ts", "console.log(123)"), ("b.rs", "console.log(456)")])?;
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path())
     .args(["-p", "console.log($A)"])
     .assert()
@@ -22,7 +22,7 @@ fn test_simple_infer_lang() -> Result<()> {
 #[test]
 fn test_simple_specific_lang() -> Result<()> {
   let dir = create_test_files([("a.ts", "console.log(123)"), ("b.rs", "console.log(456)")])?;
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path())
     .args(["-p", "console.log($A)", "-l", "rs"])
     .assert()
@@ -38,7 +38,7 @@ fn test_js_in_html() -> Result<()> {
     ("a.html", "<script>alert(1)</script>"),
     ("b.js", "alert(456)"),
   ])?;
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path())
     .args(["-p", "alert($A)", "-l", "js"])
     .assert()
@@ -51,7 +51,7 @@ fn test_js_in_html() -> Result<()> {
 #[test]
 fn test_rewrite_js_in_html() -> Result<()> {
   let dir = create_test_files([("a.html", "<script>alert(1)</script>")])?;
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path())
     .args(["-p", "alert($A)", "-r", "alert(456)"])
     .assert()
@@ -64,7 +64,7 @@ fn test_rewrite_js_in_html() -> Result<()> {
 #[test]
 fn test_inspect() -> Result<()> {
   let dir = create_test_files([("a.js", "alert(1)"), ("b.js", "alert(456)")])?;
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path())
     .args(["-p", "alert($A)", "-l", "js", "--inspect", "entity"])
     .assert()
@@ -77,16 +77,16 @@ fn test_inspect() -> Result<()> {
 #[test]
 fn test_debug_query() -> Result<()> {
   // should not print pattern if invalid
-  Command::cargo_bin("ast-grep")?
-    .args(["-p", "foo;bar;", "-l", "js", "--debug-query"])
+  cargo_bin_cmd!("ast-grep")
+    .args(["-p", "foo bar", "-l", "js", "--debug-query"])
     .assert()
     .failure()
     .stderr(contains("Debug Pattern").not())
     .stderr(contains("Cannot parse query as a valid pattern"));
 
   // should  print debug tree even for invalid pattern
-  Command::cargo_bin("ast-grep")?
-    .args(["-p", "foo;bar;", "-l", "js", "--debug-query=ast"])
+  cargo_bin_cmd!("ast-grep")
+    .args(["-p", "foo bar", "-l", "js", "--debug-query=ast"])
     .assert()
     .failure()
     .stderr(contains("Debug AST"))
@@ -98,7 +98,7 @@ fn test_debug_query() -> Result<()> {
 #[test]
 fn test_unsupport_config_arg() -> Result<()> {
   let dir = create_test_files([])?;
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path())
     .args(["-p", "alert($A)", "-c", "not-found.yml"])
     .assert()
@@ -110,7 +110,7 @@ fn test_unsupport_config_arg() -> Result<()> {
 #[test]
 fn test_trace_default_project() -> Result<()> {
   let dir = create_test_files([("sgconfig.yml", "ruleDirs: []")])?;
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path())
     .args(["-p", "alert($A)", "--inspect=summary"])
     .assert()
@@ -122,13 +122,13 @@ fn test_trace_default_project() -> Result<()> {
 #[test]
 fn test_trace_project() -> Result<()> {
   let dir = create_test_files([("not.yml", "ruleDirs: []")])?;
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path())
     .args(["-p", "alert($A)", "--inspect=summary"])
     .assert()
     .success()
     .stderr(contains("isProject=false"));
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path())
     .args(["run", "-c=not.yml", "-p", "alert($A)", "--inspect=summary"])
     .assert()
@@ -136,3 +136,114 @@ fn test_trace_project() -> Result<()> {
     .stderr(contains("isProject=true,projectDir"));
   Ok(())
 }
+
+#[test]
+fn test_format_template() -> Result<()> {
+  let dir = create_test_files([("a.ts", "let a = 1\nconsole.log(123)")])?;
+  cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args(["-p", "console.log($A)", "--format-template"])
+    .arg("{file}:{start.line}:{start.column} {$A}")
+    .assert()
+    .success()
+    .stdout("a.ts:2:1 123\n");
+  Ok(())
+}
+
+#[test]
+fn test_sequence_pattern() -> Result<()> {
+  let src = "m.lock();\nwork();\nm.unlock();\nother();\n";
+  let dir = create_test_files([("a.ts", src)])?;
+  cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args(["-p", "$A.lock(); $$$B; $A.unlock();", "--format-template"])
+    .arg("{file}:{start.line}-{end.line}")
+    .assert()
+    .success()
+    .stdout("a.ts:1-3\n");
+  cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args(["-p", "$A.lock(); $$$B; $A.unlock();", "-U"])
+    .args(["-r", "using($A, () => { $$$B });"])
+    .assert()
+    .success();
+  let fixed = std::fs::read_to_string(dir.path().join("a.ts"))?;
+  assert_eq!(fixed, "using(m, () => { work(); });\nother();\n");
+  Ok(())
+}
+
+#[test]
+fn test_sequence_matches_do_not_overlap() -> Result<()> {
+  let dir = create_test_files([("a.ts", "a(); b(); c();\n")])?;
+  let ret = cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args(["-p", "$A(); $B();", "--json=compact"])
+    .assert()
+    .success();
+  let json: serde_json::Value = serde_json::from_slice(&ret.get_output().stdout)?;
+  let matches = json.as_array().expect("should be array");
+  assert_eq!(matches.len(), 1);
+  assert_eq!(matches[0]["text"], "a(); b();");
+  cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    // overlapping matches would panic the colored printer
+    .args(["-p", "$A(); $B();", "--color=always"])
+    .assert()
+    .success();
+  Ok(())
+}
+
+#[test]
+fn test_normalized_strictness() -> Result<()> {
+  let dir = create_test_files([("a.ts", "if (null == (x)) {}")])?;
+  cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args(["-p", "x == null", "--strictness", "smart"])
+    .assert()
+    .success()
+    .stdout("");
+  cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args([
+      "-p",
+      "x == null",
+      "--strictness",
+      "normalized",
+      "--json=compact",
+    ])
+    .assert()
+    .success()
+    .stdout(contains("null == (x)"));
+  Ok(())
+}
+
+#[test]
+fn test_count_and_max_count() -> Result<()> {
+  let dir = create_test_files([("a.ts", "foo(1); foo(2); foo(3)"), ("b.ts", "bar()")])?;
+  cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args(["-p", "foo($A)", "--count"])
+    .assert()
+    .success()
+    .stdout("a.ts:3\n");
+  cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args([
+      "-p",
+      "foo($A)",
+      "--count",
+      "--max-count",
+      "2",
+      "--json=compact",
+    ])
+    .assert()
+    .success()
+    .stdout("[{\"file\":\"a.ts\",\"count\":2}]\n");
+  cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args(["-p", "foo($A)", "--files-without-match"])
+    .assert()
+    .success()
+    .stdout("b.ts\n");
+  Ok(())
+}
@@ -1,7 +1,7 @@
 mod common;
 
 use anyhow::Result;
-use assert_cmd::Command;
+use assert_cmd::cargo::cargo_bin_cmd;
 use ast_grep::main_with_args;
 use common::create_test_files;
 use predicates::prelude::*;
@@ -67,7 +67,7 @@ fn test_sg_scan() -> Result<()> {
 #[test]
 fn test_sg_rule_off() -> Result<()> {
   let dir = setup()?;
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path())
     .args(["scan"])
     .assert()
@@ -81,7 +81,7 @@ fn test_sg_rule_off() -> Result<()> {
 #[test]
 fn test_sg_scan_inline_rules() -> Result<()> {
   let inline_rules = "{id: test, language: ts, rule: {pattern: console.log($A)}}";
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .args(["scan", "--stdin", "--inline-rules", inline_rules, "--json"])
     .write_stdin("console.log(123)")
     .assert()
@@ -103,7 +103,7 @@ rule: { pattern: None }
 #[test]
 fn test_sg_scan_multiple_rules_in_one_file() -> Result<()> {
   let dir = create_test_files([("rule.yml", MULTI_RULES), ("test.ts", "Some(123) + None")])?;
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path())
     .args(["scan", "-r", "rule.yml"])
     .assert()
@@ -118,7 +118,7 @@ fn test_sg_scan_multiple_rules_in_one_file() -> Result<()> {
 #[test]
 fn test_sg_scan_py_empty_text() -> Result<()> {
   let inline_rules = "{id: test, language: py, rule: {pattern: None}}";
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .args(["scan", "--stdin", "--inline-rules", inline_rules])
     .write_stdin("\n\n\n\n\nNone")
     .assert()
@@ -132,7 +132,7 @@ fn test_sg_scan_html() -> Result<()> {
     ("rule.yml", RULE1),
     ("test.html", "<script lang=ts>Some(123)</script>"),
   ])?;
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path())
     .args(["scan", "-r", "rule.yml", "--inspect=summary"])
     .assert()
@@ -144,6 +144,70 @@ fn test_sg_scan_html() -> Result<()> {
   Ok(())
 }
 
+#[test]
+fn test_scan_prefilter() -> Result<()> {
+  let dir = create_test_files([
+    ("rule.yml", RULE1),
+    ("a.ts", "Some(123)"),
+    ("b.ts", "let a = 123"),
+  ])?;
+  cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args(["scan", "-r", "rule.yml", "--inspect=entity"])
+    .assert()
+    .success()
+    .stdout(contains("a.ts"))
+    .stdout(contains("b.ts").not())
+    .stderr(contains("b.ts: language=TypeScript,prefiltered=true"))
+    .stderr(contains("scannedFileCount=2"))
+    .stderr(contains("prefilter: skippedFileCount=1"));
+  Ok(())
+}
+
+#[test]
+fn test_scan_index_by_relation() -> Result<()> {
+  let rule = "
+id: regex-arg
+language: TypeScript
+rule:
+  regex: ^foo
+  inside: { kind: arguments }
+";
+  let dir = create_test_files([("rule.yml", rule), ("a.ts", "bar(foo); foo(bar)")])?;
+  cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args([
+      "scan",
+      "-r",
+      "rule.yml",
+      "--inspect=entity",
+      "--json=compact",
+    ])
+    .assert()
+    .success()
+    .stdout(contains(r#""byteOffset":{"start":4,"end":7}"#))
+    .stdout(contains(r#""byteOffset":{"start":10,"#).not())
+    .stderr(contains(
+      "rule|regex-arg: finalSeverity=Hint,indexedKindCount=",
+    ))
+    .stderr(contains("indexedKindCount=all").not());
+  let rule = "
+id: regex-all
+language: TypeScript
+rule:
+  regex: ^foo
+  inside: { kind: ERROR }
+";
+  let dir = create_test_files([("rule.yml", rule), ("a.ts", "foo")])?;
+  cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args(["scan", "-r", "rule.yml", "--inspect=entity"])
+    .assert()
+    .success()
+    .stderr(contains("indexedKindCount=all,suggestion=add kind"));
+  Ok(())
+}
+
 #[test]
 fn test_scan_unused_suppression() -> Result<()> {
   let dir = create_test_files([
@@ -151,7 +215,7 @@ fn test_scan_unused_suppression() -> Result<()> {
     ("rules/rule.yml", RULE1),
     ("test.ts", "None(123) // ast-grep-ignore"),
   ])?;
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path())
     .args(["scan"])
     .assert()
@@ -167,25 +231,25 @@ fn test_unused_suppression_only_in_scan() -> Result<()> {
     ("rules/rule.yml", RULE1),
     ("test.ts", "None(123) // ast-grep-ignore"),
   ])?;
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path())
     .args(["scan", "-r", "rules/rule.yml"])
     .assert()
     .success()
     .stdout(contains("unused-suppression").not());
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path())
     .args(["scan", "--filter", "on-rule"])
     .assert()
     .success()
     .stdout(contains("unused-suppression").not());
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path())
     .args(["scan", "--off", "on-rule"])
     .assert()
     .success()
     .stdout(contains("unused-suppression").not());
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path())
     .args(["scan", "--inline-rules", RULE1])
     .assert()
@@ -201,7 +265,7 @@ fn test_scan_unused_suppression_off() -> Result<()> {
     ("rules/rule.yml", RULE1),
     ("test.ts", "None(123) // ast-grep-ignore"),
   ])?;
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path())
     .args(["scan", "--off"])
     .assert()
@@ -212,19 +276,19 @@ fn test_scan_unused_suppression_off() -> Result<()> {
 #[test]
 fn test_severity_override() -> Result<()> {
   let dir = setup()?;
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path())
     .args(["scan", "--error"])
     .assert()
     .failure()
     .stdout(contains("error"));
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path())
     .args(["scan", "--error=on-rule"])
     .assert()
     .failure()
     .stdout(contains("error"));
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path())
     .args(["scan", "--error=not-exist"])
     .assert()
@@ -259,7 +323,7 @@ fn test_transform_indent() -> Result<()> {
     ("rules/rule.yml", PY_RULE),
     ("test.py", PY_FILE),
   ])?;
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path())
     .args(["scan"])
     .assert()
@@ -289,7 +353,7 @@ fn test_label() -> Result<()> {
     ("rules/rule.yml", LABEL_RULE),
     ("test.ts", "Some(123) + None"),
   ])?;
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path())
     .args(["scan"])
     .assert()
@@ -316,21 +380,21 @@ fn test_file() -> Result<()> {
     ("test/hit.ts", "Some(123)"),
     ("not.ts", "Some(456)"),
   ])?;
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path().join("test"))
     .args(["scan"])
     .assert()
     .success()
     .stdout(contains("hit.ts"))
     .stdout(contains("not.ts").not());
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path().join("test"))
     .args(["scan", "-c", "../sgconfig.yml"])
     .assert()
     .success()
     .stdout(contains("hit.ts"))
     .stdout(contains("not.ts").not());
-  Command::cargo_bin("ast-grep")?
+  cargo_bin_cmd!("ast-grep")
     .current_dir(dir.path())
     .args(["scan", "-c", "sgconfig.yml"])
     .assert()
@@ -339,3 +403,121 @@ fn test_file() -> Result<()> {
     .stdout(contains("not.ts").not());
   Ok(())
 }
+
+#[test]
+fn test_scan_node_visit_budget() -> Result<()> {
+  let dir = create_test_files([("test.ts", "Some(a + b + c)\nSome(1)")])?;
+  let rule =
+    "{id: deep, language: ts, rule: {kind: identifier, inside: {kind: program, stopBy: end}}}";
+  let ret = cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args([
+      "scan",
+      "--inline-rules",
+      rule,
+      "--max-node-visits",
+      "3",
+      "--json=compact",
+    ])
+    .assert()
+    .success()
+    .stderr("");
+  let json: Value = from_slice(&ret.get_output().stdout)?;
+  let diagnostics = json.as_array().expect("should be array");
+  assert_eq!(diagnostics.len(), 1);
+  let diagnostic = &diagnostics[0];
+  assert_eq!(diagnostic["ruleId"], "budget-exceeded");
+  assert_eq!(diagnostic["severity"], "warning");
+  assert_eq!(diagnostic["file"], "test.ts");
+  let message = diagnostic["message"].as_str().expect("should be string");
+  assert!(message.contains("rule `deep` exceeded the node visit budget of 3"));
+  cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args(["scan", "--inline-rules", rule, "--max-node-visits", "3"])
+    .args(["--error=budget-exceeded"])
+    .assert()
+    .failure()
+    .stdout(contains("budget-exceeded"));
+  cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args(["scan", "--inline-rules", rule, "--max-node-visits", "1000"])
+    .assert()
+    .success()
+    .stdout(contains("budget-exceeded").not());
+  Ok(())
+}
+
+#[test]
+fn test_scan_max_count() -> Result<()> {
+  let dir = create_test_files([
+    ("sgconfig.yml", CONFIG),
+    ("rules/rule.yml", RULE1),
+    (
+      "a.ts",
+      "Some(1); Some(2); Some(3)\nNone(4) // ast-grep-ignore",
+    ),
+    ("b.ts", "None(1)"),
+  ])?;
+  cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args(["scan", "--count", "--max-count", "2", "--json=compact"])
+    .assert()
+    .success()
+    .stdout("[{\"file\":\"a.ts\",\"count\":2}]\n");
+  cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args(["scan", "--files-without-match"])
+    .assert()
+    .success()
+    .stdout("b.ts\n");
+  Ok(())
+}
+
+#[test]
+fn test_scan_sequence_matches_do_not_overlap() -> Result<()> {
+  let rule = "
+id: call-pair
+message: two calls
+severity: warning
+language: TypeScript
+rule:
+  pattern: $A(); $B();
+";
+  let dir = create_test_files([
+    ("sgconfig.yml", CONFIG),
+    ("rules/rule.yml", rule),
+    ("a.ts", "a(); b(); c();\n"),
+  ])?;
+  let ret = cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args(["scan", "--json=compact"])
+    .assert()
+    .success();
+  let json: Value = from_slice(&ret.get_output().stdout)?;
+  let diagnostics = json.as_array().expect("should be array");
+  assert_eq!(diagnostics.len(), 1);
+  assert_eq!(diagnostics[0]["text"], "a(); b();");
+  let label = &diagnostics[0]["labels"][0]["range"]["byteOffset"];
+  assert_eq!(label["start"], 0);
+  assert_eq!(label["end"], 9);
+  cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args(["scan", "--color=always"])
+    .assert()
+    .success();
+  Ok(())
+}
+
+#[test]
+fn test_scan_remove_separator() -> Result<()> {
+  let dir = create_test_files([("test.ts", "foo(a, debug, b)\nbar(a, debug)\n")])?;
+  let rule = "{id: no-debug, language: ts, rule: {pattern: debug, inside: {kind: arguments}}, fix: {template: '', removeSeparator: true}}";
+  cargo_bin_cmd!("ast-grep")
+    .current_dir(dir.path())
+    .args(["scan", "--inline-rules", rule, "-U"])
+    .assert()
+    .success();
+  let content = std::fs::read_to_string(dir.path().join("test.ts"))?;
+  assert_eq!(content, "foo(a, b)\nbar(a)\n");
+  Ok(())
+}
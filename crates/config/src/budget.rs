@@ -0,0 +1,260 @@
+//! Per-file budgets for rule evaluation.
+//!
+//! Relational rules like `has` or `inside` with `stopBy: end` can visit a large part
+//! of the tree for every candidate node, which is quadratic on huge generated files.
+//! A [`ScanBudget`] limits how much work one file may take. The budget is installed
+//! for the current thread by [`CombinedScan::scan_with_budget`](crate::CombinedScan::scan_with_budget)
+//! and every relational traversal consumes it node by node, because the `Matcher`
+//! trait in core has no room for extra evaluation state.
+//! A [`Cancellation`] stops the scan the same way, e.g. when the scanned text is outdated.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Check the clock and cancellation only every this many polls, `Instant::now` is not free.
+const CLOCK_INTERVAL: usize = 256;
+
+/// Limits of the work spent by all rules on one file.
+#[derive(Clone, Debug, Default)]
+pub struct ScanBudget {
+  /// Wall-clock time allowed for scanning one file.
+  pub timeout: Option<Duration>,
+  /// Number of nodes that relational rules may visit in one file.
+  pub max_node_visits: Option<usize>,
+  /// Abort the scan once the check returns true.
+  pub cancellation: Option<Cancellation>,
+}
+
+/// A check polled during the scan, e.g. whether a newer version of the file has arrived.
+#[derive(Clone)]
+pub struct Cancellation(Arc<dyn Fn() -> bool + Send + Sync>);
+
+impl Cancellation {
+  pub fn new(is_cancelled: impl Fn() -> bool + Send + Sync + 'static) -> Self {
+    Self(Arc::new(is_cancelled))
+  }
+
+  pub fn is_cancelled(&self) -> bool {
+    (self.0)()
+  }
+}
+
+impl fmt::Debug for Cancellation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str("Cancellation")
+  }
+}
+
+/// Which limit of a [`ScanBudget`] was exceeded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BudgetKind {
+  Timeout(Duration),
+  NodeVisits(usize),
+  /// the [`Cancellation`] of the budget returned true
+  Cancelled,
+}
+
+impl fmt::Display for BudgetKind {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      BudgetKind::Timeout(limit) => write!(f, "time budget of {}ms", limit.as_millis()),
+      BudgetKind::NodeVisits(limit) => write!(f, "node visit budget of {limit}"),
+      BudgetKind::Cancelled => write!(f, "cancellation"),
+    }
+  }
+}
+
+/// The scan of a file was aborted because a rule exceeded the budget.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BudgetExceeded {
+  /// id of the rule being evaluated when the budget ran out
+  pub rule_id: String,
+  pub kind: BudgetKind,
+}
+
+impl fmt::Display for BudgetExceeded {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if let BudgetKind::Cancelled = self.kind {
+      return write!(f, "scan was cancelled during rule `{}`", self.rule_id);
+    }
+    write!(f, "rule `{}` exceeded the {}", self.rule_id, self.kind)
+  }
+}
+
+struct Tracker {
+  budget: ScanBudget,
+  deadline: Option<Instant>,
+  visits: usize,
+  polls: usize,
+  exceeded: Option<BudgetKind>,
+}
+
+impl Tracker {
+  fn new(budget: ScanBudget) -> Self {
+    Self {
+      deadline: budget.timeout.map(|t| Instant::now() + t),
+      budget,
+      visits: 0,
+      polls: 0,
+      exceeded: None,
+    }
+  }
+
+  fn visit(&mut self) -> bool {
+    self.visits += 1;
+    if let Some(max) = self.budget.max_node_visits {
+      if self.visits > max {
+        self.exceeded = Some(BudgetKind::NodeVisits(max));
+        return false;
+      }
+    }
+    self.poll()
+  }
+
+  fn poll(&mut self) -> bool {
+    if self.exceeded.is_some() {
+      return false;
+    }
+    self.polls += 1;
+    if self.polls % CLOCK_INTERVAL != 0 {
+      return true;
+    }
+    if let (Some(deadline), Some(timeout)) = (self.deadline, self.budget.timeout) {
+      if Instant::now() >= deadline {
+        self.exceeded = Some(BudgetKind::Timeout(timeout));
+        return false;
+      }
+    }
+    if let Some(cancellation) = &self.budget.cancellation {
+      if cancellation.is_cancelled() {
+        self.exceeded = Some(BudgetKind::Cancelled);
+        return false;
+      }
+    }
+    true
+  }
+}
+
+thread_local! {
+  static TRACKER: RefCell<Option<Tracker>> = const { RefCell::new(None) };
+}
+
+/// Installs a budget for the current thread until dropped.
+/// The previously installed budget, if any, is restored on drop.
+pub(crate) struct BudgetGuard {
+  previous: Option<Tracker>,
+}
+
+impl BudgetGuard {
+  pub fn install(budget: ScanBudget) -> Self {
+    let previous = TRACKER.with(|t| t.borrow_mut().replace(Tracker::new(budget)));
+    Self { previous }
+  }
+
+  /// Count time spent outside relational traversal, e.g. in the scan loop.
+  pub fn poll(&self) -> bool {
+    TRACKER.with(|t| t.borrow_mut().as_mut().map_or(true, Tracker::poll))
+  }
+
+  pub fn exceeded(&self) -> Option<BudgetKind> {
+    TRACKER.with(|t| t.borrow().as_ref().and_then(|t| t.exceeded))
+  }
+}
+
+impl Drop for BudgetGuard {
+  fn drop(&mut self) {
+    let previous = self.previous.take();
+    TRACKER.with(|t| *t.borrow_mut() = previous);
+  }
+}
+
+/// Consume one node visit of the installed budget.
+/// Returns false if the budget is exhausted and the traversal should stop.
+/// It always returns true if no budget is installed.
+pub(crate) fn visit() -> bool {
+  TRACKER.with(|t| t.borrow_mut().as_mut().map_or(true, Tracker::visit))
+}
+
+/// Stop the traversal of nodes once the installed budget is exhausted.
+pub(crate) fn visit_all<T>(iter: impl Iterator<Item = T>) -> impl Iterator<Item = T> {
+  iter.take_while(|_| visit())
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_no_budget() {
+    for _ in 0..1000 {
+      assert!(visit());
+    }
+  }
+
+  #[test]
+  fn test_node_visits() {
+    let guard = BudgetGuard::install(ScanBudget {
+      timeout: None,
+      max_node_visits: Some(3),
+      cancellation: None,
+    });
+    assert!(visit());
+    assert!(visit());
+    assert!(visit());
+    assert!(guard.exceeded().is_none());
+    assert!(!visit());
+    assert!(!guard.poll());
+    assert_eq!(guard.exceeded(), Some(BudgetKind::NodeVisits(3)));
+    drop(guard);
+    assert!(visit());
+  }
+
+  #[test]
+  fn test_timeout() {
+    let timeout = Duration::from_millis(1);
+    let guard = BudgetGuard::install(ScanBudget {
+      timeout: Some(timeout),
+      max_node_visits: None,
+      cancellation: None,
+    });
+    std::thread::sleep(Duration::from_millis(5));
+    let stopped = (0..CLOCK_INTERVAL).any(|_| !visit());
+    assert!(stopped);
+    assert_eq!(guard.exceeded(), Some(BudgetKind::Timeout(timeout)));
+  }
+
+  #[test]
+  fn test_cancellation() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let flag = cancelled.clone();
+    let guard = BudgetGuard::install(ScanBudget {
+      cancellation: Some(Cancellation::new(move || flag.load(Ordering::Relaxed))),
+      ..Default::default()
+    });
+    assert!((0..CLOCK_INTERVAL).all(|_| visit()));
+    cancelled.store(true, Ordering::Relaxed);
+    let stopped = (0..CLOCK_INTERVAL).any(|_| !visit());
+    assert!(stopped);
+    assert_eq!(guard.exceeded(), Some(BudgetKind::Cancelled));
+  }
+
+  #[test]
+  fn test_nested_guard() {
+    let outer = BudgetGuard::install(ScanBudget {
+      timeout: None,
+      max_node_visits: Some(1),
+      cancellation: None,
+    });
+    {
+      let _inner = BudgetGuard::install(ScanBudget::default());
+      assert!(visit());
+      assert!(visit());
+    }
+    assert!(visit());
+    assert!(!visit());
+    assert!(outer.exceeded().is_some());
+  }
+}
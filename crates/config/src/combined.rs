@@ -1,3 +1,5 @@
+use crate::budget::{BudgetExceeded, BudgetGuard, ScanBudget};
+use crate::fixer::SerializableFixer;
 use crate::{RuleConfig, SerializableRule, SerializableRuleConfig, SerializableRuleCore, Severity};
 
 use ast_grep_core::language::Language;
@@ -9,6 +11,8 @@ use std::collections::{HashMap, HashSet};
 pub struct ScanResult<'t, 'r, D: Doc, L: Language> {
   pub diffs: Vec<(&'r RuleConfig<L>, NodeMatch<'t, D>)>,
   pub matches: Vec<(&'r RuleConfig<L>, Vec<NodeMatch<'t, D>>)>,
+  /// set if the scan was aborted by the budget, diffs and matches are empty then
+  pub budget_exceeded: Option<BudgetExceeded>,
 }
 
 /// store the index to the rule and the matched node
@@ -46,17 +50,69 @@ impl<'t, D: Doc> ScanResultInner<'t, D> {
         matches.push((rule, supprs));
       }
     }
-    ScanResult { diffs, matches }
+    ScanResult {
+      diffs,
+      matches,
+      budget_exceeded: None,
+    }
+  }
+
+  fn aborted<'r, L: Language>(exceeded: BudgetExceeded) -> ScanResult<'t, 'r, D, L> {
+    ScanResult {
+      diffs: vec![],
+      matches: vec![],
+      budget_exceeded: Some(exceeded),
+    }
   }
 }
 
-enum SuppressKind {
+/// Where an `ast-grep-ignore` comment applies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SuppressKind {
   /// suppress the whole file
   File,
   /// suppress specific line
   Line(usize),
 }
 
+/// A comment like `// ast-grep-ignore: rule-id` found in a source file.
+pub struct SuppressionComment<'r, D: Doc> {
+  pub node: Node<'r, D>,
+  pub kind: SuppressKind,
+  /// suppressed rule ids in written order, None = suppress all
+  pub rules: Option<Vec<String>>,
+}
+
+/// Find all suppression comments in document order.
+pub fn find_suppressions<'r, D: Doc>(root: &Node<'r, D>) -> Vec<SuppressionComment<'r, D>> {
+  root
+    .dfs()
+    .filter_map(|node| {
+      let kind = get_suppression_kind(&node)?;
+      let rules = parse_suppression_list(&node.text());
+      Some(SuppressionComment { node, kind, rules })
+    })
+    .collect()
+}
+
+/// The body of a comment suppressing the rules, without comment delimiters.
+pub fn suppression_text(rules: &[&str]) -> String {
+  format!("{IGNORE_TEXT}: {}", rules.join(", "))
+}
+
+/// Add a rule to the text of a suppression comment, keeping the comment prefix.
+/// Returns None if the comment already suppresses the rule.
+pub fn merge_suppression(comment: &str, rule_id: &str) -> Option<String> {
+  let mut rules = parse_suppression_list(comment)?;
+  if rules.iter().any(|r| r == rule_id) {
+    return None;
+  }
+  rules.push(rule_id.to_string());
+  let (prefix, _) = comment.split_once(IGNORE_TEXT)?;
+  let rules: Vec<_> = rules.iter().map(String::as_str).collect();
+  Some(format!("{prefix}{}", suppression_text(&rules)))
+}
+
 fn get_suppression_kind(node: &Node<'_, impl Doc>) -> Option<SuppressKind> {
   if !node.kind().contains("comment") || !node.text().contains(IGNORE_TEXT) {
     return None;
@@ -237,6 +293,24 @@ impl<'r, L: Language> CombinedScan<'r, L> {
   where
     D: Doc<Lang = L>,
   {
+    self.scan_with_budget(root, separate_fix, ScanBudget::default(), None)
+  }
+
+  /// Scan the file like `scan`, but abort once the rules exceed the budget.
+  /// An aborted scan returns no matches and reports the rule being evaluated.
+  /// The scan stops early without error when `max_matches` are found across all rules.
+  pub fn scan_with_budget<'a, D>(
+    &self,
+    root: &'a AstGrep<D>,
+    separate_fix: bool,
+    budget: ScanBudget,
+    max_matches: Option<usize>,
+  ) -> ScanResult<'a, '_, D, L>
+  where
+    D: Doc<Lang = L>,
+  {
+    let mut remaining = max_matches.unwrap_or(usize::MAX);
+    let guard = BudgetGuard::install(budget);
     let mut result = ScanResultInner {
       diffs: vec![],
       matches: HashMap::new(),
@@ -249,7 +323,12 @@ impl<'r, L: Language> CombinedScan<'r, L> {
         return result.into_result(self, separate_fix);
       }
     }
-    for node in root.root().dfs() {
+    // the last sequence match of each rule, whose later siblings are not matched again
+    let mut sequences: HashMap<usize, NodeMatch<D>> = HashMap::new();
+    'scan: for node in root.root().dfs() {
+      if remaining == 0 {
+        break 'scan;
+      }
       let kind = node.kind_id() as usize;
       let Some(rule_idx) = self.kind_rule_mapping.get(kind) else {
         continue;
@@ -257,9 +336,21 @@ impl<'r, L: Language> CombinedScan<'r, L> {
       let line_sup = suppressions.line_suppression(&node);
       for &idx in rule_idx {
         let rule = &self.rules[idx];
-        let Some(ret) = rule.matcher.match_node(node.clone()) else {
+        if sequences.get(&idx).is_some_and(|m| m.covers_sibling(&node)) {
+          continue;
+        }
+        let matched = rule.matcher.match_node(node.clone());
+        if !guard.poll() {
+          let kind = guard.exceeded().expect("budget must be exceeded");
+          let rule_id = rule.id.clone();
+          return ScanResultInner::aborted(BudgetExceeded { rule_id, kind });
+        }
+        let Some(ret) = matched else {
           continue;
         };
+        if ret.is_sequence() {
+          sequences.insert(idx, ret.clone());
+        }
         if let Some(id) = file_sup.suppressed_id(&rule.id) {
           suppression_nodes.remove(&id);
           continue;
@@ -274,8 +365,16 @@ impl<'r, L: Language> CombinedScan<'r, L> {
         } else {
           result.diffs.push((idx, ret));
         }
+        remaining -= 1;
+        if remaining == 0 {
+          break 'scan;
+        }
       }
     }
+    if remaining == 0 {
+      // suppressions after the last match are not visited
+      return result.into_result(self, separate_fix);
+    }
     result.unused_suppressions = suppression_nodes
       .into_values()
       .map(NodeMatch::from)
@@ -288,22 +387,41 @@ impl<'r, L: Language> CombinedScan<'r, L> {
   }
 
   pub fn unused_config(severity: Severity, lang: L) -> RuleConfig<L> {
+    let fix = crate::from_str(r#"''"#).unwrap();
+    let message = "Unused 'ast-grep-ignore' directive.".into();
+    Self::diagnostic_config("unused-suppression", message, fix, severity, lang)
+  }
+
+  /// The rule to report a file whose scan was aborted by the budget.
+  pub fn budget_config(exceeded: &BudgetExceeded, severity: Severity, lang: L) -> RuleConfig<L> {
+    let message = format!("Scan aborted: {exceeded}. Matches in this file are not reported.");
+    Self::diagnostic_config("budget-exceeded", message, None, severity, lang)
+  }
+
+  fn diagnostic_config(
+    id: &str,
+    message: String,
+    fix: Option<SerializableFixer>,
+    severity: Severity,
+    lang: L,
+  ) -> RuleConfig<L> {
     let rule: SerializableRule = crate::from_str(r#"{"any": []}"#).unwrap();
     let core = SerializableRuleCore {
       rule,
       constraints: None,
-      fix: crate::from_str(r#"''"#).unwrap(),
+      fix,
+      exact_back_reference: None,
       transform: None,
       utils: None,
     };
     let config = SerializableRuleConfig {
       core,
-      id: "unused-suppression".to_string(),
+      id: id.to_string(),
       severity,
       files: None,
       ignores: None,
       language: lang,
-      message: "Unused 'ast-grep-ignore' directive.".into(),
+      message,
       metadata: None,
       note: None,
       rewriters: None,
@@ -315,14 +433,19 @@ impl<'r, L: Language> CombinedScan<'r, L> {
 }
 
 fn parse_suppression_set(text: &str) -> Option<HashSet<String>> {
+  let list = parse_suppression_list(text)?;
+  Some(list.into_iter().collect())
+}
+
+fn parse_suppression_list(text: &str) -> Option<Vec<String>> {
   let (_, after) = text.trim().split_once(IGNORE_TEXT)?;
   let after = after.trim();
   if after.is_empty() {
     return None;
   }
   let (_, rules) = after.split_once(':')?;
-  let set = rules.split(',').map(|r| r.trim().to_string()).collect();
-  Some(set)
+  let list = rules.split(',').map(|r| r.trim().to_string()).collect();
+  Some(list)
 }
 
 #[cfg(test)]
@@ -477,4 +600,92 @@ language: Tsx",
       assert_eq!(scanned.len(), 0);
     });
   }
+
+  #[test]
+  fn test_find_suppressions() {
+    let source = format!(
+      "// {}\n\nlet a = 1 // {}\n// ast-grep-ignore\nlet b = 2",
+      suppression_text(&["a", "b"]),
+      suppression_text(&["c"]),
+    );
+    let root = TypeScript::Tsx.ast_grep(source);
+    let found = find_suppressions(&root.root());
+    let found: Vec<_> = found.iter().map(|s| (s.kind, s.rules.clone())).collect();
+    assert_eq!(
+      found,
+      [
+        (SuppressKind::File, Some(vec!["a".into(), "b".into()])),
+        (SuppressKind::Line(2), Some(vec!["c".into()])),
+        (SuppressKind::Line(4), None),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_merge_suppression() {
+    let merged = merge_suppression("#ast-grep-ignore: a", "b");
+    assert_eq!(merged.as_deref(), Some("#ast-grep-ignore: a, b"));
+    assert!(merge_suppression("// ast-grep-ignore: a, b", "b").is_none());
+    assert!(merge_suppression("// ast-grep-ignore", "b").is_none());
+  }
+
+  #[test]
+  fn test_scan_with_budget() {
+    let rule: SerializableRuleConfig<TypeScript> = from_str(
+      r"
+id: deep
+rule: {kind: identifier, inside: {kind: program, stopBy: end}}
+language: Tsx",
+    )
+    .expect("parse");
+    let rule = RuleConfig::try_from(rule, &Default::default()).expect("work");
+    let root = TypeScript::Tsx.ast_grep("a + b + c + d");
+    let scan = CombinedScan::new(vec![&rule]);
+    let scanned = scan.scan(&root, false);
+    assert_eq!(scanned.matches[0].1.len(), 4);
+    assert!(scanned.budget_exceeded.is_none());
+    let budget = ScanBudget {
+      timeout: None,
+      max_node_visits: Some(5),
+      cancellation: None,
+    };
+    let scanned = scan.scan_with_budget(&root, false, budget, None);
+    assert!(scanned.matches.is_empty());
+    let exceeded = scanned.budget_exceeded.expect("should exceed");
+    assert_eq!(exceeded.rule_id, "deep");
+    assert_eq!(exceeded.kind, crate::BudgetKind::NodeVisits(5));
+    // budget is uninstalled after scan
+    let scanned = scan.scan(&root, false);
+    assert_eq!(scanned.matches[0].1.len(), 4);
+  }
+
+  #[test]
+  fn test_scan_max_matches() {
+    let rule = create_rule();
+    let rules = vec![&rule];
+    let scan = CombinedScan::new(rules);
+    let source = "console.log(1); console.log(2); console.log(3)";
+    let root = TypeScript::Tsx.ast_grep(source);
+    let scanned = scan.scan_with_budget(&root, false, ScanBudget::default(), Some(2));
+    assert_eq!(scanned.matches[0].1.len(), 2);
+    assert!(scanned.budget_exceeded.is_none());
+  }
+
+  #[test]
+  fn test_scan_sequence_no_overlap() {
+    let rule: SerializableRuleConfig<TypeScript> = from_str(
+      r"
+id: test
+rule: {pattern: '$A(); $B();'}
+language: Tsx",
+    )
+    .expect("parse");
+    let rule = RuleConfig::try_from(rule, &Default::default()).expect("work");
+    let scan = CombinedScan::new(vec![&rule]);
+    let root = TypeScript::Tsx.ast_grep("a(); b(); c();");
+    let scanned = scan.scan(&root, false);
+    let matches = &scanned.matches[0].1;
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].range(), 0..9);
+  }
 }
@@ -31,6 +31,10 @@ pub struct SerializableFixConfig {
   expand_start: Maybe<Relation>,
   #[serde(skip_serializing_if = "Option::is_none")]
   title: Option<String>,
+  /// Remove the matched node as a list item, along with its adjacent separator like comma.
+  /// The template must be empty and no expansion can be used.
+  #[serde(default)]
+  remove_separator: bool,
 }
 
 #[derive(Debug, Error)]
@@ -43,6 +47,8 @@ pub enum FixerError {
   InvalidRewriter,
   #[error("Fixer in list must have title.")]
   MissingTitle,
+  #[error("Fixer removing separator must have empty template and no expansion.")]
+  InvalidRemoval,
 }
 
 struct Expansion {
@@ -70,6 +76,7 @@ pub struct Fixer {
   expand_start: Option<Expansion>,
   expand_end: Option<Expansion>,
   title: Option<String>,
+  remove_separator: bool,
 }
 
 impl Fixer {
@@ -83,7 +90,13 @@ impl Fixer {
       expand_end,
       expand_start,
       title,
+      remove_separator,
     } = serialized;
+    if *remove_separator
+      && (!fix.is_empty() || expand_start.is_present() || expand_end.is_present())
+    {
+      return Err(FixerError::InvalidRemoval);
+    }
     let expand_start = Expansion::parse(expand_start, env)?;
     let expand_end = Expansion::parse(expand_end, env)?;
     let template = if let Some(trans) = transform {
@@ -97,6 +110,7 @@ impl Fixer {
       expand_start,
       expand_end,
       title: title.clone(),
+      remove_separator: *remove_separator,
     })
   }
 
@@ -147,6 +161,7 @@ impl Fixer {
       expand_end: None,
       expand_start: None,
       title: None,
+      remove_separator: false,
     })
   }
 
@@ -157,6 +172,7 @@ impl Fixer {
       expand_start: None,
       expand_end: None,
       title: None,
+      remove_separator: false,
     })
   }
 
@@ -180,6 +196,9 @@ where
   }
   fn get_replaced_range(&self, nm: &NodeMatch<'_, D>, matcher: impl Matcher) -> Range<usize> {
     let range = nm.range();
+    if self.remove_separator {
+      return nm.get_node().list_item_range();
+    }
     if self.expand_start.is_none() && self.expand_end.is_none() {
       return if let Some(len) = matcher.get_match_len(nm.get_node().clone()) {
         range.start..range.start + len
@@ -270,6 +289,7 @@ mod test {
       expand_start: Maybe::Absent,
       template: "abcd".to_string(),
       title: None,
+      remove_separator: false,
     };
     let ret = parse(config)?;
     assert!(ret.expand_start.is_none());
@@ -297,6 +317,7 @@ mod test {
       expand_start: Maybe::Absent,
       template: "var $A = 456".to_string(),
       title: None,
+      remove_separator: false,
     };
     let fixer = parse(config)?;
     let grep = TypeScript::Tsx.ast_grep("let a = 123");
@@ -315,6 +336,7 @@ mod test {
       expand_start: Maybe::Absent,
       template: "c: 456".to_string(),
       title: None,
+      remove_separator: false,
     };
     let fixer = parse(config)?;
     let grep = TypeScript::Tsx.ast_grep("var a = { b: 123, }");
@@ -328,6 +350,29 @@ mod test {
     Ok(())
   }
 
+  #[test]
+  fn test_remove_separator() -> Result<(), FixerError> {
+    use ast_grep_core::matcher::KindMatcher;
+    let config = from_str("{template: '', removeSeparator: true}").expect("should parse");
+    let SerializableFixer::Config(config) = config else {
+      panic!("wrong parsing")
+    };
+    let fixer = parse(*config)?;
+    let grep = TypeScript::Tsx.ast_grep("var a = { b: 123, c: 456 }");
+    let matcher = KindMatcher::new("pair", TypeScript::Tsx);
+    let node = grep.root().find(&matcher).expect("should found");
+    let edit = node.make_edit(&matcher, &fixer);
+    assert!(edit.inserted_text.is_empty());
+    assert_eq!(edit.position, 10);
+    assert_eq!(edit.deleted_length, 8);
+    let config = from_str("{template: 'abc', removeSeparator: true}").expect("should parse");
+    let SerializableFixer::Config(config) = config else {
+      panic!("wrong parsing")
+    };
+    assert!(matches!(parse(*config), Err(FixerError::InvalidRemoval)));
+    Ok(())
+  }
+
   #[test]
   fn test_fixer_list() -> Result<(), FixerError> {
     let config: SerializableFixer = from_str(
@@ -30,11 +30,12 @@ pub struct Label<'r, 't, D: Doc> {
 }
 
 impl<'t, D: Doc> Label<'_, 't, D> {
-  fn primary(n: &Node<'t, D>) -> Self {
+  /// The primary label spans the whole match, including the siblings of a sequence.
+  fn primary(n: &NodeMatch<'t, D>) -> Self {
     Self {
       style: LabelStyle::Primary,
-      start_node: n.clone(),
-      end_node: n.clone(),
+      start_node: n.get_node().clone(),
+      end_node: n.get_end_node().clone(),
       message: None,
     }
   }
@@ -100,13 +101,23 @@ mod tests {
   fn test_label_primary_secondary() {
     let doc = TypeScript::Tsx.ast_grep("let a = 1;");
     let root = doc.root();
-    let label = Label::primary(&root);
+    let label = Label::primary(&NodeMatch::from(root.clone()));
     assert_eq!(label.style, LabelStyle::Primary);
     assert_eq!(label.range(), root.range());
     let label2 = Label::<'_, '_, StrDoc<TypeScript>>::secondary(&root);
     assert_eq!(label2.style, LabelStyle::Secondary);
   }
 
+  #[test]
+  fn test_default_label_of_sequence() {
+    let doc = TypeScript::Tsx.ast_grep("a(); b(); c();");
+    let pattern = Pattern::try_new("$A(); $B();", TypeScript::Tsx).unwrap();
+    let m = doc.root().find(pattern).unwrap();
+    let labels = get_default_labels(&m);
+    assert_eq!(labels.len(), 1);
+    assert_eq!(labels[0].range(), 0..9);
+  }
+
   #[test]
   fn test_get_labels_from_config_single() {
     let doc = TypeScript::Tsx.ast_grep("let foo = 42;");
@@ -1,3 +1,4 @@
+mod budget;
 mod check_var;
 mod combined;
 mod fixer;
@@ -14,10 +15,14 @@ use serde_yaml::{with::singleton_map_recursive::deserialize, Deserializer, Error
 
 use ast_grep_core::language::Language;
 
-pub use combined::CombinedScan;
+pub use budget::{BudgetExceeded, BudgetKind, Cancellation, ScanBudget};
+pub use combined::{
+  find_suppressions, merge_suppression, suppression_text, CombinedScan, SuppressKind,
+  SuppressionComment,
+};
 pub use fixer::Fixer;
 pub use label::{Label, LabelStyle};
-pub use rule::referent_rule::GlobalRules;
+pub use rule::referent_rule::{GlobalRules, ReferentRuleError};
 pub use rule::DeserializeEnv;
 pub use rule::{Rule, RuleSerializeError, SerializableRule};
 pub use rule_collection::RuleCollection;
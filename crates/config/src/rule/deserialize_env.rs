@@ -41,6 +41,8 @@ pub struct DeserializeEnv<L: Language> {
   pub(crate) registration: RuleRegistration,
   /// current rules' language
   pub(crate) lang: L,
+  /// whether patterns compare repeated meta variables by exact text
+  pub(crate) exact_back_reference: bool,
 }
 
 trait DependentRule: Sized {
@@ -149,6 +151,7 @@ impl<L: Language> DeserializeEnv<L> {
     Self {
       registration: Default::default(),
       lang,
+      exact_back_reference: false,
     }
   }
 
@@ -205,6 +208,14 @@ impl<L: Language> DeserializeEnv<L> {
     Self {
       registration: RuleRegistration::from_globals(globals),
       lang: self.lang,
+      exact_back_reference: self.exact_back_reference,
+    }
+  }
+
+  pub fn with_exact_back_reference(self, exact_back_reference: bool) -> Self {
+    Self {
+      exact_back_reference,
+      ..self
     }
   }
 }
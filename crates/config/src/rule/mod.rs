@@ -46,6 +46,10 @@ pub struct SerializableRule {
 
   // atomic
   /// A pattern string or a pattern object.
+  /// A meta variable can require a node kind with a lowercase suffix, e.g. `$A_identifier`
+  /// or `$$$ARGS_number`. The suffix is used instead of `$A:identifier` because `:` collides
+  /// with type annotations and object keys. This is a syntax change: a pattern like
+  /// `$X_string` used to match that literal text and now matches any `string` node.
   #[serde(default, skip_serializing_if = "Maybe::is_absent")]
   pub pattern: Maybe<PatternStyle>,
   /// The kind name of the node to match. You can look up code's kind names in playground.
@@ -151,6 +155,9 @@ pub enum Strictness {
   Signature,
   /// similar to smart, but node kinds are ignored, only text is matched.
   Template,
+  /// similar to smart, but redundant parentheses, trailing commas and
+  /// operand order of commutative operators are ignored.
+  Normalized,
 }
 
 impl From<MatchStrictness> for Strictness {
@@ -164,6 +171,7 @@ impl From<MatchStrictness> for Strictness {
       M::Relaxed => S::Relaxed,
       M::Signature => S::Signature,
       M::Template => S::Template,
+      M::Normalized => S::Normalized,
     }
   }
 }
@@ -179,6 +187,7 @@ impl From<Strictness> for MatchStrictness {
       S::Relaxed => M::Relaxed,
       S::Signature => M::Signature,
       S::Template => M::Template,
+      S::Normalized => M::Normalized,
     }
   }
 }
@@ -245,7 +254,7 @@ impl Rule {
 
   pub fn defined_vars(&self) -> HashSet<&str> {
     match self {
-      Rule::Pattern(p) => p.defined_vars(),
+      Rule::Pattern(p) => p.defined_vars().into_keys().collect(),
       Rule::Kind(_) => HashSet::new(),
       Rule::Regex(_) => HashSet::new(),
       Rule::NthChild(n) => n.defined_vars(),
@@ -262,6 +271,30 @@ impl Rule {
     }
   }
 
+  /// Literals of which at least one must appear in the source text for the rule to match.
+  /// None if the rule can match without any literal, e.g. a `kind` rule.
+  pub fn required_literals(&self) -> Option<Vec<String>> {
+    match self {
+      // only strictness comparing every token's text keeps the pattern's terminals
+      Rule::Pattern(p) if matches!(p.strictness, MatchStrictness::Cst | MatchStrictness::Smart) => {
+        let fixed = p.fixed_string();
+        (!fixed.is_empty()).then(|| vec![fixed.into_owned()])
+      }
+      Rule::Regex(r) => r.literal_prefix().map(|p| vec![p]),
+      // every sub rule must match, so the most selective literals suffice
+      Rule::All(sub) => sub
+        .inner()
+        .iter()
+        .filter_map(|r| r.required_literals())
+        .max_by_key(|lits| lits.iter().map(String::len).min()),
+      Rule::Any(sub) => {
+        let lits: Option<Vec<_>> = sub.inner().iter().map(|r| r.required_literals()).collect();
+        lits.map(|l| l.into_iter().flatten().collect())
+      }
+      _ => None,
+    }
+  }
+
   /// check if util rules used are defined
   pub fn verify_util(&self) -> Result<(), RuleSerializeError> {
     match self {
@@ -455,7 +488,10 @@ fn deserialze_atomic_rule<L: Language>(
   use Rule as R;
   if let Some(pattern) = atomic.pattern {
     rules.push(match pattern {
-      PatternStyle::Str(pat) => R::Pattern(Pattern::try_new(&pat, env.lang.clone())?),
+      PatternStyle::Str(pat) => R::Pattern(
+        Pattern::try_new(&pat, env.lang.clone())?
+          .with_exact_back_reference(env.exact_back_reference),
+      ),
       PatternStyle::Contextual {
         context,
         selector,
@@ -471,7 +507,7 @@ fn deserialze_atomic_rule<L: Language>(
         } else {
           pattern
         };
-        R::Pattern(pattern)
+        R::Pattern(pattern.with_exact_back_reference(env.exact_back_reference))
       }
     });
   }
@@ -513,6 +549,19 @@ pattern:
 ";
     let rule: SerializableRule = from_str(src).expect("cannot parse rule");
     assert!(matches!(rule.pattern, Maybe::Present(Contextual { .. }),));
+    let src = r"
+pattern:
+  context: a == b
+  strictness: normalized
+";
+    let rule: SerializableRule = from_str(src).expect("cannot parse rule");
+    assert!(matches!(
+      rule.pattern,
+      Maybe::Present(Contextual {
+        strictness: Some(Strictness::Normalized),
+        ..
+      }),
+    ));
   }
 
   #[test]
@@ -617,6 +666,28 @@ inside:
     assert!(matches!(rule, Rule::All(_)));
   }
 
+  fn required_literals(src: &str) -> Option<Vec<String>> {
+    let rule: SerializableRule = from_str(src).expect("cannot parse rule");
+    let env = DeserializeEnv::new(TypeScript::Tsx);
+    let rule = deserialize_rule(rule, &env).expect("should deserialize");
+    rule.required_literals()
+  }
+
+  #[test]
+  fn test_required_literals() {
+    let lits = required_literals("pattern: console.log($A)");
+    assert_eq!(lits, Some(vec!["console".to_string()]));
+    let lits = required_literals("regex: ^useState");
+    assert_eq!(lits, Some(vec!["useState".to_string()]));
+    let lits = required_literals("{kind: call_expression, regex: fetch}");
+    assert_eq!(lits, Some(vec!["fetch".to_string()]));
+    let lits = required_literals("any: [pattern: foo($A), regex: bar]");
+    assert_eq!(lits, Some(vec!["foo".to_string(), "bar".to_string()]));
+    assert!(required_literals("any: [pattern: foo($A), kind: number]").is_none());
+    assert!(required_literals("kind: number").is_none());
+    assert!(required_literals("pattern: {context: foo, strictness: signature}").is_none());
+  }
+
   #[test]
   fn test_defined_vars() {
     let src = r"
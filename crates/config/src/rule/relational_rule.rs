@@ -1,10 +1,13 @@
 use super::deserialize_env::DeserializeEnv;
 use super::stop_by::{SerializableStopBy, StopBy};
+use crate::budget::visit_all;
 use crate::rule::{Rule, RuleSerializeError, SerializableRule};
-use ast_grep_core::language::Language;
+use ast_grep_core::language::{Language, NodeTypes};
 use ast_grep_core::meta_var::MetaVarEnv;
 use ast_grep_core::{Doc, Matcher, Node};
 
+use bit_set::BitSet;
+
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
@@ -36,10 +39,30 @@ fn field_name_to_id<L: Language>(
   }
 }
 
+/// Kinds of the nodes that the grammar allows to be next to the nodes matched by `rule`.
+/// ERROR nodes can contain any node, so farther relations do not narrow down kinds.
+fn neighbor_kinds<L, F>(
+  rule: &Rule,
+  stop_by: &StopBy,
+  env: &DeserializeEnv<L>,
+  relate: F,
+) -> Option<BitSet>
+where
+  L: Language,
+  F: FnOnce(&NodeTypes, &BitSet) -> Option<BitSet>,
+{
+  if !matches!(stop_by, StopBy::Neighbor) {
+    return None;
+  }
+  let node_types = env.lang.node_types()?;
+  relate(node_types, &rule.potential_kinds()?)
+}
+
 pub struct Inside {
   outer: Rule,
   field: Option<u16>,
   stop_by: StopBy,
+  kinds: Option<BitSet>,
 }
 impl Inside {
   pub(crate) fn rule(rule: Rule) -> Self {
@@ -47,6 +70,7 @@ impl Inside {
       outer: rule,
       field: None,
       stop_by: StopBy::Neighbor,
+      kinds: None,
     }
   }
   pub(crate) fn rule_descent(rule: Rule) -> Self {
@@ -54,16 +78,23 @@ impl Inside {
       outer: rule,
       field: None,
       stop_by: StopBy::End,
+      kinds: None,
     }
   }
   pub fn try_new<L: Language>(
     relation: Relation,
     env: &DeserializeEnv<L>,
   ) -> Result<Self, RuleSerializeError> {
+    let stop_by = StopBy::try_from(relation.stop_by, env)?;
+    let outer = env.deserialize_rule(relation.rule)?; // TODO
+    let kinds = neighbor_kinds(&outer, &stop_by, env, |types, outer| {
+      Some(types.children_of(outer))
+    });
     Ok(Self {
-      stop_by: StopBy::try_from(relation.stop_by, env)?,
+      stop_by,
       field: field_name_to_id(relation.field, env)?,
-      outer: env.deserialize_rule(relation.rule)?, // TODO
+      outer,
+      kinds,
     })
   }
 
@@ -108,22 +139,31 @@ impl Matcher for Inside {
       self.stop_by.find(parent, ancestors, finder)
     }
   }
+
+  fn potential_kinds(&self) -> Option<BitSet> {
+    self.kinds.clone()
+  }
 }
 
 pub struct Has {
   inner: Rule,
   stop_by: StopBy,
   field: Option<u16>,
+  kinds: Option<BitSet>,
 }
 impl Has {
   pub fn try_new<L: Language>(
     relation: Relation,
     env: &DeserializeEnv<L>,
   ) -> Result<Self, RuleSerializeError> {
+    let stop_by = StopBy::try_from(relation.stop_by, env)?;
+    let inner = env.deserialize_rule(relation.rule)?;
+    let kinds = neighbor_kinds(&inner, &stop_by, env, NodeTypes::parents_of);
     Ok(Self {
-      stop_by: StopBy::try_from(relation.stop_by, env)?,
-      inner: env.deserialize_rule(relation.rule)?,
+      stop_by,
+      inner,
       field: field_name_to_id(relation.field, env)?,
+      kinds,
     })
   }
 
@@ -152,33 +192,29 @@ impl Matcher for Has {
       let nd = node.child_by_field_id(field)?;
       return match &self.stop_by {
         StopBy::Neighbor => self.inner.match_node_with_env(nd, env),
-        StopBy::End => nd
-          .dfs()
-          .find_map(|n| self.inner.match_node_with_env(n, env)),
+        StopBy::End => visit_all(nd.dfs()).find_map(|n| self.inner.match_node_with_env(n, env)),
         StopBy::Rule(matcher) => {
           // TODO: use Pre traversal to reduce stack allocation
           self.inner.match_node_with_env(nd.clone(), env).or_else(|| {
             if nd.matches(matcher) {
               None
             } else {
-              nd.children()
-                .find_map(|n| self.inner.match_node_with_env(n, env))
+              visit_all(nd.children()).find_map(|n| self.inner.match_node_with_env(n, env))
             }
           })
         }
       };
     }
     match &self.stop_by {
-      StopBy::Neighbor => node
-        .children()
-        .find_map(|n| self.inner.match_node_with_env(n, env)),
-      StopBy::End => node
-        .dfs()
-        .skip(1)
-        .find_map(|n| self.inner.match_node_with_env(n, env)),
+      StopBy::Neighbor => {
+        visit_all(node.children()).find_map(|n| self.inner.match_node_with_env(n, env))
+      }
+      StopBy::End => {
+        visit_all(node.dfs().skip(1)).find_map(|n| self.inner.match_node_with_env(n, env))
+      }
       StopBy::Rule(matcher) => {
         // TODO: use Pre traversal to reduce stack allocation
-        node.children().find_map(|n| {
+        visit_all(node.children()).find_map(|n| {
           self.inner.match_node_with_env(n.clone(), env).or_else(|| {
             if n.matches(matcher) {
               None
@@ -190,6 +226,10 @@ impl Matcher for Has {
       }
     }
   }
+
+  fn potential_kinds(&self) -> Option<BitSet> {
+    self.kinds.clone()
+  }
 }
 
 pub struct Precedes {
@@ -445,6 +485,7 @@ mod test {
   #[test]
   fn test_has_rule() {
     let has = Has {
+      kinds: None,
       stop_by: StopBy::End,
       inner: Rule::Pattern(Pattern::new("var a = 1", TS::Tsx)),
       field: None,
@@ -473,6 +514,7 @@ mod test {
   #[test]
   fn test_has_until_should_not_abort_prematurely() {
     let has = Has {
+      kinds: None,
       stop_by: StopBy::Rule(Rule::Kind(KindMatcher::new(
         "function_declaration",
         TS::Tsx,
@@ -500,6 +542,7 @@ mod test {
   #[test]
   fn test_has_until_should_be_inclusive() {
     let has = Has {
+      kinds: None,
       stop_by: StopBy::Rule(Rule::Kind(KindMatcher::new(
         "function_declaration",
         TS::Tsx,
@@ -528,6 +571,7 @@ mod test {
   #[test]
   fn test_has_immediate() {
     let has = Has {
+      kinds: None,
       stop_by: StopBy::Neighbor,
       inner: Rule::Pattern(Pattern::new("var a = 1", TS::Tsx)),
       field: None,
@@ -535,6 +579,7 @@ mod test {
     let rule = o::All::new(vec![
       Rule::Pattern(Pattern::new("{ $$$ }", TS::Tsx)),
       Rule::Inside(Box::new(Inside {
+        kinds: None,
         outer: Rule::Pattern(Pattern::new("function test() { $$$ }", TS::Tsx)),
         stop_by: StopBy::Neighbor,
         field: None,
@@ -565,6 +610,7 @@ mod test {
   #[test]
   fn test_inside_rule() {
     let inside = Inside {
+      kinds: None,
       stop_by: StopBy::End,
       outer: Rule::Pattern(Pattern::new("function test() { $$$ }", TS::Tsx)),
       field: None,
@@ -593,6 +639,7 @@ mod test {
   #[test]
   fn test_inside_inclusive() {
     let inside = Inside {
+      kinds: None,
       stop_by: StopBy::Rule(Rule::Kind(KindMatcher::new(
         "function_declaration",
         TS::Tsx,
@@ -624,10 +671,12 @@ mod test {
   #[test]
   fn test_inside_immediate() {
     let inside = Inside {
+      kinds: None,
       stop_by: StopBy::Neighbor,
       outer: Rule::All(o::All::new(vec![
         Rule::Pattern(Pattern::new("{ $$$ }", TS::Tsx)),
         Rule::Inside(Box::new(Inside {
+          kinds: None,
           outer: Rule::Pattern(Pattern::new("function test() { $$$ }", TS::Tsx)),
           stop_by: StopBy::Neighbor,
           field: None,
@@ -660,6 +709,7 @@ mod test {
   #[test]
   fn test_inside_field() {
     let inside = Inside {
+      kinds: None,
       stop_by: StopBy::End,
       outer: Rule::Kind(KindMatcher::new("for_statement", TS::Tsx)),
       field: TS::Tsx.field_to_id("condition"),
@@ -672,6 +722,7 @@ mod test {
   #[test]
   fn test_has_field() {
     let has = Has {
+      kinds: None,
       stop_by: StopBy::End,
       inner: Rule::Pattern(Pattern::new("a = 1", TS::Tsx)),
       field: TS::Tsx.field_to_id("condition"),
@@ -713,16 +764,45 @@ mod test {
     };
     assert_eq!(follows.defined_vars(), ["B"].into_iter().collect());
     let inside = Inside {
+      kinds: None,
       stop_by: StopBy::Rule(Rule::Pattern(Pattern::new("var $C", TS::Tsx))),
       outer: Rule::Pattern(Pattern::new("var a = $A", TS::Tsx)),
       field: TS::Tsx.field_to_id("condition"),
     };
     assert_eq!(inside.defined_vars(), ["A", "C"].into_iter().collect());
     let has = Has {
+      kinds: None,
       stop_by: StopBy::Rule(Rule::Kind(KindMatcher::new("for_statement", TS::Tsx))),
       inner: Rule::Pattern(Pattern::new("var a = $A", TS::Tsx)),
       field: TS::Tsx.field_to_id("condition"),
     };
     assert_eq!(has.defined_vars(), ["A"].into_iter().collect());
   }
+
+  fn related_kinds(rule: &str) -> Option<BitSet> {
+    use ast_grep_language::SupportLang;
+    let env = DeserializeEnv::new(SupportLang::TypeScript);
+    let rule: SerializableRule = crate::from_str(rule).expect("cannot parse rule");
+    let rule = env.deserialize_rule(rule).expect("should deserialize");
+    rule.potential_kinds()
+  }
+
+  #[test]
+  fn test_related_kinds() {
+    use ast_grep_language::SupportLang;
+    let kind = |k| SupportLang::TypeScript.kind_to_id(k) as usize;
+    let kinds = related_kinds("{regex: foo, inside: {kind: arguments}}").expect("should index");
+    assert!(kinds.contains(kind("identifier")));
+    assert!(kinds.contains(kind("comment")));
+    assert!(!kinds.contains(kind("statement_block")));
+    let kinds = related_kinds("{regex: foo, has: {kind: arguments}}").expect("should index");
+    assert!(kinds.contains(kind("call_expression")));
+    assert!(!kinds.contains(kind("identifier")));
+    // ERROR nodes can contain any node, only direct relations are indexed
+    assert!(related_kinds("{regex: foo, inside: {kind: arguments, stopBy: end}}").is_none());
+    assert!(related_kinds("{regex: foo, has: {kind: arguments, stopBy: end}}").is_none());
+    // the relations of a comment are unknown
+    assert!(related_kinds("{regex: foo, has: {kind: comment}}").is_none());
+    assert!(related_kinds("{regex: foo, inside: {regex: bar}}").is_none());
+  }
 }
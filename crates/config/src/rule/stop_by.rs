@@ -1,4 +1,5 @@
 use super::deserialize_env::DeserializeEnv;
+use crate::budget::visit_all;
 use crate::rule::{Rule, RuleSerializeError, SerializableRule};
 
 use ast_grep_core::language::Language;
@@ -157,11 +158,11 @@ impl StopBy {
     match self {
       StopBy::Neighbor => finder(once()?),
       StopBy::End => {
-        let mut iter = multi();
+        let mut iter = visit_all(multi());
         iter.find_map(finder)
       }
       StopBy::Rule(stop) => {
-        let iter = multi();
+        let iter = visit_all(multi());
         iter.take_while(inclusive_until(stop)).find_map(finder)
       }
     }
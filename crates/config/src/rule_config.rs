@@ -264,6 +264,7 @@ mod test {
       transform: None,
       utils: None,
       fix: None,
+      exact_back_reference: None,
     };
     SerializableRuleConfig {
       core,
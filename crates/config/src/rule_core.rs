@@ -57,11 +57,16 @@ pub struct SerializableRuleCore {
   /// It can reference metavariables appeared in rule.
   /// See details in fix [object reference](https://ast-grep.github.io/reference/yaml/fix.html#fixconfig).
   pub fix: Option<SerializableFixer>,
+  /// Compare repeated meta variables by exact text instead of the pattern strictness.
+  /// By default, `$A == $A` under `relaxed` strictness ignores comments in the captures.
+  #[serde(rename = "exactBackReference")]
+  pub exact_back_reference: Option<bool>,
 }
 
 impl SerializableRuleCore {
   /// This function assumes env's local is empty.
   fn get_deserialize_env<L: Language>(&self, env: DeserializeEnv<L>) -> RResult<DeserializeEnv<L>> {
+    let env = env.with_exact_back_reference(self.exact_back_reference.unwrap_or(false));
     if let Some(utils) = &self.utils {
       let env = env.with_utils(utils).map_err(RuleCoreError::Utils)?;
       Ok(env)
@@ -184,10 +189,16 @@ impl RuleCore {
     Self { fixer, ..self }
   }
 
+  /// Literals of which at least one must appear in the source for the rule to match.
+  pub fn required_literals(&self) -> Option<Vec<String>> {
+    self.rule.required_literals()
+  }
+
   pub fn get_env<L: Language>(&self, lang: L) -> DeserializeEnv<L> {
     DeserializeEnv {
       lang,
       registration: self.registration.clone(),
+      exact_back_reference: false,
     }
   }
   /// Get the meta variables that have real ast node matches
@@ -356,6 +367,17 @@ transform:
     assert!(grep.root().find(&not).is_none());
   }
 
+  #[test]
+  fn test_exact_back_reference() {
+    let rule = "{context: $A == $A, strictness: relaxed}";
+    let grep = TypeScript::Tsx.ast_grep("f(a /* c */) == f(a)");
+    let matcher = get_matcher(&format!("rule: {{pattern: {rule}}}")).expect("should parse");
+    assert!(grep.root().find(&matcher).is_some());
+    let src = format!("{{rule: {{pattern: {rule}}}, exactBackReference: true}}");
+    let matcher = get_matcher(&src).expect("should parse");
+    assert!(grep.root().find(&matcher).is_none());
+  }
+
   #[test]
   fn test_rule_with_constraints() {
     let mut constraints = HashMap::new();
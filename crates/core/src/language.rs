@@ -1,6 +1,9 @@
 use crate::matcher::PatternBuilder;
+use crate::matcher::TS_BUILTIN_SYM_ERROR as ERROR_KIND;
 use crate::meta_var::{extract_meta_var, MetaVariable};
+use crate::node::KindId;
 use crate::{Pattern, PatternError};
+use bit_set::BitSet;
 use std::borrow::Cow;
 use std::path::Path;
 
@@ -41,11 +44,159 @@ pub trait Language: Clone + 'static {
     None
   }
 
+  /// Return the syntax tables used by `MatchStrictness::Normalized`.
+  /// Languages without tables match as if the strictness were `Smart`.
+  fn normalization(&self) -> Option<&'static Normalization> {
+    None
+  }
+
+  /// Return the parent and child relations between node kinds in the grammar.
+  /// They are used to index rules that do not specify a node kind themselves.
+  fn node_types(&self) -> Option<&'static NodeTypes> {
+    None
+  }
+
+  /// Return the unnamed tokens that separate items in a list, like arguments or array elements.
+  /// They are removed along with an item by `Node::remove_in_list`.
+  fn list_separators(&self) -> &'static [&'static str] {
+    &[","]
+  }
+
   fn kind_to_id(&self, kind: &str) -> u16;
   fn field_to_id(&self, field: &str) -> Option<u16>;
   fn build_pattern(&self, builder: &PatternBuilder) -> Result<Pattern, PatternError>;
 }
 
+/// Syntax that the normalized strictness treats as equivalent.
+/// * binary expressions with a commutative operator match in either operand order
+/// * parenthesized expressions match their inner expression
+pub struct Normalization {
+  binary_kinds: Vec<KindId>,
+  commutative_operators: &'static [&'static str],
+  parenthesized_kinds: Vec<KindId>,
+}
+
+impl Normalization {
+  /// Build the tables from node kind names, unknown kinds are ignored.
+  pub fn new<L: Language>(
+    lang: &L,
+    binary_kinds: &[&str],
+    commutative_operators: &'static [&'static str],
+    parenthesized_kinds: &[&str],
+  ) -> Self {
+    let to_ids = |kinds: &[&str]| {
+      kinds
+        .iter()
+        .map(|k| lang.kind_to_id(k))
+        .filter(|id| *id != 0)
+        .collect()
+    };
+    Self {
+      binary_kinds: to_ids(binary_kinds),
+      commutative_operators,
+      parenthesized_kinds: to_ids(parenthesized_kinds),
+    }
+  }
+
+  pub fn is_binary(&self, kind: KindId) -> bool {
+    self.binary_kinds.contains(&kind)
+  }
+
+  pub fn is_commutative(&self, operator: &str) -> bool {
+    self.commutative_operators.contains(&operator)
+  }
+
+  pub fn is_parenthesized(&self, kind: KindId) -> bool {
+    self.parenthesized_kinds.contains(&kind)
+  }
+}
+
+/// Which node kinds can be direct children of which, read from the grammar's `node-types.json`.
+/// Kinds that are not listed as a child, e.g. extras like comments and unnamed tokens,
+/// may appear under any node. ERROR nodes may appear under any node and contain any node,
+/// so only direct parent and child relations can narrow down kinds.
+pub struct NodeTypes {
+  children: Vec<BitSet>,
+  parents: Vec<BitSet>,
+  anywhere: BitSet,
+  all: BitSet,
+  root: KindId,
+}
+
+impl NodeTypes {
+  /// `edges` are pairs of parent and child kinds. `root` is the kind of the tree root.
+  pub fn new<E, A>(edges: E, anywhere: A, root: KindId) -> Self
+  where
+    E: IntoIterator<Item = (KindId, KindId)>,
+    A: IntoIterator<Item = KindId>,
+  {
+    fn insert(sets: &mut Vec<BitSet>, at: usize, value: usize) {
+      if sets.len() <= at {
+        sets.resize_with(at + 1, BitSet::new);
+      }
+      sets[at].insert(value);
+    }
+    let mut children = vec![];
+    let mut parents = vec![];
+    let mut anywhere: BitSet = anywhere.into_iter().map(usize::from).collect();
+    anywhere.insert(ERROR_KIND.into());
+    let mut all = anywhere.clone();
+    all.insert(root.into());
+    for (parent, child) in edges {
+      let (parent, child) = (usize::from(parent), usize::from(child));
+      insert(&mut children, parent, child);
+      insert(&mut parents, child, parent);
+      all.insert(parent);
+      all.insert(child);
+    }
+    Self {
+      children,
+      parents,
+      anywhere,
+      all,
+      root,
+    }
+  }
+
+  /// Kinds of the nodes that can be a direct child of a node in `kinds`.
+  pub fn children_of(&self, kinds: &BitSet) -> BitSet {
+    if kinds.contains(ERROR_KIND.into()) {
+      return self.all.clone();
+    }
+    let mut ret = self.anywhere.clone();
+    for kind in kinds {
+      if let Some(children) = self.children.get(kind) {
+        ret.union_with(children);
+      }
+    }
+    ret
+  }
+
+  /// Kinds of the nodes that can be the direct parent of a node in `kinds`.
+  /// Returns None if some kind may appear under any node.
+  pub fn parents_of(&self, kinds: &BitSet) -> Option<BitSet> {
+    let mut ret = BitSet::new();
+    for kind in kinds {
+      match self.parents.get(kind) {
+        Some(parents) if !parents.is_empty() && !self.anywhere.contains(kind) => {
+          ret.union_with(parents)
+        }
+        // the root node has no parent
+        _ if kind == usize::from(self.root) => continue,
+        _ => return None,
+      }
+      ret.insert(ERROR_KIND.into());
+    }
+    Some(ret)
+  }
+
+  /// Whether `kinds` contains every kind that can appear in a tree.
+  /// A rule with such kinds is checked against every node.
+  pub fn covers_all(&self, kinds: &BitSet) -> bool {
+    self.all.is_subset(kinds)
+  }
+}
+
 #[cfg(test)]
 pub use test::*;
 
@@ -53,6 +204,7 @@ pub use test::*;
 mod test {
   use super::*;
   use crate::tree_sitter::{LanguageExt, StrDoc, TSLanguage};
+  use std::sync::OnceLock;
 
   #[derive(Clone)]
   pub struct Tsx;
@@ -70,10 +222,44 @@ mod test {
     fn build_pattern(&self, builder: &PatternBuilder) -> Result<Pattern, PatternError> {
       builder.build(|src| StrDoc::try_new(src, self.clone()))
     }
+    fn normalization(&self) -> Option<&'static Normalization> {
+      static TABLE: OnceLock<Normalization> = OnceLock::new();
+      Some(TABLE.get_or_init(|| {
+        let ops = &["==", "!=", "===", "!==", "*"];
+        Normalization::new(
+          self,
+          &["binary_expression"],
+          ops,
+          &["parenthesized_expression"],
+        )
+      }))
+    }
   }
   impl LanguageExt for Tsx {
     fn get_ts_language(&self) -> TSLanguage {
       tree_sitter_typescript::LANGUAGE_TSX.into()
     }
   }
+
+  fn kinds(ids: &[usize]) -> BitSet {
+    ids.iter().copied().collect()
+  }
+
+  #[test]
+  fn test_node_types() {
+    // 1 is root, 2 and 3 are statements, 4 is an expression and 9 is a comment
+    let types = NodeTypes::new([(1, 2), (1, 3), (2, 4), (3, 4), (4, 4)], [9], 1);
+    let error = ERROR_KIND as usize;
+    assert_eq!(types.children_of(&kinds(&[2])), kinds(&[4, 9, error]));
+    assert!(types.covers_all(&types.children_of(&kinds(&[error]))));
+    assert_eq!(
+      types.parents_of(&kinds(&[4])),
+      Some(kinds(&[2, 3, 4, error]))
+    );
+    assert_eq!(types.parents_of(&kinds(&[1])), Some(kinds(&[])));
+    assert_eq!(types.parents_of(&kinds(&[9])), None);
+    assert_eq!(types.parents_of(&kinds(&[error])), None);
+    assert!(!types.covers_all(&types.children_of(&kinds(&[2]))));
+    assert!(types.covers_all(&kinds(&[1, 2, 3, 4, 9, error])));
+  }
 }
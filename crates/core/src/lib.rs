@@ -20,11 +20,13 @@ pub mod pinned;
 
 mod match_tree;
 mod node;
+mod node_path;
 
 pub use language::Language;
 pub use match_tree::MatchStrictness;
 pub use matcher::{Matcher, NodeMatch, Pattern, PatternError};
 pub use node::{Node, Position};
+pub use node_path::{NodePath, PathMatch, PathParseError, PathStep};
 pub use source::Doc;
 
 use node::Root;
@@ -92,4 +94,29 @@ mod test {
     assert_eq!(source, "return bar(1, 2) /*haha*/;"); // semicolon
     Ok(())
   }
+
+  #[test]
+  fn test_replace_sequence() -> Result {
+    let mut ast_grep = Tsx.ast_grep("function f() { m.lock(); a(); b(); m.unlock(); c(); }");
+    ast_grep.replace(
+      "$A.lock(); $$$BODY; $A.unlock();",
+      "synchronized($A, () => { $$$BODY });",
+    )?;
+    let source = ast_grep.generate();
+    assert_eq!(
+      source,
+      "function f() { synchronized(m, () => { a(); b(); }); c(); }"
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn test_replace_all_sequence() {
+    let ast_grep = Tsx.ast_grep("a(); b(); c(); d(); e();");
+    let edits = ast_grep.root().replace_all("$X(); $Y();", "$Y(); $X();");
+    // runs of siblings do not overlap
+    assert_eq!(edits.len(), 2);
+    assert_eq!(edits[0].inserted_text, "b(); a();".as_bytes());
+    assert_eq!(edits[1].inserted_text, "d(); c();".as_bytes());
+  }
 }
@@ -1,7 +1,9 @@
 use super::strictness::{MatchOneNode, MatchStrictness};
 use super::Aggregator;
+use crate::language::{Language, Normalization};
 use crate::matcher::{kind_utils, PatternNode};
 use crate::meta_var::MetaVariable;
+use crate::node::KindId;
 use crate::{Doc, Node};
 use std::iter::Peekable;
 
@@ -10,6 +12,20 @@ pub(super) fn match_node_impl<'tree, D: Doc>(
   candidate: &Node<'tree, D>,
   agg: &mut impl Aggregator<'tree, D>,
   strictness: &MatchStrictness,
+) -> MatchOneNode {
+  if let MatchStrictness::Normalized = strictness {
+    if let Some(norm) = candidate.lang().normalization() {
+      return match_normalized(goal, candidate, agg, strictness, norm);
+    }
+  }
+  match_one_node(goal, candidate, agg, strictness)
+}
+
+fn match_one_node<'tree, D: Doc>(
+  goal: &PatternNode,
+  candidate: &Node<'tree, D>,
+  agg: &mut impl Aggregator<'tree, D>,
+  strictness: &MatchStrictness,
 ) -> MatchOneNode {
   use PatternNode as P;
   match &goal {
@@ -28,10 +44,13 @@ pub(super) fn match_node_impl<'tree, D: Doc>(
       }
       c => c,
     },
-    P::MetaVar { meta_var, .. } => {
+    P::MetaVar { meta_var, kind } => {
       if strictness.should_skip_cand_for_metavar(candidate) {
         return MatchOneNode::SkipCandidate;
       }
+      if kind.is_some_and(|k| k != candidate.kind_id()) {
+        return MatchOneNode::NoMatch;
+      }
       match agg.match_meta_var(meta_var, candidate) {
         Some(()) => MatchOneNode::MatchedBoth,
         None => MatchOneNode::NoMatch, // TODO: this may be wrong
@@ -46,21 +65,197 @@ pub(super) fn match_node_impl<'tree, D: Doc>(
         return MatchOneNode::NoMatch;
       }
       let cand_children = candidate.children();
-      match match_nodes_impl_recursive(children, cand_children, agg, strictness) {
+      match match_nodes_impl_recursive(children, cand_children, agg, strictness, false) {
         Some(()) => MatchOneNode::MatchedBoth,
         None => MatchOneNode::NoMatch,
       }
     }
+    // optional siblings are expanded in the parent, a lone optional node is just required
+    P::Optional(nodes) => match nodes.iter().find(|n| !n.is_trivial()) {
+      Some(node) => match_node_impl(node, candidate, agg, strictness),
+      None => MatchOneNode::NoMatch,
+    },
+    P::Alternatives(alts) => {
+      // a lone candidate can only match alternatives of one node
+      for alt in alts {
+        let [alt] = alt.as_slice() else {
+          continue;
+        };
+        let mut attempt = agg.clone();
+        if let MatchOneNode::MatchedBoth = match_node_impl(alt, candidate, &mut attempt, strictness)
+        {
+          *agg = attempt;
+          return MatchOneNode::MatchedBoth;
+        }
+      }
+      MatchOneNode::NoMatch
+    }
+  }
+}
+
+/// Match the goal against the candidate modulo the language's normalization tables.
+/// Parentheses on only one side are unwrapped and commutative operands can be swapped.
+fn match_normalized<'tree, D: Doc>(
+  goal: &PatternNode,
+  candidate: &Node<'tree, D>,
+  agg: &mut impl Aggregator<'tree, D>,
+  strictness: &MatchStrictness,
+  norm: &Normalization,
+) -> MatchOneNode {
+  let goal_paren = match goal {
+    PatternNode::Internal { kind_id, .. } => norm.is_parenthesized(*kind_id),
+    _ => false,
+  };
+  let cand_paren = norm.is_parenthesized(candidate.kind_id());
+  if goal_paren && !cand_paren {
+    if let Some(inner) = unwrap_paren_goal(goal) {
+      return match_node_impl(inner, candidate, agg, strictness);
+    }
+  }
+  // meta variables capture the parentheses to keep rewriting faithful
+  let is_meta_var = matches!(goal, PatternNode::MetaVar { .. });
+  if cand_paren && !goal_paren && !is_meta_var {
+    let inner = candidate
+      .children()
+      .find(|n| n.is_named() && !n.kind().contains("comment"));
+    if let Some(inner) = inner {
+      return match_node_impl(goal, &inner, agg, strictness);
+    }
+  }
+  let Some(swapped) = swap_operands(goal, norm) else {
+    return match_one_node(goal, candidate, agg, strictness);
+  };
+  let mut attempt = agg.clone();
+  if let MatchOneNode::MatchedBoth = match_one_node(goal, candidate, &mut attempt, strictness) {
+    *agg = attempt;
+    return MatchOneNode::MatchedBoth;
+  }
+  let PatternNode::Internal { kind_id, .. } = goal else {
+    unreachable!("only internal nodes have operands");
+  };
+  if !kind_utils::are_kinds_matching(*kind_id, candidate.kind_id()) {
+    return MatchOneNode::NoMatch;
+  }
+  let cand_children = candidate.children();
+  match match_sequence(swapped.into_iter(), cand_children, agg, strictness, false) {
+    Some(()) => MatchOneNode::MatchedBoth,
+    None => MatchOneNode::NoMatch,
+  }
+}
+
+fn unwrap_paren_goal(goal: &PatternNode) -> Option<&PatternNode> {
+  let PatternNode::Internal { children, .. } = goal else {
+    return None;
+  };
+  let mut inner = children.iter().filter(|n| !n.is_trivial());
+  let first = inner.next()?;
+  inner.next().is_none().then_some(first)
+}
+
+/// Return the children of a commutative binary expression with operands swapped.
+fn swap_operands<'p>(goal: &'p PatternNode, norm: &Normalization) -> Option<[&'p PatternNode; 3]> {
+  let PatternNode::Internal { kind_id, children } = goal else {
+    return None;
+  };
+  if !norm.is_binary(*kind_id) {
+    return None;
+  }
+  let [left, op, right] = &children[..] else {
+    return None;
+  };
+  match op {
+    PatternNode::Terminal { text, is_named, .. } if !is_named && norm.is_commutative(text) => {
+      Some([right, op, left])
+    }
+    _ => None,
   }
 }
 
+/// Match the goals against the candidate and its following siblings.
+/// Unlike the children of a node, siblings after the matched ones are allowed.
+pub(super) fn match_siblings_impl<'tree, D: Doc>(
+  goals: &[PatternNode],
+  candidate: &Node<'tree, D>,
+  agg: &mut impl Aggregator<'tree, D>,
+  strictness: &MatchStrictness,
+) -> Option<()> {
+  let siblings = std::iter::once(candidate.clone()).chain(candidate.next_all());
+  match_nodes_impl_recursive(goals, siblings, agg, strictness, true)
+}
+
 fn match_nodes_impl_recursive<'tree, D: Doc>(
   goals: &[PatternNode],
   candidates: impl Iterator<Item = Node<'tree, D>>,
   agg: &mut impl Aggregator<'tree, D>,
   strictness: &MatchStrictness,
+  allow_trailing: bool,
 ) -> Option<()> {
-  let mut goal_children = goals.iter().peekable();
+  if !goals.iter().any(PatternNode::is_choice) {
+    return match_sequence(goals.iter(), candidates, agg, strictness, allow_trailing);
+  }
+  let candidates: Vec<_> = candidates.collect();
+  let goals: Vec<_> = goals.iter().collect();
+  let mut expanded = vec![];
+  match_choices(
+    &goals,
+    &mut expanded,
+    &candidates,
+    agg,
+    strictness,
+    allow_trailing,
+  )
+}
+
+/// Backtrack over the choices of optional nodes and alternatives among the goals.
+/// Optional nodes are tried present first, alternatives in order. The first
+/// expanded goal sequence matching the candidates wins, with its own bindings.
+/// Choices inside a chosen option, like an optional node in an alternative, are expanded too.
+fn match_choices<'p, 'tree, D: Doc>(
+  goals: &[&'p PatternNode],
+  expanded: &mut Vec<&'p PatternNode>,
+  candidates: &[Node<'tree, D>],
+  agg: &mut impl Aggregator<'tree, D>,
+  strictness: &MatchStrictness,
+  allow_trailing: bool,
+) -> Option<()> {
+  let Some(i) = goals.iter().position(|g| g.is_choice()) else {
+    let mut attempt = agg.clone();
+    let goals = expanded.iter().chain(goals).copied();
+    let candidates = candidates.iter().cloned();
+    match_sequence(goals, candidates, &mut attempt, strictness, allow_trailing)?;
+    *agg = attempt;
+    return Some(());
+  };
+  let options: Vec<&'p [PatternNode]> = match goals[i] {
+    PatternNode::Optional(nodes) => vec![nodes, &[]],
+    PatternNode::Alternatives(alts) => alts.iter().map(Vec::as_slice).collect(),
+    _ => unreachable!("goal must be a choice"),
+  };
+  let len = expanded.len();
+  expanded.extend(&goals[..i]);
+  for option in options {
+    let rest: Vec<_> = option
+      .iter()
+      .chain(goals[i + 1..].iter().copied())
+      .collect();
+    let matched = match_choices(&rest, expanded, candidates, agg, strictness, allow_trailing);
+    if matched.is_some() {
+      expanded.truncate(len);
+      return Some(());
+    }
+  }
+  expanded.truncate(len);
+  None
+}
+
+fn match_sequence<'p, 'tree: 'p, D: Doc>(
+  goals: impl Iterator<Item = &'p PatternNode>,
+  candidates: impl Iterator<Item = Node<'tree, D>>,
+  agg: &mut impl Aggregator<'tree, D>,
+  strictness: &MatchStrictness,
+  allow_trailing: bool,
+) -> Option<()> {
+  let mut goal_children = goals.peekable();
   let mut cand_children = candidates.peekable();
   cand_children.peek()?;
   loop {
@@ -86,6 +281,9 @@ fn match_nodes_impl_recursive<'tree, D: Doc>(
     }
     if goal_children.peek().is_none() {
       // all goal found
+      if allow_trailing {
+        return Some(());
+      }
       let has_trailing = cand_children.all(|n| strictness.should_skip_trailing(&n));
       return has_trailing.then_some(());
     }
@@ -114,11 +312,15 @@ fn may_match_ellipsis_impl<'p, 't: 'p, D: Doc>(
   let Ok(optional_name) = try_get_ellipsis_mode(curr_node) else {
     return Some(ControlFlow::Fallthrough);
   };
+  let kind = match curr_node {
+    PatternNode::MetaVar { kind, .. } => *kind,
+    _ => None,
+  };
   let mut matched = vec![];
   goal_children.next();
   // goal has all matched
   if goal_children.peek().is_none() {
-    match_ellipsis(agg, &optional_name, matched, cand_children, 0)?;
+    match_ellipsis(agg, &optional_name, kind, matched, cand_children, 0)?;
     return Some(ControlFlow::Return);
   }
   // skip trivial nodes in goal after ellipsis
@@ -130,6 +332,7 @@ fn may_match_ellipsis_impl<'p, 't: 'p, D: Doc>(
       match_ellipsis(
         agg,
         &optional_name,
+        kind,
         matched,
         cand_children,
         skipped_anonymous,
@@ -144,6 +347,7 @@ fn may_match_ellipsis_impl<'p, 't: 'p, D: Doc>(
     match_ellipsis(
       agg,
       &optional_name,
+      kind,
       matched,
       std::iter::empty(),
       skipped_anonymous,
@@ -164,6 +368,7 @@ fn may_match_ellipsis_impl<'p, 't: 'p, D: Doc>(
       match_ellipsis(
         agg,
         &optional_name,
+        kind,
         matched,
         std::iter::empty(),
         skipped_anonymous,
@@ -194,8 +399,12 @@ fn match_single_node_while_skip_trivial<'p, 't: 'p, D: Doc>(
       MatchOneNode::MatchedBoth => return Some(ControlFlow::Fallthrough),
       MatchOneNode::SkipGoal => {
         goal_children.next();
-        if goal_children.peek().is_none() {
+        let Some(next) = goal_children.peek() else {
           return Some(ControlFlow::Fallthrough);
+        };
+        // a skipped goal is trailing, so no ellipsis is after it
+        if try_get_ellipsis_mode(next).is_ok() {
+          return None;
         }
       }
       MatchOneNode::SkipBoth => {
@@ -231,11 +440,21 @@ fn try_get_ellipsis_mode(node: &PatternNode) -> Result<Option<String>, ()> {
 fn match_ellipsis<'t, D: Doc>(
   agg: &mut impl Aggregator<'t, D>,
   optional_name: &Option<String>,
+  kind: Option<KindId>,
   mut matched: Vec<Node<'t, D>>,
   cand_children: impl Iterator<Item = Node<'t, D>>,
   skipped_anonymous: usize,
 ) -> Option<()> {
   matched.extend(cand_children);
+  // separators and comments are not checked against the annotated kind
+  if let Some(kind) = kind {
+    let mut named = matched
+      .iter()
+      .filter(|n| n.is_named() && !n.kind().contains("comment"));
+    if !named.all(|n| n.kind_id() == kind) {
+      return None;
+    }
+  }
   agg.match_ellipsis(optional_name.as_deref(), matched, skipped_anonymous)?;
   Some(())
 }
@@ -1,16 +1,18 @@
 mod match_node;
 mod strictness;
 
-use match_node::match_node_impl;
+use match_node::{match_node_impl, match_siblings_impl};
 use strictness::MatchOneNode;
 pub use strictness::MatchStrictness;
 
+use crate::language::Language;
 use crate::meta_var::{MetaVarEnv, MetaVariable};
 use crate::{Doc, Node, Pattern};
 
 use std::borrow::Cow;
 
-trait Aggregator<'t, D: Doc> {
+/// Aggregators are cloned to try each choice of optional nodes and alternatives.
+trait Aggregator<'t, D: Doc>: Clone {
   fn match_terminal(&mut self, node: &Node<'t, D>) -> Option<()>;
   fn match_meta_var(&mut self, var: &MetaVariable, node: &Node<'t, D>) -> Option<()>;
   fn match_ellipsis(
@@ -21,6 +23,7 @@ trait Aggregator<'t, D: Doc> {
   ) -> Option<()>;
 }
 
+#[derive(Clone)]
 struct ComputeEnd(usize);
 
 impl<'t, D: Doc> Aggregator<'t, D> for ComputeEnd {
@@ -44,6 +47,81 @@ impl<'t, D: Doc> Aggregator<'t, D> for ComputeEnd {
   }
 }
 
+/// Tracks the text range of the candidates matched by a sequence pattern.
+#[derive(Clone)]
+struct SequenceRange<A> {
+  agg: A,
+  start: Option<usize>,
+  end: usize,
+}
+
+impl<A> SequenceRange<A> {
+  fn cover(&mut self, range: std::ops::Range<usize>) {
+    let start = self.start.get_or_insert(range.start);
+    *start = (*start).min(range.start);
+    self.end = self.end.max(range.end);
+  }
+}
+
+impl<'t, D: Doc, A: Aggregator<'t, D>> Aggregator<'t, D> for SequenceRange<A> {
+  fn match_terminal(&mut self, node: &Node<'t, D>) -> Option<()> {
+    self.agg.match_terminal(node)?;
+    self.cover(node.range());
+    Some(())
+  }
+  fn match_meta_var(&mut self, var: &MetaVariable, node: &Node<'t, D>) -> Option<()> {
+    self.agg.match_meta_var(var, node)?;
+    self.cover(node.range());
+    Some(())
+  }
+  fn match_ellipsis(
+    &mut self,
+    var: Option<&str>,
+    nodes: Vec<Node<'t, D>>,
+    skipped_anonymous: usize,
+  ) -> Option<()> {
+    let range = match (nodes.first(), nodes.last()) {
+      (Some(first), Some(last)) => Some(first.range().start..last.range().end),
+      _ => None,
+    };
+    self.agg.match_ellipsis(var, nodes, skipped_anonymous)?;
+    if let Some(range) = range {
+      self.cover(range);
+    }
+    Some(())
+  }
+}
+
+/// Match a sequence pattern against the candidate and its following siblings.
+/// Returns the last matched sibling and the end of the matched text,
+/// which excludes trailing punctuation like `get_match_len`.
+pub fn match_sequence_non_recursive<'tree, D: Doc>(
+  goal: &Pattern,
+  candidate: Node<'tree, D>,
+  env: &mut Cow<MetaVarEnv<'tree, D>>,
+) -> Option<(Node<'tree, D>, usize)> {
+  let goals = goal.sequence()?;
+  let mut range = SequenceRange {
+    agg: Bindings {
+      env: env.clone(),
+      equality: goal.back_reference_strictness(),
+    },
+    start: None,
+    end: 0,
+  };
+  match_siblings_impl(goals, &candidate, &mut range, &goal.strictness)?;
+  // the run must start at the candidate, not at a skipped sibling after it
+  if range.start != Some(candidate.range().start) {
+    return None;
+  }
+  let last = std::iter::once(candidate.clone())
+    .chain(candidate.next_all())
+    .take_while(|n| n.range().start < range.end)
+    .last()?;
+  *env = range.agg.env;
+  Some((last, range.end))
+}
+
 pub fn match_end_non_recursive(goal: &Pattern, candidate: Node<impl Doc>) -> Option<usize> {
   let mut end = ComputeEnd(0);
   match match_node_impl(&goal.node, &candidate, &mut end, &goal.strictness) {
@@ -56,6 +134,7 @@ fn match_leaf_meta_var<'tree, D: Doc>(
   mv: &MetaVariable,
   candidate: &Node<'tree, D>,
   env: &mut Cow<MetaVarEnv<'tree, D>>,
+  equality: Option<&MatchStrictness>,
 ) -> Option<()> {
   use MetaVariable as MV;
   match mv {
@@ -63,7 +142,9 @@ fn match_leaf_meta_var<'tree, D: Doc>(
       if *named && !candidate.is_named() {
         None
       } else {
-        env.to_mut().insert(name, candidate.clone())?;
+        env
+          .to_mut()
+          .insert_under(name, candidate.clone(), equality)?;
         Some(())
       }
     }
@@ -80,18 +161,61 @@ fn match_leaf_meta_var<'tree, D: Doc>(
       Some(())
     }
     MV::MultiCapture(name) => {
-      env.to_mut().insert(name, candidate.clone())?;
+      env
+        .to_mut()
+        .insert_under(name, candidate.clone(), equality)?;
       Some(())
     }
   }
 }
 
+/// Binds meta variables into the env like `Cow<MetaVarEnv>`, but compares
+/// repeated meta variables under the strictness, or by exact text if it is None.
+#[derive(Clone)]
+struct Bindings<'e, 't, 's, D: Doc> {
+  env: Cow<'e, MetaVarEnv<'t, D>>,
+  equality: Option<&'s MatchStrictness>,
+}
+
+impl<'e, 't, 's, D: Doc> Bindings<'e, 't, 's, D> {
+  fn new(goal: &'s Pattern, env: &mut Cow<'e, MetaVarEnv<'t, D>>) -> Self {
+    Self {
+      env: std::mem::take(env),
+      equality: goal.back_reference_strictness(),
+    }
+  }
+}
+
+impl<'t, D: Doc> Aggregator<'t, D> for Bindings<'_, 't, '_, D> {
+  fn match_terminal(&mut self, _: &Node<'t, D>) -> Option<()> {
+    Some(())
+  }
+  fn match_meta_var(&mut self, var: &MetaVariable, node: &Node<'t, D>) -> Option<()> {
+    match_leaf_meta_var(var, node, &mut self.env, self.equality)
+  }
+  fn match_ellipsis(
+    &mut self,
+    var: Option<&str>,
+    nodes: Vec<Node<'t, D>>,
+    skipped_anonymous: usize,
+  ) -> Option<()> {
+    if let Some(var) = var {
+      let mut matched = nodes;
+      let skipped = matched.len().saturating_sub(skipped_anonymous);
+      drop(matched.drain(skipped..));
+      let env = self.env.to_mut();
+      env.insert_multi_under(var, matched, self.equality)?;
+    }
+    Some(())
+  }
+}
+
 impl<'t, D: Doc> Aggregator<'t, D> for Cow<'_, MetaVarEnv<'t, D>> {
   fn match_terminal(&mut self, _: &Node<'t, D>) -> Option<()> {
     Some(())
   }
   fn match_meta_var(&mut self, var: &MetaVariable, node: &Node<'t, D>) -> Option<()> {
-    match_leaf_meta_var(var, node, self)
+    match_leaf_meta_var(var, node, self, None)
   }
   fn match_ellipsis(
     &mut self,
@@ -114,12 +238,96 @@ pub fn match_node_non_recursive<'tree, D: Doc>(
   candidate: Node<'tree, D>,
   env: &mut Cow<MetaVarEnv<'tree, D>>,
 ) -> Option<Node<'tree, D>> {
-  match match_node_impl(&goal.node, &candidate, env, &goal.strictness) {
+  let mut bindings = Bindings::new(goal, env);
+  let matched = match_node_impl(&goal.node, &candidate, &mut bindings, &goal.strictness);
+  *env = bindings.env;
+  match matched {
     MatchOneNode::MatchedBoth => Some(candidate),
     _ => None,
   }
 }
 
+/// Compare the captures of a repeated meta variable under the strictness,
+/// or by exact text if the strictness is None.
+pub(crate) fn does_node_match<D: Doc>(
+  goal: &Node<D>,
+  candidate: &Node<D>,
+  equality: Option<&MatchStrictness>,
+) -> bool {
+  match equality {
+    Some(strictness) => does_node_match_with_strictness(goal, candidate, strictness),
+    None => does_node_match_exactly(goal, candidate),
+  }
+}
+
+/// Like `does_node_match_exactly`, but nodes ignored by the strictness are skipped,
+/// e.g. comments in relaxed mode and redundant parentheses in normalized mode.
+pub fn does_node_match_with_strictness<D: Doc>(
+  goal: &Node<D>,
+  candidate: &Node<D>,
+  strictness: &MatchStrictness,
+) -> bool {
+  if goal.node_id() == candidate.node_id() {
+    return true;
+  }
+  if let MatchStrictness::Normalized = strictness {
+    let (goal, candidate) = (unwrap_parens(goal), unwrap_parens(candidate));
+    return goal.node_id() == candidate.node_id() || compare_nodes(&goal, &candidate, strictness);
+  }
+  compare_nodes(goal, candidate, strictness)
+}
+
+fn compare_nodes<D: Doc>(
+  goal: &Node<D>,
+  candidate: &Node<D>,
+  strictness: &MatchStrictness,
+) -> bool {
+  if goal.is_named_leaf() || candidate.is_named_leaf() {
+    // signature strictness ignores the text of identifiers and literals
+    return match strictness {
+      MatchStrictness::Signature => goal.kind_id() == candidate.kind_id(),
+      _ => goal.text() == candidate.text(),
+    };
+  }
+  if !strictness.should_skip_kind() && goal.kind_id() != candidate.kind_id() {
+    return false;
+  }
+  let mut goal_children = goal
+    .children()
+    .filter(|n| !strictness.should_skip_in_back_reference(n));
+  let mut cand_children = candidate
+    .children()
+    .filter(|n| !strictness.should_skip_in_back_reference(n));
+  loop {
+    match (goal_children.next(), cand_children.next()) {
+      (Some(g), Some(c)) => {
+        if !does_node_match_with_strictness(&g, &c, strictness) {
+          return false;
+        }
+      }
+      (None, None) => return true,
+      _ => return false,
+    }
+  }
+}
+
+fn unwrap_parens<'t, D: Doc>(node: &Node<'t, D>) -> Node<'t, D> {
+  let mut node = node.clone();
+  let Some(norm) = node.lang().normalization() else {
+    return node;
+  };
+  while norm.is_parenthesized(node.kind_id()) {
+    let inner = node
+      .children()
+      .find(|n| n.is_named() && !n.kind().contains("comment"));
+    match inner {
+      Some(inner) => node = inner,
+      None => break,
+    }
+  }
+  node
+}
+
 pub fn does_node_match_exactly<D: Doc>(goal: &Node<D>, candidate: &Node<D>) -> bool {
   // return true if goal and candidate are the same node
   if goal.node_id() == candidate.node_id() {
@@ -336,4 +544,31 @@ mod test {
   fn test_gh_1087() {
     test_match("($P) => $F($P)", "(x) => bar(x)");
   }
+
+  fn match_back_reference(p: &str, n: &str, strictness: MatchStrictness, exact: bool) -> bool {
+    let goal = Pattern::new(p, Tsx)
+      .with_strictness(strictness)
+      .with_exact_back_reference(exact);
+    let cand = Root::str(n, Tsx);
+    let mut env = Cow::Owned(MetaVarEnv::new());
+    find_node_recursive(&goal, cand.root(), &mut env).is_some()
+  }
+
+  #[test]
+  fn test_back_reference_strictness() {
+    use MatchStrictness as M;
+    let (p, n) = ("$A == $A", "foo(x /*c*/) == foo(x)");
+    assert!(!match_back_reference(p, n, M::Smart, false));
+    assert!(match_back_reference(p, n, M::Relaxed, false));
+    assert!(!match_back_reference(p, n, M::Relaxed, true));
+    let (p, n) = ("$A($$$B) == $C($$$B)", "f(a, /*c*/ b) == g(a, b)");
+    assert!(match_back_reference(p, n, M::Relaxed, false));
+    assert!(!match_back_reference(p, n, M::Ast, false));
+    let (p, n) = ("$A == $A", "foo(a, b,) == foo(a, (b))");
+    assert!(match_back_reference(p, n, M::Normalized, false));
+    assert!(!match_back_reference(p, n, M::Smart, false));
+    let (p, n) = ("$A == $A", "foo(a) == foo(b)");
+    assert!(match_back_reference(p, n, M::Signature, false));
+    assert!(!match_back_reference(p, n, M::Relaxed, false));
+  }
 }
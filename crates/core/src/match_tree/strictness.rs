@@ -7,12 +7,13 @@ use std::str::FromStr;
 
 #[derive(Clone)]
 pub enum MatchStrictness {
-  Cst,       // all nodes are matched
-  Smart,     // all nodes except source trivial nodes are matched.
-  Ast,       // only ast nodes are matched
-  Relaxed,   // ast-nodes excluding comments are matched
-  Signature, // ast-nodes excluding comments, without text
-  Template,  // similar to smart, but node kinds are ignored, only text is matched.
+  Cst,        // all nodes are matched
+  Smart,      // all nodes except source trivial nodes are matched.
+  Ast,        // only ast nodes are matched
+  Relaxed,    // ast-nodes excluding comments are matched
+  Signature,  // ast-nodes excluding comments, without text
+  Template,   // similar to smart, but node kinds are ignored, only text is matched.
+  Normalized, // similar to smart, but equivalent syntax in language tables is matched.
 }
 
 pub(crate) enum MatchOneNode {
@@ -27,6 +28,15 @@ fn skip_comment(n: &Node<impl Doc>) -> bool {
   n.kind().contains("comment")
 }
 
+fn is_comma(is_named: bool, text: &str) -> bool {
+  !is_named && text == ","
+}
+
+/// The unnamed last child closing its parent, like `)` in `foo(a, b)`.
+fn is_closing_delimiter(n: &Node<impl Doc>) -> bool {
+  !n.is_named() && n.next().is_none()
+}
+
 fn skip_comment_or_unnamed(n: &Node<impl Doc>) -> bool {
   if !n.is_named() {
     return true;
@@ -44,13 +54,14 @@ impl MatchStrictness {
       M::Ast => false,
       M::Relaxed => false,
       M::Signature => false,
+      M::Normalized => false,
     }
   }
 
   fn should_skip_comment(&self) -> bool {
     use MatchStrictness as M;
     match self {
-      M::Cst | M::Smart | M::Ast => false,
+      M::Cst | M::Smart | M::Ast | M::Normalized => false,
       M::Relaxed | M::Signature | M::Template => true,
     }
   }
@@ -77,6 +88,9 @@ impl MatchStrictness {
     let (skip_goal, skip_candidate) = match self {
       M::Cst => (false, false),
       M::Smart => (false, !candidate.is_named()),
+      // a trailing comma in pattern, facing the closing delimiter, can be absent in candidate
+      M::Normalized if is_comma(is_named, text) && is_closing_delimiter(candidate) => (true, false),
+      M::Normalized => (false, !candidate.is_named()),
       M::Ast => (!is_named, !candidate.is_named()),
       M::Relaxed => (!is_named, !candidate.is_named()),
       M::Signature => {
@@ -101,10 +115,22 @@ impl MatchStrictness {
     }
   }
 
+  /// Whether the node is ignored when comparing the captures of a repeated meta variable.
+  pub(crate) fn should_skip_in_back_reference<D: Doc>(&self, node: &Node<D>) -> bool {
+    use MatchStrictness as M;
+    match self {
+      M::Cst | M::Smart => false,
+      M::Normalized => !node.is_named() && node.kind() == ",",
+      M::Ast => !node.is_named(),
+      M::Relaxed | M::Signature => skip_comment_or_unnamed(node),
+      M::Template => skip_comment(node),
+    }
+  }
+
   pub(crate) fn should_skip_cand_for_metavar<D: Doc>(&self, candidate: &Node<D>) -> bool {
     use MatchStrictness as M;
     match self {
-      M::Cst | M::Ast | M::Smart => false,
+      M::Cst | M::Ast | M::Smart | M::Normalized => false,
       M::Relaxed | M::Signature | M::Template => skip_comment(candidate),
     }
   }
@@ -114,7 +140,7 @@ impl MatchStrictness {
     use MatchStrictness as M;
     match self {
       M::Cst => false,
-      M::Smart => true,
+      M::Smart | M::Normalized => true,
       M::Ast => false,
       M::Relaxed => skip_comment_or_unnamed(candidate),
       M::Signature => skip_comment_or_unnamed(candidate),
@@ -128,10 +154,19 @@ impl MatchStrictness {
   ) -> bool {
     use MatchStrictness as M;
     while let Some(pattern) = goal_children.peek() {
+      let is_comma_goal = matches!(
+        pattern,
+        PatternNode::Terminal { is_named, text, .. } if is_comma(*is_named, text)
+      );
+      if matches!(self, M::Normalized) && is_comma_goal {
+        // a trailing comma can be absent only if no goal is after it
+        goal_children.next();
+        return goal_children.peek().is_none();
+      }
       let skipped = match self {
-        M::Cst => false,
+        M::Cst => matches!(pattern, PatternNode::Optional(_)),
         M::Smart | M::Template => match pattern {
-          PatternNode::MetaVar { meta_var } => match meta_var {
+          PatternNode::MetaVar { meta_var, .. } => match meta_var {
             MetaVariable::Multiple => true,
             MetaVariable::MultiCapture(_) => true,
             MetaVariable::Dropped(_) => false,
@@ -139,9 +174,21 @@ impl MatchStrictness {
           },
           PatternNode::Terminal { .. } => false,
           PatternNode::Internal { .. } => false,
+          PatternNode::Optional(_) => true,
+          PatternNode::Alternatives(_) => false,
+        },
+        M::Normalized => match pattern {
+          PatternNode::MetaVar { meta_var, .. } => matches!(
+            meta_var,
+            MetaVariable::Multiple | MetaVariable::MultiCapture(_)
+          ),
+          PatternNode::Terminal { .. } => false,
+          PatternNode::Internal { .. } => false,
+          PatternNode::Optional(_) => true,
+          PatternNode::Alternatives(_) => false,
         },
         M::Ast | M::Relaxed | M::Signature => match pattern {
-          PatternNode::MetaVar { meta_var } => match meta_var {
+          PatternNode::MetaVar { meta_var, .. } => match meta_var {
             MetaVariable::Multiple => true,
             MetaVariable::MultiCapture(_) => true,
             MetaVariable::Dropped(named) => !named,
@@ -149,6 +196,8 @@ impl MatchStrictness {
           },
           PatternNode::Terminal { is_named, .. } => !is_named,
           PatternNode::Internal { .. } => false,
+          PatternNode::Optional(_) => true,
+          PatternNode::Alternatives(_) => false,
         },
       };
       if !skipped {
@@ -170,7 +219,10 @@ impl FromStr for MatchStrictness {
       "relaxed" => Ok(MatchStrictness::Relaxed),
       "signature" => Ok(MatchStrictness::Signature),
       "template" => Ok(MatchStrictness::Template),
-      _ => Err("invalid strictness, valid options are: cst, smart, ast, relaxed, signature"),
+      "normalized" => Ok(MatchStrictness::Normalized),
+      _ => Err(
+        "invalid strictness, valid options are: cst, smart, ast, relaxed, signature, template, normalized",
+      ),
     }
   }
 }
@@ -225,4 +277,37 @@ mod test {
       )"
     ));
   }
+
+  fn normalized_pattern(p: &str, n: &str) -> bool {
+    test_match(p, n, MatchStrictness::Normalized)
+  }
+
+  #[test]
+  fn test_normalized_pattern() {
+    assert!(normalized_pattern("a == b", "b == a"));
+    assert!(normalized_pattern("$A === null", "null === x"));
+    assert!(normalized_pattern("a * (b == c)", "(c == b) * a"));
+    assert!(!normalized_pattern("a - b", "b - a"));
+    assert!(!normalized_pattern("a == b", "b != a"));
+    assert!(normalized_pattern("if (a + b) {}", "if (a + b) {}"));
+    assert!(normalized_pattern("x = (a + b)", "x = a + b"));
+    assert!(normalized_pattern("x = a + b", "x = ((a + b))"));
+    assert!(normalized_pattern("foo(a, b,)", "foo(a, b)"));
+    assert!(normalized_pattern("foo(a, b)", "foo(a, b,)"));
+    assert!(!normalized_pattern("foo(a, b)", "foo(a, c)"));
+    // only a trailing comma can be absent
+    assert!(!normalized_pattern("foo(a, b)", "foo(a b)"));
+    assert!(!normalized_pattern("[a, b, c]", "[a b, c]"));
+    assert!(!normalized_pattern("foo(a, $$$)", "foo(a)"));
+    assert!(normalized_pattern("[a, b,]", "[a, b]"));
+  }
+
+  #[test]
+  fn test_normalized_meta_var() {
+    let mut pattern = Pattern::new("$A == $B", Tsx);
+    pattern.strictness = MatchStrictness::Normalized;
+    let root = Root::str("x = (a) == b", Tsx);
+    let found = root.root().find(pattern).expect("should match");
+    assert_eq!(found.get_env().get_match("A").unwrap().text(), "(a)");
+  }
 }
@@ -16,6 +16,7 @@ use crate::{meta_var::MetaVarEnv, Node};
 use bit_set::BitSet;
 use std::borrow::Cow;
 
+pub(crate) use kind::TS_BUILTIN_SYM_ERROR;
 pub use kind::{kind_utils, KindMatcher, KindMatcherError};
 pub use node_match::NodeMatch;
 pub use pattern::{Pattern, PatternBuilder, PatternError, PatternNode};
@@ -14,7 +14,7 @@ use thiserror::Error;
 // see https://tree-sitter.docsforge.com/master/api/#TREE_SITTER_MIN_COMPATIBLE_LANGUAGE_VERSION
 // and https://tree-sitter.docsforge.com/master/api/ts_language_symbol_for_name/
 const TS_BUILTIN_SYM_END: KindId = 0;
-const TS_BUILTIN_SYM_ERROR: KindId = 65535;
+pub(crate) const TS_BUILTIN_SYM_ERROR: KindId = 65535;
 
 #[derive(Debug, Error)]
 pub enum KindMatcherError {
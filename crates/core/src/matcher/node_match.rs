@@ -1,17 +1,19 @@
 use super::Matcher;
 use crate::meta_var::MetaVarEnv;
 use crate::replacer::Replacer;
-use crate::source::Edit as E;
-use crate::{Doc, Node};
+use crate::source::{Content, Edit as E};
+use crate::{Doc, Node, Position};
 
-use std::borrow::Borrow;
-use std::ops::Deref;
+use std::borrow::{Borrow, Cow};
+use std::ops::{Deref, Range};
 
 type Edit<D> = E<<D as Doc>::Source>;
 
 /// Represents the matched node with populated MetaVarEnv.
 /// It derefs to the Node so you can use it as a Node.
 /// To access the underlying MetaVarEnv, call `get_env` method.
+/// A match of a sequence pattern spans the node and the siblings after it,
+/// which `range`, `end_pos` and `text` cover.
 #[derive(Clone)]
 pub struct NodeMatch<'t, D: Doc>(Node<'t, D>, MetaVarEnv<'t, D>);
 
@@ -24,6 +26,50 @@ impl<'tree, D: Doc> NodeMatch<'tree, D> {
     &self.0
   }
 
+  /// The last sibling matched by a sequence pattern, or the node itself.
+  pub fn get_end_node(&self) -> &Node<'tree, D> {
+    self.get_sequence_end().unwrap_or(&self.0)
+  }
+
+  pub fn range(&self) -> Range<usize> {
+    self.0.range().start..self.get_end_node().range().end
+  }
+
+  pub fn end_pos(&self) -> Position {
+    self.get_end_node().end_pos()
+  }
+
+  pub fn text(&self) -> Cow<'tree, str> {
+    if self.get_sequence_end().is_none() {
+      return self.0.text();
+    }
+    let source = self.0.get_doc().get_source();
+    <D::Source as Content>::encode_bytes(source.get_range(self.range()))
+  }
+
+  /// Whether this is a match of a sequence pattern, spanning several siblings.
+  pub fn is_sequence(&self) -> bool {
+    self.get_sequence_end().is_some()
+  }
+
+  /// Whether the node is one of the siblings after the first node of this sequence match.
+  /// Traversals skip them, so sequence matches of the same pattern do not overlap.
+  pub fn covers_sibling(&self, node: &Node<'_, D>) -> bool {
+    let Some(end) = self.get_sequence_end() else {
+      return false;
+    };
+    let start = node.range().start;
+    if start < self.0.range().end || start > end.range().start {
+      return false;
+    }
+    let parent_id = |n: &Node<'_, D>| n.parent().map(|p| p.node_id());
+    parent_id(node) == parent_id(&self.0)
+  }
+
+  fn get_sequence_end(&self) -> Option<&Node<'tree, D>> {
+    self.1.get_sequence_end(&self.0)
+  }
+
   /// Returns the populated MetaVarEnv for this match.
   pub fn get_env(&self) -> &MetaVarEnv<'tree, D> {
     &self.1
@@ -142,4 +188,23 @@ mod test {
     assert_eq!(fixed.deleted_length, 9);
     assert_eq!(fixed.inserted_text, "var b = a".as_bytes());
   }
+
+  #[test]
+  fn test_sequence_match() {
+    let src = "if (x) { let a = 1; foo(a); bar(); }";
+    let root = Tsx.ast_grep(src);
+    let find = root
+      .root()
+      .find("let $A = $V; foo($A);")
+      .expect("should find");
+    assert_eq!(find.text(), "let a = 1; foo(a);");
+    assert_eq!(find.get_node().text(), "let a = 1;");
+    assert_eq!(find.get_end_node().text(), "foo(a);");
+    assert_eq!(find.range(), 9..27);
+    assert_eq!(find.end_pos().column(&find), 27);
+    let fixed = find.replace_by("use($V);");
+    assert_eq!(fixed.position, 9);
+    assert_eq!(fixed.deleted_length, 18);
+    assert_eq!(fixed.inserted_text, "use(1);".as_bytes());
+  }
 }
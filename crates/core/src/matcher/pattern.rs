@@ -1,55 +1,267 @@
 use crate::language::Language;
-use crate::match_tree::{match_end_non_recursive, match_node_non_recursive, MatchStrictness};
+use crate::match_tree::{
+  match_end_non_recursive, match_node_non_recursive, match_sequence_non_recursive, MatchStrictness,
+};
 use crate::matcher::{kind_utils, KindMatcher, KindMatcherError, Matcher};
-use crate::meta_var::{MetaVarEnv, MetaVariable};
-use crate::source::SgNode;
+use crate::meta_var::{extract_meta_var, is_valid_meta_var_char, MetaVarEnv, MetaVariable};
+use crate::node::KindId;
+use crate::source::{Content, SgNode};
 use crate::{Doc, Node, Root};
 
 use bit_set::BitSet;
 use thiserror::Error;
 
 use std::borrow::Cow;
-use std::collections::HashSet;
+use std::collections::HashMap;
+use std::ops::Range;
 
 #[derive(Clone)]
 pub struct Pattern {
   pub node: PatternNode,
   root_kind: Option<u16>,
   pub strictness: MatchStrictness,
+  /// Sequence patterns have several statements, the children of `node`,
+  /// which match a run of consecutive siblings.
+  sequence: bool,
+  /// Compare repeated meta variables by exact text instead of the strictness.
+  exact_back_reference: bool,
 }
 
 pub struct PatternBuilder<'a> {
   selector: Option<&'a str>,
   src: Cow<'a, str>,
+  /// Alternations in `src`, which has the first alternative of each in place.
+  alternations: Vec<Alternation>,
+  /// Meta variables marked optional in `src`, including in the first alternatives.
+  optional: Vec<OptionalMark>,
+}
+
+/// Alternation like `${{ a | b }}`, with the range of the first alternative in the source.
+struct Alternation {
+  range: Range<usize>,
+  alternatives: Vec<String>,
+  /// Optional marks of each alternative but the first, relative to the alternative.
+  optional: Vec<Vec<OptionalMark>>,
+}
+
+/// A meta variable marked optional like `$?A`, whose `?` is stripped from the source.
+/// The mark is only kept if the meta variable is a node of the pattern,
+/// so `$?A` in a string or a comment stays text.
+#[derive(Clone)]
+struct OptionalMark {
+  /// offset of the meta variable in the processed source
+  offset: usize,
+  meta_var: MetaVariable,
+}
+
+/// Alternatives standing for the run of `len` sibling nodes starting at the node `first`.
+struct Slot {
+  first: usize,
+  len: usize,
+  node: PatternNode,
 }
 
 impl PatternBuilder<'_> {
   pub fn build<D, F>(&self, parse: F) -> Result<Pattern, PatternError>
   where
-    F: FnOnce(&str) -> Result<D, String>,
+    F: Fn(&str) -> Result<D, String>,
+    D: Doc,
+  {
+    self.build_with(&parse)
+  }
+
+  fn build_with<D, F>(&self, parse: &F) -> Result<Pattern, PatternError>
+  where
+    F: Fn(&str) -> Result<D, String>,
     D: Doc,
   {
     let doc = parse(&self.src).map_err(PatternError::Parse)?;
     let root = Root::doc(doc);
-    if let Some(selector) = self.selector {
-      self.contextual(&root, selector)
+    let mut misplaced = MisplacedMarks {
+      main: Self::misplaced_marks(&root, &self.src, &self.optional, 0),
+      alternatives: vec![],
+    };
+    let slots = self.alternation_slots(&root, parse, &mut misplaced)?;
+    if !misplaced.is_empty() {
+      // put the marks back in the text and parse again
+      let meta_char = root.lang().meta_var_char();
+      return self.restore_marks(&misplaced, meta_char).build_with(parse);
+    }
+    let mut pattern = if let Some(selector) = self.selector {
+      self.contextual(&root, selector, &slots)?
     } else {
-      self.single(&root)
+      self.single(&root, &slots)?
+    };
+    let marks = self
+      .alternations
+      .iter()
+      .flat_map(|a| a.optional.iter().flatten());
+    let optional: Vec<_> = self
+      .optional
+      .iter()
+      .chain(marks)
+      .map(|m| m.meta_var.clone())
+      .collect();
+    pattern.node.set_optional(&optional);
+    Ok(pattern)
+  }
+
+  /// Indices of the marks, shifted by `shift` in `src`, that are not on a meta variable.
+  fn misplaced_marks<D: Doc>(
+    root: &Root<D>,
+    src: &str,
+    marks: &[OptionalMark],
+    shift: usize,
+  ) -> Vec<usize> {
+    (0..marks.len())
+      .filter(|&i| {
+        let offset = doc_len::<D>(&src[..marks[i].offset + shift]);
+        !is_meta_var_at(root, offset, &marks[i].meta_var)
+      })
+      .collect()
+  }
+
+  /// Find the nodes of the first alternative of every alternation in the pattern tree.
+  /// Other alternatives are parsed in its place, so each is resolved at its own position.
+  fn alternation_slots<D, F>(
+    &self,
+    root: &Root<D>,
+    parse: &F,
+    misplaced: &mut MisplacedMarks,
+  ) -> Result<Vec<Slot>, PatternError>
+  where
+    F: Fn(&str) -> Result<D, String>,
+    D: Doc,
+  {
+    let mut slots = vec![];
+    for (i, alternation) in self.alternations.iter().enumerate() {
+      let Alternation {
+        range,
+        alternatives,
+        optional,
+      } = alternation;
+      let (prefix, suffix) = (&self.src[..range.start], &self.src[range.end..]);
+      let start = doc_len::<D>(prefix);
+      let invalid = |alternative: &str| PatternError::InvalidAlternation(alternative.to_string());
+      let first = find_run(root, start..start + doc_len::<D>(&alternatives[0]))
+        .ok_or_else(|| invalid(&alternatives[0]))?;
+      let mut runs = vec![convert_run(&first)];
+      for (j, alternative) in alternatives.iter().enumerate().skip(1) {
+        let src = format!("{prefix}{alternative}{suffix}");
+        let root = Root::doc(parse(&src).map_err(PatternError::Parse)?);
+        let run = find_run(&root, start..start + doc_len::<D>(alternative))
+          .ok_or_else(|| invalid(alternative))?;
+        runs.push(convert_run(&run));
+        let marks = Self::misplaced_marks(&root, &src, &optional[j], range.start);
+        misplaced
+          .alternatives
+          .extend(marks.into_iter().map(|k| (i, j, k)));
+      }
+      slots.push(Slot {
+        first: first[0].node_id(),
+        len: first.len(),
+        node: PatternNode::Alternatives(runs),
+      });
+    }
+    Ok(slots)
+  }
+
+  /// A builder with the `?` of the misplaced marks put back in the text.
+  fn restore_marks(&self, misplaced: &MisplacedMarks, meta_char: char) -> PatternBuilder<'_> {
+    let mut src = self.src.to_string();
+    let mut optional = self.optional.clone();
+    let mut alternations: Vec<_> = self
+      .alternations
+      .iter()
+      .map(|a| Alternation {
+        range: a.range.clone(),
+        alternatives: a.alternatives.clone(),
+        optional: a.optional.clone(),
+      })
+      .collect();
+    // restore from the end so earlier offsets stay valid
+    for &k in misplaced.main.iter().rev() {
+      let mark = optional.remove(k);
+      let delta = restore_mark(&mut src, mark.offset, meta_char);
+      for m in optional.iter_mut().filter(|m| m.offset > mark.offset) {
+        m.offset += delta;
+      }
+      for a in &mut alternations {
+        if a.range.start > mark.offset {
+          a.range.start += delta;
+        }
+        if a.range.end > mark.offset {
+          a.range.end += delta;
+          // the first alternative is kept in sync with the source
+          a.alternatives[0] = src[a.range.clone()].to_string();
+        }
+      }
+    }
+    for &(i, j, k) in misplaced.alternatives.iter().rev() {
+      let alternation = &mut alternations[i];
+      let mark = alternation.optional[j].remove(k);
+      let delta = restore_mark(&mut alternation.alternatives[j], mark.offset, meta_char);
+      for m in alternation.optional[j].iter_mut() {
+        if m.offset > mark.offset {
+          m.offset += delta;
+        }
+      }
+    }
+    PatternBuilder {
+      selector: self.selector,
+      src: Cow::Owned(src),
+      alternations,
+      optional,
     }
   }
-  fn single<D: Doc>(&self, root: &Root<D>) -> Result<Pattern, PatternError> {
+
+  fn single<D: Doc>(&self, root: &Root<D>, slots: &[Slot]) -> Result<Pattern, PatternError> {
     let goal = root.root();
     if goal.children().len() == 0 {
       return Err(PatternError::NoContent(self.src.to_string()));
     }
     if !is_single_node(&goal.inner) {
-      return Err(PatternError::MultipleNode(self.src.to_string()));
+      return self.sequence(root, slots);
     }
     let node = Pattern::single_matcher(root);
-    Ok(Pattern::from(node))
+    Ok(Pattern {
+      node: convert_node_to_pattern(node, slots),
+      root_kind: None,
+      strictness: MatchStrictness::Smart,
+      sequence: false,
+      exact_back_reference: false,
+    })
   }
 
-  fn contextual<D: Doc>(&self, root: &Root<D>, selector: &str) -> Result<Pattern, PatternError> {
+  /// Pattern of several statements like `$A.lock(); $$$BODY; $A.unlock();`.
+  fn sequence<D: Doc>(&self, root: &Root<D>, slots: &[Slot]) -> Result<Pattern, PatternError> {
+    let goal = root.root();
+    if goal.children().any(|n| n.is_error()) {
+      return Err(PatternError::MultipleNode(self.src.to_string()));
+    }
+    let statements = goal.children().filter(|n| !n.kind().is_empty());
+    let children = convert_siblings(statements, slots)
+      .into_iter()
+      .map(unwrap_statement)
+      .collect();
+    Ok(Pattern {
+      node: PatternNode::Internal {
+        kind_id: goal.kind_id(),
+        children,
+      },
+      root_kind: None,
+      strictness: MatchStrictness::Smart,
+      sequence: true,
+      exact_back_reference: false,
+    })
+  }
+
+  fn contextual<D: Doc>(
+    &self,
+    root: &Root<D>,
+    selector: &str,
+    slots: &[Slot],
+  ) -> Result<Pattern, PatternError> {
     let goal = root.root();
     let kind_matcher = KindMatcher::try_new(selector, root.lang().clone())?;
     let Some(node) = goal.find(&kind_matcher) else {
@@ -60,16 +272,98 @@ impl PatternBuilder<'_> {
     };
     Ok(Pattern {
       root_kind: Some(node.kind_id()),
-      node: convert_node_to_pattern(node.get_node().clone()),
+      node: convert_node_to_pattern(node.get_node().clone(), slots),
       strictness: MatchStrictness::Smart,
+      sequence: false,
+      exact_back_reference: false,
     })
   }
 }
 
-#[derive(Clone)]
+/// Marks whose meta variable is not a node of the pattern, e.g. `'a $?A'` in a string.
+struct MisplacedMarks {
+  /// indices in the marks of the source
+  main: Vec<usize>,
+  /// indices of the alternation, the alternative and the mark
+  alternatives: Vec<(usize, usize, usize)>,
+}
+
+impl MisplacedMarks {
+  fn is_empty(&self) -> bool {
+    self.main.is_empty() && self.alternatives.is_empty()
+  }
+}
+
+/// Whether a node starting at the offset is the meta variable.
+fn is_meta_var_at<D: Doc>(root: &Root<D>, offset: usize, meta_var: &MetaVariable) -> bool {
+  let mut node = root.root();
+  loop {
+    if node.range().start == offset
+      && extract_var_from_node(&node).is_some_and(|(mv, _)| &mv == meta_var)
+    {
+      return true;
+    }
+    let Some(child) = node.children().find(|n| n.range().contains(&offset)) else {
+      return false;
+    };
+    node = child;
+  }
+}
+
+/// Replace the sigil of the meta variable at the offset with the optional mark,
+/// returning how much longer the text is.
+fn restore_mark(text: &mut String, offset: usize, meta_char: char) -> usize {
+  let sigil = text[offset..].chars().next().map_or(0, char::len_utf8);
+  let mark = format!("{meta_char}?");
+  text.replace_range(offset..offset + sigil, &mark);
+  mark.len() - sigil
+}
+
+/// Length of the text in the offsets of the document, which may not be encoded in UTF-8.
+fn doc_len<D: Doc>(text: &str) -> usize {
+  let units = D::Source::decode_str(text).len();
+  units * std::mem::size_of::<<D::Source as Content>::Underlying>()
+}
+
+/// Find the sibling nodes spanning exactly the range, in the innermost node containing it.
+fn find_run<D: Doc>(root: &Root<D>, range: Range<usize>) -> Option<Vec<Node<'_, D>>> {
+  let contains = |outer: &Range<usize>, inner: &Range<usize>| {
+    outer.start <= inner.start && inner.end <= outer.end
+  };
+  let mut node = root.root();
+  loop {
+    let Some(child) = node
+      .children()
+      .find(|n| !n.is_missing() && contains(&n.range(), &range))
+    else {
+      break;
+    };
+    node = child;
+  }
+  if node.range() == range {
+    return Some(vec![node]);
+  }
+  let run: Vec<_> = node
+    .children()
+    .filter(|n| !n.is_missing() && contains(&range, &n.range()))
+    .collect();
+  let spanned = run.first()?.range().start == range.start && run.last()?.range().end == range.end;
+  spanned.then_some(run)
+}
+
+fn convert_run<D: Doc>(run: &[Node<'_, D>]) -> Vec<PatternNode> {
+  run
+    .iter()
+    .map(|n| convert_node_to_pattern(n.clone(), &[]))
+    .collect()
+}
+
+#[derive(Clone, PartialEq)]
 pub enum PatternNode {
   MetaVar {
     meta_var: MetaVariable,
+    /// kind the matched nodes must have, from annotations like `$A_identifier`
+    kind: Option<KindId>,
   },
   /// Node without children.
   Terminal {
@@ -82,6 +376,11 @@ pub enum PatternNode {
     kind_id: u16,
     children: Vec<PatternNode>,
   },
+  /// Sibling nodes that are matched together or not at all, from `$?A`.
+  /// Contains the meta variable and the separator next to it, if any.
+  Optional(Vec<PatternNode>),
+  /// Any one of the runs of sibling nodes can match at this position, from `${{ a | b }}`.
+  Alternatives(Vec<Vec<PatternNode>>),
 }
 
 impl PatternNode {
@@ -93,10 +392,35 @@ impl PatternNode {
     }
   }
 
+  /// Optional nodes and alternatives need backtracking when matched with siblings.
+  pub(crate) fn is_choice(&self) -> bool {
+    matches!(
+      self,
+      PatternNode::Optional(_) | PatternNode::Alternatives(_)
+    )
+  }
+
+  /// Wrap the meta variables marked with `?` in Optional nodes, along with their separator.
+  /// A separator is the unnamed sibling between the meta variable and another named node,
+  /// so `,` is optional in `foo(a, $?B)` and `foo($?A, b)`, but the parentheses are not.
+  fn set_optional(&mut self, optional: &[MetaVariable]) {
+    match self {
+      PatternNode::Internal { children, .. } => group_optional(children, optional),
+      PatternNode::Alternatives(alts) => {
+        for alt in alts {
+          group_optional(alt, optional);
+        }
+      }
+      _ => (),
+    }
+  }
   pub fn fixed_string(&self) -> Cow<'_, str> {
     match &self {
       PatternNode::Terminal { text, .. } => Cow::Borrowed(text),
-      PatternNode::MetaVar { .. } => Cow::Borrowed(""),
+      // these may match nothing or something else, so they have no fixed string
+      PatternNode::MetaVar { .. } | PatternNode::Optional(_) | PatternNode::Alternatives(_) => {
+        Cow::Borrowed("")
+      }
       PatternNode::Internal { children, .. } => {
         children
           .iter()
@@ -112,25 +436,58 @@ impl PatternNode {
     }
   }
 }
+/// Group the optional meta variables among the siblings with their separator.
+fn group_optional(children: &mut Vec<PatternNode>, optional: &[MetaVariable]) {
+  for child in children.iter_mut() {
+    child.set_optional(optional);
+  }
+  let mut i = 0;
+  while i < children.len() {
+    let is_optional = matches!(
+      &children[i],
+      PatternNode::MetaVar { meta_var, .. } if optional.contains(meta_var)
+    );
+    if !is_optional {
+      i += 1;
+      continue;
+    }
+    let has_named = |nodes: &[PatternNode]| nodes.iter().any(|n| !n.is_trivial());
+    let (start, end) = if i > 0 && children[i - 1].is_trivial() && has_named(&children[..i - 1]) {
+      (i - 1, i + 1)
+    } else if children.get(i + 1).is_some_and(|n| n.is_trivial()) && has_named(&children[i + 2..]) {
+      (i, i + 2)
+    } else {
+      (i, i + 1)
+    };
+    let group = children.drain(start..end).collect();
+    children.insert(start, PatternNode::Optional(group));
+    i = start + 1;
+  }
+}
+
 impl<'r, D: Doc> From<Node<'r, D>> for PatternNode {
   fn from(node: Node<'r, D>) -> Self {
-    convert_node_to_pattern(node)
+    convert_node_to_pattern(node, &[])
   }
 }
 
 impl<'r, D: Doc> From<Node<'r, D>> for Pattern {
   fn from(node: Node<'r, D>) -> Self {
     Self {
-      node: convert_node_to_pattern(node),
+      node: convert_node_to_pattern(node, &[]),
       root_kind: None,
       strictness: MatchStrictness::Smart,
+      sequence: false,
+      exact_back_reference: false,
     }
   }
 }
 
-fn convert_node_to_pattern<D: Doc>(node: Node<'_, D>) -> PatternNode {
-  if let Some(meta_var) = extract_var_from_node(&node) {
-    PatternNode::MetaVar { meta_var }
+fn convert_node_to_pattern<D: Doc>(node: Node<'_, D>, slots: &[Slot]) -> PatternNode {
+  if let Some(slot) = slots.iter().find(|s| s.first == node.node_id()) {
+    slot.node.clone()
+  } else if let Some((meta_var, kind)) = extract_var_from_node(&node) {
+    PatternNode::MetaVar { meta_var, kind }
   } else if node.is_leaf() {
     PatternNode::Terminal {
       text: node.text().to_string(),
@@ -138,23 +495,180 @@ fn convert_node_to_pattern<D: Doc>(node: Node<'_, D>) -> PatternNode {
       kind_id: node.kind_id(),
     }
   } else {
-    let children = node.children().filter_map(|n| {
-      if n.is_missing() {
-        None
-      } else {
-        Some(PatternNode::from(n))
-      }
-    });
     PatternNode::Internal {
       kind_id: node.kind_id(),
-      children: children.collect(),
+      children: convert_siblings(node.children(), slots),
+    }
+  }
+}
+
+/// Convert the sibling nodes, replacing the run of each alternation with its alternatives.
+fn convert_siblings<'r, D: Doc>(
+  nodes: impl Iterator<Item = Node<'r, D>>,
+  slots: &[Slot],
+) -> Vec<PatternNode> {
+  let mut children = vec![];
+  // nodes left in the run of the last alternation
+  let mut skipped = 0;
+  for node in nodes.filter(|n| !n.is_missing()) {
+    if skipped > 0 {
+      skipped -= 1;
+      continue;
+    }
+    if let Some(slot) = slots.iter().find(|s| s.first == node.node_id()) {
+      skipped = slot.len - 1;
     }
+    children.push(convert_node_to_pattern(node, slots));
   }
+  children
 }
 
-fn extract_var_from_node<D: Doc>(goal: &Node<'_, D>) -> Option<MetaVariable> {
+/// A statement of only a multi meta variable, like `$$$BODY;`, is an ellipsis
+/// over sibling statements, so it can match any number of statements.
+fn unwrap_statement(node: PatternNode) -> PatternNode {
+  let PatternNode::Internal { children, .. } = &node else {
+    return node;
+  };
+  let mut named = children.iter().filter(|n| !n.is_trivial());
+  match (named.next(), named.next()) {
+    (
+      Some(
+        ellipsis @ PatternNode::MetaVar {
+          meta_var: MetaVariable::Multiple | MetaVariable::MultiCapture(_),
+          ..
+        },
+      ),
+      None,
+    ) => ellipsis.clone(),
+    _ => node,
+  }
+}
+
+/// Extract the meta variable and its kind annotation, if any, from the node.
+fn extract_var_from_node<D: Doc>(goal: &Node<'_, D>) -> Option<(MetaVariable, Option<KindId>)> {
   let key = goal.text();
-  goal.lang().extract_meta_var(&key)
+  let lang = goal.lang();
+  if let Some(meta_var) = lang.extract_meta_var(&key) {
+    return Some((meta_var, None));
+  }
+  let (var, kind) = split_kind_annotation(&key)?;
+  let meta_var = lang.extract_meta_var(var)?;
+  let kind_id = lang.kind_to_id(kind);
+  if KindMatcher::from_id(kind_id).is_invalid() {
+    return None;
+  }
+  Some((meta_var, Some(kind_id)))
+}
+
+/// Split a kind annotation like `$A_identifier` or `$$$ARGS_number` into the meta variable
+/// and the kind. Names of meta variables have no lowercase letters, so the annotation
+/// starts at the first one after an underscore. The annotated meta variable is one
+/// identifier like the bare one, so it never changes how the pattern is parsed.
+fn split_kind_annotation(text: &str) -> Option<(&str, &str)> {
+  let start = text.find(|c: char| c.is_ascii_lowercase())?;
+  let var = text[..start].strip_suffix('_')?;
+  let kind = &text[start..];
+  kind
+    .chars()
+    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    .then_some((var, kind))
+}
+
+/// Pre-process a piece of the pattern source into `out`, stripping the `?` marking optional
+/// meta variables like `$?A`. The mark goes between the sigil and the name, so `?` after a
+/// meta variable stays code, e.g. `$A?.b`, `$A ? $B : $C`, Rust's `$A?` or nullable types
+/// like `$T?`. Returns the marks with their offsets in `out`.
+fn process_piece<L: Language>(
+  piece: &str,
+  lang: &L,
+  out: &mut String,
+) -> Result<Vec<OptionalMark>, PatternError> {
+  let meta_char = lang.meta_var_char();
+  let mark = format!("{meta_char}?");
+  let mut marks = vec![];
+  // text not yet pre-processed
+  let mut segment = String::new();
+  // end of the piece copied to `segment`
+  let mut copied = 0;
+  for (start, _) in piece.match_indices(&mark) {
+    let name_start = start + mark.len();
+    // lowercase letters are the kind annotation of the meta variable
+    let name_len = piece[name_start..]
+      .find(|c: char| !is_valid_meta_var_char(c) && !c.is_ascii_lowercase())
+      .unwrap_or(piece.len() - name_start);
+    let name = &piece[name_start..name_start + name_len];
+    let var = format!("{meta_char}{name}");
+    let var = split_kind_annotation(&var).map_or(var.as_str(), |(var, _)| var);
+    // only single node meta variables can be optional
+    let meta_var = match extract_meta_var(var, meta_char) {
+      Some(mv @ (MetaVariable::Capture(..) | MetaVariable::Dropped(_))) => mv,
+      _ => continue,
+    };
+    if piece[..start].ends_with(meta_char) {
+      let sigils = piece[..start].len() - piece[..start].trim_end_matches(meta_char).len();
+      return Err(PatternError::InvalidOptional(format!(
+        "{}{mark}{name}",
+        &piece[start - sigils..start]
+      )));
+    }
+    segment.push_str(&piece[copied..start]);
+    out.push_str(&lang.pre_process_pattern(&segment));
+    segment.clear();
+    marks.push(OptionalMark {
+      offset: out.len(),
+      meta_var,
+    });
+    segment.push(meta_char);
+    copied = name_start;
+  }
+  segment.push_str(&piece[copied..]);
+  out.push_str(&lang.pre_process_pattern(&segment));
+  Ok(marks)
+}
+
+/// Returns the start, the alternatives and the end of the first alternation like
+/// `${{ a | b.c }}` in the source. Alternatives are separated by `|` outside of brackets,
+/// so `${{ (a | b) | c }}` has two. Without two alternatives the text is not an alternation.
+fn find_alternation(src: &str, meta_char: char) -> Option<(usize, Vec<&str>, usize)> {
+  let open = format!("{meta_char}{{{{");
+  let mut from = 0;
+  while let Some(offset) = src[from..].find(&open) {
+    let start = from + offset;
+    let body = start + open.len();
+    from = body;
+    let mut alternatives = vec![];
+    let mut depth = 0;
+    let mut alternative_start = body;
+    let mut end = None;
+    let mut chars = src[body..].char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+      let i = body + i;
+      match c {
+        '(' | '[' | '{' => depth += 1,
+        '}' if depth == 0 && chars.next_if(|(_, c)| *c == '}').is_some() => {
+          alternatives.push(&src[alternative_start..i]);
+          end = Some(i + 2);
+          break;
+        }
+        ')' | ']' | '}' if depth > 0 => depth -= 1,
+        // unbalanced brackets end the alternation
+        ')' | ']' | '}' => break,
+        '|' if depth == 0 => {
+          alternatives.push(&src[alternative_start..i]);
+          alternative_start = i + 1;
+        }
+        _ => (),
+      }
+    }
+    let Some(end) = end else {
+      continue;
+    };
+    let alternatives: Vec<_> = alternatives.into_iter().map(str::trim).collect();
+    if alternatives.len() > 1 && alternatives.iter().all(|a| !a.is_empty()) {
+      return Some((start, alternatives, end));
+    }
+  }
+  None
 }
 
 #[derive(Debug, Error)]
@@ -169,6 +683,10 @@ pub enum PatternError {
   InvalidKind(#[from] KindMatcherError),
   #[error("Fails to create Contextual pattern: selector `{selector}` matches no node in the context `{context}`.")]
   NoSelectorInContext { context: String, selector: String },
+  #[error("Alternative `{0}` must be whole sibling nodes and cannot have another alternation.")]
+  InvalidAlternation(String),
+  #[error("Only single meta variables like `$?A` can be optional, found `{0}`.")]
+  InvalidOptional(String),
 }
 
 #[inline]
@@ -186,30 +704,46 @@ fn is_single_node<'r, N: SgNode<'r>>(n: &N) -> bool {
 }
 impl Pattern {
   pub fn has_error(&self) -> bool {
-    let kind = match &self.node {
-      PatternNode::Terminal { kind_id, .. } => *kind_id,
-      PatternNode::Internal { kind_id, .. } => *kind_id,
-      PatternNode::MetaVar { .. } => match self.root_kind {
-        Some(k) => k,
-        None => return false,
-      },
-    };
-    kind_utils::is_error_kind(kind)
+    has_error_kind(&self.node, self.root_kind)
   }
 
   pub fn fixed_string(&self) -> Cow<'_, str> {
     self.node.fixed_string()
   }
 
-  /// Get all defined variables in the pattern.
+  /// Statements of a sequence pattern, None if the pattern is a single node.
+  pub fn sequence(&self) -> Option<&[PatternNode]> {
+    match &self.node {
+      PatternNode::Internal { children, .. } if self.sequence => Some(children),
+      _ => None,
+    }
+  }
+
+  /// Get all defined variables in the pattern, with the kind annotated on them if any.
   /// Used for validating rules and report undefined variables.
-  pub fn defined_vars(&self) -> HashSet<&str> {
-    let mut vars = HashSet::new();
+  pub fn defined_vars(&self) -> HashMap<&str, Option<KindId>> {
+    let mut vars = HashMap::new();
     collect_vars(&self.node, &mut vars);
     vars
   }
 }
 
+fn has_error_kind(node: &PatternNode, root_kind: Option<u16>) -> bool {
+  let kind = match node {
+    PatternNode::Terminal { kind_id, .. } => *kind_id,
+    PatternNode::Internal { kind_id, .. } => *kind_id,
+    PatternNode::MetaVar { .. } => match root_kind {
+      Some(k) => k,
+      None => return false,
+    },
+    PatternNode::Optional(_) => return false,
+    PatternNode::Alternatives(alts) => {
+      return alts.iter().flatten().any(|n| has_error_kind(n, root_kind))
+    }
+  };
+  kind_utils::is_error_kind(kind)
+}
+
 fn meta_var_name(meta_var: &MetaVariable) -> Option<&str> {
   use MetaVariable as MV;
   match meta_var {
@@ -220,32 +754,33 @@ fn meta_var_name(meta_var: &MetaVariable) -> Option<&str> {
   }
 }
 
-fn collect_vars<'p>(p: &'p PatternNode, vars: &mut HashSet<&'p str>) {
+fn collect_vars<'p>(p: &'p PatternNode, vars: &mut HashMap<&'p str, Option<KindId>>) {
   match p {
-    PatternNode::MetaVar { meta_var, .. } => {
+    PatternNode::MetaVar { meta_var, kind } => {
       if let Some(name) = meta_var_name(meta_var) {
-        vars.insert(name);
+        let annotated = vars.entry(name).or_default();
+        *annotated = annotated.or(*kind);
       }
     }
     PatternNode::Terminal { .. } => {
       // collect nothing for terminal nodes!
     }
-    PatternNode::Internal { children, .. } => {
+    PatternNode::Internal { children, .. } | PatternNode::Optional(children) => {
       for c in children {
         collect_vars(c, vars);
       }
     }
+    PatternNode::Alternatives(alts) => {
+      for c in alts.iter().flatten() {
+        collect_vars(c, vars);
+      }
+    }
   }
 }
 
 impl Pattern {
   pub fn try_new<L: Language>(src: &str, lang: L) -> Result<Self, PatternError> {
-    let processed = lang.pre_process_pattern(src);
-    let builder = PatternBuilder {
-      selector: None,
-      src: processed,
-    };
-    lang.build_pattern(&builder)
+    Self::build(src, None, &lang)
   }
 
   pub fn new<L: Language>(src: &str, lang: L) -> Self {
@@ -257,18 +792,65 @@ impl Pattern {
     self
   }
 
+  /// By default a repeated meta variable matches nodes equal under the strictness,
+  /// e.g. relaxed `$A == $A` ignores comments. Exact mode compares the node text.
+  pub fn with_exact_back_reference(mut self, exact: bool) -> Self {
+    self.exact_back_reference = exact;
+    self
+  }
+
+  /// The strictness to compare repeated meta variables, None for exact text.
+  pub(crate) fn back_reference_strictness(&self) -> Option<&MatchStrictness> {
+    (!self.exact_back_reference).then_some(&self.strictness)
+  }
+
   pub fn contextual<L: Language>(
     context: &str,
     selector: &str,
     lang: L,
   ) -> Result<Self, PatternError> {
-    let processed = lang.pre_process_pattern(context);
+    Self::build(context, Some(selector), &lang)
+  }
+
+  fn build<L: Language>(src: &str, selector: Option<&str>, lang: &L) -> Result<Self, PatternError> {
+    let meta_char = lang.meta_var_char();
+    let open = format!("{meta_char}{{{{");
+    // pieces are processed apart to keep the offsets of the alternatives and optional marks
+    let mut processed = String::new();
+    let mut optional = vec![];
+    let mut alternations = vec![];
+    let mut rest = src;
+    while let Some((start, alternatives, end)) = find_alternation(rest, meta_char) {
+      if let Some(nested) = alternatives.iter().find(|a| a.contains(&open)) {
+        return Err(PatternError::InvalidAlternation(nested.to_string()));
+      }
+      optional.extend(process_piece(&rest[..start], lang, &mut processed)?);
+      let first = processed.len();
+      optional.extend(process_piece(alternatives[0], lang, &mut processed)?);
+      let mut processed_alternatives = vec![processed[first..].to_string()];
+      let mut alternative_marks = vec![vec![]];
+      for alternative in &alternatives[1..] {
+        let mut text = String::new();
+        alternative_marks.push(process_piece(alternative, lang, &mut text)?);
+        processed_alternatives.push(text);
+      }
+      alternations.push(Alternation {
+        range: first..processed.len(),
+        alternatives: processed_alternatives,
+        optional: alternative_marks,
+      });
+      rest = &rest[end..];
+    }
+    optional.extend(process_piece(rest, lang, &mut processed)?);
     let builder = PatternBuilder {
-      selector: Some(selector),
-      src: processed,
+      selector,
+      src: Cow::Owned(processed),
+      alternations,
+      optional,
     };
     lang.build_pattern(&builder)
   }
+
   fn single_matcher<D: Doc>(root: &Root<D>) -> Node<'_, D> {
     // debug_assert!(matches!(self.style, PatternStyle::Single));
     let node = root.root();
@@ -293,6 +875,12 @@ impl Matcher for Pattern {
     }
     // do not pollute the env if pattern does not match
     let mut may_write = Cow::Borrowed(env.as_ref());
+    if self.sequence {
+      let (last, _) = match_sequence_non_recursive(self, node.clone(), &mut may_write)?;
+      may_write.to_mut().set_sequence(node.clone(), last);
+      *env = Cow::Owned(may_write.into_owned());
+      return Some(node);
+    }
     let node = match_node_non_recursive(self, node, &mut may_write)?;
     if let Cow::Owned(map) = may_write {
       // only change env when pattern matches
@@ -306,35 +894,75 @@ impl Matcher for Pattern {
     if matches!(self.strictness, MatchStrictness::Template) {
       return None;
     }
-    let kind = match self.node {
-      PatternNode::Terminal { kind_id, .. } => kind_id,
-      PatternNode::MetaVar { .. } => self.root_kind?,
-      PatternNode::Internal { kind_id, .. } => {
-        if kind_utils::is_error_kind(kind_id) {
-          // error can match any kind
-          return None;
-        }
-        kind_id
-      }
-    };
-
     let mut kinds = BitSet::new();
-    kinds.insert(kind.into());
+    // sequences start with their first statement
+    let root = self
+      .sequence()
+      .and_then(|s| s.first())
+      .unwrap_or(&self.node);
+    insert_root_kinds(root, self.root_kind, &mut kinds)?;
     Some(kinds)
   }
 
   fn get_match_len<D: Doc>(&self, node: Node<'_, D>) -> Option<usize> {
     let start = node.range().start;
-    let end = match_end_non_recursive(self, node)?;
+    let end = if self.sequence {
+      let mut env = Cow::Owned(MetaVarEnv::new());
+      match_sequence_non_recursive(self, node, &mut env)?.1
+    } else {
+      match_end_non_recursive(self, node)?
+    };
     Some(end - start)
   }
 }
+/// Insert the kinds the pattern root can match, None if it can match any kind.
+fn insert_root_kinds(node: &PatternNode, root_kind: Option<u16>, kinds: &mut BitSet) -> Option<()> {
+  let kind = match node {
+    PatternNode::Terminal { kind_id, .. } => *kind_id,
+    PatternNode::MetaVar {
+      kind: Some(kind), ..
+    } => root_kind.unwrap_or(*kind),
+    PatternNode::MetaVar { .. } => root_kind?,
+    PatternNode::Internal { kind_id, .. } => {
+      if kind_utils::is_error_kind(*kind_id) {
+        // error can match any kind
+        return None;
+      }
+      *kind_id
+    }
+    PatternNode::Optional(_) => return None,
+    PatternNode::Alternatives(alts) => {
+      for alt in alts {
+        insert_root_kinds(alt.first()?, root_kind, kinds)?;
+      }
+      return Some(());
+    }
+  };
+  kinds.insert(kind.into());
+  Some(())
+}
+
 impl std::fmt::Debug for PatternNode {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     match self {
+      Self::MetaVar {
+        meta_var,
+        kind: Some(kind),
+      } => write!(f, "{meta_var:?}_{kind}"),
       Self::MetaVar { meta_var, .. } => write!(f, "{meta_var:?}"),
       Self::Terminal { text, .. } => write!(f, "{text}"),
       Self::Internal { children, .. } => write!(f, "{children:?}"),
+      Self::Optional(nodes) => write!(f, "{nodes:?}?"),
+      Self::Alternatives(alts) => {
+        let alts: Vec<_> = alts
+          .iter()
+          .map(|alt| match alt.as_slice() {
+            [node] => format!("{node:?}"),
+            nodes => format!("{nodes:?}"),
+          })
+          .collect();
+        write!(f, "({})", alts.join(" | "))
+      }
     }
   }
 }
@@ -521,7 +1149,6 @@ mod test {
   }
 
   #[test]
-  #[ignore]
   fn test_multi_node_pattern() {
     let pattern = Pattern::new("a;b;c;", Tsx);
     let kinds = pattern.potential_kinds().expect("should have kinds");
@@ -530,7 +1157,6 @@ mod test {
   }
 
   #[test]
-  #[ignore]
   fn test_multi_node_meta_var() {
     let env = match_env("a;$B;c", "a;b;c");
     assert_eq!(env["B"], "b");
@@ -589,7 +1215,7 @@ mod test {
     let pattern = Pattern::new(s, Tsx);
     let mut vars: Vec<_> = pattern
       .defined_vars()
-      .into_iter()
+      .into_keys()
       .map(String::from)
       .collect();
     vars.sort();
@@ -617,7 +1243,245 @@ mod test {
   #[test]
   fn test_contextual_pattern_vars() {
     let pattern = Pattern::contextual("<div ref={$A}/>", "jsx_attribute", Tsx).expect("correct");
-    assert_eq!(pattern.defined_vars(), ["A"].into_iter().collect());
+    assert_eq!(pattern.defined_vars(), [("A", None)].into_iter().collect());
+  }
+
+  #[test]
+  fn test_meta_var_kind() {
+    test_match("foo($A_identifier)", "foo(bar)");
+    test_non_match("foo($A_identifier)", "foo(1)");
+    test_match("foo($$$ARGS_number)", "foo(1, 2, 3)");
+    test_match("foo($$$ARGS_number)", "foo()");
+    test_non_match("foo($$$ARGS_number)", "foo(1, a)");
+    test_match("foo($$$ARGS_number, $B_string)", "foo(1, 2, 'a')");
+    test_match("foo($MY_VAR_identifier)", "foo(a)");
+    test_match("foo($__identifier)", "foo(a)");
+    test_non_match("foo($__identifier)", "foo(1)");
+    test_match("$A_identifier", "let a = 1");
+    // not a kind, so it is a plain identifier
+    test_match("foo($A_value)", "foo($A_value)");
+    test_non_match("foo($A_value)", "foo(a)");
+  }
+
+  #[test]
+  fn test_meta_var_kind_breaks_literal() {
+    // `$X_string` used to be a literal identifier since meta variables have no lowercase.
+    // With a kind after the underscore it is now `$X` of kind `string`, an intended break.
+    test_non_match("foo($X_string)", "foo($X_string)");
+    test_match("foo($X_string)", "foo('a')");
+    let pattern = Pattern::new("foo($X_string)", Tsx);
+    assert_eq!(pattern.defined_vars()["X"], Some(Tsx.kind_to_id("string")));
+  }
+
+  #[test]
+  fn test_meta_var_kind_in_code() {
+    // type annotations are code, not kind annotations
+    test_match("function f($A:number) {}", "function f(a: number) {}");
+    test_non_match("function f($A:number) {}", "function f(a: string) {}");
+    test_match("let $A: string = $B", "let a: string = 'b'");
+    // text in strings and comments is kept
+    test_match("foo('$A:string')", "foo('$A:string')");
+    test_non_match("foo('$A:string')", "foo('$A')");
+    test_match("foo('a $A_identifier')", "foo('a $A_identifier')");
+    test_non_match("foo('a $A_identifier')", "foo('a b')");
+  }
+
+  #[test]
+  fn test_meta_var_kind_vars() {
+    let pattern = Pattern::new("$F($A_identifier, $$$B_number, $A)", Tsx);
+    let vars = pattern.defined_vars();
+    assert_eq!(vars.len(), 3);
+    assert_eq!(vars["F"], None);
+    assert_eq!(vars["A"], Some(Tsx.kind_to_id("identifier")));
+    assert_eq!(vars["B"], Some(Tsx.kind_to_id("number")));
+    let kinds = pattern.potential_kinds().expect("should have kinds");
+    assert!(kinds.contains(get_kind("call_expression")));
+    let pattern = Pattern::new("$A_identifier", Tsx);
+    let kinds = pattern.potential_kinds().expect("should have kinds");
+    assert!(kinds.contains(get_kind("identifier")));
+  }
+
+  #[test]
+  fn test_sequence_pattern() {
+    let pattern = "$A.lock(); $$$BODY; $A.unlock();";
+    test_match(pattern, "m.lock(); m.unlock();");
+    test_match(
+      pattern,
+      "function f() { m.lock(); a(); b(); m.unlock(); c(); }",
+    );
+    test_match(pattern, "if (x) { before(); m.lock(); a(); m.unlock(); }");
+    test_non_match(pattern, "m.lock(); a(); n.unlock();");
+    test_non_match(pattern, "m.lock(); if (x) { m.unlock(); }");
+    let root = pattern_node("{ m.lock(); a(); b(); m.unlock(); }");
+    let nm = Pattern::new(pattern, Tsx)
+      .find_node(root.root())
+      .expect("should match");
+    let env = nm.get_env();
+    assert_eq!(env.get_match("A").expect("should bind").text(), "m");
+    let body: Vec<_> = env
+      .get_multiple_matches("BODY")
+      .iter()
+      .map(|n| n.text().to_string())
+      .collect();
+    assert_eq!(body, ["a();", "b();"]);
+    let env = match_env("let $A = $V; $S; use($A);", "let a = 1; log(a); use(a);");
+    assert_eq!(env["S"], "log(a)");
+    let pattern = Pattern::new("a; b;", Tsx);
+    assert!(pattern.sequence().is_some_and(|s| s.len() == 2));
+    assert!(Pattern::new("a", Tsx).sequence().is_none());
+  }
+
+  #[test]
+  fn test_optional_meta_var() {
+    test_match("foo(a, $?B)", "foo(a)");
+    test_match("foo(a, $?B)", "foo(a, b)");
+    test_non_match("foo(a, $?B)", "foo(a, b, c)");
+    test_match("foo($?A, b)", "foo(b)");
+    test_match("foo($?A, b)", "foo(a, b)");
+    test_match("foo($?A)", "foo()");
+    test_match("foo($?A, $?B)", "foo()");
+    test_match("foo($?A, $?B)", "foo(a)");
+    test_match("foo($?A, $?B)", "foo(a, b)");
+    test_match("foo($?A_identifier)", "foo()");
+    test_non_match("foo($?A_identifier)", "foo(1)");
+    test_match("foo($?_)", "foo()");
+  }
+
+  #[test]
+  fn test_optional_mark_in_text() {
+    // marks in strings and comments are text
+    test_match("foo('a $?A')", "foo('a $?A')");
+    test_non_match("foo('a $?A')", "foo('a $A')");
+    test_match("foo(a /* $?B */)", "foo(a /* $?B */)");
+    // and do not make the meta variable optional elsewhere
+    test_match("foo('a $?A', $A)", "foo('a $?A', a)");
+    test_non_match("foo('a $?A', $A)", "foo('a $?A')");
+    test_match("foo('a $?A', $?B)", "foo('a $?A')");
+    test_match("foo(${{ a | 'b $?B' }})", "foo('b $?B')");
+    test_match("foo(${{ 'a $?A' | $?B }})", "foo()");
+    test_match("foo(${{ 'a $?A' | $?B }})", "foo('a $?A')");
+    test_match("foo('c $?C', ${{ a | 'b $?B' }})", "foo('c $?C', 'b $?B')");
+    // a whole string fragment is a meta variable
+    test_match("foo('$?A')", "foo('')");
+  }
+
+  #[test]
+  fn test_optional_multi_meta_var() {
+    for src in ["foo($$$?A)", "foo($$?A)", "foo(${{ a | $$$?B }})"] {
+      let ret = Pattern::try_new(src, Tsx);
+      assert!(
+        matches!(ret, Err(PatternError::InvalidOptional(_))),
+        "{src}"
+      );
+    }
+    let ret = Pattern::try_new("foo($$$?A)", Tsx);
+    assert_eq!(
+      ret.err().map(|e| e.to_string()),
+      Some("Only single meta variables like `$?A` can be optional, found `$$$?A`.".into())
+    );
+  }
+
+  #[test]
+  fn test_question_mark_after_meta_var() {
+    // optional chaining and ternaries are code
+    test_match("$A?.b", "a?.b");
+    test_non_match("$A?.b", "a.b");
+    test_match("$A?$B:$C", "a ? b : c");
+    test_non_match("$A?$B:$C", "b");
+    test_match("let $A: $T|undefined = $B!", "let a: T|undefined = b!");
+    // so are optional properties and parameters
+    test_match("function f($A?: $T) {}", "function f(a?: number) {}");
+    test_non_match("function f($A?: $T) {}", "function f(a: number) {}");
+    test_match("interface I { $K?: $T }", "interface I { k?: string }");
+    assert!(Pattern::new("$A?$B:$C", Tsx).defined_vars().len() == 3);
+  }
+
+  #[test]
+  fn test_optional_meta_var_env() {
+    let env = match_env("foo($A, $?B)", "foo(a, b)");
+    assert_eq!(env["A"], "a");
+    assert_eq!(env["B"], "b");
+    let env = match_env("foo($A, $?B)", "foo(a)");
+    assert_eq!(env["A"], "a");
+    assert!(!env.contains_key("B"));
+    // backtrack to drop the optional node if the rest does not match
+    let env = match_env("foo($?A, $A)", "foo(a)");
+    assert_eq!(env["A"], "a");
+    test_non_match("foo($?A, $A)", "foo(a, b)");
+    test_match("foo($?A, $A)", "foo(a, a)");
+  }
+
+  #[test]
+  fn test_alternation() {
+    test_match("foo(${{ a | b }})", "foo(a)");
+    test_match("foo(${{ a | b }})", "foo(b)");
+    test_non_match("foo(${{ a | b }})", "foo(c)");
+    test_match("foo(${{ a | a, opts }})", "foo(a)");
+    test_match("foo(${{ a | a, opts }})", "foo(a, opts)");
+    test_non_match("foo(${{ a | a, opts }})", "foo(opts)");
+    test_match("${{ foo | bar.baz }}($A)", "bar.baz(1)");
+    test_match("${{ foo(a) | bar }}", "bar");
+    // brackets group the bitwise or
+    test_match("foo(${{ (a | b) | c }})", "foo((a | b))");
+    test_non_match("foo(${{ (a | b) | c }})", "foo(a)");
+    // alternatives are runs of siblings, not combinations of them
+    test_match("foo(${{ a, b | c, d }})", "foo(c, d)");
+    test_non_match("foo(${{ a, b | c, d }})", "foo(a, d)");
+    test_match("foo(${{ a | b }}, ${{ c | d }})", "foo(b, c)");
+    test_match("if (${{ a | b }}) { ${{ c() | d() }} }", "if (b) { d() }");
+  }
+
+  #[test]
+  fn test_alternation_syntax() {
+    // JS subscripts and blocks are code
+    test_match("$A[0]", "a[0]");
+    test_match("$[0]", "$[0]");
+    test_non_match("$[0]", "a");
+    test_match("`${{ a: 1 }}`", "`${{ a: 1 }}`");
+    // one alternative, unbalanced or unclosed text is not an alternation
+    assert_eq!(find_alternation("${{ a }}", '$'), None);
+    assert_eq!(find_alternation("${{ a | }}", '$'), None);
+    assert_eq!(find_alternation("${{ a | b) }}", '$'), None);
+    assert_eq!(find_alternation("${{ a | b", '$'), None);
+    assert_eq!(
+      find_alternation("f(${{ a | b }}, ${{ c | { d } }})", '$'),
+      Some((2, vec!["a", "b"], 14)),
+    );
+    let nested = Pattern::try_new("foo(${{ a | ${{ b | c }} }})", Tsx);
+    assert!(matches!(nested, Err(PatternError::InvalidAlternation(_))));
+    let partial = Pattern::try_new("${{ a + b | c }} * d", Tsx);
+    assert!(matches!(partial, Err(PatternError::InvalidAlternation(_))));
+  }
+
+  #[test]
+  fn test_alternation_env() {
+    let env = match_env("${{ foo($A) | bar($A, $B) }}", "bar(1, 2)");
+    assert_eq!(env["A"], "1");
+    assert_eq!(env["B"], "2");
+    let env = match_env("foo(${{ $A | -$A }}, $A)", "foo(-1, 1)");
+    assert_eq!(env["A"], "1");
+    test_non_match("foo(${{ $A | -$A }}, $A)", "foo(-1, 2)");
+    assert_eq!(defined_vars("${{ foo($A) | bar($B) }}"), ["A", "B"]);
+    test_match("foo(${{ a | $?B }})", "foo()");
+    test_non_match("foo(${{ a | $?B }})", "foo(a, b)");
+  }
+
+  #[test]
+  fn test_alternation_kinds() {
+    let pattern = Pattern::new("${{ foo($A) | a = $A }}", Tsx);
+    let kinds = pattern.potential_kinds().expect("should have kinds");
+    assert!(kinds.contains(get_kind("call_expression")));
+    assert!(kinds.contains(get_kind("assignment_expression")));
+    let pattern = Pattern::new("foo(${{ a | b }}, ${{ c | d }})", Tsx);
+    assert_eq!(format!("{pattern:?}"), "[foo, [(, (a | b), ,, (c | d), )]]");
+  }
+
+  #[test]
+  fn test_alternation_fixed_string() {
+    let pattern = Pattern::new("foo(${{ aaa | bbb }}, $?B)", Tsx);
+    assert_eq!(pattern.fixed_string(), "foo");
+    let pattern = Pattern::new("${{ foo | bar }}", Tsx);
+    assert!(pattern.fixed_string().is_empty());
   }
 
   #[test]
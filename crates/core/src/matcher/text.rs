@@ -26,6 +26,37 @@ impl RegexMatcher {
       regex: Regex::new(text)?,
     })
   }
+
+  /// The literal text that every match starts with, None if there is none.
+  /// It is conservative: regex with alternation or a leading group has no prefix.
+  pub fn literal_prefix(&self) -> Option<String> {
+    let src = self.regex.as_str();
+    if src.contains('|') {
+      return None;
+    }
+    let mut prefix = String::new();
+    let mut chars = src.chars().peekable();
+    // a leading anchor does not consume text
+    if chars.peek() == Some(&'^') {
+      chars.next();
+    }
+    while let Some(c) = chars.next() {
+      match c {
+        '\\' => match chars.next() {
+          Some(e) if e.is_ascii_punctuation() => prefix.push(e),
+          _ => break,
+        },
+        // the quantified char may not appear
+        '*' | '?' | '{' => {
+          prefix.pop();
+          break;
+        }
+        '+' | '.' | '^' | '$' | '(' | ')' | '[' | ']' => break,
+        c => prefix.push(c),
+      }
+    }
+    (!prefix.is_empty()).then_some(prefix)
+  }
 }
 
 impl Matcher for RegexMatcher {
@@ -41,3 +72,24 @@ impl Matcher for RegexMatcher {
     None
   }
 }
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn prefix(regex: &str) -> Option<String> {
+    RegexMatcher::try_new(regex).unwrap().literal_prefix()
+  }
+
+  #[test]
+  fn test_literal_prefix() {
+    assert_eq!(prefix("console").as_deref(), Some("console"));
+    assert_eq!(prefix("^use[A-Z]").as_deref(), Some("use"));
+    assert_eq!(prefix(r"foo\.bar+").as_deref(), Some("foo.bar"));
+    assert_eq!(prefix("abc?d").as_deref(), Some("ab"));
+    assert_eq!(prefix("a{2}").as_deref(), None);
+    assert_eq!(prefix("(?i)foo").as_deref(), None);
+    assert_eq!(prefix("foo|bar").as_deref(), None);
+    assert_eq!(prefix(r"\w+").as_deref(), None);
+  }
+}
@@ -1,4 +1,4 @@
-use crate::match_tree::does_node_match_exactly;
+use crate::match_tree::{does_node_match, MatchStrictness};
 use crate::matcher::Matcher;
 use crate::source::Content;
 use crate::{Doc, Node};
@@ -18,6 +18,8 @@ pub struct MetaVarEnv<'tree, D: Doc> {
   single_matched: HashMap<MetaVariableID, Node<'tree, D>>,
   multi_matched: HashMap<MetaVariableID, Vec<Node<'tree, D>>>,
   transformed_var: HashMap<MetaVariableID, Underlying<D>>,
+  /// first and last sibling matched by a sequence pattern
+  sequence: Option<(Node<'tree, D>, Node<'tree, D>)>,
 }
 
 impl<'t, D: Doc> MetaVarEnv<'t, D> {
@@ -26,11 +28,27 @@ impl<'t, D: Doc> MetaVarEnv<'t, D> {
       single_matched: HashMap::new(),
       multi_matched: HashMap::new(),
       transformed_var: HashMap::new(),
+      sequence: None,
     }
   }
 
   pub fn insert(&mut self, id: &str, ret: Node<'t, D>) -> Option<&mut Self> {
-    if self.match_variable(id, &ret) {
+    self.insert_under(id, ret, None)
+  }
+
+  pub fn insert_multi(&mut self, id: &str, ret: Vec<Node<'t, D>>) -> Option<&mut Self> {
+    self.insert_multi_under(id, ret, None)
+  }
+
+  /// Insert a single capture. If the variable is already bound, the nodes are
+  /// compared under the strictness, or by exact text if it is None.
+  pub(crate) fn insert_under(
+    &mut self,
+    id: &str,
+    ret: Node<'t, D>,
+    equality: Option<&MatchStrictness>,
+  ) -> Option<&mut Self> {
+    if self.match_variable(id, &ret, equality) {
       self.single_matched.insert(id.to_string(), ret);
       Some(self)
     } else {
@@ -38,8 +56,13 @@ impl<'t, D: Doc> MetaVarEnv<'t, D> {
     }
   }
 
-  pub fn insert_multi(&mut self, id: &str, ret: Vec<Node<'t, D>>) -> Option<&mut Self> {
-    if self.match_multi_var(id, &ret) {
+  pub(crate) fn insert_multi_under(
+    &mut self,
+    id: &str,
+    ret: Vec<Node<'t, D>>,
+    equality: Option<&MatchStrictness>,
+  ) -> Option<&mut Self> {
+    if self.match_multi_var(id, &ret, equality) {
       self.multi_matched.insert(id.to_string(), ret);
       Some(self)
     } else {
@@ -67,6 +90,16 @@ impl<'t, D: Doc> MetaVarEnv<'t, D> {
     self.multi_matched.get(label)
   }
 
+  pub(crate) fn set_sequence(&mut self, first: Node<'t, D>, last: Node<'t, D>) {
+    self.sequence = Some((first, last));
+  }
+
+  /// The last sibling matched by a sequence pattern starting at the node.
+  pub(crate) fn get_sequence_end(&self, first: &Node<'t, D>) -> Option<&Node<'t, D>> {
+    let (start, end) = self.sequence.as_ref()?;
+    (start.node_id() == first.node_id()).then_some(end)
+  }
+
   pub fn get_matched_variables(&self) -> impl Iterator<Item = MetaVariable> + use<'_, 't, D> {
     let single = self
       .single_matched
@@ -86,25 +119,38 @@ impl<'t, D: Doc> MetaVarEnv<'t, D> {
     single.chain(multi).chain(transformed)
   }
 
-  fn match_variable(&self, id: &str, candidate: &Node<'t, D>) -> bool {
+  fn match_variable(
+    &self,
+    id: &str,
+    candidate: &Node<'t, D>,
+    equality: Option<&MatchStrictness>,
+  ) -> bool {
     if let Some(m) = self.single_matched.get(id) {
-      return does_node_match_exactly(m, candidate);
+      return does_node_match(m, candidate, equality);
     }
     true
   }
-  fn match_multi_var(&self, id: &str, cands: &[Node<'t, D>]) -> bool {
+  fn match_multi_var(
+    &self,
+    id: &str,
+    cands: &[Node<'t, D>],
+    equality: Option<&MatchStrictness>,
+  ) -> bool {
     let Some(nodes) = self.multi_matched.get(id) else {
       return true;
     };
-    let mut named_nodes = nodes.iter().filter(|n| n.is_named());
-    let mut named_cands = cands.iter().filter(|n| n.is_named());
+    let significant = |n: &&Node<'t, D>| {
+      n.is_named() && equality.map_or(true, |s| !s.should_skip_in_back_reference(n))
+    };
+    let mut named_nodes = nodes.iter().filter(significant);
+    let mut named_cands = cands.iter().filter(significant);
     loop {
       if let Some(node) = named_nodes.next() {
         let Some(cand) = named_cands.next() else {
           // cand is done but node is not
           break false;
         };
-        if !does_node_match_exactly(node, cand) {
+        if !does_node_match(node, cand, equality) {
           break false;
         }
       } else if named_cands.next().is_some() {
@@ -175,6 +221,10 @@ impl<D: Doc> MetaVarEnv<'_, D> {
         f(n)
       }
     }
+    if let Some((first, last)) = &mut self.sequence {
+      f(first);
+      f(last);
+    }
   }
 }
 
@@ -1,6 +1,6 @@
 use crate::matcher::{Matcher, MatcherExt, NodeMatch};
 use crate::replacer::Replacer;
-use crate::source::{Content, Edit as E, SgNode};
+use crate::source::{Content, Edit as E, EditBatch, OffsetMap, SgNode};
 use crate::Doc;
 use crate::Language;
 
@@ -73,6 +73,13 @@ impl<D: Doc> Root<D> {
     Ok(self)
   }
 
+  /// Apply a batch of edits with a single reparse.
+  /// The returned map translates offsets of the old source, e.g. `Node` ranges.
+  pub fn edit_batch(&mut self, batch: EditBatch<D::Source>) -> Result<OffsetMap, String> {
+    self.doc.do_edits(batch.edits())?;
+    Ok(batch.offset_map())
+  }
+
   pub fn replace<M: Matcher, R: Replacer<D>>(
     &mut self,
     pattern: M,
@@ -325,13 +332,22 @@ impl<'r, D: Doc> Node<'r, D> {
     pat: M,
   ) -> impl Iterator<Item = NodeMatch<'r, D>> + 's {
     let kinds = pat.potential_kinds();
+    // the last sequence match, whose later siblings are not matched again
+    let mut sequence: Option<NodeMatch<'r, D>> = None;
     self.dfs().filter_map(move |cand| {
       if let Some(k) = &kinds {
         if !k.contains(cand.kind_id().into()) {
           return None;
         }
       }
-      pat.match_node(cand)
+      if sequence.as_ref().is_some_and(|m| m.covers_sibling(&cand)) {
+        return None;
+      }
+      let matched = pat.match_node(cand)?;
+      if matched.is_sequence() {
+        sequence = Some(matched.clone());
+      }
+      Some(matched)
     })
   }
 }
@@ -378,6 +394,43 @@ impl<D: Doc> Node<'_, D> {
       inserted_text: Vec::new(),
     }
   }
+
+  /// Remove the node as an item of a list, together with one adjacent separator.
+  /// e.g. removing `b` in `foo(a, b, c)` yields `foo(a, c)`.
+  pub fn remove_in_list(&self) -> Edit<D> {
+    let range = self.list_item_range();
+    Edit::<D> {
+      position: range.start,
+      deleted_length: range.end - range.start,
+      inserted_text: Vec::new(),
+    }
+  }
+
+  /// The range to delete for removing the node from a list. It covers the separator after
+  /// the node, or the one before the node if the node is the last item.
+  /// A trailing separator is kept if the list has one.
+  pub fn list_item_range(&self) -> std::ops::Range<usize> {
+    let separators = self.lang().list_separators();
+    let is_separator = |n: &Node<D>| !n.is_named() && separators.contains(&&*n.text());
+    let range = self.range();
+    let prev = self.prev().filter(is_separator);
+    let Some(next) = self.next().filter(is_separator) else {
+      // the last item without trailing separator, e.g. `b` in `[a, b]`
+      return match prev {
+        Some(prev) => prev.range().start..range.end,
+        None => range,
+      };
+    };
+    match next.next() {
+      // the item is followed by another item, e.g. `a` in `[a, b]`
+      Some(after) if after.is_named() || is_separator(&after) => range.start..after.range().start,
+      // the last item with trailing separator, e.g. `b` in `[a, b,]`
+      _ => match prev {
+        Some(prev) => prev.range().end..next.range().end,
+        None => range.start..next.range().end,
+      },
+    }
+  }
 }
 
 #[cfg(test)]
@@ -473,6 +526,17 @@ if (a) {
     assert_eq!(edits[0].inserted_text, "Some(1)".as_bytes());
   }
 
+  #[test]
+  fn test_find_all_sequence_no_overlap() {
+    let root = Tsx.ast_grep("a(); b(); c(); d(); e();");
+    let node = root.root();
+    let found: Vec<_> = node
+      .find_all("$A(); $B();")
+      .map(|m| m.text().to_string())
+      .collect();
+    assert_eq!(found, ["a(); b();", "c(); d();"]);
+  }
+
   #[test]
   fn test_replace_all_multiple_sorted() {
     let root = Tsx.ast_grep("Some(Some(1)); Some(2)");
@@ -541,6 +605,32 @@ if (a) {
     assert_eq!(edit.deleted_length, 8);
   }
 
+  fn remove_in_list(src: &str, item: &str) -> String {
+    let root = Tsx.ast_grep(src);
+    let node = root.root().find(item).expect("should exist");
+    let edit = node.remove_in_list();
+    let mut ret = src.to_string();
+    let range = edit.position..edit.position + edit.deleted_length;
+    ret.replace_range(range, "");
+    ret
+  }
+
+  #[test]
+  fn test_remove_in_list() {
+    assert_eq!(remove_in_list("foo(a, b, c)", "a"), "foo(b, c)");
+    assert_eq!(remove_in_list("foo(a, b, c)", "b"), "foo(a, c)");
+    assert_eq!(remove_in_list("foo(a, b, c)", "c"), "foo(a, b)");
+    assert_eq!(remove_in_list("foo(a)", "a"), "foo()");
+    assert_eq!(remove_in_list("[a, b,]", "b"), "[a,]");
+    assert_eq!(remove_in_list("[a,]", "a"), "[]");
+    assert_eq!(remove_in_list("[\n  a,\n  b,\n]", "a"), "[\n  b,\n]");
+    assert_eq!(remove_in_list("[\n  a,\n  b\n]", "b"), "[\n  a\n]");
+    let root = Tsx.ast_grep("let a = 1, b = 2;");
+    let declarator = root.root().find("b").and_then(|n| n.parent());
+    let edit = declarator.expect("should exist").remove_in_list();
+    assert_eq!((edit.position, edit.deleted_length), (9, 7));
+  }
+
   #[test]
   fn test_ascii_pos() {
     let root = Tsx.ast_grep("a");
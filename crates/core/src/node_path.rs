@@ -0,0 +1,273 @@
+//! Stable paths to address nodes across parses.
+//!
+//! `Node::node_id` changes on every parse. A [`NodePath`] records the steps from the root to
+//! a node instead. Each step has the field name and kind of a child, and its index among the
+//! siblings with the same field and kind, so inserting unrelated siblings like comments does
+//! not shift the path. The hash of the node text tells if the node has drifted since then.
+
+use crate::node::Root;
+use crate::source::SgNode;
+use crate::{Doc, Node};
+use thiserror::Error;
+
+use std::fmt;
+use std::str::FromStr;
+
+/// One step from a parent node to its child.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PathStep {
+  /// the field name of the child, if any
+  pub field: Option<String>,
+  pub kind: String,
+  /// the index among the siblings with the same field and kind
+  pub index: usize,
+}
+
+/// The path from the root to a node, with the hash of the node text.
+/// It can be persisted by its string form, e.g. `body:statement_block[0]/return_statement[1]#<hash>`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NodePath {
+  pub steps: Vec<PathStep>,
+  pub text_hash: u64,
+}
+
+/// The node found by a [`NodePath`] in a new root.
+pub enum PathMatch<'r, D: Doc> {
+  /// the node has the same text as when the path was recorded
+  Exact(Node<'r, D>),
+  /// the node is found by the path but its text has changed
+  Drifted(Node<'r, D>),
+  /// no node has the field and kind of every step
+  Missing,
+}
+
+impl<'r, D: Doc> PathMatch<'r, D> {
+  pub fn node(&self) -> Option<&Node<'r, D>> {
+    match self {
+      Self::Exact(node) | Self::Drifted(node) => Some(node),
+      Self::Missing => None,
+    }
+  }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("Invalid node path `{0}`.")]
+pub struct PathParseError(String);
+
+/// FNV-1a is used because the hash must be stable across runs and Rust versions.
+fn text_hash(text: &str) -> u64 {
+  text.bytes().fold(0xcbf29ce484222325, |hash, b| {
+    (hash ^ b as u64).wrapping_mul(0x100000001b3)
+  })
+}
+
+/// Children of the node with their field names.
+fn children_with_field<'r, N: SgNode<'r>>(node: &N) -> Vec<(N, Option<&'static str>)> {
+  let children = node.children().enumerate();
+  children
+    .map(|(i, child)| (child, node.field_name_for_child(i)))
+    .collect()
+}
+
+impl<'r, D: Doc> Node<'r, D> {
+  /// Return the stable path of the node from the root.
+  pub fn path(&self) -> NodePath {
+    let mut steps = vec![];
+    let mut current = self.inner.clone();
+    while let Some(parent) = current.parent() {
+      let kind = current.kind();
+      let siblings = children_with_field(&parent);
+      let nth = siblings
+        .iter()
+        .position(|(child, _)| child.node_id() == current.node_id())
+        .unwrap_or_default();
+      let field = siblings[nth].1;
+      let index = siblings[..nth]
+        .iter()
+        .filter(|(child, f)| *f == field && child.kind() == kind)
+        .count();
+      steps.push(PathStep {
+        field: field.map(String::from),
+        kind: kind.into_owned(),
+        index,
+      });
+      current = parent;
+    }
+    steps.reverse();
+    NodePath {
+      steps,
+      text_hash: text_hash(&self.text()),
+    }
+  }
+}
+
+impl NodePath {
+  /// Return true if the node has the same text as when the path was recorded.
+  pub fn matches_text<D: Doc>(&self, node: &Node<'_, D>) -> bool {
+    text_hash(&node.text()) == self.text_hash
+  }
+
+  /// Find the node in a root, e.g. the root of the edited source.
+  pub fn resolve<'r, D: Doc>(&self, root: &'r Root<D>) -> PathMatch<'r, D> {
+    let mut current = root.root();
+    for step in &self.steps {
+      let field = step.field.as_deref();
+      let found = children_with_field(&current.inner)
+        .into_iter()
+        .filter(|(child, f)| *f == field && child.kind() == step.kind)
+        .nth(step.index);
+      let Some((inner, _)) = found else {
+        return PathMatch::Missing;
+      };
+      current = root.adopt(inner);
+    }
+    if self.matches_text(&current) {
+      PathMatch::Exact(current)
+    } else {
+      PathMatch::Drifted(current)
+    }
+  }
+}
+
+const RESERVED: &[char] = &['%', '/', ':', '[', ']', '#'];
+
+fn escape(s: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+  for c in s.chars() {
+    if RESERVED.contains(&c) {
+      write!(f, "%{:02X}", c as u8)?;
+    } else {
+      write!(f, "{c}")?;
+    }
+  }
+  Ok(())
+}
+
+fn unescape(s: &str) -> Option<String> {
+  let mut ret = String::with_capacity(s.len());
+  let mut chars = s.chars();
+  while let Some(c) = chars.next() {
+    if c != '%' {
+      ret.push(c);
+      continue;
+    }
+    let hex: String = chars.by_ref().take(2).collect();
+    let byte = u8::from_str_radix(&hex, 16).ok()?;
+    ret.push(char::from(byte));
+  }
+  Some(ret)
+}
+
+impl fmt::Display for NodePath {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (i, step) in self.steps.iter().enumerate() {
+      if i > 0 {
+        write!(f, "/")?;
+      }
+      if let Some(field) = &step.field {
+        escape(field, f)?;
+        write!(f, ":")?;
+      }
+      escape(&step.kind, f)?;
+      write!(f, "[{}]", step.index)?;
+    }
+    write!(f, "#{:016x}", self.text_hash)
+  }
+}
+
+fn parse_step(s: &str) -> Option<PathStep> {
+  let (head, index) = s.strip_suffix(']')?.rsplit_once('[')?;
+  let index = index.parse().ok()?;
+  let (field, kind) = match head.split_once(':') {
+    Some((field, kind)) => (Some(unescape(field)?), kind),
+    None => (None, head),
+  };
+  Some(PathStep {
+    field,
+    kind: unescape(kind)?,
+    index,
+  })
+}
+
+impl FromStr for NodePath {
+  type Err = PathParseError;
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let error = || PathParseError(s.to_string());
+    let (steps, hash) = s.rsplit_once('#').ok_or_else(error)?;
+    let text_hash = u64::from_str_radix(hash, 16).map_err(|_| error())?;
+    let steps = if steps.is_empty() {
+      vec![]
+    } else {
+      steps
+        .split('/')
+        .map(parse_step)
+        .collect::<Option<_>>()
+        .ok_or_else(error)?
+    };
+    Ok(Self { steps, text_hash })
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::language::Tsx;
+  use crate::tree_sitter::{LanguageExt, StrDoc};
+
+  fn resolve(path: &NodePath, root: &Root<StrDoc<Tsx>>) -> Option<String> {
+    match path.resolve(root) {
+      PathMatch::Exact(node) => Some(node.text().to_string()),
+      PathMatch::Drifted(node) => Some(format!("drifted {}", node.text())),
+      PathMatch::Missing => None,
+    }
+  }
+
+  #[test]
+  fn test_node_path() {
+    let root = Tsx.ast_grep("function a() { foo(1); return bar(2) }");
+    let node = root.root().find("bar($A)").expect("should exist");
+    let path = node.path();
+    let kinds: Vec<_> = path.steps.iter().map(|s| s.kind.as_str()).collect();
+    assert_eq!(
+      kinds,
+      [
+        "function_declaration",
+        "statement_block",
+        "return_statement",
+        "call_expression"
+      ]
+    );
+    assert_eq!(path.steps[1].field.as_deref(), Some("body"));
+    assert_eq!(resolve(&path, &root).as_deref(), Some("bar(2)"));
+    let root = Tsx.ast_grep("function a() {\n  // comment\n  foo(1);\n  return bar(2)\n}");
+    assert_eq!(resolve(&path, &root).as_deref(), Some("bar(2)"));
+    let root = Tsx.ast_grep("function a() { foo(1); return bar(3) }");
+    assert_eq!(resolve(&path, &root).as_deref(), Some("drifted bar(3)"));
+    let root = Tsx.ast_grep("function a() { foo(1); bar(2) }");
+    assert_eq!(resolve(&path, &root), None);
+  }
+
+  #[test]
+  fn test_sibling_index() {
+    let root = Tsx.ast_grep("foo(1); foo(2); foo(3);");
+    let node = root.root().find("foo(2)").expect("should exist");
+    let path = node.path();
+    assert_eq!(path.steps[0].index, 1);
+    let root = Tsx.ast_grep("foo(2); foo(3);");
+    assert_eq!(resolve(&path, &root).as_deref(), Some("drifted foo(3)"));
+  }
+
+  #[test]
+  fn test_path_string() {
+    let root = Tsx.ast_grep("let a = b / c");
+    let node = root.root().find("b").expect("should exist");
+    let path = node.path();
+    let text = path.to_string();
+    assert!(text.starts_with("lexical_declaration[0]/variable_declarator[0]/value:"));
+    assert_eq!(text.parse::<NodePath>(), Ok(path));
+    let root_path = root.root().path();
+    assert!(root_path.steps.is_empty());
+    assert_eq!(root_path.to_string().parse::<NodePath>(), Ok(root_path));
+    assert!("foo[x]#00".parse::<NodePath>().is_err());
+    assert!("foo[0]".parse::<NodePath>().is_err());
+  }
+}
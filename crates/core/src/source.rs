@@ -14,6 +14,7 @@
 use crate::{language::Language, node::KindId, Position};
 use std::borrow::Cow;
 use std::ops::Range;
+use thiserror::Error;
 
 // https://github.com/tree-sitter/tree-sitter/blob/e4e5ffe517ca2c668689b24cb17c51b8c6db0790/cli/src/parse.rs
 #[derive(Debug)]
@@ -23,6 +24,170 @@ pub struct Edit<S: Content> {
   pub inserted_text: Vec<S::Underlying>,
 }
 
+impl<S: Content> Edit<S> {
+  fn range(&self) -> Range<usize> {
+    self.position..self.position + self.deleted_length
+  }
+}
+
+/// How a batch of edits resolves edits whose ranges overlap or nest.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverlapPolicy {
+  /// Reject the whole batch.
+  #[default]
+  Error,
+  /// Keep the edit that starts first. Edits starting together keep the one ending first,
+  /// so an insertion is kept along with a replacement at the same position.
+  FirstWins,
+  /// Keep the edit that contains the other. Partially overlapping edits keep the earlier one.
+  OuterWins,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("Edit at {second:?} overlaps with edit at {first:?}.")]
+pub struct EditOverlap {
+  pub first: Range<usize>,
+  pub second: Range<usize>,
+}
+
+/// Why a batch of edits is rejected.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum EditError {
+  #[error(transparent)]
+  Overlap(#[from] EditOverlap),
+  #[error("Edit at {range:?} is out of the source of length {len}.")]
+  OutOfBounds { range: Range<usize>, len: usize },
+}
+
+impl OverlapPolicy {
+  /// Sort the ranges by start and return the indices of the ranges to keep.
+  /// Ranges starting together are sorted by end, so the result does not depend on input order
+  /// except for equal ranges.
+  pub fn select(self, ranges: &[Range<usize>]) -> Result<Vec<usize>, EditOverlap> {
+    let mut indices: Vec<_> = (0..ranges.len()).collect();
+    match self {
+      Self::OuterWins => indices.sort_by_key(|&i| (ranges[i].start, usize::MAX - ranges[i].end)),
+      _ => indices.sort_by_key(|&i| (ranges[i].start, ranges[i].end)),
+    }
+    let mut kept: Vec<usize> = Vec::with_capacity(indices.len());
+    for i in indices {
+      let Some(&last) = kept.last() else {
+        kept.push(i);
+        continue;
+      };
+      if ranges[i].start >= ranges[last].end {
+        kept.push(i);
+      } else if self == Self::Error {
+        return Err(EditOverlap {
+          first: ranges[last].clone(),
+          second: ranges[i].clone(),
+        });
+      }
+    }
+    Ok(kept)
+  }
+}
+
+/// Edits against the same source version, sorted and without overlaps.
+pub struct EditBatch<S: Content> {
+  edits: Vec<Edit<S>>,
+}
+
+impl<S: Content> EditBatch<S> {
+  /// `source_len` is the length of the source to edit, in units of `S::Underlying`.
+  pub fn new(
+    edits: Vec<Edit<S>>,
+    policy: OverlapPolicy,
+    source_len: usize,
+  ) -> Result<Self, EditError> {
+    if let Some(edit) = edits
+      .iter()
+      .find(|e| e.position > source_len || e.deleted_length > source_len - e.position)
+    {
+      return Err(EditError::OutOfBounds {
+        range: edit.position..edit.position.saturating_add(edit.deleted_length),
+        len: source_len,
+      });
+    }
+    let ranges: Vec<_> = edits.iter().map(Edit::range).collect();
+    let kept = policy.select(&ranges)?;
+    let mut edits: Vec<_> = edits.into_iter().map(Some).collect();
+    let edits = kept.into_iter().filter_map(|i| edits[i].take()).collect();
+    Ok(Self { edits })
+  }
+
+  pub fn edits(&self) -> &[Edit<S>] {
+    &self.edits
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.edits.is_empty()
+  }
+
+  /// Apply all edits to `source` in one pass.
+  pub fn apply(&self, source: &[S::Underlying]) -> Vec<S::Underlying> {
+    let mut ret = Vec::with_capacity(source.len());
+    let mut start = 0;
+    for edit in &self.edits {
+      ret.extend_from_slice(&source[start..edit.position]);
+      ret.extend_from_slice(&edit.inserted_text);
+      start = edit.position + edit.deleted_length;
+    }
+    ret.extend_from_slice(&source[start..]);
+    ret
+  }
+
+  /// Map offsets in the source before the edits to the source after them.
+  pub fn offset_map(&self) -> OffsetMap {
+    let mut shift = 0isize;
+    let spans = self
+      .edits
+      .iter()
+      .map(|edit| {
+        shift += edit.inserted_text.len() as isize - edit.deleted_length as isize;
+        let old_end = edit.position + edit.deleted_length;
+        (old_end, old_end.saturating_add_signed(shift))
+      })
+      .collect();
+    OffsetMap {
+      starts: self.edits.iter().map(|e| e.position).collect(),
+      spans,
+    }
+  }
+}
+
+/// Translate offsets, e.g. `Node` ranges, from the source before a batch of edits.
+pub struct OffsetMap {
+  /// start of each edit in the old source
+  starts: Vec<usize>,
+  /// end of each edit in the old source and in the new source
+  spans: Vec<(usize, usize)>,
+}
+
+impl OffsetMap {
+  /// Returns None if the offset is inside a replaced range.
+  /// An offset at an insertion point is moved after the inserted text.
+  pub fn translate(&self, offset: usize) -> Option<usize> {
+    let idx = self
+      .spans
+      .partition_point(|(old_end, _)| *old_end <= offset);
+    if self.starts.get(idx).is_some_and(|start| *start < offset) {
+      return None;
+    }
+    match idx.checked_sub(1) {
+      Some(prev) => {
+        let (old_end, new_end) = self.spans[prev];
+        Some(offset - old_end + new_end)
+      }
+      None => Some(offset),
+    }
+  }
+
+  pub fn translate_range(&self, range: Range<usize>) -> Option<Range<usize>> {
+    Some(self.translate(range.start)?..self.translate(range.end)?)
+  }
+}
+
 /// NOTE: Some method names are the same as tree-sitter's methods.
 /// Fully Qualified Syntax may needed https://stackoverflow.com/a/44445976/2198656
 pub trait SgNode<'r>: Clone {
@@ -120,6 +285,10 @@ pub trait SgNode<'r>: Clone {
   }
 
   fn field(&self, name: &str) -> Option<Self>;
+  /// Return the field name of the nth child, if the child is a field.
+  fn field_name_for_child(&self, _nth: usize) -> Option<&'static str> {
+    None
+  }
   fn field_children(&self, field_id: Option<u16>) -> impl Iterator<Item = Self>;
   fn child_by_field_id(&self, field_id: u16) -> Option<Self>;
 }
@@ -131,6 +300,14 @@ pub trait Doc: Clone + 'static {
   fn get_lang(&self) -> &Self::Lang;
   fn get_source(&self) -> &Self::Source;
   fn do_edit(&mut self, edit: &Edit<Self::Source>) -> Result<(), String>;
+  /// Apply sorted and non-overlapping edits. Implementations should reparse only once.
+  fn do_edits(&mut self, edits: &[Edit<Self::Source>]) -> Result<(), String> {
+    // apply later edits first so positions of earlier edits stay valid
+    for edit in edits.iter().rev() {
+      self.do_edit(edit)?;
+    }
+    Ok(())
+  }
   fn root_node(&self) -> Self::Node<'_>;
   fn get_node_text<'a>(&'a self, node: &Self::Node<'a>) -> Cow<'a, str>;
 }
@@ -179,3 +356,109 @@ impl Content for String {
     col
   }
 }
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn edit(position: usize, deleted_length: usize, text: &str) -> Edit<String> {
+    Edit {
+      position,
+      deleted_length,
+      inserted_text: text.as_bytes().to_vec(),
+    }
+  }
+
+  fn apply(edits: Vec<Edit<String>>, policy: OverlapPolicy) -> Result<String, EditError> {
+    let source = b"let a = 123";
+    let batch = EditBatch::new(edits, policy, source.len())?;
+    Ok(String::from_utf8(batch.apply(source)).expect("should be utf8"))
+  }
+
+  #[test]
+  fn test_apply_batch() {
+    let edits = vec![edit(8, 3, "456"), edit(4, 1, "bb"), edit(0, 0, "/**/")];
+    let ret = apply(edits, OverlapPolicy::Error);
+    assert_eq!(ret, Ok("/**/let bb = 456".to_string()));
+  }
+
+  #[test]
+  fn test_overlap_policy() {
+    let edits = || {
+      vec![
+        edit(4, 1, "b"),
+        edit(0, 11, "x"),
+        edit(8, 3, "4"),
+        edit(6, 3, "?"),
+      ]
+    };
+    let ret = apply(edits(), OverlapPolicy::Error);
+    let err = EditOverlap {
+      first: 0..11,
+      second: 4..5,
+    };
+    assert_eq!(ret, Err(err.into()));
+    assert_eq!(apply(edits(), OverlapPolicy::OuterWins), Ok("x".into()));
+    let edits = vec![
+      edit(4, 1, "b"),
+      edit(4, 7, "x"),
+      edit(6, 3, "?"),
+      edit(8, 3, "4"),
+    ];
+    let ret = apply(edits, OverlapPolicy::FirstWins);
+    assert_eq!(ret, Ok("let b ?23".into()));
+  }
+
+  #[test]
+  fn test_insertion_before_replacement() {
+    // the insertion comes first regardless of input order
+    let ret = apply(
+      vec![edit(4, 0, "/**/"), edit(4, 1, "b")],
+      OverlapPolicy::Error,
+    );
+    assert_eq!(ret, Ok("let /**/b = 123".into()));
+    let ret = apply(
+      vec![edit(4, 1, "b"), edit(4, 0, "/**/")],
+      OverlapPolicy::Error,
+    );
+    assert_eq!(ret, Ok("let /**/b = 123".into()));
+    let ret = apply(
+      vec![edit(4, 1, "b"), edit(4, 0, "/**/")],
+      OverlapPolicy::FirstWins,
+    );
+    assert_eq!(ret, Ok("let /**/b = 123".into()));
+  }
+
+  #[test]
+  fn test_edit_out_of_bounds() {
+    let ret = apply(vec![edit(8, 4, "4")], OverlapPolicy::FirstWins);
+    let err = EditError::OutOfBounds {
+      range: 8..12,
+      len: 11,
+    };
+    assert_eq!(ret, Err(err));
+    let ret = apply(vec![edit(12, 0, "4")], OverlapPolicy::Error);
+    assert!(matches!(ret, Err(EditError::OutOfBounds { .. })));
+    assert_eq!(
+      apply(vec![edit(11, 0, ";")], OverlapPolicy::Error),
+      Ok("let a = 123;".into())
+    );
+  }
+
+  #[test]
+  fn test_offset_map() {
+    let edits = vec![edit(0, 0, "//"), edit(4, 1, "bbb"), edit(8, 3, "4")];
+    let map = EditBatch::new(edits, OverlapPolicy::Error, 11)
+      .expect("should not overlap")
+      .offset_map();
+    // "let a = 123" => "//let bbb = 4"
+    assert_eq!(map.translate(0), Some(2));
+    assert_eq!(map.translate(4), Some(6));
+    assert_eq!(map.translate(5), Some(9));
+    assert_eq!(map.translate(6), Some(10));
+    assert_eq!(map.translate(9), None);
+    assert_eq!(map.translate(11), Some(13));
+    assert_eq!(map.translate_range(4..5), Some(6..9));
+    assert_eq!(map.translate_range(8..10), None);
+  }
+}
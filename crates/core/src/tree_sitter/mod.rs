@@ -82,6 +82,13 @@ impl<L: LanguageExt> Doc for StrDoc<L> {
     self.tree = self.parse(Some(&self.tree)).map_err(|e| e.to_string())?;
     Ok(())
   }
+  fn do_edits(&mut self, edits: &[Edit<Self::Source>]) -> Result<(), String> {
+    for edit in edits.iter().rev() {
+      perform_edit(&mut self.tree, &mut self.src, edit);
+    }
+    self.tree = self.parse(Some(&self.tree)).map_err(|e| e.to_string())?;
+    Ok(())
+  }
   fn root_node(&self) -> Node<'_> {
     self.tree.root_node()
   }
@@ -156,6 +163,9 @@ impl<'r> SgNode<'r> for Node<'r> {
   fn child_by_field_id(&self, field_id: u16) -> Option<Self> {
     Node::child_by_field_id(self, field_id)
   }
+  fn field_name_for_child(&self, nth: usize) -> Option<&'static str> {
+    Node::field_name_for_child(self, nth as u32)
+  }
   fn next(&self) -> Option<Self> {
     self.next_sibling()
   }
@@ -379,6 +389,15 @@ impl<L: LanguageExt> Root<StrDoc<L>> {
   }
 }
 
+/// these methods are only for `StrDoc`
+impl<'r, L: LanguageExt> crate::NodeMatch<'r, StrDoc<L>> {
+  #[doc(hidden)]
+  pub fn display_context(&self, before: usize, after: usize) -> DisplayContext<'r> {
+    let end = self.range().end;
+    self.get_node().display_context_until(end, before, after)
+  }
+}
+
 pub struct DisplayContext<'r> {
   /// content for the matched node
   pub matched: Cow<'r, str>,
@@ -394,10 +413,15 @@ pub struct DisplayContext<'r> {
 impl<'r, L: LanguageExt> crate::Node<'r, StrDoc<L>> {
   #[doc(hidden)]
   pub fn display_context(&self, before: usize, after: usize) -> DisplayContext<'r> {
+    self.display_context_until(self.inner.end_byte(), before, after)
+  }
+
+  /// Display the text from the node's start to the end offset, which is
+  /// after the node for the runs of siblings matched by sequence patterns.
+  fn display_context_until(&self, end: usize, before: usize, after: usize) -> DisplayContext<'r> {
     let source = self.root.doc.get_source().as_str();
     let bytes = source.as_bytes();
     let start = self.inner.start_byte();
-    let end = self.inner.end_byte();
     let (mut leading, mut trailing) = (start, end);
     let mut lines_before = before + 1;
     while leading > 0 {
@@ -429,7 +453,7 @@ impl<'r, L: LanguageExt> crate::Node<'r, StrDoc<L>> {
       before + 1 - lines_before
     };
     DisplayContext {
-      matched: self.text(),
+      matched: Cow::Borrowed(&source[start..end]),
       leading: &source[leading..start],
       trailing: &source[end..trailing],
       start_line: self.start_pos().line() - offset,
@@ -525,4 +549,31 @@ mod test {
     assert_eq!(tree2.root_node().to_sexp(), "(program (expression_statement (binary_expression left: (binary_expression left: (identifier) right: (identifier)) right: (identifier))))");
     Ok(())
   }
+
+  #[test]
+  fn test_edit_batch() {
+    use crate::source::{EditBatch, OverlapPolicy};
+    let mut root = Root::str("let a = 1; let b = 2", Tsx);
+    let (edits, b_range) = {
+      let node = root.root();
+      let numbers: Vec<_> = node
+        .find_all("$N")
+        .filter(|n| n.kind() == "number")
+        .collect();
+      let edits: Vec<_> = numbers.iter().map(|n| n.replace_by("123")).collect();
+      let b_range = node.find("b").expect("should find").range();
+      (edits, b_range)
+    };
+    let len = root.get_text().len();
+    let batch = EditBatch::new(edits, OverlapPolicy::Error, len).expect("should not overlap");
+    let map = root.edit_batch(batch).expect("should edit");
+    assert_eq!(root.get_text(), "let a = 123; let b = 123");
+    let fresh = Root::str(root.get_text(), Tsx);
+    assert_eq!(
+      root.root().get_inner_node().to_sexp(),
+      fresh.root().get_inner_node().to_sexp()
+    );
+    let b_range = map.translate_range(b_range).expect("should translate");
+    assert_eq!(&root.get_text()[b_range], "b");
+  }
 }
@@ -86,16 +86,22 @@ where
       named: self.named_only,
       matcher: self.matcher,
       traversal,
+      matched_end: 0,
+      sequence: None,
       lang: PhantomData,
     }
   }
 }
 
-pub struct Visit<'t, D, T, M> {
+pub struct Visit<'t, D: Doc, T, M> {
   reentrant: bool,
   named: bool,
   matcher: M,
   traversal: T,
+  /// end of the last match, siblings in a sequence match are skipped if not reentrant
+  matched_end: usize,
+  /// the last sequence match, whose later siblings are skipped even if reentrant
+  sequence: Option<NodeMatch<'t, D>>,
   lang: PhantomData<&'t D>,
 }
 impl<'t, D, T, M> Visit<'t, D, T, M>
@@ -123,9 +129,21 @@ where
     loop {
       let match_depth = self.traversal.get_current_depth();
       let node = self.traversal.next()?;
+      let in_sequence = self
+        .sequence
+        .as_ref()
+        .is_some_and(|m| m.covers_sibling(&node));
+      if in_sequence || !self.reentrant && node.range().start < self.matched_end {
+        self.mark_match(None);
+        continue;
+      }
       let pass_named = !self.named || node.is_named();
       if let Some(node_match) = pass_named.then(|| self.matcher.match_node(node)).flatten() {
         self.mark_match(Some(match_depth));
+        self.matched_end = node_match.range().end;
+        if node_match.is_sequence() {
+          self.sequence = Some(node_match.clone());
+        }
         return Some(node_match);
       } else {
         self.mark_match(None);
@@ -576,6 +594,19 @@ mod test {
     }
   }
 
+  #[test]
+  fn test_sequence_visitor() {
+    let grep = Tsx.ast_grep("a(); b(); c();");
+    for reentrant in [true, false] {
+      let visit: Vec<_> = Visitor::new("$A(); $B();")
+        .reentrant(reentrant)
+        .visit(grep.root())
+        .map(|n| n.text().to_string())
+        .collect();
+      assert_eq!(visit, ["a(); b();"]);
+    }
+  }
+
   // match a leaf node will trace_up the cursor
   #[test]
   fn test_traversal_leaf() {
@@ -30,6 +30,18 @@ impl CustomLang {
       .collect();
     unsafe { DynamicLang::register(registrations?) }
   }
+
+  /// Resolve the library path against `base`, so languages of several projects can be registered together.
+  pub fn with_base(mut self, base: &Path) -> Self {
+    self.library_path = match self.library_path {
+      LibraryPath::Single(path) => LibraryPath::Single(base.join(path)),
+      LibraryPath::Platform(map) => {
+        let map = map.into_iter().map(|(k, p)| (k, base.join(p))).collect();
+        LibraryPath::Platform(map)
+      }
+    };
+    self
+  }
 }
 
 fn to_registration(
@@ -97,6 +109,17 @@ extensions: [d, e, f]";
     assert_eq!(registration.lib_path.to_str(), Some("./a/b/c.so"));
   }
 
+  #[test]
+  fn test_with_base() {
+    let yaml = r"
+libraryPath: a/b/c.so
+extensions: [d]";
+    let cus: CustomLang = from_str(yaml).unwrap();
+    let cus = cus.with_base(Path::new("project"));
+    let registration = to_registration("test_lang".to_string(), cus, Path::new("")).unwrap();
+    assert_eq!(registration.lib_path, Path::new("project/a/b/c.so"));
+  }
+
   #[test]
   fn test_unsupport_platform() {
     let yaml = r"
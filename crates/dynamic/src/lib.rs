@@ -155,10 +155,10 @@ pub struct Registration {
 
 impl DynamicLang {
   /// # Safety
-  /// the register function should be called exactly once before use.
+  /// the register function should be called before use.
   /// It relies on a global mut static variable to be initialized.
+  /// Calling it again replaces all languages, so no DynamicLang can be in use at that time.
   pub unsafe fn register(regs: Vec<Registration>) -> Result<(), DynamicLangError> {
-    debug_assert!(Self::langs().is_empty());
     let mut langs = vec![];
     let mut mapping = vec![];
     for reg in regs {
@@ -31,6 +31,23 @@ func plus(a int, b int) int {
   );
 }
 
+#[test]
+fn test_go_meta_var_kind() {
+  test_match("print($A_identifier)", "print(a)");
+  test_non_match("print($A_identifier)", "print(1)");
+  test_match("print($$$A_int_literal)", "print(1, 2)");
+  test_non_match("print($$$A_int_literal)", "print(1, a)");
+}
+
+#[test]
+fn test_go_optional_and_alternation() {
+  test_match("print(a, $?B)", "print(a)");
+  test_match("print(a, $?B)", "print(a, b)");
+  test_match("${{ print | println }}($A)", "println(a)");
+  test_non_match("${{ print | println }}($A)", "panic(a)");
+  test_match("print(${{ $A | $A, $B }})", "print(a, b)");
+}
+
 fn test_replace(src: &str, pattern: &str, replacer: &str) -> String {
   use crate::test::test_replace_lang;
   test_replace_lang(src, pattern, replacer, Go)
@@ -1,4 +1,5 @@
 use super::pre_process_pattern;
+use ast_grep_core::language::NodeTypes;
 use ast_grep_core::matcher::{Pattern, PatternBuilder, PatternError};
 use ast_grep_core::tree_sitter::{LanguageExt, StrDoc, TSLanguage, TSRange};
 use ast_grep_core::Language;
@@ -27,6 +28,9 @@ impl Language for Html {
   fn build_pattern(&self, builder: &PatternBuilder) -> Result<Pattern, PatternError> {
     builder.build(|src| StrDoc::try_new(src, *self))
   }
+  fn node_types(&self) -> Option<&'static NodeTypes> {
+    crate::node_types::node_types(crate::SupportLang::Html)
+  }
 }
 impl LanguageExt for Html {
   fn get_ts_language(&self) -> TSLanguage {
@@ -31,6 +31,13 @@ fun plus(a: Int, b: Int): Int {
   );
 }
 
+#[test]
+fn test_kotlin_nullable_type() {
+  // `?` after a meta variable is a nullable type, not an optional meta variable
+  test_match("val $A: $T? = null", "val a: String? = null");
+  test_non_match("val $A: $T? = null", "val a: String = null");
+}
+
 fn test_replace(src: &str, pattern: &str, replacer: &str) -> String {
   use crate::test::test_replace_lang;
   test_replace_lang(src, pattern, replacer, Kotlin)
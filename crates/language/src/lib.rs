@@ -31,6 +31,8 @@ mod json;
 mod kotlin;
 mod lua;
 mod nix;
+mod node_types;
+mod normalization;
 mod parsers;
 mod php;
 mod python;
@@ -41,6 +43,7 @@ mod solidity;
 mod swift;
 mod yaml;
 
+use ast_grep_core::language::{NodeTypes, Normalization};
 use ast_grep_core::matcher::{Pattern, PatternBuilder, PatternError};
 pub use html::Html;
 
@@ -61,6 +64,14 @@ use std::str::FromStr;
 pub use ast_grep_core::language::Language;
 pub use ast_grep_core::tree_sitter::LanguageExt;
 
+/// Lua table fields can be separated by either comma or semicolon.
+fn list_separators(lang: SupportLang) -> &'static [&'static str] {
+  match lang {
+    SupportLang::Lua => &[",", ";"],
+    _ => &[","],
+  }
+}
+
 /// this macro implements bare-bone methods for a language
 macro_rules! impl_lang {
   ($lang: ident, $func: ident) => {
@@ -81,6 +92,15 @@ macro_rules! impl_lang {
       fn build_pattern(&self, builder: &PatternBuilder) -> Result<Pattern, PatternError> {
         builder.build(|src| StrDoc::try_new(src, self.clone()))
       }
+      fn normalization(&self) -> Option<&'static Normalization> {
+        normalization::normalization(SupportLang::$lang)
+      }
+      fn node_types(&self) -> Option<&'static NodeTypes> {
+        node_types::node_types(SupportLang::$lang)
+      }
+      fn list_separators(&self) -> &'static [&'static str] {
+        list_separators(SupportLang::$lang)
+      }
     }
     impl LanguageExt for $lang {
       fn get_ts_language(&self) -> TSLanguage {
@@ -138,6 +158,15 @@ macro_rules! impl_lang_expando {
       fn build_pattern(&self, builder: &PatternBuilder) -> Result<Pattern, PatternError> {
         builder.build(|src| StrDoc::try_new(src, self.clone()))
       }
+      fn normalization(&self) -> Option<&'static Normalization> {
+        normalization::normalization(SupportLang::$lang)
+      }
+      fn node_types(&self) -> Option<&'static NodeTypes> {
+        node_types::node_types(SupportLang::$lang)
+      }
+      fn list_separators(&self) -> &'static [&'static str] {
+        list_separators(SupportLang::$lang)
+      }
     }
     impl LanguageExt for $lang {
       fn get_ts_language(&self) -> TSLanguage {
@@ -461,6 +490,9 @@ impl Language for SupportLang {
   impl_lang_method!(expando_char, () => char);
   impl_lang_method!(extract_meta_var, (source: &str) => Option<MetaVariable>);
   impl_lang_method!(build_pattern, (builder: &PatternBuilder) => Result<Pattern, PatternError>);
+  impl_lang_method!(normalization, () => Option<&'static Normalization>);
+  impl_lang_method!(node_types, () => Option<&'static NodeTypes>);
+  impl_lang_method!(list_separators, () => &'static [&'static str]);
   fn pre_process_pattern<'q>(&self, query: &'q str) -> Cow<'q, str> {
     execute_lang_method! { self, pre_process_pattern, query }
   }
@@ -34,3 +34,14 @@ fn test_lua_replace() {
   );
   assert_eq!(ret, "fact = function (n) return 1 end");
 }
+
+#[test]
+fn test_lua_remove_in_list() {
+  let src = "t = {a = 1; b = 2; c = 3}";
+  let grep = Lua.ast_grep(src);
+  let field = grep.root().find("b").and_then(|n| n.parent());
+  let edit = field.expect("should exist").remove_in_list();
+  let mut ret = src.to_string();
+  ret.replace_range(edit.position..edit.position + edit.deleted_length, "");
+  assert_eq!(ret, "t = {a = 1; c = 3}");
+}
@@ -0,0 +1,168 @@
+//! Read the parent and child relations between node kinds from `node-types.json`.
+//!
+//! Each named node type lists the types of its fields and children. Supertypes like
+//! `expression` are expanded to their subtypes. Kinds that are never listed as a child,
+//! such as comments and other extras, are treated as possible children of any node.
+
+use crate::{parsers, LanguageExt, SupportLang};
+use ast_grep_core::language::NodeTypes;
+use serde::Deserialize;
+
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+#[derive(Deserialize)]
+struct TypeRef {
+  #[serde(rename = "type")]
+  kind: String,
+  named: bool,
+}
+
+#[derive(Deserialize)]
+struct ChildTypes {
+  types: Vec<TypeRef>,
+}
+
+#[derive(Deserialize)]
+struct NodeInfo {
+  #[serde(rename = "type")]
+  kind: String,
+  named: bool,
+  #[serde(default)]
+  extra: bool,
+  #[serde(default)]
+  fields: HashMap<String, ChildTypes>,
+  children: Option<ChildTypes>,
+  subtypes: Option<Vec<TypeRef>>,
+}
+
+/// Expand supertypes into the concrete node kinds they stand for.
+fn concrete_kinds<'a>(
+  kind: &'a str,
+  supertypes: &HashMap<&'a str, &'a [TypeRef]>,
+  ret: &mut Vec<&'a str>,
+) {
+  match supertypes.get(kind) {
+    Some(subtypes) => {
+      for sub in subtypes.iter().filter(|t| t.named) {
+        if sub.kind != kind {
+          concrete_kinds(&sub.kind, supertypes, ret);
+        }
+      }
+    }
+    None => ret.push(kind),
+  }
+}
+
+fn build_node_types(lang: SupportLang) -> Option<NodeTypes> {
+  let infos: Vec<NodeInfo> = serde_json::from_str(parsers::node_types_json(lang)).ok()?;
+  if infos.is_empty() {
+    return None;
+  }
+  let ts_lang = lang.get_ts_language();
+  let to_id = |kind: &str| Some(ts_lang.id_for_node_kind(kind, true)).filter(|id| *id != 0);
+  let supertypes: HashMap<_, _> = infos
+    .iter()
+    .filter_map(|info| Some((info.kind.as_str(), info.subtypes.as_deref()?)))
+    .collect();
+  let mut edges = vec![];
+  let mut extras = HashSet::new();
+  for info in infos.iter().filter(|i| i.named && i.subtypes.is_none()) {
+    if info.extra {
+      extras.extend(to_id(&info.kind));
+    }
+    let Some(parent) = to_id(&info.kind) else {
+      continue;
+    };
+    let listed = info.fields.values().chain(&info.children);
+    let mut kinds = vec![];
+    for child in listed.flat_map(|c| &c.types).filter(|t| t.named) {
+      concrete_kinds(&child.kind, &supertypes, &mut kinds);
+    }
+    edges.extend(kinds.into_iter().filter_map(to_id).map(|c| (parent, c)));
+  }
+  let listed: HashSet<u16> = edges.iter().map(|(_, child)| *child).collect();
+  // only count the canonical id of a visible kind, aliases share the same name
+  let anywhere = (1..ts_lang.node_kind_count() as u16).filter(|&id| {
+    if !ts_lang.node_kind_is_visible(id) {
+      return false;
+    }
+    let Some(name) = ts_lang.node_kind_for_id(id) else {
+      return false;
+    };
+    let named = ts_lang.node_kind_is_named(id);
+    ts_lang.id_for_node_kind(name, named) == id
+      && (!named || !listed.contains(&id) || extras.contains(&id))
+  });
+  let anywhere: Vec<_> = anywhere.collect();
+  let root = lang.ast_grep("").root().kind_id();
+  let edges = edges
+    .into_iter()
+    .filter(|(_, child)| !extras.contains(child));
+  Some(NodeTypes::new(edges, anywhere, root))
+}
+
+macro_rules! tables {
+  ($lang: expr, $($name: ident),*) => {{
+    use SupportLang as S;
+    match $lang {
+      $(S::$name => {
+        static TABLE: OnceLock<Option<NodeTypes>> = OnceLock::new();
+        TABLE.get_or_init(|| build_node_types(S::$name)).as_ref()
+      })*
+    }
+  }};
+}
+
+/// Return the node kind relations of a built-in language.
+pub fn node_types(lang: SupportLang) -> Option<&'static NodeTypes> {
+  tables!(
+    lang, Bash, C, Cpp, CSharp, Css, Elixir, Go, Haskell, Hcl, Html, Java, JavaScript, Json,
+    Kotlin, Lua, Nix, Php, Python, Ruby, Rust, Scala, Solidity, Swift, Tsx, TypeScript, Yaml
+  )
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::Language;
+  use bit_set::BitSet;
+
+  fn kinds(lang: SupportLang, names: &[&str]) -> BitSet {
+    names.iter().map(|n| lang.kind_to_id(n) as usize).collect()
+  }
+
+  #[test]
+  fn test_tables_resolve() {
+    for &lang in SupportLang::all_langs() {
+      let types = node_types(lang).unwrap_or_else(|| panic!("{lang} has no node types"));
+      let root = lang.ast_grep("").root().kind_id() as usize;
+      assert!(types.parents_of(&[root].into_iter().collect()).is_some());
+    }
+  }
+
+  #[test]
+  fn test_children() {
+    let lang = SupportLang::TypeScript;
+    let types = node_types(lang).expect("should exist");
+    let children = types.children_of(&kinds(lang, &["call_expression"]));
+    assert!(children.is_superset(&kinds(lang, &["arguments", "identifier", "comment"])));
+    assert!(!children.contains(lang.kind_to_id("string_fragment") as usize));
+    // supertypes are expanded
+    let children = types.children_of(&kinds(lang, &["expression_statement"]));
+    assert!(children.contains(lang.kind_to_id("call_expression") as usize));
+  }
+
+  #[test]
+  fn test_parents() {
+    let lang = SupportLang::Rust;
+    let types = node_types(lang).expect("should exist");
+    let parents = types
+      .parents_of(&kinds(lang, &["arguments"]))
+      .expect("should have parents");
+    assert!(parents.contains(lang.kind_to_id("call_expression") as usize));
+    assert!(!parents.contains(lang.kind_to_id("source_file") as usize));
+    // comments can be anywhere
+    assert!(types.parents_of(&kinds(lang, &["line_comment"])).is_none());
+  }
+}
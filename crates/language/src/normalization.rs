@@ -0,0 +1,120 @@
+//! Tables for the `normalized` match strictness.
+//!
+//! Each table lists the binary expression kinds, the operators that can swap
+//! their operands, and the kinds of parenthesized expressions in a language.
+//! Operators are only declared for languages without operator overloading, since an
+//! overloaded `==` or `&` may not be commutative. Languages with overloading, like
+//! C++, C#, Python and Rust, only normalize parentheses and trailing commas.
+
+use crate::SupportLang;
+use ast_grep_core::language::Normalization;
+use std::sync::OnceLock;
+
+// operators may be overloaded, so none is known to be commutative
+const NO_OPERATORS: &[&str] = &[];
+const WITH_MULTIPLY: &[&str] = &["==", "!=", "&", "|", "^", "*"];
+const JS_LIKE: &[&str] = &["==", "!=", "===", "!==", "&", "|", "^", "*"];
+const PHP: &[&str] = &["==", "!=", "<>", "===", "!==", "&", "|", "^", "*"];
+
+const BINARY: &[&str] = &["binary_expression"];
+const PYTHON_BINARY: &[&str] = &["binary_operator", "comparison_operator"];
+const PARENTHESIZED: &[&str] = &["parenthesized_expression"];
+
+macro_rules! table {
+  ($lang: expr, $binary: expr, $ops: expr) => {{
+    static TABLE: OnceLock<Normalization> = OnceLock::new();
+    Some(TABLE.get_or_init(|| Normalization::new(&$lang, $binary, $ops, PARENTHESIZED)))
+  }};
+}
+
+/// Return the normalization tables of a built-in language, if it has any.
+pub fn normalization(lang: SupportLang) -> Option<&'static Normalization> {
+  use SupportLang as S;
+  match lang {
+    S::C => table!(lang, BINARY, WITH_MULTIPLY),
+    S::Cpp => table!(lang, BINARY, NO_OPERATORS),
+    S::CSharp => table!(lang, BINARY, NO_OPERATORS),
+    S::Go => table!(lang, BINARY, WITH_MULTIPLY),
+    S::Java => table!(lang, BINARY, WITH_MULTIPLY),
+    S::JavaScript => table!(lang, BINARY, JS_LIKE),
+    S::Php => table!(lang, BINARY, PHP),
+    S::Python => table!(lang, PYTHON_BINARY, NO_OPERATORS),
+    S::Rust => table!(lang, BINARY, NO_OPERATORS),
+    S::Tsx => table!(lang, BINARY, JS_LIKE),
+    S::TypeScript => table!(lang, BINARY, JS_LIKE),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{Language, LanguageExt};
+  use ast_grep_core::MatchStrictness;
+
+  fn test_normalized(lang: SupportLang, pattern: &str, source: &str) -> bool {
+    let mut pattern = ast_grep_core::Pattern::new(pattern, lang);
+    pattern.strictness = MatchStrictness::Normalized;
+    lang.ast_grep(source).root().find(pattern).is_some()
+  }
+
+  #[test]
+  fn test_tables_resolve() {
+    use SupportLang as S;
+    let langs = [
+      S::C,
+      S::Cpp,
+      S::CSharp,
+      S::Go,
+      S::Java,
+      S::JavaScript,
+      S::Php,
+      S::Python,
+      S::Rust,
+      S::Tsx,
+      S::TypeScript,
+    ];
+    for lang in langs {
+      let binary = if lang == S::Python {
+        PYTHON_BINARY
+      } else {
+        BINARY
+      };
+      for kind in binary.iter().chain(PARENTHESIZED) {
+        assert_ne!(lang.kind_to_id(kind), 0, "{lang} has no kind {kind}");
+      }
+      assert!(normalization(lang).is_some());
+    }
+    assert!(normalization(S::Yaml).is_none());
+  }
+
+  #[test]
+  fn test_commutative_operators() {
+    use SupportLang as S;
+    assert!(test_normalized(S::Go, "a == b", "x := b == a"));
+    assert!(test_normalized(S::C, "a & b", "int x = b & a;"));
+    assert!(test_normalized(S::Java, "a * b", "int x = b * a;"));
+    assert!(!test_normalized(S::Java, "a - b", "int x = b - a;"));
+  }
+
+  #[test]
+  fn test_overloadable_operators() {
+    use SupportLang as S;
+    // operators may be overloaded, so operands are never swapped
+    assert!(!test_normalized(S::Python, "$A != None", "y = None != x"));
+    assert!(!test_normalized(S::Rust, "a & b", "let x = b & a;"));
+    assert!(!test_normalized(S::Rust, "a * b", "let x = b * a;"));
+    assert!(!test_normalized(S::Cpp, "a == b", "int x = b == a;"));
+    assert!(!test_normalized(S::CSharp, "a == b", "var x = b == a;"));
+    assert!(test_normalized(S::Rust, "a & b", "let x = (a & b);"));
+  }
+
+  #[test]
+  fn test_parentheses_and_commas() {
+    use SupportLang as S;
+    assert!(test_normalized(S::C, "a + b", "int x = (a + b);"));
+    assert!(test_normalized(S::Python, "foo((a))", "foo(a)"));
+    assert!(test_normalized(S::Python, "foo(a, b,)", "foo(a, b)"));
+    assert!(test_normalized(S::Rust, "foo(a, b)", "foo(a, b,);"));
+  }
+}
@@ -121,3 +121,53 @@ pub fn language_typescript() -> TSLanguage {
 pub fn language_yaml() -> TSLanguage {
   into_lang!(tree_sitter_yaml)
 }
+
+#[cfg(feature = "builtin-parser")]
+macro_rules! node_types {
+  ($lang: ident, $field: ident) => {
+    $lang::$field
+  };
+  ($lang: ident) => {
+    node_types!($lang, NODE_TYPES)
+  };
+}
+
+#[cfg(not(feature = "builtin-parser"))]
+macro_rules! node_types {
+  ($($lang: ident),*) => {
+    "[]"
+  };
+}
+
+/// Return the content of the grammar's `node-types.json`.
+pub fn node_types_json(lang: crate::SupportLang) -> &'static str {
+  use crate::SupportLang as S;
+  match lang {
+    S::Bash => node_types!(tree_sitter_bash),
+    S::C => node_types!(tree_sitter_c),
+    S::Cpp => node_types!(tree_sitter_cpp),
+    S::CSharp => node_types!(tree_sitter_c_sharp),
+    S::Css => node_types!(tree_sitter_css),
+    S::Elixir => node_types!(tree_sitter_elixir),
+    S::Go => node_types!(tree_sitter_go),
+    S::Haskell => node_types!(tree_sitter_haskell),
+    S::Hcl => node_types!(tree_sitter_hcl),
+    S::Html => node_types!(tree_sitter_html),
+    S::Java => node_types!(tree_sitter_java),
+    S::JavaScript => node_types!(tree_sitter_javascript),
+    S::Json => node_types!(tree_sitter_json),
+    S::Kotlin => node_types!(tree_sitter_kotlin),
+    S::Lua => node_types!(tree_sitter_lua),
+    S::Nix => node_types!(tree_sitter_nix),
+    S::Php => node_types!(tree_sitter_php, PHP_ONLY_NODE_TYPES),
+    S::Python => node_types!(tree_sitter_python),
+    S::Ruby => node_types!(tree_sitter_ruby),
+    S::Rust => node_types!(tree_sitter_rust),
+    S::Scala => node_types!(tree_sitter_scala),
+    S::Solidity => node_types!(tree_sitter_solidity),
+    S::Swift => node_types!(tree_sitter_swift),
+    S::Tsx => node_types!(tree_sitter_typescript, TSX_NODE_TYPES),
+    S::TypeScript => node_types!(tree_sitter_typescript, TYPESCRIPT_NODE_TYPES),
+    S::Yaml => node_types!(tree_sitter_yaml),
+  }
+}
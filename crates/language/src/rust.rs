@@ -70,6 +70,18 @@ fn test_replace(src: &str, pattern: &str, replacer: &str) -> String {
   test_replace_lang(src, pattern, replacer, Rust)
 }
 
+#[test]
+fn test_rust_try_operator() {
+  // `?` after a meta variable is the try operator, `$?A` is optional
+  test_match("let $A = $B?;", "fn f() { let a = b?; }");
+  test_non_match("let $A = $B?;", "fn f() { let a = b; }");
+  test_match("$F($?A)", "fn f() { g(); }");
+  test_match("${{ Ok | Some }}($A)?", "fn f() { Some(a)?; }");
+  // marks in strings are text, with the expando sigil put back
+  test_match(r#"$F("a $?A")"#, r#"fn f() { g("a $?A"); }"#);
+  test_non_match(r#"$F("a $?A")"#, r#"fn f() { g("a $A"); }"#);
+}
+
 #[test]
 fn test_rust_replace() {
   let ret = test_replace("fn test() { Some(123) }", "Some($A)", "Ok($A)");
@@ -0,0 +1,708 @@
+//! Authoring support for ast-grep's own YAML files: `sgconfig.yml`, rule files and rule tests.
+//!
+//! These documents are recognized by file name and top level keys and are not scanned by rules.
+//! Keys are located with a line based reading of block style YAML, the style rules are written in.
+//! Flow mappings like `{ pattern: a }` are parsed for diagnostics but have no completion.
+use crate::schema::{self, KeyDoc, CONFIG_KEYS, TEST_KEYS};
+use crate::utils::position_to_offset;
+use crate::workspace::Project;
+use crate::{Backend, LSPLang};
+
+use ast_grep_config::{
+  from_str, DeserializeEnv, GlobalRules, ReferentRuleError, RuleConfig, RuleConfigError,
+  RuleCoreError, RuleSerializeError, SerializableRuleConfig,
+};
+use tower_lsp_server::lsp_types::*;
+use tower_lsp_server::UriExt;
+
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum RuleFileKind {
+  /// project configuration, `sgconfig.yml`
+  Config,
+  /// rule or global util rule files
+  Rule,
+  /// rule test cases
+  Test,
+}
+
+impl RuleFileKind {
+  pub fn detect(uri: &Uri, text: &str) -> Option<Self> {
+    let name = uri.as_str().rsplit('/').next()?;
+    if !name.ends_with(".yml") && !name.ends_with(".yaml") {
+      return None;
+    }
+    if name == "sgconfig.yml" || name == "sgconfig.yaml" {
+      return Some(Self::Config);
+    }
+    let docs = split_documents(text);
+    let top_level: Vec<_> = docs
+      .iter()
+      .flat_map(|doc| doc.keys.iter().filter(|k| k.path.is_empty()))
+      .map(|k| k.key)
+      .collect();
+    let has = |key| top_level.contains(&key);
+    if has("id") && (has("valid") || has("invalid")) {
+      Some(Self::Test)
+    } else if has("rule") && has("language") {
+      Some(Self::Rule)
+    } else {
+      None
+    }
+  }
+}
+
+pub(crate) struct RuleDocument {
+  pub version: i32,
+  pub text: String,
+  pub kind: RuleFileKind,
+  pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Util rules shared by all rule files of the project.
+#[derive(Default)]
+pub(crate) struct GlobalUtils {
+  pub rules: GlobalRules,
+  /// where each util is defined, for go to definition
+  pub locations: HashMap<String, Location>,
+}
+
+impl GlobalUtils {
+  /// Read util files, returning the utils and the errors found in them.
+  pub fn load<L: LSPLang>(files: &[PathBuf]) -> (Self, Vec<String>) {
+    let mut utils = vec![];
+    let mut locations = HashMap::new();
+    let mut errors = vec![];
+    for path in files {
+      let Ok(text) = std::fs::read_to_string(path) else {
+        continue;
+      };
+      let Some(uri) = Uri::from_file_path(path) else {
+        continue;
+      };
+      let doc = YamlDoc::new(0, &text);
+      if let Some(id) = doc.top_level("id") {
+        let range = doc.value_range(id);
+        locations.insert(unquote(id.value).to_string(), Location { uri, range });
+      }
+      match from_str(&text) {
+        Ok(util) => utils.push(util),
+        Err(e) => errors.push(format!("{}: {e}", path.display())),
+      }
+    }
+    let rules = match DeserializeEnv::<L>::parse_global_utils(utils) {
+      Ok(rules) => rules,
+      Err(e) => {
+        errors.push(error_message(&e));
+        GlobalRules::default()
+      }
+    };
+    (Self { rules, locations }, errors)
+  }
+}
+
+/// A key of a block mapping, read from one line of YAML.
+struct YamlKey<'a> {
+  line: u32,
+  /// byte column of the key
+  indent: usize,
+  key: &'a str,
+  /// text after the colon without comment, empty for nested mappings
+  value: &'a str,
+  value_col: usize,
+  /// keys of the enclosing mappings, list items are transparent
+  path: Vec<&'a str>,
+}
+
+/// One document of a multi-document YAML file.
+struct YamlDoc<'a> {
+  start_line: u32,
+  text: &'a str,
+  keys: Vec<YamlKey<'a>>,
+}
+
+/// Skip indentation and list markers, returning the byte column of the content.
+fn content_start(line: &str) -> usize {
+  let bytes = line.as_bytes();
+  let mut i = 0;
+  loop {
+    while bytes.get(i) == Some(&b' ') {
+      i += 1;
+    }
+    if bytes.get(i) == Some(&b'-') && matches!(bytes.get(i + 1), Some(b' ') | None) {
+      i += 1;
+    } else {
+      return i;
+    }
+  }
+}
+
+fn is_key_char(c: char) -> bool {
+  c.is_alphanumeric() || matches!(c, '_' | '-' | '$' | '.')
+}
+
+/// Parse `key: value` starting at the content column, returning the key, value and value column.
+fn parse_key(line: &str, start: usize) -> Option<(&str, &str, usize)> {
+  let content = &line[start..];
+  let colon = content.find(':')?;
+  let key = &content[..colon];
+  if key.is_empty() || !key.chars().all(is_key_char) {
+    return None;
+  }
+  let rest = &content[colon + 1..];
+  if !rest.is_empty() && !rest.starts_with(' ') {
+    return None;
+  }
+  let trimmed = rest.trim_start();
+  let value_col = start + colon + 1 + rest.len() - trimmed.len();
+  let value = match trimmed.find(" #") {
+    _ if trimmed.starts_with('#') => "",
+    Some(i) => trimmed[..i].trim_end(),
+    None => trimmed.trim_end(),
+  };
+  Some((key, value, value_col))
+}
+
+fn unquote(value: &str) -> &str {
+  value.trim_matches(|c| c == '"' || c == '\'')
+}
+
+fn split_documents(text: &str) -> Vec<YamlDoc<'_>> {
+  let mut docs = vec![];
+  let (mut start, mut start_line, mut offset) = (0, 0, 0);
+  for (i, line) in text.split_inclusive('\n').enumerate() {
+    let trimmed = line.trim_end();
+    if trimmed == "---" || trimmed.starts_with("--- ") {
+      docs.push(YamlDoc::new(start_line, &text[start..offset]));
+      start = offset + line.len();
+      start_line = i as u32 + 1;
+    }
+    offset += line.len();
+  }
+  docs.push(YamlDoc::new(start_line, &text[start..]));
+  docs
+}
+
+impl<'a> YamlDoc<'a> {
+  fn new(start_line: u32, text: &'a str) -> Self {
+    let mut keys = vec![];
+    let mut stack: Vec<(usize, &str)> = vec![];
+    // lines of a block scalar like `pattern: |` are code, not keys
+    let mut block_indent = None;
+    for (i, line) in text.lines().enumerate() {
+      let start = content_start(line);
+      if let Some(indent) = block_indent {
+        if line.trim().is_empty() || start > indent {
+          continue;
+        }
+        block_indent = None;
+      }
+      if line[start..].starts_with('#') {
+        continue;
+      }
+      let Some((key, value, value_col)) = parse_key(line, start) else {
+        continue;
+      };
+      while stack.last().is_some_and(|(indent, _)| *indent >= start) {
+        stack.pop();
+      }
+      if value.starts_with('|') || value.starts_with('>') {
+        block_indent = Some(start);
+      }
+      keys.push(YamlKey {
+        line: start_line + i as u32,
+        indent: start,
+        key,
+        value,
+        value_col,
+        path: stack.iter().map(|(_, k)| *k).collect(),
+      });
+      stack.push((start, key));
+    }
+    Self {
+      start_line,
+      text,
+      keys,
+    }
+  }
+
+  fn is_empty(&self) -> bool {
+    self.keys.is_empty()
+      && self
+        .text
+        .lines()
+        .all(|l| l.trim().is_empty() || l.trim_start().starts_with('#'))
+  }
+
+  fn contains_line(&self, line: u32) -> bool {
+    line >= self.start_line && line <= self.start_line + self.text.lines().count() as u32
+  }
+
+  fn line_text(&self, line: u32) -> &'a str {
+    let index = line.saturating_sub(self.start_line) as usize;
+    self.text.lines().nth(index).unwrap_or("")
+  }
+
+  /// Range of the byte columns `start..end` in the given line.
+  fn span(&self, line: u32, start: usize, end: usize) -> Range {
+    let text = self.line_text(line);
+    let character = |col: usize| text[..col.min(text.len())].encode_utf16().count() as u32;
+    Range::new(
+      Position::new(line, character(start)),
+      Position::new(line, character(end)),
+    )
+  }
+
+  fn key_range(&self, key: &YamlKey) -> Range {
+    self.span(key.line, key.indent, key.indent + key.key.len())
+  }
+
+  fn value_range(&self, key: &YamlKey) -> Range {
+    self.span(key.line, key.value_col, key.value_col + key.value.len())
+  }
+
+  fn top_level(&self, name: &str) -> Option<&YamlKey<'a>> {
+    self
+      .keys
+      .iter()
+      .find(|k| k.path.is_empty() && k.key == name)
+  }
+
+  fn key_at(&self, line: u32, col: usize) -> Option<&YamlKey<'a>> {
+    self.keys.iter().find(|k| k.line == line && k.indent <= col)
+  }
+
+  /// Keys enclosing the cursor, and the key whose value is under the cursor if any.
+  fn cursor_context(&self, line: u32, col: usize) -> (Vec<&'a str>, Option<&'a str>) {
+    let text = self.line_text(line);
+    let prefix = &text[..col.min(text.len())];
+    let start = content_start(prefix);
+    let path = self
+      .keys
+      .iter()
+      .rev()
+      .find(|k| k.line < line && k.indent < start)
+      .map(|k| {
+        let mut path = k.path.clone();
+        path.push(k.key);
+        path
+      })
+      .unwrap_or_default();
+    let value_of = if prefix[start..].contains(':') {
+      parse_key(text, start).map(|(key, _, _)| key)
+    } else {
+      None
+    };
+    (path, value_of)
+  }
+
+  fn locate(&self, anchor: &Anchor) -> Range {
+    let in_section = |k: &&YamlKey| k.path.first() == Some(&anchor.section);
+    let has_value = |k: &&YamlKey| anchor.value.map_or(true, |v| unquote(k.value) == v);
+    let found = self
+      .keys
+      .iter()
+      .filter(in_section)
+      .filter(has_value)
+      .find(|k| {
+        anchor
+          .key
+          .map_or(anchor.value.is_some(), |key| k.key == key)
+      });
+    if let Some(key) = found {
+      return if anchor.value.is_some() {
+        self.value_range(key)
+      } else {
+        self.key_range(key)
+      };
+    }
+    if let Some(section) = self.top_level(anchor.section) {
+      return self.key_range(section);
+    }
+    let start = Position::new(self.start_line, 0);
+    Range::new(start, start)
+  }
+}
+
+/// Where a rule error is reported: a key in a top level section, optionally with a value.
+struct Anchor<'e> {
+  section: &'static str,
+  key: Option<&'e str>,
+  value: Option<&'e str>,
+}
+
+impl<'e> Anchor<'e> {
+  fn section(section: &'static str) -> Self {
+    Self {
+      section,
+      key: None,
+      value: None,
+    }
+  }
+
+  fn key(section: &'static str, key: &'e str, value: Option<&'e str>) -> Self {
+    Self {
+      section,
+      key: Some(key),
+      value,
+    }
+  }
+
+  fn from_config_error(error: &'e RuleConfigError) -> Self {
+    use RuleConfigError as E;
+    match error {
+      E::Yaml(_) | E::MissingPotentialKinds => Self::section("rule"),
+      E::Core(e) => Self::from_core_error(e),
+      E::Rewriter(_, id) | E::NoFixInRewriter(id) => {
+        Self::key("rewriters", "id", Some(id.as_str()))
+      }
+      E::UndefinedRewriter(name) => Self {
+        section: "transform",
+        key: None,
+        value: Some(name.as_str()),
+      },
+      E::LabelVariable(var) => Self::key("labels", var, None),
+    }
+  }
+
+  fn from_core_error(error: &'e RuleCoreError) -> Self {
+    use RuleCoreError as E;
+    match error {
+      E::Yaml(_) => Self::section("rule"),
+      E::Utils(e) => Self::from_rule_error("utils", e),
+      E::Rule(e) => Self::from_rule_error("rule", e),
+      E::Constraints(e) => Self::from_rule_error("constraints", e),
+      E::Transform(_) => Self::section("transform"),
+      E::Fixer(_) => Self::section("fix"),
+      E::UndefinedMetaVar(var, "constraints") => Self::key("constraints", var, None),
+      E::UndefinedMetaVar(_, section) => Self::section(section),
+    }
+  }
+
+  fn from_rule_error(section: &'static str, error: &'e RuleSerializeError) -> Self {
+    use RuleSerializeError as E;
+    let key = match error {
+      E::MissPositiveMatcher => return Self::section(section),
+      E::InvalidKind(_) => "kind",
+      E::InvalidPattern(_) => "pattern",
+      E::NthChild(_) => "nthChild",
+      E::WrongRegex(_) => "regex",
+      E::MatchesReference(ReferentRuleError::UndefinedUtil(id)) => {
+        return Self::key(section, "matches", Some(id.as_str()))
+      }
+      E::MatchesReference(_) => "matches",
+      E::InvalidRange(_) => "range",
+      E::FieldNotSupported => "field",
+      E::InvalidField(field) => return Self::key(section, "field", Some(field.as_str())),
+    };
+    Self::key(section, key, None)
+  }
+}
+
+/// The error and all its sources, one per line.
+fn error_message(error: &dyn Error) -> String {
+  let mut message = error.to_string();
+  let mut source = error.source();
+  while let Some(e) = source {
+    message.push('\n');
+    message.push_str(&e.to_string());
+    source = e.source();
+  }
+  message
+}
+
+fn error_diagnostic(range: Range, message: String, severity: DiagnosticSeverity) -> Diagnostic {
+  Diagnostic {
+    range,
+    severity: Some(severity),
+    source: Some("ast-grep".into()),
+    message,
+    ..Default::default()
+  }
+}
+
+fn yaml_diagnostic(doc: &YamlDoc, error: &serde_yaml::Error) -> Diagnostic {
+  let range = match error.location() {
+    Some(location) => {
+      let line = doc.start_line + location.line().saturating_sub(1) as u32;
+      let text = doc.line_text(line);
+      let col = text
+        .char_indices()
+        .nth(location.column().saturating_sub(1))
+        .map_or(text.len(), |(i, _)| i);
+      doc.span(line, col, text.len())
+    }
+    None => doc.span(doc.start_line, 0, 0),
+  };
+  error_diagnostic(range, error.to_string(), DiagnosticSeverity::ERROR)
+}
+
+fn markdown(value: String) -> Documentation {
+  Documentation::MarkupContent(MarkupContent {
+    kind: MarkupKind::Markdown,
+    value,
+  })
+}
+
+fn key_item(key: KeyDoc) -> CompletionItem {
+  CompletionItem {
+    insert_text: Some(format!("{}: ", key.name)),
+    label: key.name,
+    kind: Some(CompletionItemKind::PROPERTY),
+    documentation: key.description.map(markdown),
+    ..Default::default()
+  }
+}
+
+fn value_item(label: String, kind: CompletionItemKind) -> CompletionItem {
+  CompletionItem {
+    label,
+    kind: Some(kind),
+    ..Default::default()
+  }
+}
+
+impl<L: LSPLang> Backend<L> {
+  /// Read global utils from util files, logging errors in them.
+  pub(crate) async fn load_global_utils(&self, files: &[PathBuf]) -> GlobalUtils {
+    let (utils, errors) = GlobalUtils::load::<L>(files);
+    for error in errors {
+      self
+        .client
+        .log_message(MessageType::ERROR, format!("Failed to load util: {error}"))
+        .await;
+    }
+    utils
+  }
+
+  pub(crate) fn rule_file_diagnostics(
+    &self,
+    uri: &Uri,
+    kind: RuleFileKind,
+    text: &str,
+  ) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let Some(project) = self.project_for(uri) else {
+      return diagnostics;
+    };
+    for doc in split_documents(text) {
+      if doc.is_empty() {
+        continue;
+      }
+      match kind {
+        RuleFileKind::Config => {
+          if let Err(e) = from_str::<serde_yaml::Value>(doc.text) {
+            diagnostics.push(yaml_diagnostic(&doc, &e));
+          }
+        }
+        RuleFileKind::Test => diagnostics.extend(self.test_diagnostic(&project, &doc)),
+        RuleFileKind::Rule => diagnostics.extend(self.rule_diagnostic(&project, &doc)),
+      }
+    }
+    diagnostics
+  }
+
+  fn rule_diagnostic(&self, project: &Project<L>, doc: &YamlDoc) -> Option<Diagnostic> {
+    let config: SerializableRuleConfig<L> = match from_str(doc.text) {
+      Ok(config) => config,
+      Err(e) => return Some(yaml_diagnostic(doc, &e)),
+    };
+    let globals = &project.global_utils.rules;
+    let error = RuleConfig::try_from(config, globals).err()?;
+    let range = doc.locate(&Anchor::from_config_error(&error));
+    let message = error_message(&error);
+    Some(error_diagnostic(range, message, DiagnosticSeverity::ERROR))
+  }
+
+  fn test_diagnostic(&self, project: &Project<L>, doc: &YamlDoc) -> Option<Diagnostic> {
+    if let Err(e) = from_str::<serde_yaml::Value>(doc.text) {
+      return Some(yaml_diagnostic(doc, &e));
+    }
+    let id = doc.top_level("id")?;
+    if project.rules.get_rule(unquote(id.value)).is_some() {
+      return None;
+    }
+    let message = format!("Rule `{}` is not found in the project.", unquote(id.value));
+    let range = doc.value_range(id);
+    Some(error_diagnostic(
+      range,
+      message,
+      DiagnosticSeverity::WARNING,
+    ))
+  }
+
+  pub(crate) fn rule_file_completion(
+    &self,
+    uri: &Uri,
+    kind: RuleFileKind,
+    text: &str,
+    position: Position,
+  ) -> Option<CompletionResponse> {
+    let docs = split_documents(text);
+    let doc = docs.iter().find(|d| d.contains_line(position.line))?;
+    let line_start = position_to_offset(text, Position::new(position.line, 0));
+    let col = position_to_offset(text, position) - line_start;
+    let (path, value_of) = doc.cursor_context(position.line, col);
+    let project = self.project_for(uri)?;
+    let items: Vec<_> = match (kind, value_of) {
+      (RuleFileKind::Config, None) if path.is_empty() => schema::static_keys(CONFIG_KEYS)
+        .into_iter()
+        .map(key_item)
+        .collect(),
+      (RuleFileKind::Test, None) if path.is_empty() => schema::static_keys(TEST_KEYS)
+        .into_iter()
+        .map(key_item)
+        .collect(),
+      (RuleFileKind::Test, Some("id")) => {
+        let mut ids = vec![];
+        project
+          .rules
+          .for_each_rule(|rule| ids.push(rule.id.clone()));
+        let kind = CompletionItemKind::REFERENCE;
+        ids.into_iter().map(|id| value_item(id, kind)).collect()
+      }
+      (RuleFileKind::Rule, None) => schema::rule_keys(&path).into_iter().map(key_item).collect(),
+      (RuleFileKind::Rule, Some(key)) => self.rule_value_items(&project, doc, &path, key),
+      _ => return None,
+    };
+    Some(CompletionResponse::Array(items))
+  }
+
+  fn rule_value_items(
+    &self,
+    project: &Project<L>,
+    doc: &YamlDoc,
+    path: &[&str],
+    key: &str,
+  ) -> Vec<CompletionItem> {
+    let lang = || {
+      let lang = doc.top_level("language")?;
+      from_str::<L>(unquote(lang.value)).ok()
+    };
+    match key {
+      "kind" => {
+        let Some(lang) = lang() else {
+          return vec![];
+        };
+        let ts_lang = lang.get_ts_language();
+        let kinds: BTreeSet<_> = (0..ts_lang.node_kind_count() as u16)
+          .filter(|id| ts_lang.node_kind_is_named(*id))
+          .filter_map(|id| ts_lang.node_kind_for_id(id))
+          .collect();
+        let kind = CompletionItemKind::ENUM_MEMBER;
+        kinds
+          .into_iter()
+          .map(|k| value_item(k.into(), kind))
+          .collect()
+      }
+      "field" => {
+        let Some(lang) = lang() else {
+          return vec![];
+        };
+        let ts_lang = lang.get_ts_language();
+        // field ids start from 1 in tree-sitter
+        let fields: BTreeSet<_> = (1..=ts_lang.field_count() as u16)
+          .filter_map(|id| ts_lang.field_name_for_id(id))
+          .collect();
+        let kind = CompletionItemKind::FIELD;
+        fields
+          .into_iter()
+          .map(|f| value_item(f.into(), kind))
+          .collect()
+      }
+      "matches" => {
+        let mut ids: BTreeSet<String> = doc
+          .keys
+          .iter()
+          .filter(|k| k.path == ["utils"])
+          .map(|k| k.key.to_string())
+          .collect();
+        ids.extend(project.global_utils.locations.keys().cloned());
+        let kind = CompletionItemKind::REFERENCE;
+        ids.into_iter().map(|id| value_item(id, kind)).collect()
+      }
+      _ => {
+        let mut path = path.to_vec();
+        path.push(key);
+        let values = schema::rule_key_values(&path);
+        let kind = CompletionItemKind::VALUE;
+        values
+          .into_iter()
+          .map(|value| CompletionItem {
+            documentation: value.description.map(markdown),
+            ..value_item(value.name, kind)
+          })
+          .collect()
+      }
+    }
+  }
+
+  pub(crate) fn rule_file_hover(
+    &self,
+    kind: RuleFileKind,
+    text: &str,
+    position: Position,
+  ) -> Option<Hover> {
+    let docs = split_documents(text);
+    let doc = docs.iter().find(|d| d.contains_line(position.line))?;
+    let line_start = position_to_offset(text, Position::new(position.line, 0));
+    let col = position_to_offset(text, position) - line_start;
+    let key = doc.key_at(position.line, col)?;
+    if col > key.indent + key.key.len() {
+      return None;
+    }
+    let static_doc = |keys: &[(&str, &str)]| {
+      let (_, doc) = keys.iter().find(|(name, _)| *name == key.key)?;
+      key.path.is_empty().then(|| doc.to_string())
+    };
+    let description = match kind {
+      RuleFileKind::Config => static_doc(CONFIG_KEYS)?,
+      RuleFileKind::Test => static_doc(TEST_KEYS)?,
+      RuleFileKind::Rule => {
+        let mut path = key.path.clone();
+        path.push(key.key);
+        schema::rule_key_doc(&path)?
+      }
+    };
+    Some(Hover {
+      contents: HoverContents::Markup(MarkupContent {
+        kind: MarkupKind::Markdown,
+        value: description,
+      }),
+      range: Some(doc.key_range(key)),
+    })
+  }
+
+  /// Jump from a `matches` id to the local or global util rule.
+  pub(crate) fn rule_file_definition(
+    &self,
+    uri: &Uri,
+    text: &str,
+    position: Position,
+  ) -> Option<GotoDefinitionResponse> {
+    let docs = split_documents(text);
+    let doc = docs.iter().find(|d| d.contains_line(position.line))?;
+    let line_start = position_to_offset(text, Position::new(position.line, 0));
+    let col = position_to_offset(text, position) - line_start;
+    let key = doc.key_at(position.line, col)?;
+    if key.key != "matches" || col < key.value_col {
+      return None;
+    }
+    let id = unquote(key.value);
+    let local = doc.keys.iter().find(|k| k.path == ["utils"] && k.key == id);
+    let location = if let Some(util) = local {
+      Location {
+        uri: uri.clone(),
+        range: doc.key_range(util),
+      }
+    } else {
+      let project = self.project_for(uri)?;
+      let location = project.global_utils.locations.get(id)?;
+      location.clone()
+    };
+    Some(GotoDefinitionResponse::Scalar(location))
+  }
+}
@@ -0,0 +1,124 @@
+//! Inlay hints and code lenses showing the meta variables bound by each diagnostic
+//! and a preview of its fix. Both are off unless enabled in the client settings.
+use crate::utils::Fixes;
+use crate::{Backend, LSPLang, VersionedAst};
+
+use ast_grep_core::meta_var::MetaVariable;
+use ast_grep_core::tree_sitter::StrDoc;
+use ast_grep_core::NodeMatch;
+use tower_lsp_server::lsp_types::*;
+
+use std::collections::HashMap;
+
+/// Meta variables bound by the match of a diagnostic, keyed like [`Fixes`].
+pub type Bindings = HashMap<(Range, String), Vec<(String, String)>>;
+
+/// Longest text shown for a single binding or fix.
+const MAX_TEXT_LEN: usize = 30;
+
+/// `$A` and `$$$ARGS` with their matched text, sorted by name.
+/// Labels of relational rules are not meta variables and are skipped.
+pub fn bindings_of<L: LSPLang>(m: &NodeMatch<StrDoc<L>>) -> Vec<(String, String)> {
+  let env = m.get_env();
+  let mut bindings: Vec<_> = env
+    .get_matched_variables()
+    .filter_map(|var| {
+      let name = match &var {
+        MetaVariable::Capture(name, _) => format!("${name}"),
+        MetaVariable::MultiCapture(name) => format!("$$${name}"),
+        _ => return None,
+      };
+      if name.chars().any(|c| c.is_lowercase()) {
+        return None;
+      }
+      let bytes = env.get_var_bytes(&var)?;
+      Some((name, String::from_utf8_lossy(bytes).into_owned()))
+    })
+    .collect();
+  bindings.sort_by(|(a, _), (b, _)| a.trim_start_matches('$').cmp(b.trim_start_matches('$')));
+  bindings
+}
+
+/// First line of the text, shortened to fit in the editor line.
+fn shorten(text: &str) -> String {
+  let line = text.lines().next().unwrap_or_default();
+  let mut short: String = line.chars().take(MAX_TEXT_LEN).collect();
+  if short.len() < text.len() {
+    short.push('…');
+  }
+  short
+}
+
+/// e.g. `$ARG = userInput, → sanitize(userInput)`
+fn hint_label(key: &(Range, String), bindings: &Bindings, fixes: &Fixes) -> Option<String> {
+  let mut parts: Vec<_> = bindings
+    .get(key)
+    .into_iter()
+    .flatten()
+    .map(|(name, text)| format!("{name} = {}", shorten(text)))
+    .collect();
+  let fix = fixes.get(key).and_then(|f| f.fixers.first());
+  if let Some(fix) = fix {
+    parts.push(format!("→ {}", shorten(&fix.fixed)));
+  }
+  if parts.is_empty() {
+    None
+  } else {
+    Some(parts.join(", "))
+  }
+}
+
+/// Diagnostics with something to show, with the key to their bindings and fixes.
+fn labeled<D>(versioned: &VersionedAst<D>) -> impl Iterator<Item = (&Diagnostic, String)>
+where
+  D: ast_grep_core::Doc,
+{
+  versioned.diagnostics.iter().filter_map(|diagnostic| {
+    let Some(NumberOrString::String(id)) = &diagnostic.code else {
+      return None;
+    };
+    let key = (diagnostic.range, id.clone());
+    let label = hint_label(&key, &versioned.bindings, &versioned.fixes)?;
+    Some((diagnostic, label))
+  })
+}
+
+impl<L: LSPLang> Backend<L> {
+  pub(crate) fn on_inlay_hint(&self, params: InlayHintParams) -> Option<Vec<InlayHint>> {
+    if !self.settings().inlay_hints {
+      return None;
+    }
+    let versioned = self.map.get(params.text_document.uri.as_str())?;
+    let range = params.range;
+    let hints = labeled(&versioned)
+      .filter(|(d, _)| range.start <= d.range.end && d.range.end <= range.end)
+      .map(|(diagnostic, label)| InlayHint {
+        position: diagnostic.range.end,
+        label: InlayHintLabel::String(label),
+        kind: None,
+        text_edits: None,
+        tooltip: Some(InlayHintTooltip::String(diagnostic.message.clone())),
+        padding_left: Some(true),
+        padding_right: None,
+        data: None,
+      })
+      .collect();
+    Some(hints)
+  }
+
+  pub(crate) fn on_code_lens(&self, params: CodeLensParams) -> Option<Vec<CodeLens>> {
+    if !self.settings().code_lens {
+      return None;
+    }
+    let versioned = self.map.get(params.text_document.uri.as_str())?;
+    let lenses = labeled(&versioned)
+      .map(|(diagnostic, label)| CodeLens {
+        range: diagnostic.range,
+        // lenses without a command to run are only shown
+        command: Some(Command::new(label, String::new(), None)),
+        data: None,
+      })
+      .collect();
+    Some(lenses)
+  }
+}
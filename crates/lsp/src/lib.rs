@@ -1,7 +1,19 @@
+mod authoring;
+mod hints;
+mod playground;
+mod pull;
+mod schema;
+mod search;
+mod settings;
+mod suppress;
 mod utils;
+mod worker;
+mod workspace;
 
+use ast_grep_core::source::OverlapPolicy;
 use ast_grep_core::NodeMatch;
 use dashmap::DashMap;
+use serde::de::DeserializeOwned;
 use serde_json::Value;
 use tower_lsp_server::jsonrpc::Result;
 use tower_lsp_server::lsp_types::notification::{DidChangeWatchedFiles, Notification};
@@ -9,49 +21,120 @@ use tower_lsp_server::lsp_types::*;
 use tower_lsp_server::UriExt;
 use tower_lsp_server::{Client, LanguageServer};
 
-use ast_grep_config::{CombinedScan, RuleCollection, Severity};
+use ast_grep_config::{Cancellation, CombinedScan, RuleCollection, ScanBudget, Severity};
 use ast_grep_core::{
+  source::Edit,
   tree_sitter::{LanguageExt, StrDoc},
   AstGrep, Doc,
 };
 
 use std::collections::{BTreeMap, HashMap};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
-use utils::{convert_match_to_diagnostic, diagnostic_to_code_action, Fixes, RewriteData};
+use authoring::{RuleDocument, RuleFileKind};
+use hints::{bindings_of, Bindings};
+use settings::Settings;
+use utils::{
+  convert_match_to_diagnostic, diagnostic_to_code_action, position_to_offset, Fixes, RewriteData,
+};
+use worker::{sleep, WorkerPool};
 
+pub use playground::{Dump, TreeDumper};
+pub use pull::FileWalker;
 pub use tower_lsp_server::{LspService, Server};
+pub use workspace::{ConfigLoader, LanguageSetup, ProjectLoader, ProjectRules};
+use workspace::{Folder, Project};
 
-pub trait LSPLang: LanguageExt + Eq + Send + Sync + 'static {}
-impl<T> LSPLang for T where T: LanguageExt + Eq + Send + Sync + 'static {}
+pub trait LSPLang: LanguageExt + Eq + Send + Sync + DeserializeOwned + 'static {}
+impl<T> LSPLang for T where T: LanguageExt + Eq + Send + Sync + DeserializeOwned + 'static {}
 
 type Notes = BTreeMap<(u32, u32, u32, u32), Arc<String>>;
 
+/// Scans running at the same time, more edits wait for a free worker.
+const MAX_WORKERS: usize = 4;
+
 struct VersionedAst<D: Doc> {
   version: i32,
+  // the version read by workers, to skip scans of outdated versions
+  latest: Arc<AtomicI32>,
+  // version of the diagnostics, notes, fixes and bindings
+  scanned: Option<i32>,
+  // an edit is waiting for the user to stop typing
+  debouncing: bool,
   root: AstGrep<D>,
   notes: Notes,
   fixes: Fixes,
+  bindings: Bindings,
+  // last published diagnostics, to skip republishing unchanged ones
+  diagnostics: Vec<Diagnostic>,
+}
+
+impl<D: Doc> VersionedAst<D> {
+  fn new(version: i32, root: AstGrep<D>) -> Self {
+    Self {
+      version,
+      latest: Arc::new(AtomicI32::new(version)),
+      scanned: None,
+      debouncing: false,
+      root,
+      notes: BTreeMap::new(),
+      fixes: Fixes::new(),
+      bindings: Bindings::new(),
+      diagnostics: vec![],
+    }
+  }
 }
 
 pub struct Backend<L: LSPLang> {
   client: Client,
   map: DashMap<String, VersionedAst<StrDoc<L>>>,
   base: PathBuf,
-  rules: Arc<RwLock<RuleCollection<L>>>,
-  // interner for rule ids to note, to avoid duplication
+  // rules the server was started with
+  default_project: RwLock<Arc<Project<L>>>,
+  // None until the workspace folders are known
+  folders: RwLock<Option<Vec<Folder<L>>>>,
+  // find the project of a workspace folder
+  project_loader: Option<ProjectLoader<L>>,
+  // load the project of the config path in the settings
+  config_loader: Option<ConfigLoader<L>>,
+  language_setup: Option<LanguageSetup>,
+  // print syntax trees and patterns for the playground requests
+  tree_dumper: Option<TreeDumper<L>>,
+  // interner for notes, to avoid duplication
   interner: DashMap<String, Arc<String>>,
   // rule finding closure to reload rules
   rule_finder: Box<dyn Fn() -> anyhow::Result<RuleCollection<L>> + Send + Sync>,
   // store client capabilities to check support
   capabilities: Arc<RwLock<ClientCapabilities>>,
+  // list files of a workspace folder for workspace diagnostics
+  file_walker: Option<FileWalker>,
+  // pulled diagnostics of files not opened in the editor
+  report_cache: DashMap<String, pull::CachedReport>,
+  // files of the workspace folders, None until walked
+  walked_files: RwLock<Option<Vec<PathBuf>>>,
+  // wakes workspace diagnostic requests waiting for a change
+  changes: pull::ChangeSignal,
+  // bumped on rule reload to invalidate pulled result ids
+  generation: AtomicUsize,
+  // open sgconfig.yml, rule and rule test files
+  rule_docs: DashMap<String, RuleDocument>,
+  // list util rule files to resolve `matches` in rule files
+  util_finder: Option<Box<dyn Fn() -> Vec<PathBuf> + Send + Sync>>,
+  // from initializationOptions or didChangeConfiguration
+  settings: RwLock<Arc<Settings>>,
+  // scan documents off the async executor
+  workers: WorkerPool,
 }
 
 const FALLBACK_CODE_ACTION_PROVIDER: Option<CodeActionProviderCapability> =
   Some(CodeActionProviderCapability::Simple(true));
 
 const APPLY_ALL_FIXES: &str = "ast-grep.applyAllFixes";
+const SEARCH: &str = "ast-grep.search";
+const APPLY_REWRITE: &str = "ast-grep.applyRewrite";
 const QUICKFIX_AST_GREP: &str = "quickfix.ast-grep";
 const FIX_ALL_AST_GREP: &str = "source.fixAll.ast-grep";
 
@@ -84,20 +167,45 @@ impl<L: LSPLang> LanguageServer for Backend<L> {
     if let Ok(mut cap) = self.capabilities.write() {
       *cap = params.capabilities;
     }
+    if let Some(options) = params.initialization_options {
+      self.update_settings(options).await;
+    }
+    self
+      .setup_initial_folders(params.workspace_folders.as_ref())
+      .await;
     Ok(InitializeResult {
       server_info: Some(ServerInfo {
         name: "ast-grep language server".to_string(),
         version: None,
       }),
       capabilities: ServerCapabilities {
-        // TODO: change this to incremental
-        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(
+          TextDocumentSyncKind::INCREMENTAL,
+        )),
         code_action_provider: code_action_provider.or(FALLBACK_CODE_ACTION_PROVIDER),
         execute_command_provider: Some(ExecuteCommandOptions {
-          commands: vec![APPLY_ALL_FIXES.to_string()],
+          commands: vec![
+            APPLY_ALL_FIXES.to_string(),
+            SEARCH.to_string(),
+            APPLY_REWRITE.to_string(),
+          ],
           work_done_progress_options: Default::default(),
         }),
         hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions::default()),
+        definition_provider: Some(OneOf::Left(true)),
+        diagnostic_provider: Some(pull::diagnostic_options()),
+        inlay_hint_provider: Some(OneOf::Left(true)),
+        code_lens_provider: Some(CodeLensOptions {
+          resolve_provider: Some(false),
+        }),
+        workspace: Some(WorkspaceServerCapabilities {
+          workspace_folders: Some(WorkspaceFoldersServerCapabilities {
+            supported: Some(true),
+            change_notifications: Some(OneOf::Left(true)),
+          }),
+          file_operations: None,
+        }),
         ..ServerCapabilities::default()
       },
     })
@@ -131,18 +239,20 @@ impl<L: LSPLang> LanguageServer for Backend<L> {
     Ok(())
   }
 
-  async fn did_change_workspace_folders(&self, _: DidChangeWorkspaceFoldersParams) {
+  async fn did_change_workspace_folders(&self, params: DidChangeWorkspaceFoldersParams) {
     self
       .client
       .log_message(MessageType::INFO, "workspace folders changed!")
       .await;
+    self.on_change_workspace_folders(params).await;
   }
 
-  async fn did_change_configuration(&self, _: DidChangeConfigurationParams) {
+  async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
     self
       .client
       .log_message(MessageType::INFO, "configuration changed!")
       .await;
+    self.on_change_configuration(params).await;
   }
 
   async fn did_change_watched_files(&self, _params: DidChangeWatchedFilesParams) {
@@ -173,10 +283,12 @@ impl<L: LSPLang> LanguageServer for Backend<L> {
       .log_message(MessageType::INFO, "file opened!")
       .await;
     self.on_open(params).await;
+    self.changes.notify();
   }
 
   async fn did_change(&self, params: DidChangeTextDocumentParams) {
     self.on_change(params).await;
+    self.changes.notify();
   }
 
   async fn did_save(&self, _: DidSaveTextDocumentParams) {
@@ -188,6 +300,7 @@ impl<L: LSPLang> LanguageServer for Backend<L> {
 
   async fn did_close(&self, params: DidCloseTextDocumentParams) {
     self.on_close(params).await;
+    self.changes.notify();
     self
       .client
       .log_message(MessageType::INFO, "file closed!")
@@ -202,6 +315,20 @@ impl<L: LSPLang> LanguageServer for Backend<L> {
     Ok(self.on_execute_command(params).await)
   }
 
+  async fn diagnostic(
+    &self,
+    params: DocumentDiagnosticParams,
+  ) -> Result<DocumentDiagnosticReportResult> {
+    Ok(self.on_document_diagnostic(params).await)
+  }
+
+  async fn workspace_diagnostic(
+    &self,
+    params: WorkspaceDiagnosticParams,
+  ) -> Result<WorkspaceDiagnosticReportResult> {
+    Ok(self.on_workspace_diagnostic(params).await)
+  }
+
   async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
     self
       .client
@@ -209,6 +336,85 @@ impl<L: LSPLang> LanguageServer for Backend<L> {
       .await;
     Ok(self.do_hover(params.text_document_position_params))
   }
+
+  async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+    let pos_params = params.text_document_position;
+    let uri = pos_params.text_document.uri;
+    let Some(doc) = self.rule_docs.get(uri.as_str()) else {
+      return Ok(None);
+    };
+    Ok(self.rule_file_completion(&uri, doc.kind, &doc.text, pos_params.position))
+  }
+
+  async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+    Ok(self.on_inlay_hint(params))
+  }
+
+  async fn code_lens(&self, params: CodeLensParams) -> Result<Option<Vec<CodeLens>>> {
+    Ok(self.on_code_lens(params))
+  }
+
+  async fn goto_definition(
+    &self,
+    params: GotoDefinitionParams,
+  ) -> Result<Option<GotoDefinitionResponse>> {
+    let pos_params = params.text_document_position_params;
+    let uri = pos_params.text_document.uri;
+    let Some(doc) = self.rule_docs.get(uri.as_str()) else {
+      return Ok(None);
+    };
+    Ok(self.rule_file_definition(&uri, &doc.text, pos_params.position))
+  }
+}
+
+type ScanResult = (Vec<Diagnostic>, Fixes, Bindings);
+
+/// Diagnostics, fixes and bindings of the rules matching the document.
+fn scan_document<L: LSPLang>(
+  project: &Project<L>,
+  settings: &Settings,
+  uri: &Uri,
+  root: &AstGrep<StrDoc<L>>,
+  cancellation: Option<Cancellation>,
+) -> Option<ScanResult> {
+  if settings.is_too_large(root.source()) {
+    return None;
+  }
+  let path = Backend::<L>::uri_to_relative_path(uri, &project.base)?;
+  let mut rule_refs = project.rules.for_path(&path);
+  rule_refs.retain(|rule| settings.severity_of(rule).is_some());
+  if rule_refs.is_empty() {
+    return None;
+  }
+  let unused_suppression_rule =
+    CombinedScan::unused_config(Severity::Hint, rule_refs[0].language.clone());
+  let mut scan = CombinedScan::new(rule_refs);
+  scan.set_unused_suppression_rule(&unused_suppression_rule);
+  let budget = ScanBudget {
+    cancellation,
+    ..Default::default()
+  };
+  let matches = scan.scan_with_budget(root, false, budget, None).matches;
+  let mut diagnostics = vec![];
+  let mut fixes = Fixes::new();
+  let mut bindings = Bindings::new();
+  for (rule, ms) in matches {
+    let to_diagnostic = |m: NodeMatch<StrDoc<L>>| {
+      let severity = settings.severity_of(rule).unwrap_or(&rule.severity);
+      let diagnostic = convert_match_to_diagnostic(uri, &m, rule, severity);
+      let key = (diagnostic.range, rule.id.clone());
+      if let Some(r) = RewriteData::from_node_match(&m, rule) {
+        fixes.insert(key.clone(), r);
+      }
+      let bound = bindings_of(&m);
+      if !bound.is_empty() {
+        bindings.insert(key, bound);
+      }
+      diagnostic
+    };
+    diagnostics.extend(ms.into_iter().map(to_diagnostic));
+  }
+  Some((diagnostics, fixes, bindings))
 }
 
 fn pos_tuple_to_range((line, character, end_line, end_character): (u32, u32, u32, u32)) -> Range {
@@ -228,19 +434,91 @@ impl<L: LSPLang> Backend<L> {
   {
     Self {
       client,
-      rules: Arc::new(RwLock::new(RuleCollection::default())),
+      default_project: RwLock::new(Arc::new(Project::default())),
+      folders: RwLock::new(None),
+      project_loader: None,
+      config_loader: None,
+      language_setup: None,
+      tree_dumper: None,
       base,
       map: DashMap::new(),
       interner: DashMap::new(),
       rule_finder: Box::new(rule_finder),
       capabilities: Arc::new(RwLock::new(ClientCapabilities::default())),
+      file_walker: None,
+      report_cache: DashMap::new(),
+      walked_files: RwLock::new(None),
+      changes: pull::ChangeSignal::default(),
+      generation: AtomicUsize::new(0),
+      rule_docs: DashMap::new(),
+      util_finder: None,
+      settings: RwLock::new(Arc::default()),
+      workers: WorkerPool::new(MAX_WORKERS),
     }
   }
 
-  /// Convert URI to a path relative to base directory
-  fn uri_to_relative_path(&self, uri: &Uri) -> Option<PathBuf> {
+  /// Set how files of a workspace folder are found for workspace diagnostics.
+  /// Without a walker, workspace diagnostics only cover open documents.
+  pub fn with_file_walker<F>(mut self, walker: F) -> Self
+  where
+    F: Fn(&std::path::Path) -> Vec<PathBuf> + Send + Sync + 'static,
+  {
+    self.file_walker = Some(Box::new(walker));
+    self
+  }
+
+  /// Set how util rule files are found, so rule files can use global utils in `matches`.
+  pub fn with_util_finder<F>(mut self, finder: F) -> Self
+  where
+    F: Fn() -> Vec<PathBuf> + Send + Sync + 'static,
+  {
+    self.util_finder = Some(Box::new(finder));
+    self
+  }
+
+  /// Set how the project of each workspace folder is found.
+  /// Without a loader, all folders use the rules of the rule finder.
+  pub fn with_project_loader<F>(mut self, loader: F) -> Self
+  where
+    F: Fn(&Path) -> Option<anyhow::Result<ProjectRules<L>>> + Send + Sync + 'static,
+  {
+    self.project_loader = Some(Box::new(loader));
+    self
+  }
+
+  /// Set how the project of a config file is loaded, for the `configPath` setting.
+  /// Without a loader, the setting is ignored.
+  pub fn with_config_loader<F>(mut self, loader: F) -> Self
+  where
+    F: Fn(&Path) -> anyhow::Result<ProjectRules<L>> + Send + Sync + 'static,
+  {
+    self.config_loader = Some(Box::new(loader));
+    self
+  }
+
+  /// Set how languages declared by the projects of the initial workspace folders are registered.
+  /// It runs once in `initialize`, before any document is parsed.
+  pub fn with_language_setup<F>(mut self, setup: F) -> Self
+  where
+    F: Fn(&[PathBuf]) -> anyhow::Result<()> + Send + Sync + 'static,
+  {
+    self.language_setup = Some(Box::new(setup));
+    self
+  }
+
+  /// Set how patterns and syntax trees are printed for `ast-grep/dumpTree`.
+  pub fn with_tree_dumper<F>(mut self, dumper: F) -> Self
+  where
+    F: Fn(Dump<L>) -> Option<String> + Send + Sync + 'static,
+  {
+    self.tree_dumper = Some(Box::new(dumper));
+    self
+  }
+
+  /// Convert URI to a path relative to the project base directory
+  fn uri_to_relative_path(uri: &Uri, base: &Path) -> Option<PathBuf> {
     let absolute_path = uri.to_file_path()?;
-    if let Ok(relative_path) = absolute_path.strip_prefix(&self.base) {
+    if let Ok(relative_path) = absolute_path.strip_prefix(base) {
       Some(relative_path.to_path_buf())
     } else {
       Some(absolute_path.to_path_buf())
@@ -249,6 +527,9 @@ impl<L: LSPLang> Backend<L> {
 
   fn do_hover(&self, pos_params: TextDocumentPositionParams) -> Option<Hover> {
     let uri = pos_params.text_document.uri;
+    if let Some(doc) = self.rule_docs.get(uri.as_str()) {
+      return self.rule_file_hover(doc.kind, &doc.text, pos_params.position);
+    }
     let Position {
       line,
       character: column,
@@ -273,106 +554,118 @@ impl<L: LSPLang> Backend<L> {
     })
   }
 
-  fn get_diagnostics(
-    &self,
-    uri: &Uri,
-    versioned: &VersionedAst<StrDoc<L>>,
-  ) -> Option<(Vec<Diagnostic>, Fixes)> {
-    let path = self.uri_to_relative_path(uri)?;
-
-    let rules = self.rules.read().ok()?;
-    let rule_refs = rules.for_path(&path);
-    if rule_refs.is_empty() {
-      return None;
-    }
-    let unused_suppression_rule =
-      CombinedScan::unused_config(Severity::Hint, rule_refs[0].language.clone());
-    let mut scan = CombinedScan::new(rule_refs);
-    scan.set_unused_suppression_rule(&unused_suppression_rule);
-    let matches = scan.scan(&versioned.root, false).matches;
-    let mut diagnostics = vec![];
-    let mut fixes = Fixes::new();
-    for (rule, ms) in matches {
-      let to_diagnostic = |m: NodeMatch<StrDoc<L>>| {
-        let diagnostic = convert_match_to_diagnostic(uri, &m, rule);
-        let rewrite_data = RewriteData::from_node_match(&m, rule);
-        if let Some(r) = rewrite_data {
-          fixes.insert((diagnostic.range, rule.id.clone()), r);
-        }
-        diagnostic
-      };
-      diagnostics.extend(ms.into_iter().map(to_diagnostic));
-    }
-    Some((diagnostics, fixes))
-  }
-
-  fn build_notes(&self, diagnostics: &[Diagnostic]) -> Notes {
+  fn build_notes(&self, uri: &Uri, diagnostics: &[Diagnostic]) -> Notes {
     let mut notes = BTreeMap::new();
+    let Some(project) = self.project_for(uri) else {
+      return notes;
+    };
     for diagnostic in diagnostics {
       let Some(NumberOrString::String(id)) = &diagnostic.code else {
         continue;
       };
-      let Ok(rules) = self.rules.read() else {
-        continue;
-      };
-      let Some(note) = rules.get_rule(id).and_then(|r| r.note.clone()) else {
+      let Some(note) = project.rules.get_rule(id).and_then(|r| r.note.clone()) else {
         continue;
       };
       let start = diagnostic.range.start;
       let end = diagnostic.range.end;
       let atom = self
         .interner
-        .entry(id.clone())
-        .or_insert_with(|| Arc::new(note.clone()))
+        .entry(note.clone())
+        .or_insert_with(|| Arc::new(note))
         .clone();
       notes.insert((start.line, start.character, end.line, end.character), atom);
     }
     notes
   }
 
-  async fn publish_diagnostics(
-    &self,
-    uri: Uri,
-    versioned: &mut VersionedAst<StrDoc<L>>,
-  ) -> Option<()> {
-    let (diagnostics, fixes) = self.get_diagnostics(&uri, versioned).unwrap_or_default();
-    versioned.notes = self.build_notes(&diagnostics);
+  /// Recompute diagnostics, notes, fixes and bindings of the document on a worker.
+  /// Returns whether the diagnostics changed, or None if the version is outdated.
+  async fn rescan(&self, uri: &Uri, version: i32) -> Option<bool> {
+    let (root, latest) = {
+      let versioned = self.map.get(uri.as_str())?;
+      if versioned.version != version {
+        return None;
+      }
+      (versioned.root.clone(), versioned.latest.clone())
+    };
+    let project = self.project_for(uri);
+    let settings = self.settings();
+    let job_uri = uri.clone();
+    let job = move || {
+      let outdated = Cancellation::new(move || latest.load(Ordering::Acquire) != version);
+      // a newer version arrived while the scan was queued
+      if outdated.is_cancelled() {
+        return None;
+      }
+      let scanned = project.and_then(|p| {
+        let outdated = Some(outdated.clone());
+        scan_document(&p, &settings, &job_uri, &root, outdated)
+      });
+      // the scan is aborted once a newer version arrives, drop its partial result
+      if outdated.is_cancelled() {
+        return None;
+      }
+      Some(scanned.unwrap_or_default())
+    };
+    let (diagnostics, fixes, bindings) = self.workers.run(job).await??;
+    let notes = self.build_notes(uri, &diagnostics);
+    let mut versioned = self.map.get_mut(uri.as_str())?;
+    if versioned.version != version {
+      return None;
+    }
+    versioned.scanned = Some(version);
+    versioned.notes = notes;
     versioned.fixes = fixes;
+    versioned.bindings = bindings;
+    let changed = versioned.diagnostics != diagnostics;
+    versioned.diagnostics = diagnostics;
+    Some(changed)
+  }
+
+  /// Rescan the document if its diagnostics are older than its text.
+  pub(crate) async fn ensure_scanned(&self, uri: &Uri) {
+    let outdated = self
+      .map
+      .get(uri.as_str())
+      .filter(|v| v.scanned != Some(v.version))
+      .map(|v| v.version);
+    if let Some(version) = outdated {
+      self.rescan(uri, version).await;
+    }
+  }
 
+  /// Send the diagnostics of the document, unless the client pulls them.
+  async fn send_diagnostics(&self, uri: Uri) {
+    if self.client_pulls_diagnostics() {
+      return;
+    }
+    let Some((diagnostics, version)) = self
+      .map
+      .get(uri.as_str())
+      .map(|v| (v.diagnostics.clone(), v.scanned))
+    else {
+      return;
+    };
     self
       .client
-      .publish_diagnostics(uri, diagnostics, Some(versioned.version))
+      .publish_diagnostics(uri, diagnostics, version)
       .await;
-    Some(())
   }
 
-  async fn get_path_of_first_workspace(&self) -> Option<std::path::PathBuf> {
-    // need drop the lock before await
-    let client_support_workspace = {
-      let cap = self.capabilities.read().ok()?;
-      cap
-        .workspace
-        .as_ref()
-        .and_then(|w| w.workspace_folders)
-        .unwrap_or(false)
-    };
-    if !client_support_workspace {
-      return None;
+  async fn publish_diagnostics(&self, uri: Uri, version: i32) {
+    if self.rescan(&uri, version).await.is_some() {
+      self.send_diagnostics(uri).await;
     }
-    let folders = self.client.workspace_folders().await.ok()??;
-    let folder = folders.first()?;
-    folder.uri.to_file_path().map(PathBuf::from)
   }
 
   // skip files outside of workspace root #1382, #1402
   async fn should_skip_file_outside_workspace(&self, text_doc: &TextDocumentItem) -> Option<()> {
-    // fallback to base if no workspace provided by client #2211
-    let workspace_root = self
-      .get_path_of_first_workspace()
-      .await
-      .unwrap_or_else(|| self.base.clone());
+    if self.settings().scan_outside_workspace {
+      return None;
+    }
+    let workspace_roots = self.workspace_roots().await;
     let doc_file_path = text_doc.uri.to_file_path()?;
-    if doc_file_path.starts_with(workspace_root) {
+    if workspace_roots.iter().any(|r| doc_file_path.starts_with(r)) {
       None
     } else {
       Some(())
@@ -390,63 +683,179 @@ impl<L: LSPLang> Backend<L> {
     }
     let uri = text_doc.uri.as_str().to_owned();
     let text = text_doc.text;
+    if let Some(kind) = RuleFileKind::detect(&text_doc.uri, &text) {
+      let doc = RuleDocument {
+        version: text_doc.version,
+        text,
+        kind,
+        diagnostics: vec![],
+      };
+      self.rule_docs.insert(uri, doc);
+      self.publish_rule_doc_diagnostics(text_doc.uri).await;
+      return Some(());
+    }
     self
       .client
       .log_message(MessageType::LOG, "Parsing doc.")
       .await;
     let lang = Self::infer_lang_from_uri(&text_doc.uri)?;
     let root = AstGrep::new(text, lang);
-    let mut versioned = VersionedAst {
-      version: text_doc.version,
-      root,
-      notes: BTreeMap::new(),
-      fixes: Fixes::new(),
-    };
+    let versioned = VersionedAst::new(text_doc.version, root);
+    self.map.insert(uri, versioned);
     self
       .client
       .log_message(MessageType::LOG, "Publishing init diagnostics.")
       .await;
-    self.publish_diagnostics(text_doc.uri, &mut versioned).await;
-    self.map.insert(uri.to_owned(), versioned); // don't lock dashmap
+    self
+      .publish_diagnostics(text_doc.uri, text_doc.version)
+      .await;
     Some(())
   }
 
   async fn on_change(&self, params: DidChangeTextDocumentParams) -> Option<()> {
     let text_doc = params.text_document;
     let uri = text_doc.uri.as_str();
-    let change = &params.content_changes.first()?;
-    let text = &change.text;
-    self
-      .client
-      .log_message(MessageType::LOG, "Parsing changed doc.")
-      .await;
-    let lang = Self::infer_lang_from_uri(&text_doc.uri)?;
-    let root = AstGrep::new(text, lang);
-    let mut versioned = self.map.get_mut(uri)?;
-    // skip old version update
-    if versioned.version > text_doc.version {
-      return None;
+    if let Some(mut doc) = self.rule_docs.get_mut(uri) {
+      if doc.version > text_doc.version {
+        return None;
+      }
+      for change in params.content_changes {
+        Self::apply_text_change(&mut doc.text, change);
+      }
+      doc.version = text_doc.version;
+      drop(doc);
+      self.publish_rule_doc_diagnostics(text_doc.uri).await;
+      return Some(());
     }
-    *versioned = VersionedAst {
-      version: text_doc.version,
-      root,
-      notes: BTreeMap::new(),
-      fixes: Fixes::new(),
+    let lang = Self::infer_lang_from_uri(&text_doc.uri)?;
+    // apply the edits before any await, later changes build on them
+    let debouncing = {
+      let mut versioned = self.map.get_mut(uri)?;
+      // skip old version update
+      if versioned.version > text_doc.version {
+        return None;
+      }
+      // apply the batch to a copy so a failed change does not leave partial edits
+      let mut root = versioned.root.clone();
+      for change in params.content_changes {
+        if let Err(e) = Self::apply_change(&mut root, change, &lang) {
+          drop(versioned);
+          self
+            .client
+            .log_message(MessageType::ERROR, format!("Failed to apply change: {e}"))
+            .await;
+          return None;
+        }
+      }
+      versioned.root = root;
+      versioned.version = text_doc.version;
+      versioned.latest.store(text_doc.version, Ordering::Release);
+      std::mem::replace(&mut versioned.debouncing, true)
     };
+    // pulled reports rescan on request
+    if debouncing || self.client_pulls_diagnostics() {
+      return Some(());
+    }
+    let version = self.debounce(uri).await?;
     self
       .client
       .log_message(MessageType::LOG, "Publishing diagnostics.")
       .await;
+    if self.rescan(&text_doc.uri, version).await? {
+      self.send_diagnostics(text_doc.uri).await;
+    }
+    Some(())
+  }
+
+  /// Wait until the document has not changed for the debounce window.
+  /// Returns the version to scan.
+  async fn debounce(&self, uri: &str) -> Option<i32> {
+    let delay = Duration::from_millis(self.settings().debounce_ms);
+    loop {
+      let version = self.map.get(uri)?.version;
+      if !delay.is_zero() {
+        sleep(delay).await;
+      }
+      let mut versioned = self.map.get_mut(uri)?;
+      if versioned.version == version {
+        versioned.debouncing = false;
+        return Some(version);
+      }
+    }
+  }
+
+  /// Apply one content change to the document.
+  /// Ranged changes are applied as edits so tree-sitter can reparse incrementally,
+  /// a change without range replaces the whole document.
+  fn apply_change(
+    root: &mut AstGrep<StrDoc<L>>,
+    change: TextDocumentContentChangeEvent,
+    lang: &L,
+  ) -> std::result::Result<(), String> {
+    let Some(range) = change.range else {
+      *root = AstGrep::new(change.text, lang.clone());
+      return Ok(());
+    };
+    let source = root.source();
+    let start = position_to_offset(source, range.start);
+    let end = position_to_offset(source, range.end).max(start);
+    let edit = Edit::<String> {
+      position: start,
+      deleted_length: end - start,
+      inserted_text: change.text.into_bytes(),
+    };
+    root.edit(edit)?;
+    Ok(())
+  }
+
+  /// Apply one content change to the text of a rule document.
+  fn apply_text_change(text: &mut String, change: TextDocumentContentChangeEvent) {
+    let Some(range) = change.range else {
+      *text = change.text;
+      return;
+    };
+    let start = position_to_offset(text, range.start);
+    let end = position_to_offset(text, range.end).max(start);
+    text.replace_range(start..end, &change.text);
+  }
+
+  /// Recompute the diagnostics of the rule document and publish them if changed.
+  /// The document is copied out so no map guard is held across awaits.
+  async fn publish_rule_doc_diagnostics(&self, uri: Uri) {
+    let Some((version, kind, text)) = self
+      .rule_docs
+      .get(uri.as_str())
+      .map(|doc| (doc.version, doc.kind, doc.text.clone()))
+    else {
+      return;
+    };
+    let diagnostics = self.rule_file_diagnostics(&uri, kind, &text);
+    {
+      let Some(mut doc) = self.rule_docs.get_mut(uri.as_str()) else {
+        return;
+      };
+      // a newer version will publish its own diagnostics
+      if doc.version != version || doc.diagnostics == diagnostics {
+        return;
+      }
+      doc.diagnostics = diagnostics.clone();
+    }
+    if self.client_pulls_diagnostics() {
+      return;
+    }
     self
-      .publish_diagnostics(text_doc.uri, &mut *versioned)
+      .client
+      .publish_diagnostics(uri, diagnostics, Some(version))
       .await;
-    Some(())
   }
+
   async fn on_close(&self, params: DidCloseTextDocumentParams) {
     self.map.remove(params.text_document.uri.as_str());
+    self.rule_docs.remove(params.text_document.uri.as_str());
   }
 
-  fn compute_all_fixes(
+  /// Fixes of the latest scan, the document is scanned on a worker if it is outdated.
+  async fn compute_all_fixes(
     &self,
     text_document: TextDocumentIdentifier,
   ) -> std::result::Result<HashMap<Uri, Vec<TextEdit>>, LspError>
@@ -454,43 +863,36 @@ impl<L: LSPLang> Backend<L> {
     L: ast_grep_core::Language + std::cmp::Eq,
   {
     let uri = text_document.uri;
-    let versioned = self
+    self.ensure_scanned(&uri).await;
+    let (text, fixes) = self
       .map
       .get(uri.as_str())
+      .map(|v| (v.root.get_text().to_string(), v.fixes.clone()))
       .ok_or(LspError::UnsupportedFileType)?;
-    let (_diagnostics, fixes) = self
-      .get_diagnostics(&uri, &versioned)
-      .ok_or(LspError::NoActionableFix)?;
 
+    // fixes may expand beyond the diagnostic range, so overlaps are checked on fix ranges
+    let text = text.as_str();
     let mut entries: Vec<_> = fixes.iter().collect();
-    entries.sort_by(|((range_a, _), _), ((range_b, _), _)| {
-      range_a
-        .start
-        .cmp(&range_b.start)
-        .then(range_a.end.cmp(&range_b.end))
+    entries.sort_by(|((range_a, id_a), _), ((range_b, id_b), _)| {
+      (range_a.start, range_a.end, id_a).cmp(&(range_b.start, range_b.end, id_b))
     });
-
-    let mut last = Position {
-      line: 0,
-      character: 0,
-    };
-    // TODO: the vec may not correct since sorting is based on diagnostic range
-    // instead of fix range, which can be different in the case of expanded_start/end
-    let edits: Vec<TextEdit> = entries
+    let edits: Vec<_> = entries
       .into_iter()
       .filter_map(|((range, _id), rewrite_data)| {
-        if range.start < last {
-          return None;
-        }
         let first_fix = rewrite_data.fixers.first()?;
-        let fixed = first_fix.fixed.to_string();
-        // compute the expanded fix range
         let range = first_fix.range.as_ref().unwrap_or(range);
-        let edit = TextEdit::new(*range, fixed);
-        last = range.end;
-        Some(edit)
+        Some(TextEdit::new(*range, first_fix.fixed.to_string()))
       })
       .collect();
+    let ranges: Vec<_> = edits
+      .iter()
+      .map(|e| position_to_offset(text, e.range.start)..position_to_offset(text, e.range.end))
+      .collect();
+    let kept = OverlapPolicy::FirstWins
+      .select(&ranges)
+      .expect("first wins never fails");
+    let mut edits: Vec<_> = edits.into_iter().map(Some).collect();
+    let edits: Vec<TextEdit> = kept.into_iter().filter_map(|i| edits[i].take()).collect();
     if edits.is_empty() {
       return Err(LspError::NoActionableFix);
     }
@@ -502,17 +904,17 @@ impl<L: LSPLang> Backend<L> {
   async fn on_code_action(&self, params: CodeActionParams) -> Option<CodeActionResponse> {
     if let Some(kinds) = params.context.only.as_ref() {
       if kinds.contains(&CodeActionKind::SOURCE_FIX_ALL) {
-        return self.fix_all_code_action(params.text_document);
+        return self.fix_all_code_action(params.text_document).await;
       }
     }
     self.quickfix_code_action(params)
   }
 
-  fn fix_all_code_action(
+  async fn fix_all_code_action(
     &self,
     text_document: TextDocumentIdentifier,
   ) -> Option<CodeActionResponse> {
-    let fixed = self.compute_all_fixes(text_document).ok()?;
+    let fixed = self.compute_all_fixes(text_document).await.ok()?;
     let edit = WorkspaceEdit::new(fixed);
     let code_action = CodeAction {
       title: "Fix by ast-grep".into(),
@@ -546,8 +948,11 @@ impl<L: LSPLang> Backend<L> {
           .map(|s| s.contains("ast-grep"))
           .unwrap_or(false)
       })
-      .filter_map(|d| diagnostic_to_code_action(&text_doc, d, fixes_cache))
-      .flatten()
+      .flat_map(|d| {
+        let suppress = suppress::suppression_actions(&text_doc.uri, &document.root, &d);
+        let fixes = diagnostic_to_code_action(&text_doc, d, fixes_cache).unwrap_or_default();
+        fixes.into_iter().chain(suppress)
+      })
       .map(CodeActionOrCommand::from)
       .collect();
     Some(response)
@@ -563,7 +968,7 @@ impl<L: LSPLang> Backend<L> {
     let ExecuteCommandParams {
       arguments,
       command,
-      work_done_progress_params: _,
+      work_done_progress_params,
     } = params;
 
     match command.as_ref() {
@@ -571,6 +976,11 @@ impl<L: LSPLang> Backend<L> {
         self.on_apply_all_fix(command, arguments).await?;
         None
       }
+      SEARCH => {
+        let token = work_done_progress_params.work_done_token;
+        self.on_search(arguments, token).await
+      }
+      APPLY_REWRITE => self.on_apply_rewrite(arguments).await,
       _ => {
         self
           .client
@@ -589,7 +999,9 @@ impl<L: LSPLang> Backend<L> {
       serde_json::from_value(first).map_err(LspError::JSONDecodeError)?;
     let uri = text_doc.uri;
     // let version = text_doc.version;
-    let changes = self.compute_all_fixes(TextDocumentIdentifier::new(uri))?;
+    let changes = self
+      .compute_all_fixes(TextDocumentIdentifier::new(uri))
+      .await?;
     let workspace_edit = WorkspaceEdit {
       changes: Some(changes),
       document_changes: None,
@@ -641,6 +1053,12 @@ impl<L: LSPLang> Backend<L> {
           .log_message(MessageType::LOG, "No actionable fix")
           .await;
       }
+      LspError::InvalidSearch(e) => {
+        self
+          .client
+          .log_message(MessageType::ERROR, format!("Invalid search: {e}"))
+          .await;
+      }
     }
   }
 
@@ -673,15 +1091,20 @@ impl<L: LSPLang> Backend<L> {
       .log_message(MessageType::INFO, "Starting rule reload...")
       .await;
 
-    match (self.rule_finder)() {
-      Ok(new_rules) => {
+    match self.find_default_rules().await {
+      Ok(found) => {
+        let global_utils = self.load_global_utils(&found.util_files).await;
         // Update the rules
         {
-          let mut rules = self
-            .rules
+          let mut project = self
+            .default_project
             .write()
             .map_err(|e| anyhow::anyhow!("Lock error: {e}"))?;
-          *rules = new_rules;
+          *project = Arc::new(Project {
+            base: found.base,
+            rules: found.rules,
+            global_utils,
+          });
         }
 
         self
@@ -706,34 +1129,88 @@ impl<L: LSPLang> Backend<L> {
       }
     }
 
+    self.reload_folder_projects().await;
+
     // Clear the interner since rule IDs might have changed
     self.interner.clear();
+    // Invalidate pulled diagnostics computed with the old rules
+    self.generation.fetch_add(1, Ordering::AcqRel);
+    self.report_cache.clear();
+    // watched files may have been added or removed
+    if let Ok(mut walked) = self.walked_files.write() {
+      *walked = None;
+    }
 
     // Republish diagnostics for all open files
     self.republish_all_diagnostics().await;
+    self.refresh_pulled_diagnostics().await;
 
     Ok(())
   }
 
+  /// Rules of the config path in the settings, or of the rule finder.
+  async fn find_default_rules(&self) -> anyhow::Result<ProjectRules<L>> {
+    let config_path = self.settings().config_path.clone();
+    if let (Some(config_path), Some(loader)) = (config_path, &self.config_loader) {
+      // relative to the first workspace folder
+      let root = self.workspace_roots().await.swap_remove(0);
+      return loader(&root.join(config_path));
+    }
+    let rules = (self.rule_finder)()?;
+    let util_files = self.util_finder.as_ref().map(|f| f()).unwrap_or_default();
+    Ok(ProjectRules {
+      base: self.base.clone(),
+      rules,
+      util_files,
+    })
+  }
+
+  /// Ask the client to pull diagnostics again, if it supports the refresh request
+  async fn refresh_pulled_diagnostics(&self) {
+    let support_refresh = self.capabilities.read().is_ok_and(|cap| {
+      cap
+        .workspace
+        .as_ref()
+        .and_then(|w| w.diagnostic.as_ref())
+        .and_then(|d| d.refresh_support)
+        .unwrap_or(false)
+    });
+    // waiting workspace requests answer with the new reports
+    self.changes.notify();
+    if support_refresh {
+      let _ = self.client.workspace_diagnostic_refresh().await;
+    }
+  }
+
   /// Republish diagnostics for all currently open files
   async fn republish_all_diagnostics(&self) {
-    // Get all currently open file URIs
-    for mut entry in self.map.iter_mut() {
-      let (uri_str, versioned) = entry.pair_mut();
+    // Get all currently open file URIs, without holding the map across awaits
+    let open: Vec<_> = self
+      .map
+      .iter()
+      .map(|entry| (entry.key().clone(), entry.version))
+      .collect();
+    for (uri_str, version) in open {
       let Ok(uri) = uri_str.parse::<Uri>() else {
         continue;
       };
       // Republish diagnostics for this file
-      let (diagnostics, fixes) = match self.get_diagnostics(&uri, versioned) {
-        Some((d, f)) => (d, f),
-        None => (Vec::new(), HashMap::new()),
+      self.publish_diagnostics(uri, version).await;
+    }
+    // rule files depend on global utils and test files on rule ids
+    let rule_docs: Vec<_> = self
+      .rule_docs
+      .iter_mut()
+      .map(|mut entry| {
+        entry.diagnostics.clear();
+        entry.key().clone()
+      })
+      .collect();
+    for uri_str in rule_docs {
+      let Ok(uri) = uri_str.parse::<Uri>() else {
+        continue;
       };
-      versioned.notes = self.build_notes(&diagnostics);
-      versioned.fixes = fixes;
-      self
-        .client
-        .publish_diagnostics(uri, diagnostics, Some(versioned.version))
-        .await;
+      self.publish_rule_doc_diagnostics(uri).await;
     }
   }
 }
@@ -742,4 +1219,5 @@ enum LspError {
   JSONDecodeError(serde_json::Error),
   UnsupportedFileType,
   NoActionableFix,
+  InvalidSearch(String),
 }
@@ -0,0 +1,203 @@
+//! Custom requests to try patterns against an open document.
+//!
+//! `ast-grep/dumpTree` prints the pattern or syntax tree of a document range
+//! in the formats of `--debug-query`, and `ast-grep/previewMatches` returns the
+//! matches of a pattern or inline rule so editors can highlight them while typing.
+use crate::utils::{end_position, position_to_offset, start_position};
+use crate::{Backend, LSPLang};
+
+use ast_grep_config::{from_str, DeserializeEnv, SerializableRuleCore};
+use ast_grep_core::meta_var::MetaVariable;
+use ast_grep_core::tree_sitter::StrDoc;
+use ast_grep_core::{Matcher, Node, Pattern};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tower_lsp_server::jsonrpc::{Error, Result};
+use tower_lsp_server::lsp_types::*;
+use tower_lsp_server::LspServiceBuilder;
+
+use std::collections::BTreeMap;
+
+pub const DUMP_TREE: &str = "ast-grep/dumpTree";
+pub const PREVIEW_MATCHES: &str = "ast-grep/previewMatches";
+
+/// What to dump, in the formats of `--debug-query`.
+pub enum Dump<'a, L: LSPLang> {
+  /// the pattern parsed from the text
+  Pattern(&'a Pattern, &'a L),
+  /// named nodes only
+  Ast(Node<'a, StrDoc<L>>),
+  /// both named and unnamed nodes
+  Cst(Node<'a, StrDoc<L>>),
+  Sexp(Node<'a, StrDoc<L>>),
+}
+
+/// Prints a dump, None if it cannot be printed.
+pub type TreeDumper<L> = Box<dyn Fn(Dump<L>) -> Option<String> + Send + Sync>;
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DumpFormat {
+  Pattern,
+  Ast,
+  Cst,
+  Sexp,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DumpTreeParams {
+  pub text_document: TextDocumentIdentifier,
+  /// the whole document if absent
+  pub range: Option<Range>,
+  pub format: DumpFormat,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DumpTreeResult {
+  pub dump: String,
+  /// range of the dumped node, absent for patterns
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub range: Option<Range>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewMatchesParams {
+  pub text_document: TextDocumentIdentifier,
+  pub pattern: Option<String>,
+  /// rule core as an object or YAML text, e.g. `{ rule: { kind: identifier } }`
+  pub rule: Option<Value>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewMatch {
+  pub range: Range,
+  pub text: String,
+  /// ranges of single meta variables
+  pub meta_variables: BTreeMap<String, Range>,
+}
+
+fn node_range<L: LSPLang>(node: &Node<StrDoc<L>>) -> Range {
+  Range::new(start_position(node), end_position(node))
+}
+
+/// The smallest node containing the byte range, an inverted range is treated as empty.
+fn covering_node<'r, L: LSPLang>(
+  root: Node<'r, StrDoc<L>>,
+  range: std::ops::Range<usize>,
+) -> Node<'r, StrDoc<L>> {
+  let range = range.start..range.end.max(range.start);
+  let mut node = root;
+  loop {
+    let inner = node.children().find(|c| {
+      let r = c.range();
+      r.start <= range.start && range.end <= r.end
+    });
+    match inner {
+      Some(child) => node = child,
+      None => return node,
+    }
+  }
+}
+
+fn invalid<E: std::fmt::Display>(e: E) -> Error {
+  Error::invalid_params(e.to_string())
+}
+
+fn collect_matches<L: LSPLang, M: Matcher>(root: Node<StrDoc<L>>, matcher: M) -> Vec<PreviewMatch> {
+  root
+    .find_all(matcher)
+    .map(|m| {
+      let env = m.get_env();
+      let meta_variables = env
+        .get_matched_variables()
+        .filter_map(|var| {
+          let MetaVariable::Capture(name, _) = var else {
+            return None;
+          };
+          let node = env.get_match(&name)?;
+          Some((name, node_range(node)))
+        })
+        .collect();
+      PreviewMatch {
+        range: Range::new(start_position(&m), end_position(m.get_end_node())),
+        text: m.text().to_string(),
+        meta_variables,
+      }
+    })
+    .collect()
+}
+
+impl<L: LSPLang> Backend<L> {
+  /// Add ast-grep's custom requests to the service.
+  pub fn custom_methods(builder: LspServiceBuilder<Self>) -> LspServiceBuilder<Self> {
+    builder
+      .custom_method(DUMP_TREE, Self::on_dump_tree)
+      .custom_method(PREVIEW_MATCHES, Self::on_preview_matches)
+  }
+
+  async fn on_dump_tree(&self, params: DumpTreeParams) -> Result<DumpTreeResult> {
+    let Some(dumper) = &self.tree_dumper else {
+      return Err(Error::method_not_found());
+    };
+    let uri = params.text_document.uri;
+    let versioned = self
+      .map
+      .get(uri.as_str())
+      .ok_or_else(|| invalid("document is not open"))?;
+    let root = &versioned.root;
+    let source = root.source();
+    let offsets = match params.range {
+      Some(range) => position_to_offset(source, range.start)..position_to_offset(source, range.end),
+      None => 0..source.len(),
+    };
+    if offsets.start > offsets.end {
+      return Err(invalid("range start is after its end"));
+    }
+    if let DumpFormat::Pattern = params.format {
+      let lang = root.lang();
+      let pattern = Pattern::try_new(&source[offsets], lang.clone()).map_err(invalid)?;
+      let dump = dumper(Dump::Pattern(&pattern, lang)).ok_or_else(|| invalid("cannot dump"))?;
+      return Ok(DumpTreeResult { dump, range: None });
+    }
+    let node = covering_node(root.root(), offsets);
+    let range = Some(node_range(&node));
+    let dump = match params.format {
+      DumpFormat::Ast => Dump::Ast(node),
+      DumpFormat::Cst => Dump::Cst(node),
+      DumpFormat::Sexp | DumpFormat::Pattern => Dump::Sexp(node),
+    };
+    let dump = dumper(dump).ok_or_else(|| invalid("cannot dump"))?;
+    Ok(DumpTreeResult { dump, range })
+  }
+
+  async fn on_preview_matches(&self, params: PreviewMatchesParams) -> Result<Vec<PreviewMatch>> {
+    let uri = params.text_document.uri;
+    let versioned = self
+      .map
+      .get(uri.as_str())
+      .ok_or_else(|| invalid("document is not open"))?;
+    let root = &versioned.root;
+    let lang = root.lang().clone();
+    match (params.pattern, params.rule) {
+      (Some(pattern), None) => {
+        let pattern = Pattern::try_new(&pattern, lang).map_err(invalid)?;
+        Ok(collect_matches(root.root(), pattern))
+      }
+      (None, Some(rule)) => {
+        let core: SerializableRuleCore = match rule {
+          Value::String(yaml) => from_str(&yaml).map_err(invalid)?,
+          value => serde_json::from_value(value).map_err(invalid)?,
+        };
+        let project = self.project_for(&uri).unwrap_or_default();
+        let env = DeserializeEnv::new(lang).with_globals(&project.global_utils.rules);
+        let matcher = core.get_matcher(env).map_err(invalid)?;
+        Ok(collect_matches(root.root(), matcher))
+      }
+      _ => Err(invalid("either `pattern` or `rule` is required")),
+    }
+  }
+}
@@ -0,0 +1,376 @@
+//! Pull diagnostics: `textDocument/diagnostic` and `workspace/diagnostic`.
+//!
+//! Open documents are rescanned on request if they changed since their last scan.
+//! Other files are read from disk and their reports are cached by modification time.
+//! Result ids encode the rule generation and the file version, so an unchanged file
+//! is answered with an unchanged report.
+//!
+//! A workspace request that would only report unchanged files is held open
+//! until a document, the rules or the settings change.
+use crate::{scan_document, Backend, LSPLang};
+
+use ast_grep_core::AstGrep;
+use tower_lsp_server::lsp_types::request::WorkDoneProgressCreate;
+use tower_lsp_server::lsp_types::*;
+use tower_lsp_server::UriExt;
+
+use std::collections::HashMap;
+use std::future::poll_fn;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::task::{Poll, Waker};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Lists the files of a workspace folder that should be scanned.
+pub type FileWalker = Box<dyn Fn(&Path) -> Vec<PathBuf> + Send + Sync>;
+
+/// Distinguishes the progress tokens created by the server.
+static PROGRESS_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Wakes workspace diagnostic requests waiting for something to change.
+#[derive(Default)]
+pub(crate) struct ChangeSignal {
+  state: Mutex<ChangeState>,
+}
+
+#[derive(Default)]
+struct ChangeState {
+  count: usize,
+  wakers: Vec<Waker>,
+}
+
+impl ChangeSignal {
+  pub fn notify(&self) {
+    let Ok(mut state) = self.state.lock() else {
+      return;
+    };
+    state.count += 1;
+    for waker in state.wakers.drain(..) {
+      waker.wake();
+    }
+  }
+
+  pub fn count(&self) -> usize {
+    self.state.lock().map(|s| s.count).unwrap_or_default()
+  }
+
+  /// Resolves once `notify` is called after `count` returned `since`.
+  pub async fn changed(&self, since: usize) {
+    poll_fn(|cx| {
+      let Ok(mut state) = self.state.lock() else {
+        return Poll::Ready(());
+      };
+      if state.count != since {
+        return Poll::Ready(());
+      }
+      state.wakers.push(cx.waker().clone());
+      Poll::Pending
+    })
+    .await
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FileVersion {
+  /// version of a document opened in the editor
+  Open(i32),
+  /// modification time of a file on disk
+  Disk(SystemTime),
+}
+
+impl FileVersion {
+  fn lsp_version(&self) -> Option<i64> {
+    match self {
+      FileVersion::Open(v) => Some(*v as i64),
+      FileVersion::Disk(_) => None,
+    }
+  }
+}
+
+pub(crate) struct CachedReport {
+  version: FileVersion,
+  result_id: String,
+  items: Vec<Diagnostic>,
+}
+
+struct FileReport {
+  version: FileVersion,
+  result_id: String,
+  /// None if the client already has the report of `result_id`
+  items: Option<Vec<Diagnostic>>,
+}
+
+impl FileReport {
+  fn into_document_report(self) -> DocumentDiagnosticReport {
+    let Some(items) = self.items else {
+      return DocumentDiagnosticReport::Unchanged(RelatedUnchangedDocumentDiagnosticReport {
+        related_documents: None,
+        unchanged_document_diagnostic_report: UnchangedDocumentDiagnosticReport {
+          result_id: self.result_id,
+        },
+      });
+    };
+    DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport {
+      related_documents: None,
+      full_document_diagnostic_report: FullDocumentDiagnosticReport {
+        result_id: Some(self.result_id),
+        items,
+      },
+    })
+  }
+
+  fn into_workspace_report(self, uri: Uri) -> WorkspaceDocumentDiagnosticReport {
+    let version = self.version.lsp_version();
+    let Some(items) = self.items else {
+      return WorkspaceDocumentDiagnosticReport::Unchanged(
+        WorkspaceUnchangedDocumentDiagnosticReport {
+          uri,
+          version,
+          unchanged_document_diagnostic_report: UnchangedDocumentDiagnosticReport {
+            result_id: self.result_id,
+          },
+        },
+      );
+    };
+    WorkspaceDocumentDiagnosticReport::Full(WorkspaceFullDocumentDiagnosticReport {
+      uri,
+      version,
+      full_document_diagnostic_report: FullDocumentDiagnosticReport {
+        result_id: Some(self.result_id),
+        items,
+      },
+    })
+  }
+}
+
+/// Yield to the executor once, so a cancelled request is dropped between files.
+pub(crate) async fn yield_now() {
+  let mut yielded = false;
+  poll_fn(|cx| {
+    if yielded {
+      Poll::Ready(())
+    } else {
+      yielded = true;
+      cx.waker().wake_by_ref();
+      Poll::Pending
+    }
+  })
+  .await
+}
+
+pub(crate) fn diagnostic_options() -> DiagnosticServerCapabilities {
+  DiagnosticServerCapabilities::Options(DiagnosticOptions {
+    identifier: Some("ast-grep".into()),
+    inter_file_dependencies: false,
+    workspace_diagnostics: true,
+    work_done_progress_options: WorkDoneProgressOptions {
+      work_done_progress: Some(true),
+    },
+  })
+}
+
+impl<L: LSPLang> Backend<L> {
+  /// Whether the client pulls diagnostics instead of receiving published ones.
+  pub(crate) fn client_pulls_diagnostics(&self) -> bool {
+    let Ok(cap) = self.capabilities.read() else {
+      return false;
+    };
+    cap
+      .text_document
+      .as_ref()
+      .is_some_and(|t| t.diagnostic.is_some())
+  }
+
+  fn result_id(&self, version: FileVersion) -> String {
+    let generation = self.generation.load(Ordering::Acquire);
+    match version {
+      FileVersion::Open(v) => format!("{generation}:open:{v}"),
+      FileVersion::Disk(time) => {
+        let nanos = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        format!("{generation}:disk:{}", nanos.as_nanos())
+      }
+    }
+  }
+
+  async fn file_report(&self, uri: &Uri, previous_result_id: Option<&str>) -> Option<FileReport> {
+    let (version, items) = if let Some(versioned) = self.map.get(uri.as_str()) {
+      // the version the diagnostics are computed from, not the latest text
+      let version = FileVersion::Open(versioned.scanned.unwrap_or(versioned.version));
+      (version, versioned.diagnostics.clone())
+    } else if let Some(doc) = self.rule_docs.get(uri.as_str()) {
+      (FileVersion::Open(doc.version), doc.diagnostics.clone())
+    } else {
+      self.disk_report(uri).await?
+    };
+    let result_id = self.result_id(version);
+    let unchanged = previous_result_id == Some(result_id.as_str());
+    Some(FileReport {
+      version,
+      result_id,
+      items: (!unchanged).then_some(items),
+    })
+  }
+
+  /// Diagnostics of a file not opened in the editor, read and scanned on a worker.
+  async fn disk_report(&self, uri: &Uri) -> Option<(FileVersion, Vec<Diagnostic>)> {
+    let path = uri.to_file_path()?.into_owned();
+    let modified = std::fs::metadata(&path).ok()?.modified().ok()?;
+    let version = FileVersion::Disk(modified);
+    if let Some(cached) = self.report_cache.get(uri.as_str()) {
+      if cached.version == version && cached.result_id == self.result_id(version) {
+        return Some((version, cached.items.clone()));
+      }
+    }
+    let lang = L::from_path(&path)?;
+    let project = self.project_for(uri)?;
+    let settings = self.settings();
+    let job_uri = uri.clone();
+    let job = move || {
+      let text = std::fs::read_to_string(&path).ok()?;
+      let root = AstGrep::new(text, lang);
+      let scanned = scan_document(&project, &settings, &job_uri, &root, None);
+      let (items, _fixes, _bindings) = scanned.unwrap_or_default();
+      Some(items)
+    };
+    let items = self.workers.run(job).await??;
+    let cached = CachedReport {
+      version,
+      result_id: self.result_id(version),
+      items: items.clone(),
+    };
+    self.report_cache.insert(uri.as_str().to_owned(), cached);
+    Some((version, items))
+  }
+
+  pub(crate) async fn on_document_diagnostic(
+    &self,
+    params: DocumentDiagnosticParams,
+  ) -> DocumentDiagnosticReportResult {
+    let uri = params.text_document.uri;
+    self.ensure_scanned(&uri).await;
+    let previous = params.previous_result_id.as_deref();
+    let report = self
+      .file_report(&uri, previous)
+      .await
+      .map(FileReport::into_document_report)
+      .unwrap_or_else(|| DocumentDiagnosticReport::Full(Default::default()));
+    DocumentDiagnosticReportResult::Report(report)
+  }
+
+  async fn progress_token(&self, token: Option<ProgressToken>) -> Option<ProgressToken> {
+    if token.is_some() {
+      return token;
+    }
+    let supported = self.capabilities.read().is_ok_and(|cap| {
+      cap
+        .window
+        .as_ref()
+        .and_then(|w| w.work_done_progress)
+        .unwrap_or(false)
+    });
+    if !supported {
+      return None;
+    }
+    let id = PROGRESS_ID.fetch_add(1, Ordering::Relaxed);
+    let token = ProgressToken::String(format!("ast-grep/workspaceDiagnostic/{id}"));
+    let params = WorkDoneProgressCreateParams {
+      token: token.clone(),
+    };
+    let created = self.client.send_request::<WorkDoneProgressCreate>(params);
+    created.await.ok().map(|_| token)
+  }
+
+  pub(crate) async fn on_workspace_diagnostic(
+    &self,
+    params: WorkspaceDiagnosticParams,
+  ) -> WorkspaceDiagnosticReportResult {
+    let previous: HashMap<_, _> = params
+      .previous_result_ids
+      .into_iter()
+      .map(|p| (p.uri.as_str().to_owned(), p.value))
+      .collect();
+    // the client token can only report the first round
+    let mut token = params.work_done_progress_params.work_done_token;
+    loop {
+      let since = self.changes.count();
+      let items = self.workspace_reports(&previous, token.take()).await;
+      let unchanged = items
+        .iter()
+        .all(|i| matches!(i, WorkspaceDocumentDiagnosticReport::Unchanged(_)));
+      if previous.is_empty() || !unchanged {
+        return WorkspaceDiagnosticReportResult::Report(WorkspaceDiagnosticReport { items });
+      }
+      // a cancelled request is dropped while waiting
+      self.changes.changed(since).await;
+    }
+  }
+
+  /// Files listed by the walker, reused until the folders or the rules change.
+  async fn walked_files(&self) -> Vec<PathBuf> {
+    if let Some(files) = self.walked_files.read().ok().and_then(|f| f.clone()) {
+      return files;
+    }
+    let mut files = vec![];
+    if let Some(walker) = &self.file_walker {
+      for root in self.workspace_roots().await {
+        files.extend(walker(&root));
+      }
+    }
+    if let Ok(mut walked) = self.walked_files.write() {
+      *walked = Some(files.clone());
+    }
+    files
+  }
+
+  async fn workspace_reports(
+    &self,
+    previous: &HashMap<String, String>,
+    token: Option<ProgressToken>,
+  ) -> Vec<WorkspaceDocumentDiagnosticReport> {
+    let files = self.walked_files().await;
+    // open documents are reported even if the walker skips them
+    let open_docs: Vec<_> = self
+      .map
+      .iter()
+      .map(|e| e.key().clone())
+      .chain(self.rule_docs.iter().map(|e| e.key().clone()))
+      .collect();
+    let mut uris: Vec<_> = files.iter().filter_map(Uri::from_file_path).collect();
+    uris.extend(open_docs.iter().filter_map(|u| u.parse::<Uri>().ok()));
+    uris.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+    uris.dedup();
+
+    let progress = match self.progress_token(token).await {
+      Some(token) => {
+        let progress = self.client.progress(token, "ast-grep: scanning workspace");
+        Some(progress.with_percentage(0).begin().await)
+      }
+      None => None,
+    };
+    let total = uris.len().max(1);
+    let mut last_percentage = 0;
+    let mut items = vec![];
+    for (i, uri) in uris.into_iter().enumerate() {
+      // a cancelled request is dropped at this await point
+      yield_now().await;
+      let previous_id = previous.get(uri.as_str()).map(String::as_str);
+      self.ensure_scanned(&uri).await;
+      if let Some(report) = self.file_report(&uri, previous_id).await {
+        items.push(report.into_workspace_report(uri));
+      }
+      let percentage = ((i + 1) * 100 / total) as u32;
+      if let Some(progress) = &progress {
+        if percentage > last_percentage {
+          last_percentage = percentage;
+          let message = format!("{}/{total} files", i + 1);
+          progress.report_with_message(message, percentage).await;
+        }
+      }
+    }
+    if let Some(progress) = progress {
+      progress.finish().await;
+    }
+    items
+  }
+}
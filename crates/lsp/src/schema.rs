@@ -0,0 +1,193 @@
+//! Documentation of YAML keys for rule authoring.
+//!
+//! Rule keys are described by the JSON schema that schemars derives for `SerializableRuleConfig`,
+//! the same source as `schemas/rule.json`. Keys of `sgconfig.yml` and rule test files
+//! are not part of that schema and are listed here.
+use ast_grep_config::SerializableRuleConfig;
+use ast_grep_core::matcher::{Pattern, PatternBuilder, PatternError};
+use ast_grep_core::Language;
+
+use schemars::{json_schema, schema_for, JsonSchema, Schema, SchemaGenerator};
+use serde_json::Value;
+
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+/// `$ref` and `anyOf` nesting is shallow in the rule schema, this only guards against cycles.
+const MAX_DEPTH: usize = 8;
+
+/// A documented key that can appear at some position of a YAML file.
+pub struct KeyDoc {
+  pub name: String,
+  pub description: Option<String>,
+}
+
+pub const CONFIG_KEYS: &[(&str, &str)] = &[
+  ("ruleDirs", "YAML rule directories"),
+  ("testConfigs", "test configurations"),
+  ("utilDirs", "util rules directories"),
+  ("customLanguages", "configuration for custom languages"),
+  ("languageGlobs", "additional file globs for languages"),
+  (
+    "languageInjections",
+    "injection config for embedded languages",
+  ),
+];
+
+pub const TEST_KEYS: &[(&str, &str)] = &[
+  ("id", "Id of the rule to test"),
+  ("valid", "Code snippets that the rule should not report"),
+  ("invalid", "Code snippets that the rule should report"),
+];
+
+pub fn static_keys(keys: &[(&str, &str)]) -> Vec<KeyDoc> {
+  keys
+    .iter()
+    .map(|(name, description)| KeyDoc {
+      name: name.to_string(),
+      description: Some(description.to_string()),
+    })
+    .collect()
+}
+
+/// Stands in for the language of a rule, so the schema can be derived without a grammar.
+#[derive(Clone)]
+struct SchemaLang;
+
+impl JsonSchema for SchemaLang {
+  fn schema_id() -> Cow<'static, str> {
+    Cow::Borrowed("Language")
+  }
+  fn schema_name() -> Cow<'static, str> {
+    Cow::Borrowed("Language")
+  }
+  fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+    json_schema!({ "type": "string" })
+  }
+}
+
+impl Language for SchemaLang {
+  fn kind_to_id(&self, _kind: &str) -> u16 {
+    unreachable!("SchemaLang is only for json schema")
+  }
+  fn field_to_id(&self, _field: &str) -> Option<u16> {
+    unreachable!("SchemaLang is only for json schema")
+  }
+  fn build_pattern(&self, _b: &PatternBuilder) -> Result<Pattern, PatternError> {
+    unreachable!("SchemaLang is only for json schema")
+  }
+}
+
+fn rule_schema() -> &'static Value {
+  static SCHEMA: OnceLock<Value> = OnceLock::new();
+  SCHEMA.get_or_init(|| schema_for!(SerializableRuleConfig<SchemaLang>).to_value())
+}
+
+/// Expand `$ref`, unions and arrays into the schemas a value can be described by.
+fn expand<'a>(node: &'a Value, out: &mut Vec<&'a Value>, depth: usize) {
+  if depth > MAX_DEPTH {
+    return;
+  }
+  if let Some(name) = node["$ref"].as_str() {
+    let name = name.trim_start_matches("#/$defs/");
+    expand(&rule_schema()["$defs"][name], out, depth + 1);
+  }
+  for union in ["anyOf", "oneOf", "allOf"] {
+    for variant in node[union].as_array().into_iter().flatten() {
+      expand(variant, out, depth + 1);
+    }
+  }
+  if node["items"].is_object() {
+    expand(&node["items"], out, depth + 1);
+  }
+  out.push(node);
+}
+
+/// Schemas of the value at `path`, e.g. `["rule", "has"]`.
+/// Map keys that are not schema properties, like util ids in `utils`, follow `additionalProperties`.
+fn nodes_at(path: &[&str]) -> Vec<&'static Value> {
+  let mut nodes = vec![rule_schema()];
+  for key in path {
+    let mut expanded = vec![];
+    for node in nodes {
+      expand(node, &mut expanded, 0);
+    }
+    nodes = expanded
+      .into_iter()
+      .filter_map(|node| {
+        let prop = &node["properties"][*key];
+        if !prop.is_null() {
+          Some(prop)
+        } else if node["additionalProperties"].is_object() {
+          Some(&node["additionalProperties"])
+        } else {
+          None
+        }
+      })
+      .collect();
+  }
+  nodes
+}
+
+fn description(node: &Value) -> Option<String> {
+  let mut expanded = vec![];
+  expand(node, &mut expanded, 0);
+  // the property's own description is pushed last, prefer it over the referenced type's
+  expanded
+    .into_iter()
+    .rev()
+    .find_map(|n| n["description"].as_str())
+    .map(ToString::to_string)
+}
+
+/// Rule keys that can be written in the mapping at `path`.
+pub fn rule_keys(path: &[&str]) -> Vec<KeyDoc> {
+  let mut expanded = vec![];
+  for node in nodes_at(path) {
+    expand(node, &mut expanded, 0);
+  }
+  let mut keys: Vec<KeyDoc> = vec![];
+  for node in expanded {
+    let Some(props) = node["properties"].as_object() else {
+      continue;
+    };
+    for (name, prop) in props {
+      if keys.iter().any(|k| &k.name == name) {
+        continue;
+      }
+      keys.push(KeyDoc {
+        name: name.clone(),
+        description: description(prop),
+      });
+    }
+  }
+  keys
+}
+
+/// Description of the rule key at the end of `path`.
+pub fn rule_key_doc(path: &[&str]) -> Option<String> {
+  nodes_at(path).into_iter().find_map(description)
+}
+
+/// Fixed values of the rule key at the end of `path`, e.g. severity levels.
+pub fn rule_key_values(path: &[&str]) -> Vec<KeyDoc> {
+  let mut expanded = vec![];
+  for node in nodes_at(path) {
+    expand(node, &mut expanded, 0);
+  }
+  let mut values = vec![];
+  for node in expanded {
+    let enums = node["enum"].as_array().into_iter().flatten();
+    let consts = node.get("const").into_iter();
+    for value in enums.chain(consts) {
+      let Some(name) = value.as_str() else {
+        continue;
+      };
+      values.push(KeyDoc {
+        name: name.to_string(),
+        description: node["description"].as_str().map(ToString::to_string),
+      });
+    }
+  }
+  values
+}
@@ -0,0 +1,312 @@
+//! Structural search and rewrite over the workspace, driven by `workspace/executeCommand`.
+//!
+//! `ast-grep.search` streams the matches of each file as `$/progress` partial results
+//! when the client passes a `partialResultToken` in the command argument.
+//! `ast-grep.applyRewrite` collects the rewrites of all files into one `WorkspaceEdit`.
+use crate::pull::yield_now;
+use crate::utils::offset_to_position;
+use crate::{Backend, LSPLang, LspError};
+
+use ast_grep_config::Fixer;
+use ast_grep_core::source::OverlapPolicy;
+use ast_grep_core::tree_sitter::StrDoc;
+use ast_grep_core::{AstGrep, Pattern};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tower_lsp_server::lsp_types::notification::Notification;
+use tower_lsp_server::lsp_types::*;
+use tower_lsp_server::UriExt;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// The argument of the search and rewrite commands.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SearchArgs<L> {
+  pub pattern: String,
+  pub lang: L,
+  pub rewrite: Option<String>,
+  /// file or directory paths or uris, defaults to the workspace folders
+  #[serde(default)]
+  pub paths: Vec<String>,
+  /// token to report matches of each file as partial results
+  pub partial_result_token: Option<ProgressToken>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SearchMatch {
+  pub uri: Uri,
+  pub range: Range,
+  pub text: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub replacement: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct PartialResultParams {
+  pub token: ProgressToken,
+  pub value: Vec<SearchMatch>,
+}
+
+/// `$/progress` carrying partial results instead of work done progress.
+pub(crate) enum PartialResult {}
+
+impl Notification for PartialResult {
+  type Params = PartialResultParams;
+  const METHOD: &'static str = "$/progress";
+}
+
+fn uri_to_path(uri: &Uri) -> Option<PathBuf> {
+  uri.to_file_path().map(PathBuf::from)
+}
+
+fn to_lsp_range(text: &str, range: std::ops::Range<usize>) -> Range {
+  Range::new(
+    offset_to_position(text, range.start),
+    offset_to_position(text, range.end),
+  )
+}
+
+struct Query<L> {
+  lang: L,
+  pattern: Pattern,
+  fixer: Option<Fixer>,
+}
+
+impl<L: LSPLang> Query<L> {
+  fn try_new(args: &SearchArgs<L>) -> Result<Self, LspError> {
+    let lang = args.lang.clone();
+    let pattern = Pattern::try_new(&args.pattern, lang.clone())
+      .map_err(|e| LspError::InvalidSearch(e.to_string()))?;
+    let fixer = match &args.rewrite {
+      Some(rewrite) => {
+        Some(Fixer::from_str(rewrite, &lang).map_err(|e| LspError::InvalidSearch(e.to_string()))?)
+      }
+      None => None,
+    };
+    Ok(Self {
+      lang,
+      pattern,
+      fixer,
+    })
+  }
+
+  fn search(&self, uri: &Uri, text: String) -> Vec<SearchMatch> {
+    let root = AstGrep::<StrDoc<L>>::new(text, self.lang.clone());
+    let node = root.root();
+    node
+      .find_all(&self.pattern)
+      .map(|m| {
+        let replacement = self.fixer.as_ref().and_then(|fixer| {
+          let edit = m.make_edit(&self.pattern, fixer);
+          String::from_utf8(edit.inserted_text).ok()
+        });
+        SearchMatch {
+          uri: uri.clone(),
+          range: to_lsp_range(root.source(), m.range()),
+          text: m.text().to_string(),
+          replacement,
+        }
+      })
+      .collect()
+  }
+
+  /// Edits rewriting the matches, overlapping edits keep the outer one.
+  fn rewrite(&self, text: String) -> Vec<TextEdit> {
+    let Some(fixer) = &self.fixer else {
+      return vec![];
+    };
+    let root = AstGrep::<StrDoc<L>>::new(text, self.lang.clone());
+    let node = root.root();
+    let edits: Vec<_> = node
+      .find_all(&self.pattern)
+      .map(|m| m.make_edit(&self.pattern, fixer))
+      .collect();
+    // the edit may expand beyond the match
+    let ranges: Vec<_> = edits
+      .iter()
+      .map(|e| e.position..e.position + e.deleted_length)
+      .collect();
+    let kept = OverlapPolicy::OuterWins
+      .select(&ranges)
+      .expect("outer wins never fails");
+    let mut edits: Vec<_> = edits.into_iter().map(Some).collect();
+    kept
+      .into_iter()
+      .filter_map(|i| {
+        let edit = edits[i].take()?;
+        let replacement = String::from_utf8(edit.inserted_text).ok()?;
+        let range = to_lsp_range(root.source(), ranges[i].clone());
+        Some(TextEdit::new(range, replacement))
+      })
+      .collect()
+  }
+}
+
+impl<L: LSPLang> Backend<L> {
+  /// Files of the language under the paths, open documents use their unsaved text.
+  async fn search_files(&self, paths: &[String], lang: &L) -> Vec<(Uri, PathBuf)> {
+    let roots = if paths.is_empty() {
+      self.workspace_roots().await
+    } else {
+      paths
+        .iter()
+        .map(
+          |p| match p.parse::<Uri>().ok().and_then(|u| uri_to_path(&u)) {
+            Some(path) => path,
+            None => self.base.join(p),
+          },
+        )
+        .collect()
+    };
+    let mut files = vec![];
+    for root in &roots {
+      if root.is_file() {
+        files.push(root.clone());
+      } else if let Some(walker) = &self.file_walker {
+        files.extend(walker(root));
+      }
+    }
+    for entry in self.map.iter() {
+      let path = entry
+        .key()
+        .parse::<Uri>()
+        .ok()
+        .and_then(|u| uri_to_path(&u));
+      if let Some(path) = path.filter(|p| roots.iter().any(|r| p.starts_with(r))) {
+        files.push(path);
+      }
+    }
+    files.sort();
+    files.dedup();
+    files
+      .into_iter()
+      .filter(|path| L::from_path(path).as_ref() == Some(lang))
+      .filter_map(|path| Some((Uri::from_file_path(&path)?, path)))
+      .collect()
+  }
+
+  fn read_text(&self, uri: &Uri, path: &PathBuf) -> Option<String> {
+    if let Some(versioned) = self.map.get(uri.as_str()) {
+      return Some(versioned.root.source().to_string());
+    }
+    std::fs::read_to_string(path).ok()
+  }
+
+  async fn search_impl(
+    &self,
+    args: SearchArgs<L>,
+    work_done_token: Option<ProgressToken>,
+  ) -> Result<Vec<SearchMatch>, LspError> {
+    let query = Query::try_new(&args)?;
+    let files = self.search_files(&args.paths, &query.lang).await;
+    let progress = match work_done_token {
+      Some(token) => {
+        let progress = self.client.progress(token, "ast-grep: searching");
+        Some(progress.with_percentage(0).begin().await)
+      }
+      None => None,
+    };
+    let total = files.len().max(1);
+    let mut ret = vec![];
+    for (i, (uri, path)) in files.iter().enumerate() {
+      // a cancelled request is dropped at this await point
+      yield_now().await;
+      let Some(text) = self.read_text(uri, path) else {
+        continue;
+      };
+      let matches = query.search(uri, text);
+      if let Some(progress) = &progress {
+        let percentage = ((i + 1) * 100 / total) as u32;
+        let message = format!("{}/{total} files", i + 1);
+        progress.report_with_message(message, percentage).await;
+      }
+      if matches.is_empty() {
+        continue;
+      }
+      match &args.partial_result_token {
+        Some(token) => {
+          let params = PartialResultParams {
+            token: token.clone(),
+            value: matches,
+          };
+          self.client.send_notification::<PartialResult>(params).await;
+        }
+        None => ret.extend(matches),
+      }
+    }
+    if let Some(progress) = progress {
+      progress.finish().await;
+    }
+    Ok(ret)
+  }
+
+  /// Rewrite all matches and return the edit, overlapping matches keep the outer one.
+  async fn compute_rewrite(&self, args: SearchArgs<L>) -> Result<WorkspaceEdit, LspError> {
+    if args.rewrite.is_none() {
+      let message = "`rewrite` is required to apply a rewrite".to_string();
+      return Err(LspError::InvalidSearch(message));
+    }
+    let query = Query::try_new(&args)?;
+    let mut changes: HashMap<Uri, Vec<TextEdit>> = HashMap::new();
+    for (uri, path) in self.search_files(&args.paths, &query.lang).await {
+      yield_now().await;
+      let Some(text) = self.read_text(&uri, &path) else {
+        continue;
+      };
+      let edits = query.rewrite(text);
+      if !edits.is_empty() {
+        changes.insert(uri, edits);
+      }
+    }
+    Ok(WorkspaceEdit::new(changes))
+  }
+
+  fn parse_args(arguments: Vec<Value>) -> Result<SearchArgs<L>, LspError> {
+    let first = arguments.into_iter().next().unwrap_or_default();
+    serde_json::from_value(first).map_err(LspError::JSONDecodeError)
+  }
+
+  /// Matches are returned in the response, or `[]` if they are streamed as partial results.
+  pub(crate) async fn on_search(
+    &self,
+    arguments: Vec<Value>,
+    work_done_token: Option<ProgressToken>,
+  ) -> Option<Value> {
+    let matches = match Self::parse_args(arguments) {
+      Ok(args) => self.search_impl(args, work_done_token).await,
+      Err(e) => Err(e),
+    };
+    match matches {
+      Ok(matches) => serde_json::to_value(matches).ok(),
+      Err(error) => {
+        self.report_error(error).await;
+        None
+      }
+    }
+  }
+
+  pub(crate) async fn on_apply_rewrite(&self, arguments: Vec<Value>) -> Option<Value> {
+    let edit = match Self::parse_args(arguments) {
+      Ok(args) => self.compute_rewrite(args).await,
+      Err(e) => Err(e),
+    };
+    let edit = match edit {
+      Ok(edit) => edit,
+      Err(error) => {
+        self.report_error(error).await;
+        return None;
+      }
+    };
+    let changes = edit.changes.as_ref();
+    let files = changes.map_or(0, |c| c.len());
+    let edits = changes.map_or(0, |c| c.values().map(Vec::len).sum());
+    let applied = match self.client.apply_edit(edit).await {
+      Ok(response) => response.applied,
+      Err(_) => false,
+    };
+    Some(json!({ "applied": applied, "files": files, "edits": edits }))
+  }
+}
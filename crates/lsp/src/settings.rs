@@ -0,0 +1,180 @@
+//! Client settings, from `initializationOptions` or `workspace/didChangeConfiguration`.
+//!
+//! Changed settings apply to open documents right away. Filters and severity overrides
+//! apply when a document is scanned, so rules are only reloaded for a new config path.
+use crate::{Backend, LSPLang};
+
+use ast_grep_config::{RuleConfig, Severity};
+use ast_grep_core::Language;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use tower_lsp_server::lsp_types::*;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+/// Settings may be sent as is or under the extension's section.
+const SECTION: &str = "astGrep";
+/// Keys of settings sent as is, to tell them from the settings of other extensions.
+const KEYS: &[&str] = &[
+  "inlayHints",
+  "codeLens",
+  "debounceMs",
+  "configPath",
+  "ruleFilter",
+  "severityOverrides",
+  "scanOutsideWorkspace",
+  "maxFileSize",
+];
+
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct Settings {
+  /// show bound meta variables and fix previews as inlay hints
+  pub inlay_hints: bool,
+  /// show bound meta variables and fix previews as code lenses
+  pub code_lens: bool,
+  /// milliseconds without edits before a changed document is scanned
+  pub debounce_ms: u64,
+  /// config file to load rules from, instead of the one the server was started with
+  pub config_path: Option<PathBuf>,
+  /// only run rules with ids matching the regex, like `--filter`
+  pub rule_filter: Option<String>,
+  #[serde(skip)]
+  filter: Option<Regex>,
+  /// severity by rule id, `off` turns the rule off
+  pub severity_overrides: HashMap<String, Severity>,
+  /// diagnose documents opened outside of the workspace folders
+  pub scan_outside_workspace: bool,
+  /// documents larger than this many bytes are not scanned
+  pub max_file_size: Option<usize>,
+}
+
+impl Default for Settings {
+  fn default() -> Self {
+    Self {
+      inlay_hints: false,
+      code_lens: false,
+      debounce_ms: 200,
+      config_path: None,
+      rule_filter: None,
+      filter: None,
+      severity_overrides: HashMap::new(),
+      scan_outside_workspace: false,
+      max_file_size: None,
+    }
+  }
+}
+
+impl Settings {
+  /// The settings in the value, None if it has no ast-grep settings, like `null`
+  /// in a pull-model notification or the settings of other extensions.
+  fn from_value(value: Value) -> anyhow::Result<Option<Self>> {
+    let value = match value {
+      Value::Object(mut map) if map.contains_key(SECTION) => {
+        map.remove(SECTION).unwrap_or_default()
+      }
+      Value::Object(map) if !map.is_empty() && map.keys().all(|k| KEYS.contains(&k.as_str())) => {
+        Value::Object(map)
+      }
+      _ => return Ok(None),
+    };
+    if value.is_null() {
+      return Ok(None);
+    }
+    let mut settings: Self = serde_json::from_value(value)?;
+    settings.filter = settings
+      .rule_filter
+      .as_deref()
+      .map(Regex::new)
+      .transpose()?;
+    Ok(Some(settings))
+  }
+
+  /// The severity of the rule after overrides, None if the rule is filtered out or off.
+  pub fn severity_of<'a, L: Language>(&'a self, rule: &'a RuleConfig<L>) -> Option<&'a Severity> {
+    if let Some(filter) = &self.filter {
+      if !filter.is_match(&rule.id) {
+        return None;
+      }
+    }
+    let severity = self
+      .severity_overrides
+      .get(&rule.id)
+      .unwrap_or(&rule.severity);
+    (!matches!(severity, Severity::Off)).then_some(severity)
+  }
+
+  pub fn is_too_large(&self, text: &str) -> bool {
+    self.max_file_size.is_some_and(|max| text.len() > max)
+  }
+}
+
+impl<L: LSPLang> Backend<L> {
+  pub(crate) fn settings(&self) -> Arc<Settings> {
+    self.settings.read().map(|s| s.clone()).unwrap_or_default()
+  }
+
+  /// Replace the settings, returns the previous ones.
+  /// None if the value has no ast-grep settings or they are invalid, which keeps the current ones.
+  pub(crate) async fn update_settings(&self, value: Value) -> Option<Arc<Settings>> {
+    let settings = match Settings::from_value(value) {
+      Ok(settings) => settings?,
+      Err(e) => {
+        self
+          .client
+          .show_message(MessageType::ERROR, format!("Invalid settings: {e}"))
+          .await;
+        return None;
+      }
+    };
+    let mut current = self.settings.write().ok()?;
+    Some(std::mem::replace(&mut *current, Arc::new(settings)))
+  }
+
+  pub(crate) async fn on_change_configuration(&self, params: DidChangeConfigurationParams) {
+    let Some(previous) = self.update_settings(params.settings).await else {
+      return;
+    };
+    let settings = self.settings();
+    if previous.config_path != settings.config_path {
+      if let Err(e) = self.reload_rules().await {
+        self
+          .client
+          .show_message(MessageType::ERROR, format!("Failed to reload rules: {e}"))
+          .await;
+      }
+    } else {
+      // filters and overrides apply to the next scan
+      self.generation.fetch_add(1, Ordering::AcqRel);
+      self.report_cache.clear();
+      self.republish_all_diagnostics().await;
+      self.refresh_pulled_diagnostics().await;
+    }
+    let workspace = self
+      .capabilities
+      .read()
+      .ok()
+      .and_then(|cap| cap.workspace.clone());
+    let Some(workspace) = workspace else {
+      return;
+    };
+    let can_refresh_hints = workspace
+      .inlay_hint
+      .and_then(|h| h.refresh_support)
+      .unwrap_or(false);
+    if can_refresh_hints && previous.inlay_hints != settings.inlay_hints {
+      let _ = self.client.inlay_hint_refresh().await;
+    }
+    let can_refresh_lens = workspace
+      .code_lens
+      .and_then(|c| c.refresh_support)
+      .unwrap_or(false);
+    if can_refresh_lens && previous.code_lens != settings.code_lens {
+      let _ = self.client.code_lens_refresh().await;
+    }
+  }
+}
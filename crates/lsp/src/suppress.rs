@@ -0,0 +1,110 @@
+//! Code actions that suppress a rule with an `ast-grep-ignore` comment.
+//!
+//! Comments are written and merged with the suppression grammar of `ast-grep-config`,
+//! and the comment syntax is probed with the document's parser, so an inserted comment
+//! is always recognized by the next scan.
+use crate::utils::{end_position, start_position};
+
+use ast_grep_config::{find_suppressions, merge_suppression, suppression_text, SuppressKind};
+use ast_grep_core::tree_sitter::{LanguageExt, StrDoc};
+use ast_grep_core::AstGrep;
+use tower_lsp_server::lsp_types::*;
+
+use std::collections::HashMap;
+
+/// Comment delimiters to try, the first one parsed as a suppression comment is used.
+const COMMENT_STYLES: &[(&str, &str)] = &[
+  ("//", ""),
+  ("#", ""),
+  ("--", ""),
+  (";", ""),
+  ("%", ""),
+  ("/*", " */"),
+  ("<!--", " -->"),
+];
+
+/// Reported by the unused suppression rule, suppressing it has no use.
+const UNUSED_SUPPRESSION: &str = "unused-suppression";
+
+fn comment_style<L: LanguageExt>(lang: &L) -> Option<(&'static str, &'static str)> {
+  let expected = vec!["probe".to_string()];
+  COMMENT_STYLES.iter().copied().find(|(start, end)| {
+    let probe = format!("{start} {}{end}", suppression_text(&["probe"]));
+    let root = lang.ast_grep(probe);
+    let found = find_suppressions(&root.root());
+    matches!(found.as_slice(), [s] if s.rules.as_ref() == Some(&expected))
+  })
+}
+
+fn suppress_action(uri: &Uri, title: String, edit: TextEdit) -> CodeAction {
+  let mut changes = HashMap::new();
+  changes.insert(uri.clone(), vec![edit]);
+  CodeAction {
+    title,
+    kind: Some(CodeActionKind::QUICKFIX),
+    edit: Some(WorkspaceEdit::new(changes)),
+    is_preferred: Some(false),
+    ..Default::default()
+  }
+}
+
+/// Actions to suppress the diagnostic's rule on its line or in the whole file.
+pub fn suppression_actions<L: LanguageExt>(
+  uri: &Uri,
+  root: &AstGrep<StrDoc<L>>,
+  diagnostic: &Diagnostic,
+) -> Vec<CodeAction> {
+  let Some(NumberOrString::String(id)) = &diagnostic.code else {
+    return vec![];
+  };
+  if id == UNUSED_SUPPRESSION {
+    return vec![];
+  }
+  let Some((start, end)) = comment_style(root.lang()) else {
+    return vec![];
+  };
+  let source = root.source();
+  let suppressions = find_suppressions(&root.root());
+  let existing = |kind| suppressions.iter().find(|s| s.kind == kind);
+  let merge = |kind| {
+    let comment = existing(kind)?;
+    let text = merge_suppression(&comment.node.text(), id)?;
+    let range = Range::new(start_position(&comment.node), end_position(&comment.node));
+    Some(TextEdit::new(range, text))
+  };
+  let comment = format!("{start} {}{end}", suppression_text(&[id]));
+  let mut actions = vec![];
+
+  let line = diagnostic.range.start.line;
+  let line_kind = SuppressKind::Line(line as usize);
+  let line_edit = if existing(line_kind).is_some() {
+    merge(line_kind)
+  } else {
+    let line_text = source.lines().nth(line as usize).unwrap_or("");
+    let indent = &line_text[..line_text.len() - line_text.trim_start().len()];
+    let position = Position::new(line, 0);
+    let text = format!("{indent}{comment}\n");
+    Some(TextEdit::new(Range::new(position, position), text))
+  };
+  if let Some(edit) = line_edit {
+    let title = format!("Suppress `{id}` on this line");
+    actions.push(suppress_action(uri, title, edit));
+  }
+
+  let file_edit = if existing(SuppressKind::File).is_some() {
+    merge(SuppressKind::File)
+  } else if source.starts_with("#!") {
+    // a comment cannot precede the shebang
+    None
+  } else {
+    // an empty line after the first line comment suppresses the whole file
+    let position = Position::new(0, 0);
+    let text = format!("{comment}\n\n");
+    Some(TextEdit::new(Range::new(position, position), text))
+  };
+  if let Some(edit) = file_edit {
+    let title = format!("Suppress `{id}` in this file");
+    actions.push(suppress_action(uri, title, edit));
+  }
+  actions
+}
@@ -20,6 +20,7 @@ pub struct OneFix {
   pub range: Option<Range>,
 }
 
+#[derive(Clone)]
 pub struct RewriteData {
   pub fixers: Vec<OneFix>,
   // maybe we should have fixed range
@@ -66,40 +67,51 @@ fn expand_start_position<L: LanguageExt>(
   node_match: &NodeMatch<StrDoc<L>>,
   offset: usize,
 ) -> Position {
-  let mut start = node_match.start_pos();
   if node_match.range().start > offset {
     for prev in node_match.prev_all() {
       if prev.range().start <= offset {
-        start = prev.start_pos();
-        break;
+        return start_position(&prev);
       }
     }
   }
-  Position {
-    line: start.line() as u32,
-    character: start.column(node_match) as u32,
-  }
+  start_position(node_match)
 }
 
 fn expand_end_position<L: LanguageExt>(
   node_match: &NodeMatch<StrDoc<L>>,
   offset: usize,
 ) -> Position {
-  let mut end = node_match.end_pos();
   if node_match.range().end < offset {
     for next in node_match.next_all() {
       if next.range().end >= offset {
-        end = next.end_pos();
-        break;
+        return end_position(&next);
       }
     }
   }
+  end_position(node_match)
+}
+
+/// Convert a byte offset on the given line to an LSP position.
+/// LSP counts `character` in UTF-16 code units by default.
+fn to_lsp_position(source: &str, line: usize, offset: usize) -> Position {
+  let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+  let character = source[line_start..offset].encode_utf16().count();
   Position {
-    line: end.line() as u32,
-    character: end.column(node_match) as u32,
+    line: line as u32,
+    character: character as u32,
   }
 }
 
+pub fn start_position<L: LanguageExt>(node: &Node<StrDoc<L>>) -> Position {
+  let source = node.get_doc().get_source();
+  to_lsp_position(source, node.start_pos().line(), node.range().start)
+}
+
+pub fn end_position<L: LanguageExt>(node: &Node<StrDoc<L>>) -> Position {
+  let source = node.get_doc().get_source();
+  to_lsp_position(source, node.end_pos().line(), node.range().end)
+}
+
 // Accepts an optional fixes cache for fallback
 pub fn diagnostic_to_code_action(
   text_doc: &TextDocumentIdentifier,
@@ -141,18 +153,13 @@ pub fn diagnostic_to_code_action(
   Some(actions.collect())
 }
 
-fn convert_nodes_to_range<D: Doc>(start_node: &Node<D>, end_node: &Node<D>) -> Range {
-  let start = start_node.start_pos();
-  let end = end_node.end_pos();
+fn convert_nodes_to_range<L: LanguageExt>(
+  start_node: &Node<StrDoc<L>>,
+  end_node: &Node<StrDoc<L>>,
+) -> Range {
   Range {
-    start: Position {
-      line: start.line() as u32,
-      character: start.column(start_node) as u32,
-    },
-    end: Position {
-      line: end.line() as u32,
-      character: end.column(end_node) as u32,
-    },
+    start: start_position(start_node),
+    end: end_position(end_node),
   }
 }
 
@@ -182,20 +189,9 @@ fn get_primary_label<L: LanguageExt>(
   labels: &[Label<StrDoc<L>>],
 ) -> Range {
   let Some(label) = labels.iter().find(|l| l.style == LabelStyle::Primary) else {
-    return convert_nodes_to_range(node_match, node_match);
+    return convert_nodes_to_range(node_match, node_match.get_end_node());
   };
-  let start = label.start_node.start_pos();
-  let end = label.end_node.end_pos();
-  Range {
-    start: Position {
-      line: start.line() as u32,
-      character: start.column(&label.start_node) as u32,
-    },
-    end: Position {
-      line: end.line() as u32,
-      character: end.column(&label.end_node) as u32,
-    },
-  }
+  convert_nodes_to_range(&label.start_node, &label.end_node)
 }
 
 fn get_node_range_and_related_info<L: LanguageExt>(
@@ -206,7 +202,7 @@ fn get_node_range_and_related_info<L: LanguageExt>(
   // if user has not specified any labels, we don't need to show anything
   // the default labels are pretty noisy
   if rule.labels.is_none() {
-    let range = convert_nodes_to_range(node_match, node_match);
+    let range = convert_nodes_to_range(node_match, node_match.get_end_node());
     return (range, None);
   }
   let labels = rule.get_labels(node_match);
@@ -214,17 +210,19 @@ fn get_node_range_and_related_info<L: LanguageExt>(
   (get_primary_label(node_match, &labels), related_information)
 }
 
+/// `severity` is the rule's severity after client overrides.
 pub fn convert_match_to_diagnostic<L: LanguageExt>(
   uri: &Uri,
   node_match: &NodeMatch<StrDoc<L>>,
   rule: &RuleConfig<L>,
+  severity: &Severity,
 ) -> Diagnostic {
   let (range, related_information) = get_node_range_and_related_info(uri, node_match, rule);
   Diagnostic {
     range,
     code: Some(NumberOrString::String(rule.id.clone())),
     code_description: url_to_code_description(&rule.url),
-    severity: Some(match rule.severity {
+    severity: Some(match severity {
       Severity::Error => DiagnosticSeverity::ERROR,
       Severity::Warning => DiagnosticSeverity::WARNING,
       Severity::Info => DiagnosticSeverity::INFORMATION,
@@ -251,6 +249,33 @@ fn get_non_empty_message<L: LanguageExt>(
   }
 }
 
+/// Convert a byte offset of the text to an LSP position, the inverse of `position_to_offset`.
+pub fn offset_to_position(text: &str, offset: usize) -> Position {
+  let line = text[..offset].matches('\n').count();
+  to_lsp_position(text, line, offset)
+}
+
+/// Convert an LSP position to a byte offset of the text.
+/// LSP counts `character` in UTF-16 code units unless another encoding is negotiated.
+/// Positions past the end of a line or of the text are clamped to it.
+pub fn position_to_offset(text: &str, position: Position) -> usize {
+  let mut offset = 0;
+  for _ in 0..position.line {
+    match text[offset..].find('\n') {
+      Some(i) => offset += i + 1,
+      None => return text.len(),
+    }
+  }
+  let mut units = 0;
+  for (i, c) in text[offset..].char_indices() {
+    if units >= position.character || c == '\n' {
+      return offset + i;
+    }
+    units += c.len_utf16() as u32;
+  }
+  text.len()
+}
+
 fn url_to_code_description(url: &Option<String>) -> Option<CodeDescription> {
   let href = Uri::from_str(url.as_ref()?).ok()?;
   Some(CodeDescription { href })
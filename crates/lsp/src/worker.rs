@@ -0,0 +1,173 @@
+//! A bounded pool of threads to scan documents off the async executor,
+//! and a timer to debounce scans while the user is typing.
+//!
+//! Both only use std threads, so the server does not depend on an async runtime.
+use std::future::Future;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Threads running rule scans, at most `size` scans run at the same time.
+pub(crate) struct WorkerPool {
+  sender: Sender<Job>,
+}
+
+impl WorkerPool {
+  pub fn new(size: usize) -> Self {
+    let (sender, receiver) = channel::<Job>();
+    let receiver = Arc::new(Mutex::new(receiver));
+    for i in 0..size.max(1) {
+      let receiver = receiver.clone();
+      let _ = thread::Builder::new()
+        .name(format!("ast-grep-lsp-worker-{i}"))
+        .spawn(move || Self::work(&receiver));
+    }
+    Self { sender }
+  }
+
+  /// Run jobs until the pool is dropped.
+  fn work(receiver: &Mutex<Receiver<Job>>) {
+    loop {
+      // release the lock before running the job
+      let job = match receiver.lock() {
+        Ok(receiver) => receiver.recv(),
+        Err(_) => return,
+      };
+      let Ok(job) = job else {
+        return;
+      };
+      // a panicking job drops its result slot, the waiting task gets None
+      let _ = catch_unwind(AssertUnwindSafe(job));
+    }
+  }
+
+  /// Run the job on a worker, resolves to None if the job panicked.
+  pub fn run<T, F>(&self, job: F) -> JobResult<T>
+  where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+  {
+    let slot = Arc::new(Mutex::new(Slot {
+      value: None,
+      done: false,
+      waker: None,
+    }));
+    let completer = Completer(slot.clone());
+    let job = Box::new(move || completer.complete(job()));
+    // if the workers are gone, the dropped completer marks the slot done
+    let _ = self.sender.send(job);
+    JobResult(slot)
+  }
+}
+
+struct Slot<T> {
+  value: Option<T>,
+  done: bool,
+  waker: Option<Waker>,
+}
+
+/// Completes the slot when dropped, with or without a value.
+struct Completer<T>(Arc<Mutex<Slot<T>>>);
+
+impl<T> Completer<T> {
+  fn complete(self, value: T) {
+    if let Ok(mut slot) = self.0.lock() {
+      slot.value = Some(value);
+    }
+  }
+}
+
+impl<T> Drop for Completer<T> {
+  fn drop(&mut self) {
+    let waker = self.0.lock().ok().and_then(|mut slot| {
+      slot.done = true;
+      slot.waker.take()
+    });
+    if let Some(waker) = waker {
+      waker.wake();
+    }
+  }
+}
+
+pub(crate) struct JobResult<T>(Arc<Mutex<Slot<T>>>);
+
+impl<T> Future for JobResult<T> {
+  type Output = Option<T>;
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    let Ok(mut slot) = self.0.lock() else {
+      return Poll::Ready(None);
+    };
+    if slot.done {
+      return Poll::Ready(slot.value.take());
+    }
+    slot.waker = Some(cx.waker().clone());
+    Poll::Pending
+  }
+}
+
+/// Wakes sleeping tasks when their deadline passes.
+fn timer() -> &'static Sender<(Instant, Waker)> {
+  static TIMER: OnceLock<Sender<(Instant, Waker)>> = OnceLock::new();
+  TIMER.get_or_init(|| {
+    let (sender, receiver) = channel::<(Instant, Waker)>();
+    let _ = thread::Builder::new()
+      .name("ast-grep-lsp-timer".into())
+      .spawn(move || {
+        let mut sleeping: Vec<(Instant, Waker)> = vec![];
+        loop {
+          let next = sleeping.iter().map(|(deadline, _)| *deadline).min();
+          let received = match next {
+            Some(deadline) => {
+              let timeout = deadline.saturating_duration_since(Instant::now());
+              receiver.recv_timeout(timeout).ok()
+            }
+            None => match receiver.recv() {
+              Ok(received) => Some(received),
+              Err(_) => return,
+            },
+          };
+          sleeping.extend(received);
+          let now = Instant::now();
+          sleeping.retain(|(deadline, waker)| {
+            if *deadline <= now {
+              waker.wake_by_ref();
+              false
+            } else {
+              true
+            }
+          });
+        }
+      });
+    sender
+  })
+}
+
+pub(crate) struct Sleep {
+  deadline: Instant,
+}
+
+impl Future for Sleep {
+  type Output = ();
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+    if Instant::now() >= self.deadline {
+      return Poll::Ready(());
+    }
+    match timer().send((self.deadline, cx.waker().clone())) {
+      Ok(()) => Poll::Pending,
+      // no timer thread, do not sleep at all
+      Err(_) => Poll::Ready(()),
+    }
+  }
+}
+
+pub(crate) fn sleep(duration: Duration) -> Sleep {
+  Sleep {
+    deadline: Instant::now() + duration,
+  }
+}
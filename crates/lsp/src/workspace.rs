@@ -0,0 +1,237 @@
+//! Workspace folders, each checked with the rules of its own project.
+//!
+//! A document uses the project of the innermost workspace folder containing it.
+//! Folders without a project of their own, and files outside of all folders,
+//! use the rules the server was started with.
+use crate::authoring::GlobalUtils;
+use crate::{Backend, LSPLang};
+
+use ast_grep_config::RuleCollection;
+use tower_lsp_server::lsp_types::*;
+use tower_lsp_server::UriExt;
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+/// Rules and util rule files of the project a workspace folder belongs to.
+pub struct ProjectRules<L: LSPLang> {
+  /// directory of the project's config, rule file globs are relative to it
+  pub base: PathBuf,
+  pub rules: RuleCollection<L>,
+  pub util_files: Vec<PathBuf>,
+}
+
+/// Finds the project of a workspace folder, None if the folder does not belong to any.
+pub type ProjectLoader<L> =
+  Box<dyn Fn(&Path) -> Option<anyhow::Result<ProjectRules<L>>> + Send + Sync>;
+
+/// Loads the project of a config file.
+pub type ConfigLoader<L> = Box<dyn Fn(&Path) -> anyhow::Result<ProjectRules<L>> + Send + Sync>;
+
+/// Registers languages declared by the projects of workspace folders.
+/// It is called with the folders of `initialize`, possibly none, before any document is parsed,
+/// and later with every added folder. An error is shown to the user.
+pub type LanguageSetup = Box<dyn Fn(&[PathBuf]) -> anyhow::Result<()> + Send + Sync>;
+
+pub(crate) struct Project<L: LSPLang> {
+  pub base: PathBuf,
+  pub rules: RuleCollection<L>,
+  pub global_utils: GlobalUtils,
+}
+
+impl<L: LSPLang> Default for Project<L> {
+  fn default() -> Self {
+    Self {
+      base: PathBuf::new(),
+      rules: RuleCollection::default(),
+      global_utils: GlobalUtils::default(),
+    }
+  }
+}
+
+pub(crate) struct Folder<L: LSPLang> {
+  root: PathBuf,
+  /// None if the folder uses the default project
+  project: Option<Arc<Project<L>>>,
+}
+
+fn folder_roots(folders: &[WorkspaceFolder]) -> Vec<PathBuf> {
+  folders
+    .iter()
+    .filter_map(|f| f.uri.to_file_path().map(PathBuf::from))
+    .collect()
+}
+
+impl<L: LSPLang> Backend<L> {
+  /// Register languages of the folders sent in `initialize`, before any document is parsed.
+  pub(crate) async fn setup_initial_folders(&self, folders: Option<&Vec<WorkspaceFolder>>) {
+    let roots = folders.map(|f| folder_roots(f)).unwrap_or_default();
+    self.setup_languages(&roots).await;
+    if folders.is_none() {
+      return;
+    }
+    // projects are loaded with the rules in `initialized`
+    let folders = roots
+      .into_iter()
+      .map(|root| Folder {
+        root,
+        project: None,
+      })
+      .collect();
+    if let Ok(mut known) = self.folders.write() {
+      *known = Some(folders);
+    }
+  }
+
+  /// The project that checks the document, from the innermost folder containing it.
+  pub(crate) fn project_for(&self, uri: &Uri) -> Option<Arc<Project<L>>> {
+    if let (Some(path), Ok(folders)) = (uri.to_file_path(), self.folders.read()) {
+      let innermost = folders
+        .iter()
+        .flatten()
+        .filter(|f| path.starts_with(&f.root))
+        .max_by_key(|f| f.root.components().count());
+      if let Some(project) = innermost.and_then(|f| f.project.clone()) {
+        return Some(project);
+      }
+    }
+    self.default_project.read().ok().map(|p| p.clone())
+  }
+
+  fn known_roots(&self) -> Option<Vec<PathBuf>> {
+    let folders = self.folders.read().ok()?;
+    let roots = folders.as_ref()?.iter().map(|f| f.root.clone()).collect();
+    Some(roots)
+  }
+
+  /// Workspace folders, asked from the client the first time if `initialize` had none.
+  pub(crate) async fn workspace_roots(&self) -> Vec<PathBuf> {
+    let roots = match self.known_roots() {
+      Some(roots) => roots,
+      None => self.fetch_workspace_folders().await,
+    };
+    // fallback to base if no workspace provided by client #2211
+    if roots.is_empty() {
+      vec![self.base.clone()]
+    } else {
+      roots
+    }
+  }
+
+  async fn fetch_workspace_folders(&self) -> Vec<PathBuf> {
+    let client_support_workspace = self.capabilities.read().is_ok_and(|cap| {
+      cap
+        .workspace
+        .as_ref()
+        .and_then(|w| w.workspace_folders)
+        .unwrap_or(false)
+    });
+    if !client_support_workspace {
+      return vec![];
+    }
+    let Ok(Some(folders)) = self.client.workspace_folders().await else {
+      return vec![];
+    };
+    let roots = folder_roots(&folders);
+    self.add_folders(&roots).await;
+    roots
+  }
+
+  /// Load the project of a folder, errors are reported and leave the folder without one.
+  async fn load_project(&self, root: &Path) -> Option<Arc<Project<L>>> {
+    let loader = self.project_loader.as_ref()?;
+    match loader(root)? {
+      Ok(found) => {
+        let global_utils = self.load_global_utils(&found.util_files).await;
+        Some(Arc::new(Project {
+          base: found.base,
+          rules: found.rules,
+          global_utils,
+        }))
+      }
+      Err(error) => {
+        let message = format!("Failed to load rules of {}: {error}", root.display());
+        self
+          .client
+          .show_message(MessageType::ERROR, message.clone())
+          .await;
+        self.client.log_message(MessageType::ERROR, message).await;
+        None
+      }
+    }
+  }
+
+  async fn setup_languages(&self, roots: &[PathBuf]) {
+    let Some(setup) = &self.language_setup else {
+      return;
+    };
+    if let Err(e) = setup(roots) {
+      self
+        .client
+        .show_message(MessageType::ERROR, format!("Failed to load languages: {e}"))
+        .await;
+    }
+  }
+
+  async fn add_folders(&self, roots: &[PathBuf]) {
+    let known = self.known_roots().unwrap_or_default();
+    let mut new_roots = vec![];
+    for root in roots {
+      if !known.contains(root) && !new_roots.contains(root) {
+        new_roots.push(root.clone());
+      }
+    }
+    if new_roots.is_empty() {
+      return;
+    }
+    // languages must be known before the rules of the folders are parsed
+    self.setup_languages(&new_roots).await;
+    let mut added = vec![];
+    for root in new_roots {
+      let project = self.load_project(&root).await;
+      added.push(Folder { root, project });
+    }
+    if let Ok(mut folders) = self.folders.write() {
+      folders.get_or_insert_with(Vec::new).extend(added);
+    }
+  }
+
+  /// Reload the project of every known folder.
+  pub(crate) async fn reload_folder_projects(&self) {
+    let Some(roots) = self.known_roots() else {
+      return;
+    };
+    let mut projects = vec![];
+    for root in roots {
+      let project = self.load_project(&root).await;
+      projects.push(Folder { root, project });
+    }
+    if let Ok(mut folders) = self.folders.write() {
+      *folders = Some(projects);
+    }
+  }
+
+  pub(crate) async fn on_change_workspace_folders(&self, params: DidChangeWorkspaceFoldersParams) {
+    let event = params.event;
+    let removed = folder_roots(&event.removed);
+    if self.known_roots().is_none() {
+      // the client already answers with the changed folders
+      self.fetch_workspace_folders().await;
+    }
+    if let Ok(mut folders) = self.folders.write() {
+      if let Some(folders) = folders.as_mut() {
+        folders.retain(|f| !removed.contains(&f.root));
+      }
+    }
+    self.add_folders(&folder_roots(&event.added)).await;
+    // documents may now belong to another project
+    self.generation.fetch_add(1, Ordering::AcqRel);
+    self.report_cache.clear();
+    if let Ok(mut walked) = self.walked_files.write() {
+      *walked = None;
+    }
+    self.republish_all_diagnostics().await;
+    self.refresh_pulled_diagnostics().await;
+  }
+}
@@ -4,11 +4,12 @@ use ast_grep_lsp::*;
 use futures::{SinkExt, StreamExt};
 use serde_json::Value;
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tokio::io::{duplex, split, AsyncReadExt, AsyncWriteExt, DuplexStream};
 use tokio_util::bytes::{BufMut, BytesMut};
 use tokio_util::codec::{Decoder, Encoder, Framed};
 use tower_lsp_server::lsp_types::CodeAction;
+use tower_lsp_server::ClientSocket;
 
 pub fn req(msg: &str) -> String {
   format!("Content-Length: {}\r\n\r\n{}", msg.len(), msg)
@@ -312,14 +313,21 @@ pub async fn send_did_open_framed(
 pub async fn wait_for_diagnostics(
   sender: &mut Framed<DuplexStream, LspCodec>,
 ) -> Option<serde_json::Value> {
-  // Wait for diagnostics
+  let params = wait_for_publish_diagnostics(sender).await?;
+  Some(params["diagnostics"].clone())
+}
+
+// Wait for publishDiagnostics and return its params
+pub async fn wait_for_publish_diagnostics(
+  sender: &mut Framed<DuplexStream, LspCodec>,
+) -> Option<serde_json::Value> {
   let mut diagnostics: Option<serde_json::Value> = None;
   for _ in 0..20 {
     if let Ok(Some(Ok(msg))) =
       tokio::time::timeout(std::time::Duration::from_secs(2), sender.next()).await
     {
       if msg.get("method") == Some(&serde_json::json!("textDocument/publishDiagnostics")) {
-        diagnostics = Some(msg["params"]["diagnostics"].clone());
+        diagnostics = Some(msg["params"].clone());
         break;
       } else if msg.get("method") == Some(&serde_json::json!("workspace/workspaceFolders")) {
         // Respond with empty workspaceFolders
@@ -339,6 +347,43 @@ pub async fn wait_for_diagnostics(
   diagnostics
 }
 
+pub async fn send_did_change_framed(
+  framed: &mut Framed<DuplexStream, LspCodec>,
+  uri: &str,
+  version: i32,
+  range: Value,
+  text: &str,
+) {
+  let did_change = serde_json::json!({
+    "jsonrpc": "2.0",
+    "method": "textDocument/didChange",
+    "params": {
+      "textDocument": { "uri": uri, "version": version },
+      "contentChanges": [{ "range": range, "text": text }]
+    }
+  });
+  framed.send(did_change).await.unwrap();
+}
+
+pub async fn send_configuration_framed(
+  framed: &mut Framed<DuplexStream, LspCodec>,
+  settings: Value,
+) {
+  let change = serde_json::json!({
+    "jsonrpc": "2.0",
+    "method": "workspace/didChangeConfiguration",
+    "params": { "settings": settings },
+  });
+  framed.send(change).await.unwrap();
+}
+
+fn range_json(line: u32, start: u32, end: u32) -> Value {
+  serde_json::json!({
+    "start": { "line": line, "character": start },
+    "end": { "line": line, "character": end }
+  })
+}
+
 async fn wait_for_response(
   sender: &mut Framed<DuplexStream, LspCodec>,
   id: i32,
@@ -377,6 +422,14 @@ async fn request_code_action(
   wait_for_response(sender, method_call_id).await
 }
 
+/// Split code actions into fixes and suppression comments.
+fn split_suppressions(actions: &[Value]) -> (Vec<Value>, Vec<Value>) {
+  actions.iter().cloned().partition(|action| {
+    let title = action["title"].as_str().unwrap_or_default();
+    !title.starts_with("Suppress")
+  })
+}
+
 fn apply_all_code_actions(text: &str, actions: &[Value]) -> String {
   // As offsets are based on the original text, we need to track changes
   let mut lines: Vec<String> = text.lines().map(|s| s.to_string()).collect();
@@ -420,6 +473,834 @@ fn apply_all_code_actions(text: &str, actions: &[Value]) -> String {
   lines.join("\n")
 }
 
+#[tokio::test]
+async fn test_incremental_change() {
+  let yamls = r"
+id: no-console-rule
+message: No console.log
+language: TypeScript
+rule:
+  pattern: console.log($$$A)
+";
+  let mut client = create_lsp_framed(yamls).await;
+  let file_uri = "file:///Users/codes/ast-grep-vscode/test.ts";
+  // the emoji takes two UTF-16 code units
+  let file_content = "const s = '😀'; foo()\n";
+  send_did_open_framed(&mut client, file_uri, "typescript", file_content).await;
+  let diagnostics = wait_for_diagnostics(&mut client).await.unwrap();
+  assert_eq!(diagnostics, serde_json::json!([]));
+
+  // replace `foo` with `console.log`
+  send_did_change_framed(
+    &mut client,
+    file_uri,
+    2,
+    range_json(0, 16, 19),
+    "console.log",
+  )
+  .await;
+  let params = wait_for_publish_diagnostics(&mut client).await.unwrap();
+  assert_eq!(params["version"], 2);
+  let diagnostics = params["diagnostics"].as_array().unwrap();
+  assert_eq!(diagnostics.len(), 1);
+  assert_eq!(diagnostics[0]["range"], range_json(0, 16, 29));
+
+  // diagnostics are unchanged after editing the next line, so nothing is published
+  send_did_change_framed(&mut client, file_uri, 3, range_json(1, 0, 0), "let a = 1").await;
+  // adding another match publishes the new diagnostics
+  send_did_change_framed(
+    &mut client,
+    file_uri,
+    4,
+    range_json(1, 9, 9),
+    "; console.log(2)",
+  )
+  .await;
+  let params = wait_for_publish_diagnostics(&mut client).await.unwrap();
+  assert_eq!(params["version"], 4);
+  let diagnostics = params["diagnostics"].as_array().unwrap();
+  assert_eq!(diagnostics.len(), 2);
+  assert_eq!(diagnostics[1]["range"], range_json(1, 11, 25));
+}
+
+#[tokio::test]
+async fn test_debounced_change() {
+  let mut client = create_lsp_framed(NO_CONSOLE).await;
+  let file_uri = "file:///Users/codes/ast-grep-vscode/test.ts";
+  send_did_open_framed(&mut client, file_uri, "typescript", "foo()\n").await;
+  wait_for_diagnostics(&mut client).await.unwrap();
+
+  // quick edits are scanned once, for the latest version
+  send_did_change_framed(&mut client, file_uri, 2, range_json(0, 0, 3), "console.log").await;
+  send_did_change_framed(&mut client, file_uri, 3, range_json(1, 0, 0), "bar()").await;
+  send_did_change_framed(&mut client, file_uri, 4, range_json(1, 0, 3), "console.log").await;
+  let params = wait_for_publish_diagnostics(&mut client).await.unwrap();
+  assert_eq!(params["version"], 4);
+  assert_eq!(params["diagnostics"].as_array().unwrap().len(), 2);
+  let outdated = tokio::time::timeout(std::time::Duration::from_millis(500), async {
+    while let Some(Ok(msg)) = client.next().await {
+      if msg["method"] == "textDocument/publishDiagnostics" {
+        return msg;
+      }
+    }
+    Value::Null
+  })
+  .await;
+  assert!(outdated.is_err(), "outdated diagnostics: {outdated:?}");
+}
+
+async fn request_framed(
+  client: &mut Framed<DuplexStream, LspCodec>,
+  method: &str,
+  params: Value,
+) -> (Value, Vec<Value>) {
+  let id = allocate_method_call_id();
+  let request = serde_json::json!({
+    "jsonrpc": "2.0",
+    "id": id,
+    "method": method,
+    "params": params,
+  });
+  client.send(request).await.unwrap();
+  let mut notifications = vec![];
+  for _ in 0..50 {
+    let Ok(Some(Ok(msg))) =
+      tokio::time::timeout(std::time::Duration::from_secs(2), client.next()).await
+    else {
+      break;
+    };
+    if msg.get("id") == Some(&serde_json::json!(id)) {
+      return (msg, notifications);
+    }
+    if msg["method"] == "workspace/workspaceFolders" {
+      let response = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": msg["id"].clone(),
+        "result": [{
+          "uri": "file:///Users/codes/ast-grep-vscode",
+          "name": "ast-grep-vscode"
+        }]
+      });
+      client.send(response).await.unwrap();
+      continue;
+    }
+    if msg["method"] == "workspace/applyEdit" {
+      let response = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": msg["id"].clone(),
+        "result": { "applied": true }
+      });
+      client.send(response).await.unwrap();
+    }
+    notifications.push(msg);
+  }
+  panic!("no response for {method}");
+}
+
+const NO_CONSOLE: &str = r"
+id: no-console-rule
+message: No console.log
+language: TypeScript
+rule:
+  pattern: console.log($$$A)
+";
+
+#[tokio::test]
+async fn test_document_pull_diagnostic() {
+  let mut client = create_lsp_framed(NO_CONSOLE).await;
+  let file_uri = "file:///Users/codes/ast-grep-vscode/test.ts";
+  send_did_open_framed(&mut client, file_uri, "typescript", "console.log(1)").await;
+  wait_for_diagnostics(&mut client).await.unwrap();
+  let params = serde_json::json!({ "textDocument": { "uri": file_uri } });
+  let (resp, _) = request_framed(&mut client, "textDocument/diagnostic", params).await;
+  let result = &resp["result"];
+  assert_eq!(result["kind"], "full");
+  assert_eq!(result["items"].as_array().unwrap().len(), 1);
+  let result_id = result["resultId"].clone();
+  let params = serde_json::json!({
+    "textDocument": { "uri": file_uri },
+    "previousResultId": result_id,
+  });
+  let (resp, _) = request_framed(&mut client, "textDocument/diagnostic", params).await;
+  assert_eq!(resp["result"]["kind"], "unchanged");
+  assert_eq!(resp["result"]["resultId"], result_id);
+}
+
+#[tokio::test]
+async fn test_workspace_pull_diagnostic() {
+  let dir = std::env::temp_dir().join(format!("ast-grep-lsp-{}", std::process::id()));
+  std::fs::create_dir_all(&dir).unwrap();
+  let file = dir.join("closed.ts");
+  std::fs::write(&file, "console.log(1); console.log(2)").unwrap();
+  let listed = file.clone();
+  let mut client = create_lsp_framed_with_walker(NO_CONSOLE, move |_| vec![listed.clone()]).await;
+  let params = serde_json::json!({
+    "previousResultIds": [],
+    "workDoneToken": "workspace-scan",
+  });
+  let (resp, mut notifications) = request_framed(&mut client, "workspace/diagnostic", params).await;
+  let items = resp["result"]["items"].as_array().unwrap();
+  assert_eq!(items.len(), 1);
+  assert_eq!(items[0]["kind"], "full");
+  assert!(items[0]["uri"].as_str().unwrap().ends_with("closed.ts"));
+  assert_eq!(items[0]["items"].as_array().unwrap().len(), 2);
+  // the end notification may arrive after the response
+  if let Ok(Some(Ok(msg))) =
+    tokio::time::timeout(std::time::Duration::from_millis(200), client.next()).await
+  {
+    notifications.push(msg);
+  }
+  let progress: Vec<_> = notifications
+    .iter()
+    .filter(|n| n["method"] == "$/progress")
+    .map(|n| n["params"]["value"]["kind"].clone())
+    .collect();
+  assert_eq!(progress.first(), Some(&serde_json::json!("begin")));
+  assert_eq!(progress.last(), Some(&serde_json::json!("end")));
+
+  // an unchanged workspace is not answered until something changes
+  let id = allocate_method_call_id();
+  let request = serde_json::json!({
+    "jsonrpc": "2.0",
+    "id": id,
+    "method": "workspace/diagnostic",
+    "params": {
+      "previousResultIds": [{ "uri": items[0]["uri"], "value": items[0]["resultId"] }],
+    },
+  });
+  client.send(request).await.unwrap();
+  let early = tokio::time::timeout(std::time::Duration::from_millis(300), async {
+    while let Some(Ok(msg)) = client.next().await {
+      if msg.get("id") == Some(&serde_json::json!(id)) {
+        return msg;
+      }
+    }
+    Value::Null
+  })
+  .await;
+  assert!(early.is_err(), "unchanged workspace answered: {early:?}");
+  let opened = "file:///Users/codes/ast-grep-vscode/test.ts";
+  send_did_open_framed(&mut client, opened, "typescript", "console.log(1)").await;
+  // both the opened document and the rescan ask for the workspace folders
+  let answered = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+    while let Some(Ok(msg)) = client.next().await {
+      if msg["method"] == "workspace/workspaceFolders" {
+        let response = serde_json::json!({
+          "jsonrpc": "2.0",
+          "id": msg["id"].clone(),
+          "result": [{ "uri": "file:///Users/codes/ast-grep-vscode", "name": "ast-grep-vscode" }],
+        });
+        client.send(response).await.unwrap();
+      } else if msg.get("id") == Some(&serde_json::json!(id)) {
+        return msg;
+      }
+    }
+    Value::Null
+  });
+  let resp = answered.await.unwrap();
+  let items = resp["result"]["items"].as_array().unwrap();
+  assert_eq!(items.len(), 2);
+  let kinds: Vec<_> = items
+    .iter()
+    .map(|i| (i["uri"].as_str(), i["kind"].as_str()))
+    .collect();
+  assert!(kinds.contains(&(Some(opened), Some("full"))), "{kinds:?}");
+  std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_search_and_rewrite_commands() {
+  let dir = std::env::temp_dir().join(format!("ast-grep-lsp-search-{}", std::process::id()));
+  std::fs::create_dir_all(&dir).unwrap();
+  let a = dir.join("a.ts");
+  let b = dir.join("b.ts");
+  std::fs::write(&a, "console.log(1)\nconsole.log(2)").unwrap();
+  std::fs::write(&b, "let a = 1\nconsole.log(a)").unwrap();
+  std::fs::write(dir.join("c.py"), "console.log(3)").unwrap();
+  let walked = dir.clone();
+  let walker = move |_: &Path| {
+    let mut files: Vec<_> = std::fs::read_dir(&walked)
+      .unwrap()
+      .map(|e| e.unwrap().path())
+      .collect();
+    files.sort();
+    files
+  };
+  let mut client = create_lsp_framed_with_walker(NO_CONSOLE, walker).await;
+  let search = serde_json::json!({
+    "command": "ast-grep.search",
+    "arguments": [{
+      "pattern": "console.log($A)",
+      "lang": "TypeScript",
+      "rewrite": "logger.info($A)",
+      "paths": [dir.to_str().unwrap()],
+      "partialResultToken": "search-token",
+    }],
+  });
+  let (resp, mut notifications) =
+    request_framed(&mut client, "workspace/executeCommand", search).await;
+  assert_eq!(resp["result"], serde_json::json!([]));
+  // notifications are not ordered with the response by the transport
+  while let Ok(Some(Ok(msg))) =
+    tokio::time::timeout(std::time::Duration::from_millis(200), client.next()).await
+  {
+    notifications.push(msg);
+  }
+  let partials: Vec<_> = notifications
+    .iter()
+    .filter(|n| n["method"] == "$/progress" && n["params"]["token"] == "search-token")
+    .map(|n| n["params"]["value"].as_array().unwrap().clone())
+    .collect();
+  assert_eq!(partials.len(), 2);
+  assert_eq!(partials[0].len(), 2);
+  assert!(partials[0][0]["uri"].as_str().unwrap().ends_with("a.ts"));
+  assert_eq!(partials[0][1]["text"], "console.log(2)");
+  assert_eq!(partials[0][1]["replacement"], "logger.info(2)");
+  assert_eq!(partials[0][1]["range"], range_json(1, 0, 14));
+  assert_eq!(partials[1][0]["text"], "console.log(a)");
+
+  let rewrite = serde_json::json!({
+    "command": "ast-grep.applyRewrite",
+    "arguments": [{
+      "pattern": "console.log($A)",
+      "lang": "TypeScript",
+      "rewrite": "logger.info($A)",
+      "paths": [b.to_str().unwrap()],
+    }],
+  });
+  let (resp, notifications) =
+    request_framed(&mut client, "workspace/executeCommand", rewrite).await;
+  assert_eq!(
+    resp["result"],
+    serde_json::json!({ "applied": true, "files": 1, "edits": 1 })
+  );
+  let apply = notifications
+    .iter()
+    .find(|n| n["method"] == "workspace/applyEdit")
+    .expect("should request applyEdit");
+  let changes = apply["params"]["edit"]["changes"].as_object().unwrap();
+  let (uri, edits) = changes.iter().next().unwrap();
+  assert!(uri.ends_with("b.ts"));
+  assert_eq!(edits[0]["newText"], "logger.info(a)");
+  assert_eq!(edits[0]["range"], range_json(1, 0, 14));
+
+  // the edit of a sequence spans all of its statements
+  let rewrite = serde_json::json!({
+    "command": "ast-grep.applyRewrite",
+    "arguments": [{
+      "pattern": "console.log(1)\nconsole.log($A)",
+      "lang": "TypeScript",
+      "rewrite": "log($A)",
+      "paths": [a.to_str().unwrap()],
+    }],
+  });
+  let (_, notifications) = request_framed(&mut client, "workspace/executeCommand", rewrite).await;
+  let apply = notifications
+    .iter()
+    .find(|n| n["method"] == "workspace/applyEdit")
+    .expect("should request applyEdit");
+  let changes = apply["params"]["edit"]["changes"].as_object().unwrap();
+  let edits = changes.values().next().unwrap();
+  assert_eq!(edits[0]["newText"], "log(2)");
+  let range = serde_json::json!({
+    "start": { "line": 0, "character": 0 },
+    "end": { "line": 1, "character": 14 }
+  });
+  assert_eq!(edits[0]["range"], range);
+  std::fs::remove_dir_all(&dir).unwrap();
+}
+
+fn single_rule(id: &str, pattern: &str) -> String {
+  format!("id: {id}\nmessage: {id}\nlanguage: TypeScript\nrule:\n  pattern: {pattern}\n")
+}
+
+async fn wait_for_uri_codes(client: &mut Framed<DuplexStream, LspCodec>, uri: &str) -> Vec<String> {
+  for _ in 0..20 {
+    let params = wait_for_publish_diagnostics(client)
+      .await
+      .expect("no diagnostics");
+    if params["uri"] != uri {
+      continue;
+    }
+    let diagnostics = params["diagnostics"].as_array().unwrap();
+    return diagnostics
+      .iter()
+      .map(|d| d["code"].as_str().unwrap().to_string())
+      .collect();
+  }
+  panic!("no diagnostics for {uri}");
+}
+
+#[tokio::test]
+async fn test_multi_root_workspace() {
+  let loader = |root: &Path| {
+    let (id, pattern) = match root.file_name()?.to_str()? {
+      "app" => ("no-console", "console.log($A)"),
+      "sub" => ("no-alert", "alert($A)"),
+      "lib" => ("no-debugger", "debugger"),
+      _ => return None,
+    };
+    Some(Ok(ProjectRules {
+      base: root.to_path_buf(),
+      rules: rules_of(&single_rule(id, pattern)),
+      util_files: vec![],
+    }))
+  };
+  let base = Path::new("./").to_path_buf();
+  let rule_finder = || Ok(rules_of(&single_rule("default", "neverMatched()")));
+  let (service, socket) =
+    LspService::build(|client| Backend::new(client, base, rule_finder).with_project_loader(loader))
+      .finish();
+  let folders = serde_json::json!([
+    { "uri": "file:///ws/app", "name": "app" },
+    { "uri": "file:///ws/app/sub", "name": "sub" },
+  ]);
+  let mut client = start_lsp_framed(service, socket, Some(folders)).await;
+  let text = "console.log(1)\nalert(1)\ndebugger";
+
+  let app_file = "file:///ws/app/a.ts";
+  send_did_open_framed(&mut client, app_file, "typescript", text).await;
+  assert_eq!(
+    wait_for_uri_codes(&mut client, app_file).await,
+    ["no-console"]
+  );
+  // the innermost folder decides the rules
+  let sub_file = "file:///ws/app/sub/b.ts";
+  send_did_open_framed(&mut client, sub_file, "typescript", text).await;
+  assert_eq!(
+    wait_for_uri_codes(&mut client, sub_file).await,
+    ["no-alert"]
+  );
+
+  let change = serde_json::json!({
+    "jsonrpc": "2.0",
+    "method": "workspace/didChangeWorkspaceFolders",
+    "params": {
+      "event": {
+        "added": [{ "uri": "file:///ws/lib", "name": "lib" }],
+        "removed": [{ "uri": "file:///ws/app/sub", "name": "sub" }],
+      }
+    }
+  });
+  client.send(change).await.unwrap();
+  // documents of the removed folder fall back to the enclosing folder
+  assert_eq!(
+    wait_for_uri_codes(&mut client, sub_file).await,
+    ["no-console"]
+  );
+  let lib_file = "file:///ws/lib/c.ts";
+  send_did_open_framed(&mut client, lib_file, "typescript", text).await;
+  assert_eq!(
+    wait_for_uri_codes(&mut client, lib_file).await,
+    ["no-debugger"]
+  );
+}
+
+#[tokio::test]
+async fn test_added_folder_language_setup() {
+  let calls = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+  let recorded = calls.clone();
+  let setup = move |roots: &[PathBuf]| {
+    recorded.lock().unwrap().push(roots.to_vec());
+    if roots.iter().any(|r| r.ends_with("lib")) {
+      anyhow::bail!("lib declares languages");
+    }
+    Ok(())
+  };
+  let base = Path::new("./").to_path_buf();
+  let rule_finder = || Ok(rules_of(NO_CONSOLE));
+  let (service, socket) =
+    LspService::build(|client| Backend::new(client, base, rule_finder).with_language_setup(setup))
+      .finish();
+  let folders = serde_json::json!([{ "uri": "file:///ws/app", "name": "app" }]);
+  let mut client = start_lsp_framed(service, socket, Some(folders)).await;
+  let change = serde_json::json!({
+    "jsonrpc": "2.0",
+    "method": "workspace/didChangeWorkspaceFolders",
+    "params": {
+      "event": {
+        "added": [{ "uri": "file:///ws/lib", "name": "lib" }],
+        "removed": [],
+      }
+    }
+  });
+  client.send(change).await.unwrap();
+  let message = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+    while let Some(Ok(msg)) = client.next().await {
+      if msg["method"] == "window/showMessage" {
+        return msg["params"]["message"].clone();
+      }
+    }
+    Value::Null
+  })
+  .await
+  .expect("no message for the added folder");
+  assert_eq!(message, "Failed to load languages: lib declares languages");
+  let calls = calls.lock().unwrap().clone();
+  assert_eq!(
+    calls,
+    [
+      vec![PathBuf::from("/ws/app")],
+      vec![PathBuf::from("/ws/lib")]
+    ]
+  );
+}
+
+#[tokio::test]
+async fn test_playground_requests() {
+  let base = Path::new("./").to_path_buf();
+  let rule_finder = || Ok(rules_of(NO_CONSOLE));
+  let dumper = |dump: Dump<SupportLang>| {
+    Some(match dump {
+      Dump::Pattern(pattern, _) => format!("pattern {}", pattern.fixed_string()),
+      Dump::Ast(node) => format!("ast {}", node.kind()),
+      Dump::Cst(node) => format!("cst {}", node.kind()),
+      Dump::Sexp(node) => node.get_inner_node().to_sexp(),
+    })
+  };
+  let builder =
+    LspService::build(|client| Backend::new(client, base, rule_finder).with_tree_dumper(dumper));
+  let (service, socket) = Backend::custom_methods(builder).finish();
+  let mut client = start_lsp_framed(service, socket, None).await;
+  let file_uri = "file:///Users/codes/ast-grep-vscode/test.ts";
+  let text = "let a = foo(1)\nconsole.log(a, 2)";
+  send_did_open_framed(&mut client, file_uri, "typescript", text).await;
+  wait_for_diagnostics(&mut client).await.unwrap();
+
+  let params = serde_json::json!({
+    "textDocument": { "uri": file_uri },
+    "range": range_json(0, 8, 14),
+    "format": "ast",
+  });
+  let (resp, _) = request_framed(&mut client, "ast-grep/dumpTree", params).await;
+  assert_eq!(resp["result"]["dump"], "ast call_expression");
+  assert_eq!(resp["result"]["range"], range_json(0, 8, 14));
+  let params = serde_json::json!({
+    "textDocument": { "uri": file_uri },
+    "range": range_json(0, 8, 11),
+    "format": "pattern",
+  });
+  let (resp, _) = request_framed(&mut client, "ast-grep/dumpTree", params).await;
+  assert_eq!(resp["result"]["dump"], "pattern foo");
+  // an inverted range is rejected instead of panicking
+  for format in ["pattern", "ast"] {
+    let params = serde_json::json!({
+      "textDocument": { "uri": file_uri },
+      "range": range_json(0, 11, 8),
+      "format": format,
+    });
+    let (resp, _) = request_framed(&mut client, "ast-grep/dumpTree", params).await;
+    assert_eq!(resp["error"]["code"], -32602);
+  }
+
+  let params = serde_json::json!({
+    "textDocument": { "uri": file_uri },
+    "pattern": "console.log($A, $$$)",
+  });
+  let (resp, _) = request_framed(&mut client, "ast-grep/previewMatches", params).await;
+  let matches = resp["result"].as_array().unwrap();
+  assert_eq!(matches.len(), 1);
+  assert_eq!(matches[0]["range"], range_json(1, 0, 17));
+  assert_eq!(matches[0]["metaVariables"]["A"], range_json(1, 12, 13));
+  // a sequence is previewed as a whole
+  let params = serde_json::json!({
+    "textDocument": { "uri": file_uri },
+    "pattern": "let a = foo(1)\nconsole.log($$$)",
+  });
+  let (resp, _) = request_framed(&mut client, "ast-grep/previewMatches", params).await;
+  let matches = resp["result"].as_array().unwrap();
+  assert_eq!(matches[0]["text"], text);
+  assert_eq!(matches[0]["range"]["end"], range_json(1, 0, 17)["end"]);
+  let params = serde_json::json!({
+    "textDocument": { "uri": file_uri },
+    "rule": "rule:\n  kind: number",
+  });
+  let (resp, _) = request_framed(&mut client, "ast-grep/previewMatches", params).await;
+  let texts: Vec<_> = resp["result"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|m| m["text"].as_str().unwrap())
+    .collect();
+  assert_eq!(texts, ["1", "2"]);
+  let params = serde_json::json!({
+    "textDocument": { "uri": file_uri },
+    "rule": { "rule": { "kind": "not_a_kind" } },
+  });
+  let (resp, _) = request_framed(&mut client, "ast-grep/previewMatches", params).await;
+  assert!(resp["error"].is_object());
+}
+
+const WRAP_CONSOLE: &str = r"
+id: wrap-console
+message: Wrap console.log
+language: TypeScript
+rule:
+  pattern: console.log($A, $$$REST)
+fix: logger.info($A)
+";
+
+#[tokio::test]
+async fn test_binding_hints() {
+  let mut client = create_lsp_framed(WRAP_CONSOLE).await;
+  let file_uri = "file:///Users/codes/ast-grep-vscode/test.ts";
+  let text = "let a = 1\nconsole.log(a, 2, 3)";
+  send_did_open_framed(&mut client, file_uri, "typescript", text).await;
+  wait_for_diagnostics(&mut client).await.unwrap();
+  let hint_params = serde_json::json!({
+    "textDocument": { "uri": file_uri },
+    "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 2, "character": 0 } },
+  });
+  let lens_params = serde_json::json!({ "textDocument": { "uri": file_uri } });
+  // disabled by default
+  let (resp, _) = request_framed(&mut client, "textDocument/inlayHint", hint_params.clone()).await;
+  assert!(resp["result"].is_null());
+
+  let settings = serde_json::json!({ "astGrep": { "inlayHints": true, "codeLens": true } });
+  send_configuration_framed(&mut client, settings).await;
+  let label = "$A = a, $$$REST = 2, 3, → logger.info(a)";
+  let (resp, _) = request_framed(&mut client, "textDocument/inlayHint", hint_params).await;
+  let hints = resp["result"].as_array().unwrap();
+  assert_eq!(hints.len(), 1);
+  assert_eq!(hints[0]["label"], label);
+  assert_eq!(
+    hints[0]["position"],
+    serde_json::json!({ "line": 1, "character": 20 })
+  );
+  let (resp, _) = request_framed(&mut client, "textDocument/codeLens", lens_params).await;
+  let lenses = resp["result"].as_array().unwrap();
+  assert_eq!(lenses.len(), 1);
+  assert_eq!(lenses[0]["command"]["title"], label);
+  assert_eq!(lenses[0]["range"], range_json(1, 0, 20));
+}
+
+#[tokio::test]
+async fn test_settings_filter_rules() {
+  let yamls = r"
+id: no-console-rule
+message: No console.log
+language: TypeScript
+rule:
+  pattern: console.log($$$A)
+---
+id: no-foo
+message: No foo
+severity: warning
+language: TypeScript
+rule:
+  pattern: foo($$$A)
+";
+  let mut client = create_lsp_framed(yamls).await;
+  let file_uri = "file:///Users/codes/ast-grep-vscode/test.ts";
+  send_did_open_framed(
+    &mut client,
+    file_uri,
+    "typescript",
+    "foo(1)\nconsole.log(2)",
+  )
+  .await;
+  let diagnostics = wait_for_diagnostics(&mut client).await.unwrap();
+  assert_eq!(diagnostics.as_array().unwrap().len(), 2);
+
+  let settings = serde_json::json!({
+    "ruleFilter": "^no-console",
+    "severityOverrides": { "no-console-rule": "error" },
+  });
+  send_configuration_framed(&mut client, settings).await;
+  let diagnostics = wait_for_diagnostics(&mut client).await.unwrap();
+  let diagnostics = diagnostics.as_array().unwrap();
+  assert_eq!(diagnostics.len(), 1);
+  assert_eq!(diagnostics[0]["code"], "no-console-rule");
+  assert_eq!(diagnostics[0]["severity"], 1);
+
+  let settings = serde_json::json!({ "severityOverrides": { "no-foo": "off" } });
+  send_configuration_framed(&mut client, settings).await;
+  let diagnostics = wait_for_diagnostics(&mut client).await.unwrap();
+  assert_eq!(diagnostics[0]["code"], "no-console-rule");
+  assert_eq!(diagnostics[0]["severity"], 4);
+
+  send_configuration_framed(&mut client, serde_json::json!({ "maxFileSize": 5 })).await;
+  let diagnostics = wait_for_diagnostics(&mut client).await.unwrap();
+  assert_eq!(diagnostics, serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn test_settings_without_section_are_kept() {
+  let yamls = r"
+id: no-console-rule
+message: No console.log
+language: TypeScript
+rule:
+  pattern: console.log($$$A)
+---
+id: no-foo
+message: No foo
+language: TypeScript
+rule:
+  pattern: foo($$$A)
+";
+  let base = Path::new("./").to_path_buf();
+  let rule_finder = move || Ok(rules_of(yamls));
+  let (service, socket) =
+    LspService::build(|client| Backend::new(client, base, rule_finder)).finish();
+  let options = serde_json::json!({ "astGrep": { "ruleFilter": "^no-console" } });
+  let mut client = start_lsp_framed_with_options(service, socket, None, options).await;
+  let file_uri = "file:///Users/codes/ast-grep-vscode/test.ts";
+  // a pull-model notification, an empty one and one of another extension
+  send_configuration_framed(&mut client, Value::Null).await;
+  send_configuration_framed(&mut client, serde_json::json!({})).await;
+  let other = serde_json::json!({ "editor": { "tabSize": 2 } });
+  send_configuration_framed(&mut client, other).await;
+  let source = "foo(1)\nconsole.log(2)";
+  send_did_open_framed(&mut client, file_uri, "typescript", source).await;
+  let diagnostics = wait_for_diagnostics(&mut client).await.unwrap();
+  let diagnostics = diagnostics.as_array().unwrap();
+  assert_eq!(diagnostics.len(), 1);
+  assert_eq!(diagnostics[0]["code"], "no-console-rule");
+}
+
+const BAD_RULE: &str = "id: bad
+language: TypeScript
+rule:
+  pattern: console.log($A)
+  inside:
+    matches: missing-util
+---
+id: bad-yaml
+language: TypeScript
+rule:
+  kind: [
+";
+
+#[tokio::test]
+async fn test_rule_file_diagnostics() {
+  let mut client = create_lsp_framed(NO_CONSOLE).await;
+  let file_uri = "file:///Users/codes/ast-grep-vscode/rules/bad.yml";
+  send_did_open_framed(&mut client, file_uri, "yaml", BAD_RULE).await;
+  let diagnostics = wait_for_diagnostics(&mut client).await.unwrap();
+  let diagnostics = diagnostics.as_array().unwrap();
+  assert_eq!(diagnostics.len(), 2);
+  assert_eq!(diagnostics[0]["range"], range_json(5, 13, 25));
+  let message = diagnostics[0]["message"].as_str().unwrap();
+  assert!(message.contains("Rule `missing-util` is not defined."));
+  assert!(diagnostics[1]["range"]["start"]["line"].as_u64().unwrap() >= 10);
+}
+
+const AUTHORING_RULE: &str = "id: authoring
+language: TypeScript
+rule:
+  kind: 
+  has:
+    stopBy: end
+    matches: my-util
+utils:
+  my-util:
+    pattern: $A + $B
+";
+
+fn labels(resp: &Value) -> Vec<&str> {
+  resp["result"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|item| item["label"].as_str().unwrap())
+    .collect()
+}
+
+fn position_params(uri: &str, line: u32, character: u32) -> Value {
+  serde_json::json!({
+    "textDocument": { "uri": uri },
+    "position": { "line": line, "character": character },
+  })
+}
+
+#[tokio::test]
+async fn test_rule_file_authoring() {
+  let mut client = create_lsp_framed(NO_CONSOLE).await;
+  let uri = "file:///Users/codes/ast-grep-vscode/rules/authoring.yml";
+  send_did_open_framed(&mut client, uri, "yaml", AUTHORING_RULE).await;
+  // the empty kind is reported, which also means the document is open
+  wait_for_diagnostics(&mut client).await.unwrap();
+
+  let params = position_params(uri, 3, 8);
+  let (resp, _) = request_framed(&mut client, "textDocument/completion", params).await;
+  let kinds = labels(&resp);
+  assert!(kinds.contains(&"identifier"));
+  assert!(kinds.contains(&"call_expression"));
+
+  let params = position_params(uri, 5, 4);
+  let (resp, _) = request_framed(&mut client, "textDocument/completion", params).await;
+  let keys = labels(&resp);
+  assert!(keys.contains(&"stopBy"));
+  assert!(keys.contains(&"pattern"));
+
+  let params = position_params(uri, 5, 12);
+  let (resp, _) = request_framed(&mut client, "textDocument/completion", params).await;
+  assert!(labels(&resp).contains(&"end"));
+
+  let params = position_params(uri, 6, 14);
+  let (resp, _) = request_framed(&mut client, "textDocument/completion", params).await;
+  assert_eq!(labels(&resp), ["my-util"]);
+
+  let params = position_params(uri, 2, 1);
+  let (resp, _) = request_framed(&mut client, "textDocument/hover", params).await;
+  let hover = resp["result"]["contents"]["value"].as_str().unwrap();
+  assert!(hover.starts_with("A rule object to find matching AST nodes"));
+  assert_eq!(resp["result"]["range"], range_json(2, 0, 4));
+
+  let params = position_params(uri, 6, 16);
+  let (resp, _) = request_framed(&mut client, "textDocument/definition", params).await;
+  assert_eq!(resp["result"]["uri"], uri);
+  assert_eq!(resp["result"]["range"], range_json(8, 2, 9));
+}
+
+#[tokio::test]
+async fn test_suppression_code_actions() {
+  let mut client = create_lsp_framed(NO_CONSOLE).await;
+  let file_uri = "file:///Users/codes/ast-grep-vscode/test.ts";
+  let file_content =
+    "function f() {\n  console.log(1) // ast-grep-ignore: other\n  console.log(2)\n}";
+  send_did_open_framed(&mut client, file_uri, "typescript", file_content).await;
+  let diagnostics = wait_for_diagnostics(&mut client).await.unwrap();
+  // the suppression of `other` is also reported as unused
+  let mut diagnostics: Vec<_> = diagnostics
+    .as_array()
+    .unwrap()
+    .iter()
+    .filter(|d| d["code"] == "no-console-rule")
+    .cloned()
+    .collect();
+  diagnostics.sort_by_key(|d| d["range"]["start"]["line"].as_u64());
+  assert_eq!(diagnostics.len(), 2);
+
+  // merge into the existing comment on the same line
+  let resp = request_code_action(&mut client, file_uri, &diagnostics[0]).await;
+  let (_, suppressions) = split_suppressions(resp.unwrap()["result"].as_array().unwrap());
+  assert_eq!(
+    suppressions[0]["title"],
+    "Suppress `no-console-rule` on this line"
+  );
+  let fixed = apply_all_code_actions(file_content, &suppressions[..1]);
+  assert_eq!(
+    fixed.lines().nth(1),
+    Some("  console.log(1) // ast-grep-ignore: other, no-console-rule")
+  );
+
+  // insert a comment above the line with the same indentation
+  let resp = request_code_action(&mut client, file_uri, &diagnostics[1]).await;
+  let (_, suppressions) = split_suppressions(resp.unwrap()["result"].as_array().unwrap());
+  let edit = &suppressions[0]["edit"]["changes"][file_uri][0];
+  assert_eq!(edit["range"], range_json(2, 0, 0));
+  assert_eq!(edit["newText"], "  // ast-grep-ignore: no-console-rule\n");
+  assert_eq!(
+    suppressions[1]["title"],
+    "Suppress `no-console-rule` in this file"
+  );
+  let edit = &suppressions[1]["edit"]["changes"][file_uri][0];
+  assert_eq!(edit["range"], range_json(0, 0, 0));
+  assert_eq!(edit["newText"], "// ast-grep-ignore: no-console-rule\n\n");
+}
+
 #[tokio::test]
 async fn test_single_line_code_edit() {
   let yamls = r"
@@ -455,10 +1336,12 @@ fix: |-
   let actions = code_action["result"]
     .as_array()
     .expect("Result should be an array");
+  let (actions, suppressions) = split_suppressions(actions);
   assert!(actions.len() == 1, "No code actions returned");
+  assert_eq!(suppressions.len(), 2, "Expected line and file suppression");
 
   // Apply the first code action and verify the text change
-  let fixed_text = apply_all_code_actions(file_content, actions);
+  let fixed_text = apply_all_code_actions(file_content, &actions);
   assert_eq!(fixed_text, "alert('Hello, world!')");
 }
 
@@ -503,16 +1386,17 @@ fix: |-
     let actions = code_action["result"]
       .as_array()
       .expect("Result should be an array");
+    let (actions, _) = split_suppressions(actions);
     assert_eq!(actions.len(), 1, "Expected 1 code action per diagnostic");
     if diagnostic["code"] == "use-alert" {
       assert_eq!(actions[0]["title"], "Fix `use-alert` with ast-grep");
 
-      let fixed_text = apply_all_code_actions(file_content, actions);
+      let fixed_text = apply_all_code_actions(file_content, &actions);
       assert_eq!(fixed_text, "alert('Hello, world!')");
     } else if diagnostic["code"] == "use-window-alert" {
       assert_eq!(actions[0]["title"], "Fix `use-window-alert` with ast-grep");
 
-      let fixed_text = apply_all_code_actions(file_content, actions);
+      let fixed_text = apply_all_code_actions(file_content, &actions);
       assert_eq!(fixed_text, "window.alert('Hello, world!')");
     } else {
       panic!("Unexpected diagnostic code");
@@ -627,15 +1511,44 @@ impl Encoder<serde_json::Value> for LspCodec {
 }
 
 async fn create_lsp_framed(yamls: &'static str) -> Framed<DuplexStream, LspCodec> {
+  create_lsp_framed_with_walker(yamls, |_| vec![]).await
+}
+
+async fn create_lsp_framed_with_walker<F>(
+  yamls: &'static str,
+  walker: F,
+) -> Framed<DuplexStream, LspCodec>
+where
+  F: Fn(&Path) -> Vec<PathBuf> + Send + Sync + 'static,
+{
   let base = Path::new("./").to_path_buf();
-  let rule_finder = move || {
-    let globals = GlobalRules::default();
-    let configs = from_yaml_string(yamls, &globals).unwrap();
-    let rc: RuleCollection<SupportLang> = RuleCollection::try_new(configs).unwrap();
-    Ok(rc)
-  };
+  let rule_finder = move || Ok(rules_of(yamls));
   let (service, socket) =
-    LspService::build(|client| Backend::new(client, base, rule_finder)).finish();
+    LspService::build(|client| Backend::new(client, base, rule_finder).with_file_walker(walker))
+      .finish();
+  start_lsp_framed(service, socket, None).await
+}
+
+fn rules_of(yamls: &str) -> RuleCollection<SupportLang> {
+  let globals = GlobalRules::default();
+  let configs = from_yaml_string(yamls, &globals).unwrap();
+  RuleCollection::try_new(configs).unwrap()
+}
+
+async fn start_lsp_framed(
+  service: LspService<Backend<SupportLang>>,
+  socket: ClientSocket,
+  workspace_folders: Option<Value>,
+) -> Framed<DuplexStream, LspCodec> {
+  start_lsp_framed_with_options(service, socket, workspace_folders, Value::Null).await
+}
+
+async fn start_lsp_framed_with_options(
+  service: LspService<Backend<SupportLang>>,
+  socket: ClientSocket,
+  workspace_folders: Option<Value>,
+  initialization_options: Value,
+) -> Framed<DuplexStream, LspCodec> {
   let (client_write, server_read) = duplex(16384);
   //let (server_write, client_read) = duplex(16384);
   let (r, w) = split(server_read);
@@ -657,7 +1570,9 @@ async fn create_lsp_framed(yamls: &'static str) -> Framed<DuplexStream, LspCodec
                 "dynamicRegistration": true
               }
             }
-          }
+          },
+          "workspaceFolders": workspace_folders,
+          "initializationOptions": initialization_options,
       }
   });
   client.send(initialize).await.unwrap();
@@ -459,3 +459,14 @@ test('check if a node has another using config', t => {
   t.assert(match!.has({ rule: { pattern: 'const x = 1' } }))
   t.assert(match!.has({ rule: { pattern: 'const y = 2' } }) === false)
 })
+
+test('resolve node path in reparsed code', t => {
+  const match = parse('function a() { foo(1); return bar(2) }').root().find('bar($A)')!
+  const path = match.path()
+  t.assert(match.matchesPath(path))
+  const sg = parse('function a() {\n  // comment\n  foo(1);\n  return bar(2)\n}')
+  t.is(sg.resolvePath(path)!.text(), 'bar(2)')
+  const drifted = parse('function a() { return bar(3) }').resolvePath(path)!
+  t.assert(drifted.matchesPath(path) === false)
+  t.is(parse('bar(2)').resolvePath(path), null)
+})
@@ -36,6 +36,7 @@ impl NapiConfig {
       transform: self.transform.map(serde_json::from_value).transpose()?,
       utils: self.utils.map(serde_json::from_value).transpose()?,
       fix: None,
+      exact_back_reference: None,
     };
     let env = DeserializeEnv::new(lang);
     rule.get_matcher(env).map_err(|e| {
@@ -161,6 +162,15 @@ impl Doc for JsDoc {
     self.tree = parse(source, &self.lang, Some(&self.tree)).map_err(|e| e.to_string())?;
     Ok(())
   }
+  fn do_edits(&mut self, edits: &[Edit<Self::Source>]) -> Result<(), String> {
+    let source = &mut self.source;
+    for edit in edits.iter().rev() {
+      let input_edit = source.accept_edit(edit);
+      self.tree.edit(&input_edit);
+    }
+    self.tree = parse(source, &self.lang, Some(&self.tree)).map_err(|e| e.to_string())?;
+    Ok(())
+  }
   fn root_node(&self) -> Node<'_> {
     self.tree.root_node()
   }
@@ -1,4 +1,6 @@
-use ast_grep_core::{matcher::KindMatcher, AstGrep, NodeMatch, Pattern, Position};
+use ast_grep_core::{
+  matcher::KindMatcher, AstGrep, NodeMatch, NodePath, PathParseError, Pattern, Position,
+};
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
 
@@ -219,6 +221,20 @@ impl SgNode {
     Ok(self.inner.node_id() as u32)
   }
 
+  /// Returns a path that finds the node again after the source is parsed again.
+  /// See `SgRoot.resolvePath`.
+  #[napi]
+  pub fn path(&self) -> String {
+    self.inner.path().to_string()
+  }
+
+  /// Returns true if the node text is the same as when the path was recorded.
+  #[napi]
+  pub fn matches_path(&self, path: String) -> Result<bool> {
+    let path = parse_node_path(&path)?;
+    Ok(path.matches_text(&self.inner))
+  }
+
   #[napi]
   pub fn find(
     &self,
@@ -431,6 +447,31 @@ impl SgRoot {
   pub fn filename(&self) -> Result<String> {
     Ok(self.1.clone())
   }
+  /// Finds the node by a path from `SgNode.path`, even if the node text has changed.
+  /// Returns `null` if no node is at the path.
+  #[napi]
+  pub fn resolve_path(
+    &self,
+    root_ref: Reference<SgRoot>,
+    env: Env,
+    path: String,
+  ) -> Result<Option<SgNode>> {
+    let path = parse_node_path(&path)?;
+    // share_with fails only if the closure does, that is when the path leads nowhere
+    let shared = root_ref.share_with(env, |root| {
+      let node = path.resolve(&root.0).node().cloned();
+      node
+        .map(NodeMatch::from)
+        .ok_or_else(|| napi::Error::from_reason("path does not resolve to a node"))
+    });
+    Ok(shared.ok().map(|inner| SgNode { inner }))
+  }
+}
+
+fn parse_node_path(path: &str) -> Result<NodePath> {
+  path
+    .parse()
+    .map_err(|e: PathParseError| napi::Error::new(napi::Status::InvalidArg, e.to_string()))
 }
 
 fn napi_pattern(pattern: &str, lang: NapiLang) -> Result<Pattern> {
@@ -1,6 +1,6 @@
 import type { NamedKinds, TypesMap } from './staticTypes'
 
-export type Strictness = 'cst' | 'smart' | 'ast' | 'relaxed' | 'signature'
+export type Strictness = 'cst' | 'smart' | 'ast' | 'relaxed' | 'signature' | 'normalized'
 
 export interface PatternObject<M extends TypesMap = TypesMap> {
   context: string
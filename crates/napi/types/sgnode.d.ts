@@ -64,6 +64,13 @@ export declare class SgNode<
   getTransformed(m: string): string | null
   /** Returns the node's SgRoot */
   getRoot(): SgRoot<M>
+  /**
+   * Returns a path that finds the node again after the source is parsed again.
+   * See `SgRoot.resolvePath`.
+   */
+  path(): string
+  /** Returns true if the node text is the same as when the path was recorded. */
+  matchesPath(path: string): boolean
   children(): Array<SgNode<M>>
   find: NodeMethod<M, [matcher: string | number | NapiConfig<M>]>
   findAll<K extends Kinds<M>>(
@@ -95,6 +102,11 @@ export declare class SgRoot<M extends TypesMap = TypesMap> {
    * Returns `"anonymous"` if the instance is created by `lang.parse(source)`.
    */
   filename(): string
+  /**
+   * Finds the node by a path from `SgNode.path`, even if the node text has changed.
+   * Returns `null` if no node is at the path.
+   */
+  resolvePath(path: string): SgNode<M> | null
 }
 
 interface NodeMethod<M extends TypesMap, Args extends unknown[] = []> {
@@ -20,6 +20,7 @@ class SgRoot:
     def __init__(self, src: str, language: str) -> None: ...
     def root(self) -> SgNode: ...
     def filename(self) -> str: ...
+    def resolve_path(self, path: str) -> Optional[SgNode]: ...
 
 class SgNode:
     # Node Inspection
@@ -53,6 +54,8 @@ class SgNode:
 
     # Tree Traversal
     def get_root(self) -> SgRoot: ...
+    def path(self) -> str: ...
+    def matches_path(self, path: str) -> bool: ...
     def field(self, name: str) -> Optional[SgNode]: ...
     def field_children(self, name: str) -> List[SgNode]: ...
     def parent(self) -> Optional[SgNode]: ...
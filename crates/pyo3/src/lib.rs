@@ -10,9 +10,10 @@ use range::{Pos, Range};
 
 use ast_grep_core::{
   tree_sitter::{LanguageExt, StrDoc},
-  AstGrep, NodeMatch,
+  AstGrep, NodeMatch, NodePath,
 };
 use py_lang::PyLang;
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 
 use unicode_position::UnicodePosition;
@@ -62,4 +63,18 @@ impl SgRoot {
   fn filename(&self) -> &str {
     &self.filename
   }
+
+  fn resolve_path(slf: PyRef<Self>, path: &str) -> PyResult<Option<SgNode>> {
+    let path: NodePath = path
+      .parse()
+      .map_err(|e| PyValueError::new_err(format!("{e}")))?;
+    let tree = unsafe { &*(&slf.inner as *const AstGrep<_>) } as &'static AstGrep<_>;
+    let Some(node) = path.resolve(tree).node().cloned() else {
+      return Ok(None);
+    };
+    Ok(Some(SgNode {
+      inner: NodeMatch::from(node),
+      root: slf.into(),
+    }))
+  }
 }
@@ -1,4 +1,5 @@
 use anyhow::Context;
+use ast_grep_core::language::{NodeTypes, Normalization};
 use ast_grep_core::matcher::{Pattern, PatternBuilder, PatternError};
 use ast_grep_core::tree_sitter::{LanguageExt, StrDoc, TSLanguage};
 use ast_grep_dynamic::{CustomLang, DynamicLang, LibraryPath};
@@ -123,6 +124,24 @@ impl Language for PyLang {
       Custom(c) => c.field_to_id(field),
     }
   }
+  fn normalization(&self) -> Option<&'static Normalization> {
+    match self {
+      Builtin(b) => b.normalization(),
+      Custom(c) => c.normalization(),
+    }
+  }
+  fn node_types(&self) -> Option<&'static NodeTypes> {
+    match self {
+      Builtin(b) => b.node_types(),
+      Custom(c) => c.node_types(),
+    }
+  }
+  fn list_separators(&self) -> &'static [&'static str] {
+    match self {
+      Builtin(b) => b.list_separators(),
+      Custom(c) => c.list_separators(),
+    }
+  }
   fn build_pattern(&self, builder: &PatternBuilder) -> Result<Pattern, PatternError> {
     builder.build(|src| StrDoc::try_new(src, *self))
   }
@@ -3,7 +3,8 @@ use crate::range::Range;
 use crate::SgRoot;
 
 use ast_grep_config::{DeserializeEnv, RuleCore, SerializableRuleCore};
-use ast_grep_core::{tree_sitter::StrDoc, NodeMatch};
+use ast_grep_core::source::{self, EditBatch, OverlapPolicy};
+use ast_grep_core::{tree_sitter::StrDoc, NodeMatch, NodePath};
 
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
@@ -125,6 +126,17 @@ impl SgNode {
     self.root.clone()
   }
 
+  fn path(&self) -> String {
+    self.inner.path().to_string()
+  }
+
+  fn matches_path(&self, path: &str) -> PyResult<bool> {
+    let path: NodePath = path
+      .parse()
+      .map_err(|e| PyValueError::new_err(format!("{e}")))?;
+    Ok(path.matches_text(&self.inner))
+  }
+
   #[pyo3(signature = (config=None, **rule))]
   fn find(
     &self,
@@ -267,38 +279,34 @@ impl SgNode {
     })
   }
 
-  fn commit_edits(&self, mut edits: Vec<Edit>) -> String {
-    edits.sort_by_key(|edit| edit.start_pos);
-    let mut new_content = String::new();
+  fn commit_edits(&self, edits: Vec<Edit>) -> PyResult<String> {
     let old_content = self.text();
-    let converted: Vec<_> = Python::attach(move |py| {
+    let offset = self.inner.range().start;
+    let converted: Option<Vec<_>> = Python::attach(move |py| {
       let root = self.root.bind(py);
       let root = root.borrow();
       let conv = &root.position;
       edits
         .into_iter()
-        .map(|mut e| {
-          e.start_pos = conv.char_to_byte(e.start_pos);
-          e.end_pos = conv.char_to_byte(e.end_pos);
-          e
+        .map(|e| {
+          let start = conv.char_to_byte(e.start_pos);
+          let end = conv.char_to_byte(e.end_pos);
+          Some(source::Edit::<String> {
+            position: start.checked_sub(offset)?,
+            deleted_length: end.checked_sub(start)?,
+            inserted_text: e.inserted_text.into_bytes(),
+          })
         })
         .collect()
     });
-    let offset = self.inner.range().start;
-    let mut start = 0;
-    for diff in converted {
-      let pos = diff.start_pos - offset;
-      // skip overlapping edits
-      if start > pos {
-        continue;
-      }
-      new_content.push_str(&old_content[start..pos]);
-      new_content.push_str(&diff.inserted_text);
-      start = diff.end_pos - offset;
-    }
-    // add trailing statements
-    new_content.push_str(&old_content[start..]);
-    new_content
+    let out_of_range = || PyValueError::new_err("Edit is out of the node range.");
+    let converted = converted.ok_or_else(out_of_range)?;
+    // skip overlapping edits
+    let source = old_content.as_bytes();
+    let batch = EditBatch::new(converted, OverlapPolicy::FirstWins, source.len())
+      .map_err(|_| out_of_range())?;
+    let new_content = batch.apply(source);
+    Ok(String::from_utf8_lossy(&new_content).into_owned())
   }
 
   /*---------- Magic Method  ----------*/
@@ -366,6 +374,7 @@ fn config_from_rule(dict: Bound<PyDict>) -> PyResult<SerializableRuleCore> {
     utils: None,
     transform: None,
     fix: None,
+    exact_back_reference: None,
   })
 }
 
@@ -1,4 +1,5 @@
 from ast_grep_py import SgRoot
+import pytest
 
 source = """
 function test() {
@@ -49,4 +50,13 @@ def test_modified_fix():
     edit.start_pos -= 1
     edit.end_pos += 1
     s = root.commit_edits([edit])
-    assert s == "いいよ = logこいよ"
\ No newline at end of file
+    assert s == "いいよ = logこいよ"
+
+def test_edit_out_of_node_range():
+    sg = SgRoot('let a = log(514)', "javascript")
+    root = sg.root()
+    node = root.find(kind="number")
+    assert node
+    edit = root.find(kind="identifier").replace('b')
+    with pytest.raises(ValueError):
+        node.commit_edits([edit])
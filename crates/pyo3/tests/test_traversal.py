@@ -128,4 +128,19 @@ def test_prev_all():
     assert len(prev_all) == 3
     assert len(prev_all[0].prev_all()) == 2
     assert prev_all[0].text() == "let b = 456"
-    assert not prev_all[2].prev_all()
\ No newline at end of file
+    assert not prev_all[2].prev_all()
+
+
+def test_node_path():
+    source = "function a() { foo(1); return bar(2) }"
+    node = SgRoot(source, "javascript").root().find(pattern="bar($A)")
+    assert node
+    path = node.path()
+    assert node.matches_path(path)
+    root = SgRoot("function a() {\n  // comment\n  foo(1);\n  return bar(2)\n}", "javascript")
+    found = root.resolve_path(path)
+    assert found and found.text() == "bar(2)"
+    assert found.matches_path(path)
+    found = SgRoot("function a() { return bar(3) }", "javascript").resolve_path(path)
+    assert found and not found.matches_path(path)
+    assert SgRoot("bar(2)", "javascript").resolve_path(path) is None